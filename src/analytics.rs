@@ -0,0 +1,174 @@
+//! Statistics derived from the keystrokes of a typing test.
+//!
+//! These go beyond the summary in [`ToipeResults`] and are shown with
+//! `--detailed-results`.
+
+use crate::{
+    layout::{Finger, Hand, KeyboardLayout},
+    results::{Keystroke, ToipeResults},
+};
+
+/// Where in words the mistakes were made.
+///
+/// The space after a word counts as its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ErrorPositions {
+    /// mistakes in the first char of a word
+    pub start: usize,
+    /// mistakes between the first and last chars of a word
+    pub middle: usize,
+    /// mistakes in the last char of a word or the space after it
+    pub end: usize,
+}
+
+impl ErrorPositions {
+    /// Finds where in words each wrongly typed char was.
+    pub fn from_results(results: &ToipeResults) -> Self {
+        let text: Vec<char> = results.text.chars().collect();
+        let mut positions = Self::default();
+
+        for keystroke in results.keystrokes.iter().filter(|k| !k.is_correct()) {
+            let index = keystroke.index;
+            if text.get(index).is_none_or(|c| c.is_whitespace()) {
+                positions.end += 1;
+                continue;
+            }
+
+            let is_boundary = |i: Option<usize>| {
+                i.and_then(|i| text.get(i))
+                    .is_none_or(|c| c.is_whitespace())
+            };
+            if is_boundary(index.checked_sub(1)) {
+                positions.start += 1;
+            } else if is_boundary(Some(index + 1)) {
+                positions.end += 1;
+            } else {
+                positions.middle += 1;
+            }
+        }
+
+        positions
+    }
+
+    /// Total number of mistakes.
+    pub fn total(&self) -> usize {
+        self.start + self.middle + self.end
+    }
+}
+
+/// How the typing was split between the two hands.
+///
+/// Only pairs of consecutive keystrokes typed by fingers (not thumbs) are
+/// considered, so the space bar does not count towards alternation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HandBalance {
+    /// fraction of keystrokes typed with the left hand
+    pub left_hand_ratio: f64,
+    /// fraction of consecutive keystroke pairs typed with different hands
+    pub alternation_rate: f64,
+    /// fraction of consecutive keystroke pairs that are different keys
+    /// typed with the same finger
+    pub same_finger_bigram_rate: f64,
+}
+
+impl HandBalance {
+    /// Computes the hand balance of the keystrokes on the given layout.
+    ///
+    /// Keystrokes of chars that are not on the layout are skipped.
+    /// Returns `None` if there are too few keystrokes on the layout.
+    pub fn from_keystrokes(keystrokes: &[Keystroke], layout: &KeyboardLayout) -> Option<Self> {
+        let positions: Vec<_> = keystrokes
+            .iter()
+            .filter_map(|keystroke| layout.position(keystroke.typed))
+            .filter(|position| !matches!(position.finger, Finger::LeftThumb | Finger::RightThumb))
+            .collect();
+
+        if positions.len() < 2 {
+            return None;
+        }
+
+        let num_left = positions
+            .iter()
+            .filter(|position| position.hand() == Hand::Left)
+            .count();
+
+        let mut num_alternations = 0;
+        let mut num_same_finger = 0;
+        for pair in positions.windows(2) {
+            let (prev, cur) = (pair[0], pair[1]);
+            if prev.hand() != cur.hand() {
+                num_alternations += 1;
+            } else if prev.finger == cur.finger && (prev.row, prev.column) != (cur.row, cur.column)
+            {
+                num_same_finger += 1;
+            }
+        }
+
+        let num_pairs = (positions.len() - 1) as f64;
+        Some(Self {
+            left_hand_ratio: num_left as f64 / positions.len() as f64,
+            alternation_rate: num_alternations as f64 / num_pairs,
+            same_finger_bigram_rate: num_same_finger as f64 / num_pairs,
+        })
+    }
+
+    /// Computes the hand balance of a typing test.
+    pub fn from_results(results: &ToipeResults, layout: &KeyboardLayout) -> Option<Self> {
+        Self::from_keystrokes(&results.keystrokes, layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn hand_balance(text: &str) -> Option<HandBalance> {
+        let results = ToipeResults::from_typed(text, text, Duration::ZERO);
+        HandBalance::from_results(&results, &KeyboardLayout::qwerty())
+    }
+
+    #[test]
+    fn hand_balance_of_keystrokes() {
+        // the space bar is typed with a thumb and does not count
+        let alternating = hand_balance("fj fj").unwrap();
+        assert_eq!(alternating.left_hand_ratio, 0.5);
+        assert_eq!(alternating.alternation_rate, 1.0);
+        assert_eq!(alternating.same_finger_bigram_rate, 0.0);
+
+        // "d" and "e" are both typed with the left middle finger
+        let same_finger = hand_balance("ded").unwrap();
+        assert_eq!(same_finger.left_hand_ratio, 1.0);
+        assert_eq!(same_finger.alternation_rate, 0.0);
+        assert_eq!(same_finger.same_finger_bigram_rate, 1.0);
+
+        // the same key twice is not a same-finger bigram
+        let repeated = hand_balance("ddk").unwrap();
+        assert_eq!(repeated.alternation_rate, 0.5);
+        assert_eq!(repeated.same_finger_bigram_rate, 0.0);
+
+        assert_eq!(hand_balance("f "), None);
+        assert_eq!(hand_balance("    "), None);
+    }
+
+    #[test]
+    fn error_positions_in_words() {
+        // typos at the start, middle and end of "abcd", in the space after
+        // it, at the start of "ef" and in the space after it
+        let results = ToipeResults::from_typed("abcd ef g", "xxcxx~f~", Duration::ZERO);
+        assert_eq!(
+            ErrorPositions::from_results(&results),
+            ErrorPositions {
+                start: 2,
+                middle: 1,
+                end: 3,
+            }
+        );
+
+        // a single-letter word only has a start
+        let results = ToipeResults::from_typed("a b", "a x", Duration::ZERO);
+        let positions = ErrorPositions::from_results(&results);
+        assert_eq!((positions.start, positions.total()), (1, 1));
+    }
+}
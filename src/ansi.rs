@@ -0,0 +1,284 @@
+//! ANSI escape sequences for drawing the terminal UI.
+//!
+//! Written by toipe itself rather than by the terminal backend (see
+//! [`crate::backend`]) so that the UI looks the same with every backend.
+//! The modules mirror the ones of termion, which toipe used before.
+
+/// Writes a CSI (Control Sequence Introducer) sequence.
+macro_rules! csi {
+    ($($arg:tt)*) => {
+        concat!("\x1b[", $($arg)*)
+    };
+}
+
+/// Defines a unit struct that displays as a fixed CSI sequence.
+macro_rules! csi_sequence {
+    ($(#[$doc:meta])* $name:ident, $sequence:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name;
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str(csi!($sequence))
+            }
+        }
+    };
+}
+
+/// Moving, hiding and changing the shape of the cursor.
+pub mod cursor {
+    use std::fmt;
+
+    /// Moves the cursor to a column and row, both starting at 1.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Goto(pub u16, pub u16);
+
+    impl fmt::Display for Goto {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, csi!("{};{}H"), self.1, self.0)
+        }
+    }
+
+    /// Moves the cursor left by a number of columns.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Left(pub u16);
+
+    impl fmt::Display for Left {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, csi!("{}D"), self.0)
+        }
+    }
+
+    csi_sequence!(Hide, "?25l");
+    csi_sequence!(Show, "?25h");
+    csi_sequence!(BlinkingBar, "5 q");
+    csi_sequence!(SteadyBlock, "2 q");
+}
+
+/// Clearing parts of the screen.
+pub mod clear {
+    csi_sequence!(All, "2J");
+    csi_sequence!(CurrentLine, "2K");
+}
+
+/// Title of the terminal window.
+pub mod title {
+    use std::fmt;
+
+    /// Sets the window title, through an OSC (Operating System Command)
+    /// sequence. Control chars in the title are left out.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Set<'a>(pub &'a str);
+
+    impl fmt::Display for Set<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("\x1b]0;")?;
+            for c in self.0.chars().filter(|c| !c.is_control()) {
+                write!(f, "{}", c)?;
+            }
+            f.write_str("\x07")
+        }
+    }
+
+    csi_sequence!(
+        /// Saves the window title on the title stack of xterm-like
+        /// terminals, to be restored by [`Restore`].
+        Save,
+        "22;0t"
+    );
+    csi_sequence!(
+        /// Restores the window title saved by [`Save`].
+        Restore,
+        "23;0t"
+    );
+}
+
+/// Text styles.
+pub mod style {
+    csi_sequence!(
+        /// Resets all styles and colors.
+        Reset,
+        "m"
+    );
+    csi_sequence!(Bold, "1m");
+    csi_sequence!(Faint, "2m");
+    csi_sequence!(Italic, "3m");
+    csi_sequence!(Underline, "4m");
+    csi_sequence!(
+        /// Undoes [`Faint`] and [`Bold`], which share the same reset.
+        NoFaint,
+        "22m"
+    );
+    csi_sequence!(NoItalic, "23m");
+    csi_sequence!(NoUnderline, "24m");
+}
+
+/// Foreground and background colors.
+pub mod color {
+    use std::fmt;
+
+    /// A color that can be written as the foreground or background.
+    pub trait Color {
+        fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result;
+        fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result;
+    }
+
+    impl<C: Color + ?Sized> Color for &C {
+        fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            (**self).write_fg(f)
+        }
+
+        fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            (**self).write_bg(f)
+        }
+    }
+
+    /// Displays as the sequence setting `C` as the foreground color.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Fg<C: Color>(pub C);
+
+    impl<C: Color> fmt::Display for Fg<C> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            self.0.write_fg(f)
+        }
+    }
+
+    /// Displays as the sequence setting `C` as the background color.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Bg<C: Color>(pub C);
+
+    impl<C: Color> fmt::Display for Bg<C> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            self.0.write_bg(f)
+        }
+    }
+
+    /// The default color of the terminal.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Reset;
+
+    impl Color for Reset {
+        fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(csi!("39m"))
+        }
+
+        fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(csi!("49m"))
+        }
+    }
+
+    /// One of the 256 colors of the terminal, the first 16 of which can
+    /// be changed by the terminal theme.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct AnsiValue(pub u8);
+
+    impl Color for AnsiValue {
+        fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, csi!("38;5;{}m"), self.0)
+        }
+
+        fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, csi!("48;5;{}m"), self.0)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Rgb(pub u8, pub u8, pub u8);
+
+    impl Color for Rgb {
+        fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, csi!("38;2;{};{};{}m"), self.0, self.1, self.2)
+        }
+
+        fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, csi!("48;2;{};{};{}m"), self.0, self.1, self.2)
+        }
+    }
+
+    /// Defines named colors as the [`AnsiValue`]s they stand for.
+    macro_rules! named_colors {
+        ($($name:ident = $value:expr,)*) => {
+            $(
+                #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+                pub struct $name;
+
+                impl From<$name> for crate::theme::ThemeColor {
+                    fn from(_: $name) -> Self {
+                        crate::theme::ThemeColor::Ansi($value)
+                    }
+                }
+
+                impl Color for $name {
+                    fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        AnsiValue($value).write_fg(f)
+                    }
+
+                    fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        AnsiValue($value).write_bg(f)
+                    }
+                }
+            )*
+        };
+    }
+
+    named_colors! {
+        Black = 0,
+        Red = 1,
+        Green = 2,
+        Yellow = 3,
+        Blue = 4,
+        Magenta = 5,
+        Cyan = 6,
+        White = 7,
+        LightBlack = 8,
+        LightRed = 9,
+        LightGreen = 10,
+        LightYellow = 11,
+        LightBlue = 12,
+        LightMagenta = 13,
+        LightCyan = 14,
+        LightWhite = 15,
+    }
+}
+
+#[cfg(all(test, feature = "termion"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_sequences_as_termion() {
+        assert_eq!(
+            cursor::Goto(3, 7).to_string(),
+            termion::cursor::Goto(3, 7).to_string()
+        );
+        assert_eq!(
+            cursor::Left(5).to_string(),
+            termion::cursor::Left(5).to_string()
+        );
+        assert_eq!(
+            cursor::BlinkingBar.to_string(),
+            termion::cursor::BlinkingBar.to_string()
+        );
+        assert_eq!(
+            clear::CurrentLine.to_string(),
+            termion::clear::CurrentLine.to_string()
+        );
+        assert_eq!(
+            style::NoFaint.to_string(),
+            termion::style::NoFaint.to_string()
+        );
+        assert_eq!(
+            color::Fg(color::LightGreen).to_string(),
+            termion::color::Fg(termion::color::LightGreen).to_string()
+        );
+        assert_eq!(
+            color::Fg(color::Rgb(1, 2, 3)).to_string(),
+            termion::color::Fg(termion::color::Rgb(1, 2, 3)).to_string()
+        );
+        assert_eq!(
+            color::Fg(color::Reset).to_string(),
+            termion::color::Fg(termion::color::Reset).to_string()
+        );
+    }
+}
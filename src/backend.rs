@@ -0,0 +1,252 @@
+//! Terminal backends: raw mode, the size of the terminal and key presses.
+//!
+//! termion is used by default. Building with the `crossterm` feature
+//! uses crossterm instead, which also works on Windows:
+//!
+//! ```sh
+//! cargo install toipe --no-default-features --features crossterm
+//! ```
+//!
+//! Styled output does not depend on the backend, see [`crate::ansi`].
+
+use std::io::{self, Read, Write};
+
+/// A key pressed in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Backspace,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    /// shift + tab
+    BackTab,
+    Delete,
+    Insert,
+    /// a function key, e.g. `F(1)` for F1
+    F(u8),
+    /// a char typed without modifiers, or with shift. Enter is `'\n'`.
+    Char(char),
+    /// a char typed with alt. Alt + backspace is `Alt('\x7f')`.
+    Alt(char),
+    /// a char typed with ctrl. Ctrl + backspace is `Ctrl('\x7f')` when
+    /// the backend can tell it apart, while terminals that send it as
+    /// ctrl-h read as `Ctrl('h')`, which is a plain backspace.
+    Ctrl(char),
+    Null,
+    Esc,
+}
+
+/// Keys read from a terminal, one at a time.
+pub type Keys = Box<dyn Iterator<Item = io::Result<Key>> + Send>;
+
+/// A library for handling the terminal.
+pub trait Backend {
+    /// Standard output in raw mode, which is restored when dropped.
+    type RawStdout: Write;
+
+    /// Whether keys are read from the console itself rather than from
+    /// the tty given to [`Backend::keys`], so no tty has to be opened
+    /// when stdin is not one.
+    const READS_CONSOLE: bool;
+
+    /// Puts the terminal in raw mode.
+    fn raw_stdout() -> io::Result<Self::RawStdout>;
+
+    /// Columns and rows of the terminal.
+    fn terminal_size() -> io::Result<(u16, u16)>;
+
+    /// Keys pressed in the terminal, read from `tty` unless
+    /// [`Backend::READS_CONSOLE`].
+    fn keys(tty: Box<dyn Read + Send>) -> Keys;
+}
+
+#[cfg(feature = "crossterm")]
+pub type Terminal = crossterm_backend::Crossterm;
+
+#[cfg(all(feature = "termion", not(feature = "crossterm")))]
+pub type Terminal = termion_backend::Termion;
+
+#[cfg(not(any(feature = "termion", feature = "crossterm")))]
+compile_error!(
+    "toipe needs a terminal backend, enable either the `termion` or the `crossterm` feature"
+);
+
+/// Columns and rows of the terminal, see [`Backend::terminal_size`].
+pub fn terminal_size() -> io::Result<(u16, u16)> {
+    Terminal::terminal_size()
+}
+
+#[cfg(feature = "termion")]
+pub mod termion_backend {
+    use std::io::{self, stdout, Read, Stdout};
+
+    use termion::{
+        event,
+        input::TermRead,
+        raw::{IntoRawMode, RawTerminal},
+    };
+
+    use super::{Backend, Key, Keys};
+
+    pub struct Termion;
+
+    impl Backend for Termion {
+        type RawStdout = RawTerminal<Stdout>;
+
+        const READS_CONSOLE: bool = false;
+
+        fn raw_stdout() -> io::Result<Self::RawStdout> {
+            stdout().into_raw_mode()
+        }
+
+        fn terminal_size() -> io::Result<(u16, u16)> {
+            termion::terminal_size()
+        }
+
+        fn keys(tty: Box<dyn Read + Send>) -> Keys {
+            Box::new(tty.keys().map(|key| key.map(Key::from)))
+        }
+    }
+
+    impl From<event::Key> for Key {
+        fn from(key: event::Key) -> Self {
+            match key {
+                event::Key::Backspace => Key::Backspace,
+                event::Key::Left => Key::Left,
+                event::Key::Right => Key::Right,
+                event::Key::Up => Key::Up,
+                event::Key::Down => Key::Down,
+                event::Key::Home => Key::Home,
+                event::Key::End => Key::End,
+                event::Key::PageUp => Key::PageUp,
+                event::Key::PageDown => Key::PageDown,
+                event::Key::BackTab => Key::BackTab,
+                event::Key::Delete => Key::Delete,
+                event::Key::Insert => Key::Insert,
+                event::Key::F(n) => Key::F(n),
+                event::Key::Char(c) => Key::Char(c),
+                event::Key::Alt(c) => Key::Alt(c),
+                event::Key::Ctrl(c) => Key::Ctrl(c),
+                event::Key::Esc => Key::Esc,
+                _ => Key::Null,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "crossterm")]
+pub mod crossterm_backend {
+    use std::io::{self, stdout, Read, Stdout, Write};
+
+    use crossterm::{
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        terminal,
+    };
+
+    use super::{Backend, Key, Keys};
+
+    pub struct Crossterm;
+
+    /// Standard output while the terminal is in raw mode.
+    pub struct RawStdout(Stdout);
+
+    impl Write for RawStdout {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    impl Drop for RawStdout {
+        fn drop(&mut self) {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+
+    impl Backend for Crossterm {
+        type RawStdout = RawStdout;
+
+        const READS_CONSOLE: bool = true;
+
+        fn raw_stdout() -> io::Result<Self::RawStdout> {
+            // lets the Windows console understand the escape sequences of
+            // `crate::ansi`
+            #[cfg(windows)]
+            crossterm::ansi_support::supports_ansi();
+            terminal::enable_raw_mode()?;
+            Ok(RawStdout(stdout()))
+        }
+
+        fn terminal_size() -> io::Result<(u16, u16)> {
+            terminal::size()
+        }
+
+        fn keys(_tty: Box<dyn Read + Send>) -> Keys {
+            Box::new(std::iter::from_fn(|| loop {
+                match event::read() {
+                    // Windows also reports key releases
+                    Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => {
+                        return Some(Ok(Key::from(key)))
+                    }
+                    Ok(_) => continue,
+                    Err(err) => return Some(Err(err)),
+                }
+            }))
+        }
+    }
+
+    impl From<KeyEvent> for Key {
+        fn from(key: KeyEvent) -> Self {
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            let alt = key.modifiers.contains(KeyModifiers::ALT);
+            match key.code {
+                KeyCode::Char(c) if ctrl => Key::Ctrl(c.to_ascii_lowercase()),
+                KeyCode::Char(c) if alt => Key::Alt(c),
+                KeyCode::Char(c) => Key::Char(c),
+                KeyCode::Enter => Key::Char('\n'),
+                KeyCode::Tab => Key::Char('\t'),
+                KeyCode::Backspace if ctrl => Key::Ctrl('\x7f'),
+                KeyCode::Backspace if alt => Key::Alt('\x7f'),
+                KeyCode::Backspace => Key::Backspace,
+                KeyCode::Left => Key::Left,
+                KeyCode::Right => Key::Right,
+                KeyCode::Up => Key::Up,
+                KeyCode::Down => Key::Down,
+                KeyCode::Home => Key::Home,
+                KeyCode::End => Key::End,
+                KeyCode::PageUp => Key::PageUp,
+                KeyCode::PageDown => Key::PageDown,
+                KeyCode::BackTab => Key::BackTab,
+                KeyCode::Delete => Key::Delete,
+                KeyCode::Insert => Key::Insert,
+                KeyCode::F(n) => Key::F(n),
+                KeyCode::Esc => Key::Esc,
+                _ => Key::Null,
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "termion"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_termion_keys() {
+        let keys: Vec<Key> = termion_backend::Termion::keys(Box::new(&b"a\x7f\x17\x1b[A"[..]))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            keys,
+            [Key::Char('a'), Key::Backspace, Key::Ctrl('w'), Key::Up]
+        );
+    }
+}
@@ -4,12 +4,10 @@ use toipe::trie::Trie;
 fn main() {
     let mut trie = Trie::new();
 
-    for result in io::stdin().lines() {
-        if let Ok(line) = result {
-            for word in line.split(char::is_whitespace) {
-                if let Err(err) = trie.insert(&word.to_ascii_lowercase()) {
-                    println!("{}", err);
-                }
+    for line in io::stdin().lines().map_while(Result::ok) {
+        for word in line.split(char::is_whitespace) {
+            if let Err(err) = trie.insert(&word.to_ascii_lowercase()) {
+                println!("{}", err);
             }
         }
     }
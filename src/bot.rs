@@ -0,0 +1,124 @@
+//! A simulated opponent that types through the same text at a fixed
+//! speed, for racing against.
+
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+/// A simulated typist racing the user.
+pub struct Bot {
+    /// typing speed in words per minute
+    wpm: f64,
+    /// how much the speed varies, as a fraction of `wpm`
+    noise: f64,
+    /// number of chars typed so far
+    progress: f64,
+    last_update: Option<Instant>,
+}
+
+impl Bot {
+    /// Creates a bot typing at `wpm` words per minute.
+    ///
+    /// With a non-zero `noise`, the speed randomly varies by up to
+    /// `noise * wpm` at any moment (e.g. 0.1 for ±10%).
+    pub fn new(wpm: f64, noise: f64) -> Self {
+        Self {
+            wpm,
+            noise: noise.clamp(0.0, 1.0),
+            progress: 0.0,
+            last_update: None,
+        }
+    }
+
+    /// Typing speed of the bot in words per minute.
+    pub fn wpm(&self) -> f64 {
+        self.wpm
+    }
+
+    /// Puts the bot back at the start of the text.
+    pub fn reset(&mut self) {
+        self.progress = 0.0;
+        self.last_update = None;
+    }
+
+    /// Keeps the bot from typing until the next call to
+    /// [`Bot::advance`], such as during a break.
+    pub fn pause(&mut self) {
+        self.last_update = None;
+    }
+
+    /// Moves the bot forward to where it would be at `now`.
+    ///
+    /// The bot starts typing at the first call after a reset.
+    pub fn advance(&mut self, now: Instant) {
+        if let Some(last_update) = self.last_update {
+            let elapsed = now.saturating_duration_since(last_update).as_secs_f64();
+            let variation = if self.noise > 0.0 {
+                rand::thread_rng().gen_range(-self.noise..=self.noise)
+            } else {
+                0.0
+            };
+            // a "word" is 5 chars, see `ToipeResults::wpm`
+            let chars_per_sec = self.wpm * 5.0 / 60.0;
+            self.progress += elapsed * chars_per_sec * (1.0 + variation);
+        }
+        self.last_update = Some(now);
+    }
+
+    /// Number of chars the bot has typed so far.
+    pub fn position(&self) -> usize {
+        self.progress as usize
+    }
+
+    /// Time the bot would take to type `num_chars` chars, ignoring noise.
+    pub fn time_to_type(&self, num_chars: usize) -> Duration {
+        Duration::from_secs_f64(num_chars as f64 / (self.wpm * 5.0 / 60.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advances_at_its_speed() {
+        // 60 wpm is 5 chars per second
+        let mut bot = Bot::new(60.0, 0.0);
+        let start = Instant::now();
+        bot.advance(start);
+        assert_eq!(bot.position(), 0);
+        bot.advance(start + Duration::from_secs(2));
+        assert_eq!(bot.position(), 10);
+
+        bot.pause();
+        bot.advance(start + Duration::from_secs(60));
+        assert_eq!(bot.position(), 10);
+        bot.advance(start + Duration::from_secs(61));
+        assert_eq!(bot.position(), 15);
+
+        bot.reset();
+        assert_eq!(bot.position(), 0);
+    }
+
+    #[test]
+    fn noise_stays_within_bounds() {
+        let mut bot = Bot::new(60.0, 0.5);
+        let start = Instant::now();
+        bot.advance(start);
+        for second in 1..=100 {
+            bot.advance(start + Duration::from_secs(second));
+        }
+        assert!((250..=750).contains(&bot.position()), "{}", bot.position());
+    }
+
+    #[test]
+    fn time_to_type() {
+        let bot = Bot::new(60.0, 0.2);
+        assert_eq!(bot.time_to_type(0), Duration::ZERO);
+        assert_eq!(bot.time_to_type(5), Duration::from_secs(1));
+        assert_eq!(
+            Bot::new(120.0, 0.0).time_to_type(50),
+            Duration::from_secs(5)
+        );
+    }
+}
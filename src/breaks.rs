@@ -0,0 +1,100 @@
+//! Reminders to take a break after typing for a long time without one,
+//! see `--break-after`.
+
+use std::time::{Duration, Instant};
+
+/// Time without typing that counts as a break, such as a pause between
+/// tests.
+pub const MIN_BREAK: Duration = Duration::from_secs(5 * 60);
+
+/// Tracks how long the user has been typing without a break.
+pub struct BreakReminder {
+    /// typing time after which a break is due
+    after: Duration,
+    /// typing time since the last break, up to `last_typed`
+    practiced: Duration,
+    /// when a key was last typed since the last break
+    last_typed: Option<Instant>,
+}
+
+impl BreakReminder {
+    /// Reminds to take a break after typing for `after`.
+    pub fn new(after: Duration) -> Self {
+        Self {
+            after,
+            practiced: Duration::ZERO,
+            last_typed: None,
+        }
+    }
+
+    /// Typing time after which a break is due.
+    pub fn after(&self) -> Duration {
+        self.after
+    }
+
+    /// Counts a key typed at `now`. Time since the last key counts as
+    /// typing, unless it was long enough to be a break.
+    pub fn typed(&mut self, now: Instant) {
+        self.practiced = self.practiced_at(now);
+        self.last_typed = Some(now);
+    }
+
+    /// Whether a break is due at `now`.
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.practiced_at(now) >= self.after
+    }
+
+    /// Starts counting again after a break.
+    pub fn took_break(&mut self) {
+        self.practiced = Duration::ZERO;
+        self.last_typed = None;
+    }
+
+    fn practiced_at(&self, now: Instant) -> Duration {
+        match self.last_typed {
+            Some(last_typed) => {
+                let idle = now.saturating_duration_since(last_typed);
+                if idle >= MIN_BREAK {
+                    Duration::ZERO
+                } else {
+                    self.practiced + idle
+                }
+            }
+            None => self.practiced,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reminds_after_typing_without_a_break() {
+        let minutes = |n: u64| Duration::from_secs(n * 60);
+        let start = Instant::now();
+        let mut reminder = BreakReminder::new(minutes(18));
+
+        // typing for 15 minutes, with short pauses
+        for minute in 0..=15 {
+            reminder.typed(start + minutes(minute));
+        }
+        assert!(!reminder.is_due(start + minutes(15)));
+        // a pause counts as typing until it is long enough to be a break
+        assert!(reminder.is_due(start + minutes(19) + Duration::from_secs(59)));
+        assert!(!reminder.is_due(start + minutes(20)));
+
+        // a break between tests starts the count over
+        for minute in 25..=42 {
+            reminder.typed(start + minutes(minute));
+        }
+        assert!(!reminder.is_due(start + minutes(42)));
+        reminder.typed(start + minutes(43));
+        assert!(reminder.is_due(start + minutes(43)));
+
+        reminder.took_break();
+        assert!(!reminder.is_due(start + minutes(50)));
+        reminder.typed(start + minutes(50));
+        assert!(!reminder.is_due(start + minutes(54)));
+    }
+}
@@ -0,0 +1,507 @@
+//! Challenge boards, where the results of tests taken with the same
+//! challenge code (see [`crate::challenge_code`]) are compared.
+//!
+//! A board is a small HTTP server, run with `toipe challenge serve`, that
+//! anyone taking part can reach. With `--challenge-server`, the result
+//! of every test that has a challenge code is submitted to it, and
+//! `toipe challenge results <CODE>` lists the best result of everyone who
+//! took the test. Submissions are sent through `curl`.
+//!
+//! The server stores the results of each code as JSON lines in
+//! [`board_dir`], and answers:
+//! - `GET /challenges/<CODE>`: the results as a JSON array
+//! - `POST /challenges/<CODE>`: adds the [`Submission`] in the body
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Error, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    config::ChallengeCommand,
+    dirs,
+    http::{download, post_json},
+    results::ToipeResults,
+    storage,
+};
+
+/// Longest name results can be submitted under.
+const MAX_NAME_LEN: usize = 40;
+
+/// Largest request body the server reads, in bytes.
+const MAX_BODY_LEN: usize = 4096;
+
+/// Largest request line and headers the server reads, in bytes.
+const MAX_HEAD_LEN: usize = 8192;
+
+/// Connections the server handles at the same time.
+const WORKERS: usize = 8;
+
+/// How long the server waits for a client to send or receive.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Address `toipe challenge serve` listens on by default.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+/// The result of a test submitted to a challenge board.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Submission {
+    pub name: String,
+    /// see [`ToipeResults::wpm`]
+    pub wpm: f64,
+    /// see [`ToipeResults::accuracy`]
+    pub accuracy: f64,
+    /// when the test ended, in seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// A result to [`submit`] once the terminal is restored, as it can take
+/// a while.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingSubmission {
+    pub server: String,
+    pub code: String,
+    pub submission: Submission,
+}
+
+impl PendingSubmission {
+    /// See [`submit`].
+    pub fn submit(&self) -> Result<(), Error> {
+        submit(&self.server, &self.code, &self.submission)
+    }
+}
+
+impl Submission {
+    /// Submission of a test that ended now.
+    pub fn new(name: &str, results: &ToipeResults) -> Self {
+        Self {
+            name: name.to_string(),
+            wpm: results.wpm(),
+            accuracy: results.accuracy(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("name".into(), self.name.as_str().into());
+        object.insert("wpm".into(), self.wpm.into());
+        object.insert("accuracy".into(), self.accuracy.into());
+        object.insert("timestamp".into(), self.timestamp.into());
+        object.into()
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, Error> {
+        let float = |field| {
+            value
+                .get(field)
+                .and_then(Value::as_f64)
+                .filter(|x| x.is_finite() && *x >= 0.0)
+                .ok_or_else(|| invalid(field))
+        };
+        Ok(Self {
+            name: value
+                .get("name")
+                .and_then(Value::as_str)
+                .filter(|name| is_valid_name(name))
+                .ok_or_else(|| invalid("name"))?
+                .to_string(),
+            wpm: float("wpm")?,
+            accuracy: float("accuracy")?.min(1.0),
+            timestamp: value
+                .get("timestamp")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| invalid("timestamp"))?,
+        })
+    }
+}
+
+fn invalid(field: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Missing or invalid `{}` in challenge result", field),
+    )
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.trim().is_empty()
+        && name.chars().count() <= MAX_NAME_LEN
+        && !name.chars().any(char::is_control)
+}
+
+/// Whether `code` looks like a challenge code, which keeps it from
+/// naming files outside of [`board_dir`].
+fn is_valid_code(code: &str) -> bool {
+    !code.is_empty()
+        && code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Name results are submitted under by default: the name of the user.
+pub fn default_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|name| is_valid_name(name))
+        .unwrap_or_else(|| "anonymous".to_string())
+}
+
+fn results_url(server: &str, code: &str) -> String {
+    format!("{}/challenges/{}", server.trim_end_matches('/'), code)
+}
+
+/// Submits the result of a test with the challenge code `code` to the
+/// board at `server`. Nothing is submitted in incognito mode.
+pub fn submit(server: &str, code: &str, submission: &Submission) -> Result<(), Error> {
+    if storage::is_incognito() {
+        return Ok(());
+    }
+    post_json(&results_url(server, code), &submission.to_json())
+        .map_err(|err| Error::new(err.kind(), format!("Could not submit result: {}", err)))
+}
+
+/// All results submitted for the challenge code `code` to the board at
+/// `server`.
+pub fn fetch(server: &str, code: &str) -> Result<Vec<Submission>, Error> {
+    let contents = download(&results_url(server, code))?;
+    let value = serde_json::from_str::<Value>(&String::from_utf8_lossy(&contents))?;
+    value
+        .as_array()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Challenge results are not a list"))?
+        .iter()
+        .map(Submission::from_json)
+        .collect()
+}
+
+/// The best result of everyone in `submissions`, fastest first.
+pub fn standings(submissions: &[Submission]) -> Vec<&Submission> {
+    let mut best: HashMap<&str, &Submission> = HashMap::new();
+    for submission in submissions {
+        best.entry(&submission.name)
+            .and_modify(|best| {
+                if submission.wpm > best.wpm {
+                    *best = submission;
+                }
+            })
+            .or_insert(submission);
+    }
+    let mut standings: Vec<&Submission> = best.into_values().collect();
+    standings.sort_by(|a, b| b.wpm.total_cmp(&a.wpm).then(a.name.cmp(&b.name)));
+    standings
+}
+
+/// Runs a `toipe challenge` command, with results submitted to `server`.
+pub fn run(command: &ChallengeCommand, server: Option<&str>) -> Result<(), Error> {
+    match command {
+        ChallengeCommand::Results { code } => {
+            let server = server.ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    "No challenge board to list results from, set one with --challenge-server",
+                )
+            })?;
+            let submissions = fetch(server, code)?;
+            if submissions.is_empty() {
+                println!("No results yet");
+            }
+            for (rank, submission) in standings(&submissions).iter().enumerate() {
+                println!(
+                    "{:>3}. {:<20} {:>6.1} wpm {:>5.1}%",
+                    rank + 1,
+                    submission.name,
+                    submission.wpm,
+                    submission.accuracy * 100.0
+                );
+            }
+            Ok(())
+        }
+        ChallengeCommand::Serve { address } => serve(address),
+    }
+}
+
+/// Directory a challenge board stores results in.
+///
+/// Usually `~/.local/share/toipe/challenges`.
+pub fn board_dir() -> Result<PathBuf, Error> {
+    Ok(dirs::data_dir()?.join("challenges"))
+}
+
+/// Serves a challenge board on `address` until stopped.
+pub fn serve(address: &str) -> Result<(), Error> {
+    let dir = board_dir()?;
+    let listener = TcpListener::bind(address)?;
+    println!(
+        "Serving the challenge board on http://{}",
+        listener.local_addr()?
+    );
+    if storage::is_incognito() {
+        println!("Submissions are not stored in incognito mode");
+    }
+
+    // a pool of workers, so that a slow client does not hold up the others
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(WORKERS);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..WORKERS {
+        let (receiver, dir) = (Arc::clone(&receiver), dir.clone());
+        thread::spawn(move || loop {
+            let stream = receiver
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .recv();
+            let Ok(stream) = stream else {
+                return;
+            };
+            // a broken connection only fails its own request
+            if let Err(err) = handle_connection(stream, &dir) {
+                eprintln!("{}", err);
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => sender.send(stream).expect("the workers never stop"),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, dir: &Path) -> Result<(), Error> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    handle(&mut stream, dir)
+}
+
+/// Reads a request from `stream` and writes the response.
+fn handle(stream: &mut (impl Read + Write), dir: &Path) -> Result<(), Error> {
+    let (status, body) = read_request(&mut BufReader::new(&mut *stream), dir)?;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Reads a request, returning the status and body of the response.
+fn read_request(reader: &mut impl BufRead, dir: &Path) -> Result<(&'static str, String), Error> {
+    // the request line and the headers, which end with an empty line
+    let mut head = reader.by_ref().take(MAX_HEAD_LEN as u64);
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        let len = head.read_line(&mut line)?;
+        if !line.ends_with('\n') && head.limit() == 0 {
+            return Ok(("431 Request Header Fields Too Large", "{}".to_string()));
+        }
+        if len == 0 || line.trim().is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+
+    let mut parts = lines.first().map_or("", String::as_str).split_whitespace();
+    let (method, path) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    );
+    let mut content_length = 0;
+    for header in lines.iter().skip(1) {
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(usize::MAX);
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_LEN {
+        return Ok(("413 Payload Too Large", "{}".to_string()));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(respond(method, path, &String::from_utf8_lossy(&body), dir))
+}
+
+/// Status and body of the response to a request for `path`, with results
+/// stored in `dir`.
+fn respond(method: &str, path: &str, body: &str, dir: &Path) -> (&'static str, String) {
+    let code = match path.strip_prefix("/challenges/") {
+        Some(code) if is_valid_code(code) => code,
+        _ => return ("404 Not Found", "{}".to_string()),
+    };
+    let file = dir.join(format!("{}.jsonl", code));
+
+    match method {
+        "GET" => {
+            let lines = match fs::read_to_string(&file) {
+                Ok(contents) => contents.lines().map(String::from).collect(),
+                Err(_) => Vec::new(),
+            };
+            ("200 OK", format!("[{}]", lines.join(",")))
+        }
+        "POST" => {
+            let submission = serde_json::from_str::<Value>(body)
+                .map_err(Error::from)
+                .and_then(|value| Submission::from_json(&value));
+            match submission {
+                // the submission would not be stored
+                Ok(_) if storage::is_incognito() => ("503 Service Unavailable", "{}".to_string()),
+                Ok(submission) => {
+                    let line = format!("{}\n", submission.to_json());
+                    match storage::append(&file, line.as_bytes()) {
+                        Ok(()) => ("201 Created", "{}".to_string()),
+                        Err(_) => ("500 Internal Server Error", "{}".to_string()),
+                    }
+                }
+                Err(_) => ("400 Bad Request", "{}".to_string()),
+            }
+        }
+        _ => ("405 Method Not Allowed", "{}".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submission(name: &str, wpm: f64) -> Submission {
+        Submission {
+            name: name.to_string(),
+            wpm,
+            accuracy: 0.95,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn ranks_the_best_of_everyone() {
+        let submissions = [
+            submission("ana", 70.0),
+            submission("bo", 80.0),
+            submission("ana", 85.0),
+            submission("cy", 60.0),
+        ];
+        let names: Vec<(&str, f64)> = standings(&submissions)
+            .iter()
+            .map(|submission| (submission.name.as_str(), submission.wpm))
+            .collect();
+        assert_eq!(names, [("ana", 85.0), ("bo", 80.0), ("cy", 60.0)]);
+    }
+
+    #[test]
+    fn serves_results() {
+        let dir = std::env::temp_dir().join(format!("toipe-board-{}", std::process::id()));
+        let post = |code: &str, body: &str| {
+            respond("POST", &format!("/challenges/{}", code), body, &dir).0
+        };
+        let get = |code: &str| respond("GET", &format!("/challenges/{}", code), "", &dir);
+
+        assert_eq!(get("1abc"), ("200 OK", "[]".to_string()));
+        let body = submission("ana", 70.0).to_json().to_string();
+        assert_eq!(post("1abc", &body), "201 Created");
+        assert_eq!(post("1abc", "{\"name\": \"\"}"), "400 Bad Request");
+        assert_eq!(post("../etc", &body), "404 Not Found");
+        assert_eq!(
+            respond("DELETE", "/challenges/1abc", "", &dir).0,
+            "405 Method Not Allowed"
+        );
+
+        let (status, results) = get("1abc");
+        assert_eq!(status, "200 OK");
+        let results = serde_json::from_str::<Value>(&results).unwrap();
+        let results = results.as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            Submission::from_json(&results[0]).unwrap(),
+            submission("ana", 70.0)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A connection reading `request` and keeping the response.
+    struct Connection {
+        request: std::io::Cursor<Vec<u8>>,
+        response: Vec<u8>,
+    }
+
+    impl Read for Connection {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            self.request.read(buf)
+        }
+    }
+
+    impl Write for Connection {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            self.response.write(buf)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    /// Status line of the response to `request`.
+    fn status(request: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("toipe-board-http-{}", std::process::id()));
+        let mut connection = Connection {
+            request: std::io::Cursor::new(request.as_bytes().to_vec()),
+            response: Vec::new(),
+        };
+        handle(&mut connection, &dir).unwrap();
+        let response = String::from_utf8(connection.response).unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn limits_request_sizes() {
+        assert_eq!(
+            status("GET /challenges/1abc HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            "HTTP/1.1 200 OK"
+        );
+
+        let long_header = format!("X-Padding: {}\r\n", "a".repeat(MAX_HEAD_LEN));
+        assert_eq!(
+            status(&format!(
+                "GET /challenges/1abc HTTP/1.1\r\n{}\r\n",
+                long_header
+            )),
+            "HTTP/1.1 431 Request Header Fields Too Large"
+        );
+        let many_headers = "X-Padding: a\r\n".repeat(MAX_HEAD_LEN);
+        assert_eq!(
+            status(&format!(
+                "GET /challenges/1abc HTTP/1.1\r\n{}\r\n",
+                many_headers
+            )),
+            "HTTP/1.1 431 Request Header Fields Too Large"
+        );
+        assert_eq!(
+            status(&format!(
+                "GET /{} HTTP/1.1\r\n\r\n",
+                "a".repeat(MAX_HEAD_LEN)
+            )),
+            "HTTP/1.1 431 Request Header Fields Too Large"
+        );
+
+        assert_eq!(
+            status("POST /challenges/1abc HTTP/1.1\r\nContent-Length: 5000\r\n\r\n"),
+            "HTTP/1.1 413 Payload Too Large"
+        );
+    }
+}
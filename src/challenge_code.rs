@@ -0,0 +1,163 @@
+//! Challenge codes, which let others take a test on the same text, see
+//! `--challenge-code`.
+//!
+//! A code holds the options that pick the text of a test and change how
+//! it is taken, including the seed of the text (see
+//! [`ToipeConfig::challenge_options`]). They are written as a TOML
+//! document like the config file, encoded with URL-safe base64 after a
+//! version number.
+//!
+//! Codes are shared by others, so they can only hold the options in
+//! [`OPTIONS`].
+
+use std::io::{Error, ErrorKind};
+
+use toml::value::Table;
+
+use crate::config::to_toml_document;
+
+#[cfg(doc)]
+use crate::config::ToipeConfig;
+
+/// Version of the format of challenge codes, their first char.
+const VERSION: char = '1';
+
+/// Options a challenge code can hold, the ones written by
+/// [`ToipeConfig::challenge_options`].
+pub const OPTIONS: &[&str] = &[
+    "seed",
+    "generators",
+    "wordlist",
+    "duration",
+    "num-words",
+    "layout",
+    "one-hand",
+    "punctuation-chance",
+    "punctuation-cooldown",
+    "max-marks-per-word",
+    "number-chance",
+    "number-max",
+    "number-template",
+    "mix",
+    "locale",
+    "lang",
+    "typo-chance",
+    "challenge",
+    "fade",
+    "punctuation",
+    "numbers",
+    "proofread",
+    "reveal",
+    "assist",
+    "forgive",
+];
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// The challenge code for `options`.
+pub fn encode(options: &Table) -> String {
+    let mut code = String::from(VERSION);
+    for chunk in to_toml_document(options).as_bytes().chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | ((*byte as u32) << (16 - 8 * i))
+        });
+        // a char for every 6 bits of the chunk, without padding
+        for i in 0..=chunk.len() {
+            code.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    code
+}
+
+/// The options in a challenge code.
+///
+/// Fails for a code with options other than [`OPTIONS`], or with a
+/// layout given as a path instead of a name.
+pub fn decode(code: &str) -> Result<Table, Error> {
+    let invalid = || Error::new(ErrorKind::InvalidInput, "invalid challenge code");
+
+    let encoded = code.trim().strip_prefix(VERSION).ok_or_else(invalid)?;
+    let mut bytes = Vec::new();
+    for chunk in encoded.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return Err(invalid());
+        }
+        let mut bits = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|a| a == c).ok_or_else(invalid)?;
+            bits |= (value as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            bytes.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    let document = String::from_utf8(bytes).map_err(|_| invalid())?;
+    let options: Table = toml::from_str(&document).map_err(|_| invalid())?;
+
+    if let Some(key) = options.keys().find(|key| !OPTIONS.contains(&key.as_str())) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("challenge code sets `{}`, which challenges can not", key),
+        ));
+    }
+    if let Some(layout) = options.get("layout") {
+        let is_name = layout.as_str().is_some_and(|name| {
+            name.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        });
+        if !is_name {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "challenge code sets a layout that is not a name",
+            ));
+        }
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes() {
+        let options =
+            toml::from_str::<Table>("seed = \"42\"\npunctuation = true\nnum-words = \"25\"")
+                .unwrap();
+        let code = encode(&options);
+        assert!(code.starts_with('1'));
+        assert!(code[1..]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(decode(&code).unwrap(), options);
+        assert_eq!(decode(&format!(" {}\n", code)).unwrap(), options);
+
+        // every length of the last chunk
+        for document in ["fade = 1\n", "seed = 1\n", "lang = 1\n"] {
+            let options = toml::from_str::<Table>(document).unwrap();
+            assert_eq!(decode(&encode(&options)).unwrap(), options);
+        }
+
+        assert!(decode("").is_err());
+        assert!(decode("2abc").is_err());
+        assert!(decode("1a").is_err());
+        assert!(decode("1!!!!").is_err());
+    }
+
+    #[test]
+    fn refuses_options_outside_challenges() {
+        for document in [
+            "seed = \"1\"\ncommand = \"echo pwned\"\n",
+            "sync-git = \"https://example.com/data.git\"\n",
+            "log-file = \"/tmp/log\"\n",
+            "layout = \"../../layout.toml\"\n",
+        ] {
+            let code = encode(&toml::from_str::<Table>(document).unwrap());
+            let err = decode(&code).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput, "{}", document);
+        }
+        let code = encode(&toml::from_str::<Table>("layout = \"my_dvorak\"\n").unwrap());
+        assert!(decode(&code).is_ok());
+    }
+}
@@ -0,0 +1,86 @@
+//! The source of time for typing tests.
+//!
+//! [`Toipe::test`](crate::Toipe::test) asks its [`Clock`] for the time
+//! instead of calling [`Instant::now`], and passes it on to the parts
+//! that need it, such as [`TypingSession`](crate::session::TypingSession),
+//! the metronome and the break reminder. A [`ManualClock`] lets tests of
+//! timed mode and of breaks run without waiting, and can be moved along
+//! recorded timestamps to play a test back.
+
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// Tells the time.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The real time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time, so one can be given to the code under
+/// test and another kept to move it along.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Rc<Cell<Instant>>,
+}
+
+impl ManualClock {
+    /// A clock stopped at `now`.
+    pub fn new(now: Instant) -> Self {
+        Self {
+            now: Rc::new(Cell::new(now)),
+        }
+    }
+
+    /// Moves the time `duration` forward.
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+
+    /// Moves the time to `now`, which may be earlier.
+    pub fn set(&self, now: Instant) {
+        self.now.set(now);
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clocks_share_the_time() {
+        let start = Instant::now();
+        let clock = ManualClock::new(start);
+        let shared = clock.clone();
+        clock.advance(Duration::from_secs(3));
+        assert_eq!(shared.now(), start + Duration::from_secs(3));
+
+        shared.set(start + Duration::from_millis(500));
+        assert_eq!(clock.now(), start + Duration::from_millis(500));
+    }
+}
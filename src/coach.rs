@@ -0,0 +1,311 @@
+//! Tips shown on the results page, each suggesting what to practice
+//! next.
+//!
+//! A tip comes from the first of these that stands out in a test:
+//! - low accuracy, drilled with `--practice`
+//! - symbols or numbers typed much slower than letters, drilled with
+//!   `--punctuation` or `--numbers`
+//! - a finger much slower than the others, drilled with `--one-hand`
+//! - most mistakes made at the same place in words (see
+//!   [`ErrorPositions`])
+
+use std::{collections::HashMap, time::Duration};
+
+use clap::ArgEnum;
+
+use crate::{
+    analytics::ErrorPositions,
+    config::ToipeConfig,
+    layout::{Finger, Hand, KeyboardLayout},
+    results::{Keystroke, ToipeResults},
+};
+
+/// Accuracy below which slowing down is suggested.
+const LOW_ACCURACY: f64 = 0.9;
+
+/// How many times slower than the rest something has to be typed to be
+/// worth a tip.
+const SLOW_FACTOR: f64 = 1.5;
+
+/// Fewest keystrokes an average time between keys is taken over.
+const MIN_SAMPLES: usize = 5;
+
+/// Fewest mistakes their position in words is looked at for.
+const MIN_MISTAKES: usize = 3;
+
+/// A test with options that drill a weakness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drill {
+    Practice,
+    Punctuation,
+    Numbers,
+    OneHand(Hand),
+}
+
+impl Drill {
+    /// The command-line option for the drill, such as `--one-hand left`.
+    pub fn option(&self) -> String {
+        match self {
+            Drill::Practice => "--practice".to_string(),
+            Drill::Punctuation => "--punctuation".to_string(),
+            Drill::Numbers => "--numbers".to_string(),
+            Drill::OneHand(hand) => format!(
+                "--one-hand {}",
+                hand.to_possible_value()
+                    .expect("hands are not skipped")
+                    .get_name()
+            ),
+        }
+    }
+
+    /// Whether the drill changes the text of tests with `config`, which
+    /// it cannot when the text is not from a word list or the drill is
+    /// already on.
+    pub fn is_available(&self, config: &ToipeConfig) -> bool {
+        let from_wordlist = config.generator_names().is_none()
+            && config.diff.is_none()
+            && config.text_command.is_none()
+            && config.is_stdin_tty
+            && !config.is_quote_mode();
+        from_wordlist
+            && match self {
+                Drill::Practice => !config.practice,
+                Drill::Punctuation => !config.punctuation,
+                Drill::Numbers => !config.numbers && config.mix.is_none(),
+                Drill::OneHand(hand) => config.one_hand != Some(*hand),
+            }
+    }
+
+    /// Turns the drill on in `config`.
+    pub fn apply(&self, config: &mut ToipeConfig) {
+        match self {
+            Drill::Practice => {
+                config.practice = true;
+                config.cover_all = false;
+            }
+            Drill::Punctuation => config.punctuation = true,
+            Drill::Numbers => config.numbers = true,
+            Drill::OneHand(hand) => config.one_hand = Some(*hand),
+        }
+    }
+}
+
+/// Something to work on, found in the results of a test.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tip {
+    pub message: String,
+    /// a drill for it that can be started with the options in use
+    pub drill: Option<Drill>,
+}
+
+/// The tip for a test taken with `config`, if anything stands out.
+pub fn tip(results: &ToipeResults, layout: &KeyboardLayout, config: &ToipeConfig) -> Option<Tip> {
+    let (message, drill) = accuracy_tip(results)
+        .or_else(|| char_class_tip(results))
+        .or_else(|| finger_tip(results, layout))
+        .or_else(|| error_position_tip(results))?;
+    Some(Tip {
+        message,
+        drill: drill.filter(|drill| drill.is_available(config)),
+    })
+}
+
+fn accuracy_tip(results: &ToipeResults) -> Option<(String, Option<Drill>)> {
+    let accuracy = results.accuracy();
+    (results.total_chars_typed > 0 && accuracy < LOW_ACCURACY).then(|| {
+        (
+            format!(
+                "your accuracy was {:.0}%, slow down and drill the chars you miss most",
+                accuracy * 100.0
+            ),
+            Some(Drill::Practice),
+        )
+    })
+}
+
+fn char_class_tip(results: &ToipeResults) -> Option<(String, Option<Drill>)> {
+    let letters = average(
+        key_intervals(results)
+            .filter(|(k, _)| k.expected.is_alphabetic())
+            .map(|(_, interval)| interval),
+    )?;
+    [
+        (
+            "symbols",
+            Drill::Punctuation,
+            char::is_ascii_punctuation as fn(&char) -> bool,
+        ),
+        ("numbers", Drill::Numbers, char::is_ascii_digit),
+    ]
+    .into_iter()
+    .filter_map(|(name, drill, is_in_class)| {
+        let average = average(
+            key_intervals(results)
+                .filter(|(k, _)| is_in_class(&k.expected))
+                .map(|(_, interval)| interval),
+        )?;
+        Some((name, drill, average.as_secs_f64() / letters.as_secs_f64()))
+    })
+    .filter(|(_, _, ratio)| *ratio >= SLOW_FACTOR)
+    .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+    .map(|(name, drill, ratio)| {
+        (
+            format!("your {} are {:.1}× slower than letters", name, ratio),
+            Some(drill),
+        )
+    })
+}
+
+fn finger_tip(results: &ToipeResults, layout: &KeyboardLayout) -> Option<(String, Option<Drill>)> {
+    let mut by_finger: HashMap<Finger, Vec<Duration>> = HashMap::new();
+    for (keystroke, interval) in key_intervals(results) {
+        match layout.finger(keystroke.expected) {
+            Some(Finger::LeftThumb | Finger::RightThumb) | None => {}
+            Some(finger) => by_finger.entry(finger).or_default().push(interval),
+        }
+    }
+
+    let (finger, slowest) = by_finger
+        .iter()
+        .filter_map(|(finger, intervals)| Some((*finger, average(intervals.iter().copied())?)))
+        .max_by_key(|(_, average)| *average)?;
+    let others = average(
+        by_finger
+            .iter()
+            .filter(|(other, _)| **other != finger)
+            .flat_map(|(_, intervals)| intervals.iter().copied()),
+    )?;
+
+    let ratio = slowest.as_secs_f64() / others.as_secs_f64();
+    (ratio >= SLOW_FACTOR).then(|| {
+        (
+            format!(
+                "your {} is {:.1}× slower than your other fingers",
+                finger.name(),
+                ratio
+            ),
+            Some(Drill::OneHand(finger.hand())),
+        )
+    })
+}
+
+fn error_position_tip(results: &ToipeResults) -> Option<(String, Option<Drill>)> {
+    let positions = ErrorPositions::from_results(results);
+    if positions.total() < MIN_MISTAKES {
+        return None;
+    }
+    let message = if positions.start * 2 > positions.total() {
+        "most mistakes were at the start of words, read the next word before finishing one"
+    } else if positions.end * 2 > positions.total() {
+        "most mistakes were at the end of words, finish each word before moving on"
+    } else {
+        return None;
+    };
+    Some((message.to_string(), None))
+}
+
+/// Correct keystrokes that followed a correct keystroke of the previous
+/// char, with the time since it.
+fn key_intervals(results: &ToipeResults) -> impl Iterator<Item = (&Keystroke, Duration)> {
+    results.keystrokes.windows(2).filter_map(|pair| {
+        let (prev, cur) = (&pair[0], &pair[1]);
+        (prev.is_correct() && cur.is_correct() && cur.index == prev.index + 1)
+            .then(|| (cur, cur.at.saturating_sub(prev.at)))
+    })
+}
+
+/// Average of `durations`, if there are at least [`MIN_SAMPLES`].
+fn average(durations: impl Iterator<Item = Duration>) -> Option<Duration> {
+    let durations: Vec<Duration> = durations.collect();
+    (durations.len() >= MIN_SAMPLES)
+        .then(|| durations.iter().sum::<Duration>() / durations.len() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    /// Results of typing `text` with the time between keys given by
+    /// `interval` for each char, and `typo` typed in place of the char
+    /// at each of `typos`.
+    fn results(text: &str, interval: impl Fn(char) -> u64, typos: &[usize]) -> ToipeResults {
+        let typed: String = text
+            .chars()
+            .enumerate()
+            .map(|(index, c)| if typos.contains(&index) { '~' } else { c })
+            .collect();
+        let mut results = ToipeResults::from_typed(text, &typed, Duration::ZERO);
+        let mut at = Duration::ZERO;
+        for keystroke in &mut results.keystrokes {
+            at += Duration::from_millis(interval(keystroke.expected));
+            keystroke.at = at;
+        }
+        results.ended_at = results.started_at + at;
+        results
+    }
+
+    fn config() -> ToipeConfig {
+        let mut config = ToipeConfig::parse_from(["toipe"]);
+        // stdin is not a terminal while testing, but the text is not
+        // read from it
+        config.is_stdin_tty = true;
+        config
+    }
+
+    #[test]
+    fn tips() {
+        let layout = KeyboardLayout::qwerty();
+        let text = "the quick brown fox jumps over the lazy dog";
+
+        let even = results(text, |_| 100, &[]);
+        assert_eq!(tip(&even, &layout, &config()), None);
+
+        let sloppy = results(text, |_| 100, &[1, 5, 9, 13, 17, 21]);
+        let sloppy_tip = tip(&sloppy, &layout, &config()).unwrap();
+        assert_eq!(sloppy_tip.drill, Some(Drill::Practice));
+
+        let symbols = results(
+            "a, b; c. d, e; f. g, h; i. j, k",
+            |c| {
+                if c.is_ascii_punctuation() {
+                    300
+                } else {
+                    100
+                }
+            },
+            &[],
+        );
+        let symbols_tip = tip(&symbols, &layout, &config()).unwrap();
+        assert_eq!(symbols_tip.drill, Some(Drill::Punctuation));
+        assert!(
+            symbols_tip.message.contains("3.0×"),
+            "{}",
+            symbols_tip.message
+        );
+
+        let slow_index = results(text, |c| if "yuhjnm".contains(c) { 400 } else { 100 }, &[]);
+        let index_tip = tip(&slow_index, &layout, &config()).unwrap();
+        assert_eq!(index_tip.drill, Some(Drill::OneHand(Hand::Right)));
+        assert!(index_tip.message.contains("right index finger"));
+        // too few keystrokes of the left pinky
+        let slow_pinky = results(text, |c| if "qaz".contains(c) { 400 } else { 100 }, &[]);
+        assert_eq!(tip(&slow_pinky, &layout, &config()), None);
+
+        let mut punctuated = config();
+        punctuated.punctuation = true;
+        assert_eq!(tip(&symbols, &layout, &punctuated).unwrap().drill, None);
+    }
+
+    #[test]
+    fn drill_options() {
+        assert_eq!(Drill::OneHand(Hand::Left).option(), "--one-hand left");
+
+        let mut config = config();
+        Drill::Practice.apply(&mut config);
+        assert!(config.practice);
+        assert!(!Drill::Practice.is_available(&config));
+        assert!(Drill::Numbers.is_available(&config));
+    }
+}
@@ -0,0 +1,133 @@
+//! Confetti falling over the results page of a test with a new personal
+//! best.
+//!
+//! The pieces are thrown up from the bottom of the screen and fall back
+//! down. Where each piece is only depends on the time since the confetti
+//! was thrown, so it is redrawn on every tick of the results page until
+//! [`Confetti::is_over`].
+
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::{theme::ThemeColor, tui::Text};
+
+/// How long the confetti is shown.
+pub const DURATION: Duration = Duration::from_millis(2500);
+
+/// Number of pieces per column of the screen.
+const PIECES_PER_COLUMN: f64 = 0.5;
+
+/// Downward acceleration of the pieces, in rows per second squared.
+const GRAVITY: f64 = 30.0;
+
+const CHARS: [char; 6] = ['*', '+', '•', '·', '◆', '▪'];
+
+/// Colors of the pieces, as ANSI values of bright colors.
+const COLORS: [u8; 6] = [9, 10, 11, 12, 13, 14];
+
+struct Piece {
+    /// column and row the piece is thrown from
+    x: f64,
+    y: f64,
+    /// speed in columns and rows per second
+    vx: f64,
+    vy: f64,
+    c: char,
+    color: ThemeColor,
+}
+
+/// Confetti thrown over a screen.
+pub struct Confetti {
+    pieces: Vec<Piece>,
+    thrown_at: Instant,
+    width: u16,
+    height: u16,
+}
+
+impl Confetti {
+    /// Confetti thrown at `now` over a screen of `width` by `height`.
+    pub fn new(width: u16, height: u16, rng: &mut impl Rng, now: Instant) -> Self {
+        let num_pieces = (width as f64 * PIECES_PER_COLUMN) as usize;
+        // high enough to reach the top of the screen, give or take
+        let max_vy = (2.0 * GRAVITY * height as f64).sqrt();
+        let pieces = (0..num_pieces)
+            .map(|_| Piece {
+                x: rng.gen_range(0.0..width as f64),
+                y: height as f64,
+                vx: rng.gen_range(-8.0..8.0),
+                vy: -rng.gen_range(max_vy * 0.6..max_vy * 1.1),
+                c: CHARS[rng.gen_range(0..CHARS.len())],
+                color: ThemeColor::Ansi(COLORS[rng.gen_range(0..COLORS.len())]),
+            })
+            .collect();
+        Self {
+            pieces,
+            thrown_at: now,
+            width,
+            height,
+        }
+    }
+
+    /// Whether all pieces have fallen.
+    pub fn is_over(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.thrown_at) >= DURATION
+    }
+
+    /// The pieces on the screen at `now`, by the column and row they are
+    /// in, both starting at 1.
+    pub fn pieces(&self, now: Instant) -> Vec<((u16, u16), Text)> {
+        if self.is_over(now) {
+            return Vec::new();
+        }
+        let t = now.saturating_duration_since(self.thrown_at).as_secs_f64();
+        self.pieces
+            .iter()
+            .filter_map(|piece| {
+                let x = (piece.x + piece.vx * t).floor();
+                let y = (piece.y + piece.vy * t + GRAVITY * t * t / 2.0).floor();
+                let on_screen =
+                    (0.0..self.width as f64).contains(&x) && (0.0..self.height as f64).contains(&y);
+                on_screen.then(|| {
+                    (
+                        (x as u16 + 1, y as u16 + 1),
+                        Text::from(piece.c).with_color(piece.color),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn pieces_rise_and_fall() {
+        let now = Instant::now();
+        let confetti = Confetti::new(80, 24, &mut StdRng::seed_from_u64(1), now);
+        // thrown from below the screen
+        assert!(confetti.pieces(now).is_empty());
+
+        let rows = |at| -> Vec<u16> {
+            confetti
+                .pieces(at)
+                .iter()
+                .map(|((x, y), _)| {
+                    assert!((1..=80).contains(x) && (1..=24).contains(y));
+                    *y
+                })
+                .collect()
+        };
+        let rising = rows(now + Duration::from_millis(300));
+        assert!(!rising.is_empty());
+        assert!(rising.iter().all(|y| *y > 1));
+
+        assert!(!confetti.is_over(now + DURATION / 2));
+        assert!(confetti.is_over(now + DURATION));
+        assert!(rows(now + DURATION).is_empty());
+    }
+}
@@ -2,17 +2,47 @@
 //!
 //! Designed for command-line arguments using [`clap`], but can be used
 //! as a library too.
+//!
+//! Defaults for the command-line arguments can be set in a config file,
+//! see [`ToipeConfig::load`].
+
+use std::{
+    ffi::OsString,
+    fs,
+    io::{Error, ErrorKind, IsTerminal},
+    path::{Path, PathBuf},
+};
 
-use clap::{ArgEnum, Parser};
+use clap::{ArgEnum, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueHint};
 
-use crate::wordlists::BuiltInWordlist;
+use crate::{
+    challenge_board::DEFAULT_ADDRESS,
+    challenge_code, dirs, keyboard_comparison,
+    language::Language,
+    layout::Hand,
+    locale::Locale,
+    results::{
+        baseline,
+        plan::{Date, WpmTarget},
+        WpmFormula,
+    },
+    schedule::TimeOfDay,
+    sync::SyncRemote,
+    textgen::{Mix, NumberTemplate},
+    theme::Theme,
+    tui::{Challenge, Region},
+    user_wordlists::DEFAULT_INDEX_URL,
+    wordlists::BuiltInWordlist,
+};
 
 const CLI_HELP: &str = "A trusty terminal typing tester.
 
 Keyboard shortcuts:
 ctrl-c: quit
 ctrl-r: restart test with a new set of words
-ctrc-w: delete last word
+ctrl-u: retry the same words
+ctrl-w, ctrl-backspace, alt-backspace: delete last word
+ctrl-e: go back to the first uncorrected error
 ";
 
 /// Main configuration for Toipe.
@@ -23,16 +53,96 @@ pub struct ToipeConfig {
     #[clap(arg_enum, short, long, default_value_t = BuiltInWordlist::Top250)]
     pub wordlist: BuiltInWordlist,
 
-    /// Path to custom word list file.
+    /// Path to custom word list file, or the name of an installed word
+    /// list (see `toipe wordlist list`).
     ///
     /// This argument cannot be used along with `-w`/`--wordlist`
-    #[clap(short = 'f', long = "file", conflicts_with = "wordlist")]
+    #[clap(
+        short = 'f',
+        long = "file",
+        conflicts_with = "wordlist",
+        value_hint = ValueHint::FilePath
+    )]
     pub wordlist_file: Option<String>,
 
+    /// Shell command whose output is used as the text, such as
+    /// `fortune`. It is run again on every restart.
+    ///
+    /// This argument cannot be used along with `-w`/`--wordlist` or
+    /// `-f`/`--file`
+    #[clap(
+        long = "command",
+        value_name = "COMMAND",
+        conflicts_with_all = &["wordlist", "wordlist-file"]
+    )]
+    pub text_command: Option<String>,
+
+    /// Use the subjects of recent commits in this git repository (the
+    /// current directory by default) as quotes
+    #[clap(
+        long,
+        value_name = "REPO",
+        value_hint = ValueHint::DirPath,
+        conflicts_with_all = &["wordlist", "wordlist-file", "text-command"]
+    )]
+    pub git_log: Option<Option<String>>,
+
+    /// Use a random section of this file as the text on every restart,
+    /// such as a book or source file. The section has as many lines as
+    /// the number of words given with `-n`. Blank lines end paragraphs,
+    /// which are typed with enter
+    #[clap(
+        long,
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = &["wordlist", "wordlist-file", "text-command", "git-log"]
+    )]
+    pub file_random_section: Option<String>,
+
+    /// Practice typing code changes: shows a random hunk of this unified
+    /// diff (such as the output of `git diff`) on every restart, and only
+    /// the added lines are typed
+    #[clap(
+        long,
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = &["wordlist", "wordlist-file", "text-command", "git-log", "file-random-section"]
+    )]
+    pub diff: Option<String>,
+
+    /// Show line numbers next to the code with --diff
+    #[clap(long, requires = "diff")]
+    pub line_numbers: bool,
+
+    /// Show faint guides in the indentation of the code with --diff. The
+    /// guides are typed as spaces
+    #[clap(long, requires = "diff")]
+    pub indent_guides: bool,
+
+    /// Type the indentation of the code with --diff too, instead of
+    /// skipping over it
+    #[clap(long, requires = "diff")]
+    pub type_indentation: bool,
+
+    /// URL of an RSS or Atom feed whose headlines and summaries are used
+    /// as quotes
+    #[cfg(feature = "feeds")]
+    #[clap(
+        long,
+        value_name = "URL",
+        conflicts_with_all = &["wordlist", "wordlist-file", "text-command"]
+    )]
+    pub feed: Option<String>,
+
     /// Number of words to show on each test.
     #[clap(short, long, default_value_t = 30)]
     pub num_words: usize,
 
+    /// Timed test: the test ends after this many seconds instead of after
+    /// a number of words, with words added while typing
+    #[clap(long, value_name = "SECONDS", conflicts_with_all = &["num-words", "diff"])]
+    pub duration: Option<u64>,
+
     /// Whether to include punctuation
     #[clap(short, long)]
     pub punctuation: bool,
@@ -41,6 +151,15 @@ pub struct ToipeConfig {
     #[clap(long, default_value_t = 0.15)]
     pub punctuation_chance: f64,
 
+    /// Smallest number of words without punctuation between punctuated
+    /// words
+    #[clap(long, default_value_t = 0, value_name = "N")]
+    pub punctuation_cooldown: usize,
+
+    /// Largest number of punctuation marks on a word
+    #[clap(long, value_name = "N")]
+    pub max_marks_per_word: Option<usize>,
+
     /// Whether to include numbers
     #[clap(short = 'N', long)]
     pub numbers: bool,
@@ -53,23 +172,733 @@ pub struct ToipeConfig {
     #[clap(long, default_value_t = 9999)]
     pub number_max: u64,
 
+    /// Format of the generated numbers, such as `N,###` or `#.##`, where
+    /// `#` is any digit and `N` is any digit but 0. Can be given more
+    /// than once to pick one at random for each number
+    #[clap(long, value_name = "TEMPLATE")]
+    pub number_template: Vec<NumberTemplate>,
+
+    /// Write the generated numbers and quotes as is usual in this
+    /// language, such as `de` for `1.250,50` and `„word“` or `fr` for
+    /// `1 250,50` and `«word»`. Numbers from `--number-template` are left
+    /// as they are
+    #[clap(long, value_name = "TAG")]
+    pub locale: Option<Locale>,
+
+    /// Language of the word list, such as `tr` or `ja`, which decides how
+    /// sentences are capitalized, whether spaces are typed between words
+    /// and, without `--locale`, how numbers and quotes are written.
+    /// Guessed from the words of a custom word list by default
+    #[clap(long, value_name = "TAG")]
+    pub lang: Option<Language>,
+
+    /// Mix words, numbers and symbols into the text by weight, such as
+    /// `words:7,numbers:2,symbols:1`
+    #[clap(long, value_name = "MIX", conflicts_with = "numbers")]
+    pub mix: Option<Mix>,
+
+    /// Pipeline of named generators for the words, such as
+    /// `top250,numbers,punctuation`: a word list followed by any of
+    /// `numbers`, `punctuation` and `no-repeat`, which take their other
+    /// options as usual
+    #[clap(
+        long,
+        value_name = "NAMES",
+        conflicts_with_all = &["wordlist", "wordlist-file", "text-command", "git-log", "file-random-section", "numbers", "punctuation", "mix"]
+    )]
+    pub generators: Option<String>,
+
+    /// Seed for picking the words, numbers, punctuation and typos, to get
+    /// the same text on every run
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    /// Take the test of a challenge code shown on the results page, to
+    /// compete with others on the same text. The options in the code
+    /// replace those of the config file
+    #[clap(long, value_name = "CODE")]
+    pub challenge_code: Option<String>,
+
+    /// URL of a challenge board (see `toipe challenge serve`) to submit
+    /// the results of tests with a challenge code to
+    #[clap(long, value_name = "URL")]
+    pub challenge_server: Option<String>,
+
+    /// Name to submit results to the challenge board under. Defaults to
+    /// the name of the user
+    #[clap(long, value_name = "NAME")]
+    pub name: Option<String>,
+
+    /// One-hand drill: only words typed with the given hand on the
+    /// keyboard layout
+    #[clap(
+        arg_enum,
+        long,
+        value_name = "HAND",
+        conflicts_with_all = &["generators", "diff", "file-random-section", "git-log", "quote-mode"]
+    )]
+    pub one_hand: Option<Hand>,
+
+    /// Ignore another press of the same key within this many
+    /// milliseconds, for chattering switches or tremors
+    #[clap(long, value_name = "N")]
+    pub debounce_ms: Option<u64>,
+
+    /// Pressing this key shifts the next key, as if shift was held while
+    /// typing it. Press it twice to type the key itself
+    #[clap(long, value_name = "KEY")]
+    pub sticky_shift: Option<char>,
+
+    /// Novelty challenge showing the text differently, while it is
+    /// typed as usual
+    #[clap(arg_enum, long)]
+    pub challenge: Option<Challenge>,
+
+    /// Memory training: only the word being typed and the next one are
+    /// shown, the rest of the text is revealed while typing
+    #[clap(long)]
+    pub reveal: bool,
+
+    /// Memory training: each word fades out this many seconds after it is
+    /// shown, so that it has to be typed from memory
+    #[clap(long, value_name = "SECONDS")]
+    pub fade: Option<f64>,
+
+    /// Learning aid: once the start of a word is typed correctly and no
+    /// other word of the text starts the same way, the rest of the word
+    /// is brightened
+    #[clap(long)]
+    pub assist: bool,
+
+    /// Learning aid: each word is spoken when the cursor reaches it, by
+    /// this text-to-speech command given the word as its last argument,
+    /// such as `espeak-ng -v fr`. Without a command, `say` is used on
+    /// Mac OS and `espeak-ng` with the voice of `--lang` elsewhere
+    #[cfg(feature = "audio")]
+    #[clap(long, value_name = "COMMAND")]
+    pub pronounce: Option<Option<String>>,
+
+    /// Proofreading practice: the text is shown with typos, and the
+    /// corrected text must be typed
+    #[clap(long)]
+    pub proofread: bool,
+
+    /// Probability of a typo in each word with --proofread, from 0 to 1
+    #[clap(long, default_value_t = 0.3, parse(try_from_str = parse_probability))]
+    pub typo_chance: f64,
+
+    /// Continue with a warning when the word list has too few distinct
+    /// words, instead of exiting with an error
+    #[clap(long)]
+    pub allow_small_wordlist: bool,
+
+    /// Show every word in the word list once before repeating any word
+    #[clap(long)]
+    pub cover_all: bool,
+
+    /// Pick more words with the chars most often typed wrong in past
+    /// tests
+    #[clap(long, conflicts_with = "cover-all")]
+    pub practice: bool,
+
+    /// Mix the due words and phrases of this file, one per line, into
+    /// every test until each was typed without mistakes at
+    /// `--queue-wpm` `--queue-times` times. They are then removed from
+    /// the file
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub queue: Option<String>,
+
+    /// Speed in words per minute the words and phrases of `--queue` must
+    /// be typed at. Defaults to the speed measured by `toipe calibrate`,
+    /// or 40
+    #[clap(long, value_name = "WPM", requires = "queue")]
+    pub queue_wpm: Option<f64>,
+
+    /// Times the words and phrases of `--queue` must be typed well
+    /// before they are removed
+    #[clap(long, default_value_t = 3, value_name = "N", requires = "queue")]
+    pub queue_times: u64,
+
+    /// Do not repeat any of the last N generated words
+    #[clap(long, value_name = "N")]
+    pub no_repeat_window: Option<usize>,
+
+    /// Also show the speed adjusted for the difficulty of the text
+    #[clap(long)]
+    pub normalized_wpm: bool,
+
+    /// How to calculate the speed shown in the results
+    #[clap(arg_enum, long, default_value_t = WpmFormula::Net)]
+    pub wpm_formula: WpmFormula,
+
+    /// Number of decimal places to show for speed and accuracy
+    #[clap(long, default_value_t = 1, value_name = "DIGITS")]
+    pub precision: usize,
+
+    /// Keyboard layout: qwerty, dvorak, colemak, the name of a layout in
+    /// ~/.config/toipe/layouts or a path to a layout file
+    #[clap(long, default_value = "qwerty")]
+    pub layout: String,
+
+    /// Name of the keyboard the tests are typed on, recorded in the
+    /// history to compare keyboards with `toipe compare-keyboards`
+    #[clap(long, value_name = "NAME")]
+    pub keyboard: Option<String>,
+
+    /// Show more statistics after each test, such as reaction latency and
+    /// hand balance
+    #[clap(long)]
+    pub detailed_results: bool,
+
+    /// Race against a bot typing at this many words per minute
+    #[clap(long, value_name = "WPM", parse(try_from_str = parse_wpm))]
+    pub bot: Option<f64>,
+
+    /// How much the speed of the bot varies, as a fraction of its speed
+    /// (e.g. 0.1 for ±10%)
+    #[clap(long, default_value_t = 0.0)]
+    pub bot_noise: f64,
+
+    /// Pressing space in the middle of a word skips the rest of it. Skipped
+    /// chars are not counted as typed or as mistakes
+    #[clap(long)]
+    pub forgive: bool,
+
+    /// Go straight to the results at the end of a test, without the text
+    /// collapsing first
+    #[clap(long)]
+    pub no_animations: bool,
+
+    /// Review the typed text and the speed of each word before the
+    /// results
+    #[clap(long)]
+    pub review: bool,
+
+    /// Show a summary of past tests, such as the personal best and the
+    /// average speed, instead of taking a test
+    #[clap(long)]
+    pub stats: bool,
+
+    /// Watch a simulated typist take a test at this many words per minute
+    #[clap(long, value_name = "WPM", parse(try_from_str = parse_wpm))]
+    pub demo: Option<f64>,
+
+    /// Maximum number of times per second the screen is redrawn while
+    /// typing
+    #[clap(long, default_value_t = 30, value_name = "FPS")]
+    pub fps: u32,
+
+    /// After each session, sync the history, stats and installed word
+    /// lists with this git repository. An encrypted history needs the
+    /// same $TOIPE_PASSPHRASE on every synced machine
+    #[clap(long, value_name = "URL", conflicts_with = "sync-rclone")]
+    pub sync_git: Option<String>,
+
+    /// After each session, sync the history, stats and installed word
+    /// lists with this rclone remote, such as `drive:toipe`. An encrypted
+    /// history needs the same $TOIPE_PASSPHRASE on every synced machine
+    #[clap(long, value_name = "REMOTE")]
+    pub sync_rclone: Option<String>,
+
+    /// Opt in to sending anonymous usage statistics to this URL at most
+    /// once a day: the version of toipe, the operating system and how
+    /// many tests were taken in each mode, such as `punctuation`. Never
+    /// the text, keystrokes or results. Nothing is collected without it,
+    /// see `toipe telemetry`
+    #[clap(long, value_name = "URL")]
+    pub telemetry_endpoint: Option<String>,
+
+    /// Log every key typed, with timings, in
+    /// ~/.local/share/toipe/keystrokes
+    #[clap(long)]
+    pub keystroke_log: bool,
+
+    /// Append the keystrokes of each test to this CSV file, with when
+    /// they were typed, the expected and typed char, whether they were
+    /// correct and the milliseconds since the previous keystroke
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub export_keystrokes: Option<String>,
+
+    /// Remove keystroke logs older than this many days, or never if 0
+    #[clap(long, default_value_t = 30, value_name = "DAYS")]
+    pub keep_logs_days: u32,
+
+    /// Show a desktop notification when the session ends, and when the
+    /// tests for today of the plan made with `toipe plan` are done
+    #[cfg(feature = "notifications")]
+    #[clap(long)]
+    pub notify: bool,
+
+    /// Beat at this many keystrokes per minute while typing, shown above
+    /// the text (and clicking with the `audio` feature), to practice
+    /// typing at an even pace. 300 is 60 wpm
+    #[clap(long, value_name = "SPM")]
+    pub metronome: Option<u32>,
+
+    /// Remind to take a break after typing for this many minutes without
+    /// one. The timer is paused until the reminder is dismissed
+    #[clap(long, value_name = "MINUTES")]
+    pub break_after: Option<u64>,
+
+    /// Do not store anything on disk during this session, such as the
+    /// results in the history
+    #[clap(long)]
+    pub incognito: bool,
+
+    /// Write a debug log to this file, with a line of JSON for every key
+    /// press, update of the screen and choice made picking the text, to
+    /// look into display problems in a terminal
+    #[clap(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub log_file: Option<String>,
+
+    /// Include the chars typed in the debug log, which otherwise only
+    /// has the keys that do not type text (see `--log-file`)
+    #[clap(long, requires = "log-file")]
+    pub log_keys: bool,
+
     /// Whether to show hint for controls at the bottom of the screen
     #[clap(long)]
     pub show_hint: bool,
 
+    /// Show a keyboard of the layout (see `--layout`) below the text, with
+    /// the key to press next highlighted and the keys pressed colored by
+    /// how often
+    #[clap(long)]
+    pub show_keyboard: bool,
+
+    /// Show the text in a single row that scrolls as it is typed, and the
+    /// results in a single line, to fit in tiny terminals such as a tmux
+    /// pane
+    #[clap(long, conflicts_with_all = &["low-vision", "show-keyboard", "challenge"])]
+    pub mini: bool,
+
+    /// Show the progress and speed of the test in the title of the
+    /// terminal window, to follow it from a background tab or tmux pane
+    #[clap(long)]
+    pub window_title: bool,
+
     /// Preserve punctuation and whitespace in words
     #[clap(long)]
     pub quote_mode: bool,
 
-    #[clap(skip=termion::is_tty(&std::io::stdin().lock()))]
+    /// Show the text to be typed spaced out, in bold and in high-contrast
+    /// colors
+    #[clap(long)]
+    pub low_vision: bool,
+
+    /// Break long words that do not fit at the end of a line with a
+    /// hyphen, rather than moving them to the next line, for tighter
+    /// lines of text
+    #[clap(long)]
+    pub hyphenate: bool,
+
+    /// Config file with defaults for these options and the colors of the
+    /// theme. Defaults to ~/.config/toipe/config.toml
+    #[clap(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub config: Option<String>,
+
+    /// Colors of the UI, from the `[theme]` section of the config file
+    #[clap(skip)]
+    pub theme: Theme,
+
+    /// Part of the terminal to draw in, for applications embedding toipe
+    /// next to a UI of their own. The whole terminal if `None`, see
+    /// [`ToipeTui::set_region`](crate::tui::ToipeTui::set_region)
+    #[clap(skip)]
+    pub region: Option<Region>,
+
+    #[clap(skip=std::io::stdin().is_terminal())]
     pub is_stdin_tty: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<ToipeCommand>,
+}
+
+/// Commands that run instead of a typing test.
+#[derive(Subcommand)]
+pub enum ToipeCommand {
+    /// Manage installed word lists
+    #[clap(subcommand)]
+    Wordlist(WordlistCommand),
+    /// Manage the history of past tests
+    #[clap(subcommand)]
+    History(HistoryCommand),
+    /// Print a summary of all tests taken, such as the personal best and
+    /// the time spent typing
+    ///
+    /// With `--merge`, the tests in histories exported from other
+    /// machines are counted too, each result only once. The history is
+    /// not changed.
+    Stats {
+        /// History file copied from another machine, can be given more
+        /// than once
+        #[clap(long, value_name = "HISTORY", value_hint = ValueHint::FilePath)]
+        merge: Vec<String>,
+    },
+    /// Back up the configuration, history and installed word lists
+    Backup {
+        /// Archive to write, a .tar.gz file
+        #[clap(value_hint = ValueHint::FilePath)]
+        archive: String,
+    },
+    /// Restore a backup made with `toipe backup`, merging its history with
+    /// the current one
+    Restore {
+        /// Archive to read, a .tar.gz file
+        #[clap(value_hint = ValueHint::FilePath)]
+        archive: String,
+    },
+    /// Print the text of a test without running it, with the options
+    /// given before the command, e.g. `toipe -p generate --seed 42`
+    ///
+    /// With a seed, the same text is printed on every run, and
+    /// `toipe --seed` tests with it.
+    Generate {
+        /// Seed for picking the words, see `--seed`
+        #[clap(long)]
+        seed: Option<u64>,
+        /// Number of words, see `--num-words`
+        #[clap(long)]
+        words: Option<usize>,
+    },
+    /// Plan daily practice sessions to reach a typing speed by a date
+    ///
+    /// Replaces the current plan. How many sessions were done so far is
+    /// shown by `toipe --stats`.
+    Plan {
+        /// Speed to reach, e.g. 90wpm. Defaults to a fifth faster than the
+        /// speed measured by `toipe calibrate`
+        #[clap(long)]
+        target: Option<WpmTarget>,
+        /// Date to reach it by, as YYYY-MM-DD
+        #[clap(long)]
+        by: Date,
+    },
+    /// Compare results of challenge codes on a challenge board, see
+    /// `--challenge-server`
+    #[clap(subcommand)]
+    Challenge(ChallengeCommand),
+    /// Check the terminal, word list, config file and data directory,
+    /// and suggest fixes for what toipe cannot use
+    Doctor,
+    /// Take a short test that is the same for everyone, to measure the
+    /// speed and problem keys other options start from
+    ///
+    /// The options given before the command only change how the test is
+    /// shown and typed, such as `--layout`, not its text.
+    Calibrate,
+    /// Watch a simulated typist take test after test, with the options
+    /// given before the command, until a key is pressed
+    ///
+    /// Like `--demo`, but nothing is saved, so it can be used for
+    /// screenshots and screencasts or as a screensaver.
+    Demo {
+        /// Speed of the typist in words per minute
+        #[clap(long, default_value_t = 60.0)]
+        wpm: f64,
+        /// Seconds the results of each test are shown for
+        #[clap(long, default_value_t = 5, value_name = "SECONDS")]
+        pause: u64,
+    },
+    /// Compare typing on different keyboards, with the options given
+    /// before the command
+    ///
+    /// Runs rounds of one test per keyboard, asking to switch keyboards
+    /// before every test, then compares the speed and mistakes of the
+    /// tests tagged with each keyboard in the history, see `--keyboard`.
+    CompareKeyboards {
+        /// Names of the keyboards, the first of which the others are
+        /// compared with
+        #[clap(required = true, min_values = 2)]
+        keyboards: Vec<String>,
+        /// Number of tests on each keyboard
+        #[clap(long, default_value_t = 3)]
+        rounds: usize,
+        /// Only compare the tests already in the history
+        #[clap(long)]
+        report: bool,
+    },
+    /// Print the completion script of a shell, for the options and
+    /// commands of this version of toipe
+    ///
+    /// e.g. `toipe completions bash > ~/.local/share/bash-completion/completions/toipe`
+    Completions {
+        /// Shell to complete in
+        #[clap(arg_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Open a terminal with a test every day, with the options given
+    /// before the command, e.g. `toipe --practice schedule install --at
+    /// 09:00`
+    #[clap(subcommand)]
+    Schedule(ScheduleCommand),
+    /// Show whether anonymous usage statistics are sent, and the payload
+    /// that would be sent next, see `--telemetry-endpoint`
+    Telemetry,
+    /// Pick a theme, word list, number of words and keyboard layout step
+    /// by step, and save them to the config file
+    ///
+    /// Other options in the config file are kept.
+    Setup,
+    /// Print the man page of toipe, in roff
+    ///
+    /// e.g. `toipe manpage > ~/.local/share/man/man1/toipe.1`
+    Manpage,
+}
+
+/// Commands for challenge boards.
+///
+/// See [`crate::challenge_board`].
+#[derive(Subcommand)]
+pub enum ChallengeCommand {
+    /// List the best result of everyone who took the test of a challenge
+    /// code, from the board set with `--challenge-server`
+    Results {
+        /// Challenge code shown on the results page
+        code: String,
+    },
+    /// Serve a challenge board that results can be submitted to
+    Serve {
+        /// Address to listen on
+        #[clap(long, default_value = DEFAULT_ADDRESS)]
+        address: String,
+    },
+}
+
+/// Commands for scheduling daily practice.
+///
+/// See [`crate::schedule`].
+#[derive(Subcommand)]
+pub enum ScheduleCommand {
+    /// Install a user-level systemd timer (or launchd agent on macOS)
+    /// that opens a test every day, replacing the one installed before
+    Install {
+        /// Time of the day to practice at, as HH:MM
+        #[clap(long)]
+        at: TimeOfDay,
+        /// Terminal to open the test in with systemd, run as `<TERMINAL>
+        /// -e toipe ...`. Defaults to $TERMINAL, or else
+        /// x-terminal-emulator
+        #[clap(long)]
+        terminal: Option<String>,
+    },
+    /// Remove the scheduled practice
+    Remove,
+    /// Show when practice is scheduled and whether the timer is active
+    Status,
+}
+
+/// Commands for managing the history of past tests.
+///
+/// See [`crate::results::history`].
+#[derive(Subcommand)]
+pub enum HistoryCommand {
+    /// Encrypt the history, with the passphrase in $TOIPE_PASSPHRASE if
+    /// set, or else a random key stored in ~/.config/toipe/encryption.key
+    Encrypt,
+    /// Decrypt the history, undoing `toipe history encrypt`
+    Decrypt,
+}
+
+/// Commands for managing installed word lists.
+///
+/// See [`crate::user_wordlists`].
+#[derive(Subcommand)]
+pub enum WordlistCommand {
+    /// Download and install a word list from the index or a URL
+    Install {
+        /// Name of a word list in the index, or a URL to download from
+        name_or_url: String,
+
+        /// Name to install the word list as
+        #[clap(long)]
+        name: Option<String>,
+
+        /// Expected SHA-256 checksum of the downloaded file
+        ///
+        /// Word lists from the index are always verified against the
+        /// checksum in the index.
+        #[clap(long)]
+        sha256: Option<String>,
+
+        /// URL of the word list index
+        #[clap(long, default_value = DEFAULT_INDEX_URL)]
+        index: String,
+    },
+    /// List installed word lists
+    List {
+        /// List word lists available in the index instead
+        #[clap(long)]
+        available: bool,
+
+        /// URL of the word list index
+        #[clap(long, default_value = DEFAULT_INDEX_URL)]
+        index: String,
+    },
+    /// Report problems in a word list file, such as duplicate words or
+    /// invisible chars
+    Lint {
+        /// Path to the word list file
+        #[clap(value_hint = ValueHint::FilePath)]
+        file: String,
+    },
+    /// Remove an installed word list
+    Remove {
+        /// Name of the installed word list
+        name: String,
+    },
 }
 
 impl ToipeConfig {
+    /// Parses the command-line arguments, with defaults taken from the
+    /// config file (see `--config`), if there is one.
+    ///
+    /// The keys of the config file are the long names of the options,
+    /// such as `num-words = 50` or `punctuation = true`. Options given on
+    /// the command line take precedence over the file. The `[theme]`
+    /// section sets the colors of the UI, see [`Theme::with_table`].
+    pub fn load() -> Result<Self, Error> {
+        Self::load_from(std::env::args_os())
+    }
+
+    /// Like [`ToipeConfig::load`], with the given command-line arguments.
+    ///
+    /// Exits on invalid arguments or `--help`, like [`Parser::parse_from`].
+    pub fn load_from<I, T>(args: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let matches = Self::command().get_matches_from(&args);
+        let command_line = Self::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+        let path = command_line.config_file()?;
+        let has_file = command_line.config.is_some() || path.exists();
+        let code_options = match &command_line.challenge_code {
+            Some(code) => challenge_code::decode(code)?,
+            None => toml::value::Table::new(),
+        };
+        if !has_file && code_options.is_empty() {
+            let mut config = command_line;
+            config.theme = config.base_theme();
+            return Ok(config);
+        }
+        let file_name = path.display().to_string();
+        let invalid_in = |source: &str, msg: String| {
+            Error::new(ErrorKind::InvalidData, format!("{}: {}", source, msg))
+        };
+        let invalid = |msg: String| invalid_in(&file_name, msg);
+
+        let mut table = if has_file {
+            read_toml_file(&path)?
+        } else {
+            toml::value::Table::new()
+        };
+        let theme = match table.remove("theme") {
+            Some(toml::Value::Table(theme)) => theme,
+            Some(value) => {
+                return Err(invalid(format!(
+                    "`theme` must be a table, not {}",
+                    value.type_str()
+                )))
+            }
+            None => toml::value::Table::new(),
+        };
+        // the options of a challenge code take precedence over the file
+        for key in code_options.keys() {
+            table.remove(key);
+        }
+
+        let code_args = table_args(&code_options, &matches, |msg| {
+            invalid_in("challenge code", msg)
+        })?;
+        if !code_args.is_empty() {
+            let args = args
+                .iter()
+                .take(1)
+                .cloned()
+                .chain(code_args.iter().cloned());
+            Self::try_parse_from(args)
+                .map_err(|err| invalid_in("challenge code", err.to_string()))?;
+        }
+        let file_args = table_args(&table, &matches, invalid)?;
+
+        // options from the file go before the subcommand, if any
+        let args = args
+            .iter()
+            .take(1)
+            .cloned()
+            .chain(file_args)
+            .chain(code_args)
+            .chain(args.iter().skip(1).cloned());
+        let mut config = Self::try_parse_from(args).map_err(|err| invalid(err.to_string()))?;
+        config.theme = config
+            .base_theme()
+            .with_table(&theme)
+            .map_err(|err| invalid(err.to_string()))?;
+        Ok(config)
+    }
+
+    /// The config file, given with `--config` or else
+    /// `~/.config/toipe/config.toml`, which may not exist.
+    pub fn config_file(&self) -> Result<PathBuf, Error> {
+        match &self.config {
+            Some(path) => Ok(PathBuf::from(path)),
+            None => Ok(dirs::config_dir()?.join("config.toml")),
+        }
+    }
+
+    /// Options of the test of `toipe calibrate`, with the text of the
+    /// baseline and the preferences of `self` for showing and typing it.
+    pub fn calibration(&self) -> Self {
+        let mut config = Self::parse_from([
+            "toipe".to_string(),
+            "--wordlist=top250".to_string(),
+            format!("--num-words={}", baseline::NUM_WORDS),
+            format!("--seed={}", baseline::SEED),
+        ]);
+        config.layout = self.layout.clone();
+        config.keyboard = self.keyboard.clone();
+        config.debounce_ms = self.debounce_ms;
+        config.sticky_shift = self.sticky_shift;
+        config.wpm_formula = self.wpm_formula;
+        config.precision = self.precision;
+        config.no_animations = self.no_animations;
+        config.show_hint = self.show_hint;
+        config.show_keyboard = self.show_keyboard;
+        config.window_title = self.window_title;
+        config.low_vision = self.low_vision;
+        config.incognito = self.incognito;
+        config.theme = self.theme;
+        config
+    }
+
+    /// Theme the `[theme]` section of the config file changes.
+    fn base_theme(&self) -> Theme {
+        if self.low_vision {
+            Theme::high_contrast()
+        } else {
+            Theme::default()
+        }
+    }
+
     /// Name of the text used for typing test
     pub fn text_name(&self) -> String {
         if !self.is_stdin_tty {
             "stdin".to_string()
+        } else if let Some(command) = &self.text_command {
+            format!("command `{}`", command)
+        } else if let Some(path) = &self.diff {
+            format!("diff `{}`", path)
+        } else if let Some(path) = &self.file_random_section {
+            format!("random sections of `{}`", path)
+        } else if let Some(repo) = self.git_log_repo() {
+            format!("commits in {}", repo)
+        } else if let Some(url) = self.feed_url() {
+            format!("feed {}", url)
+        } else if let Some(names) = self.generator_names() {
+            format!("generators {}", names.join(","))
         } else if let Some(wordlist_file) = &self.wordlist_file {
             format!("custom file `{}`", wordlist_file)
         } else {
@@ -81,4 +910,537 @@ impl ToipeConfig {
             .to_string()
         }
     }
+
+    /// URL of the feed to take quotes from, see `--feed`.
+    ///
+    /// Always `None` without the `feeds` feature.
+    pub fn feed_url(&self) -> Option<&str> {
+        #[cfg(feature = "feeds")]
+        return self.feed.as_deref();
+        #[cfg(not(feature = "feeds"))]
+        return None;
+    }
+
+    /// Whether to show desktop notifications, see `--notify`.
+    ///
+    /// Always `false` without the `notifications` feature.
+    pub fn notify(&self) -> bool {
+        #[cfg(feature = "notifications")]
+        return self.notify;
+        #[cfg(not(feature = "notifications"))]
+        return false;
+    }
+
+    /// Whether to speak the words while typing them, see `--pronounce`,
+    /// and with which command if one was given.
+    ///
+    /// Always `None` without the `audio` feature.
+    pub fn pronounce(&self) -> Option<Option<&str>> {
+        #[cfg(feature = "audio")]
+        return self.pronounce.as_ref().map(Option::as_deref);
+        #[cfg(not(feature = "audio"))]
+        return None;
+    }
+
+    /// Repository to take commit subjects from, see `--git-log`.
+    pub fn git_log_repo(&self) -> Option<&str> {
+        self.git_log
+            .as_ref()
+            .map(|repo| repo.as_deref().unwrap_or("."))
+    }
+
+    /// Names of the generators given with `--generators`, if any.
+    pub fn generator_names(&self) -> Option<Vec<String>> {
+        self.generators.as_ref().map(|generators| {
+            generators
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+    }
+
+    /// Whether each line of the text is a quote to be typed as is, see
+    /// `--quote-mode`. Always the case for feeds, commit subjects and
+    /// sections of files.
+    pub fn is_quote_mode(&self) -> bool {
+        self.quote_mode
+            || self.git_log.is_some()
+            || self.file_random_section.is_some()
+            || self.feed_url().is_some()
+    }
+
+    /// Takes the words from the built-in `wordlist` instead of any other
+    /// source of text given, such as when that one has no words.
+    pub fn use_built_in_wordlist(&mut self, wordlist: BuiltInWordlist) {
+        self.wordlist = wordlist;
+        self.wordlist_file = None;
+        self.text_command = None;
+        self.git_log = None;
+        self.file_random_section = None;
+        self.quote_mode = false;
+        #[cfg(feature = "feeds")]
+        {
+            self.feed = None;
+        }
+    }
+
+    /// Whether the text of a test can be picked again from its seed and
+    /// the options, without files or earlier tests. See
+    /// [`ToipeConfig::challenge_options`].
+    pub fn is_reproducible(&self) -> bool {
+        self.is_stdin_tty
+            && self.wordlist_file.is_none()
+            && self.text_command.is_none()
+            && self.diff.is_none()
+            && !self.is_quote_mode()
+            && !self.practice
+            && self.queue.is_none()
+            && !self.cover_all
+            && self.no_repeat_window.is_none()
+    }
+
+    /// Options for the challenge code (see `--challenge-code`) of a test
+    /// whose text was picked with `seed`.
+    ///
+    /// `None` if the text could not be picked again from the options,
+    /// such as when it comes from a file or depends on earlier tests.
+    pub fn challenge_options(&self, seed: u64) -> Option<toml::value::Table> {
+        if !self.is_reproducible() {
+            return None;
+        }
+
+        let mut options = toml::value::Table::new();
+        let mut set = |key: &str, value: String| {
+            options.insert(key.to_string(), toml::Value::String(value));
+        };
+        set("seed", seed.to_string());
+        match self.generator_names() {
+            Some(names) => set("generators", names.join(",")),
+            None => set(
+                "wordlist",
+                self.wordlist
+                    .to_possible_value()
+                    .map_or_else(String::new, |value| value.get_name().to_string()),
+            ),
+        }
+        match self.duration {
+            Some(seconds) => set("duration", seconds.to_string()),
+            None => set("num-words", self.num_words.to_string()),
+        }
+        // options at their defaults are left out, for shorter codes
+        if self.layout != "qwerty" {
+            set("layout", self.layout.clone());
+        }
+        if let Some(hand) = self.one_hand.and_then(|hand| hand.to_possible_value()) {
+            set("one-hand", hand.get_name().to_string());
+        }
+        if self.punctuation {
+            set("punctuation-chance", self.punctuation_chance.to_string());
+            set(
+                "punctuation-cooldown",
+                self.punctuation_cooldown.to_string(),
+            );
+            if let Some(max_marks) = self.max_marks_per_word {
+                set("max-marks-per-word", max_marks.to_string());
+            }
+        }
+        if self.numbers || self.mix.is_some() {
+            set("number-chance", self.number_chance.to_string());
+            set("number-max", self.number_max.to_string());
+        }
+        if let Some(mix) = &self.mix {
+            set("mix", mix.to_string());
+        }
+        if let Some(locale) = &self.locale {
+            set("locale", locale.to_string());
+        }
+        if let Some(lang) = &self.lang {
+            set("lang", lang.to_string());
+        }
+        if self.proofread {
+            set("typo-chance", self.typo_chance.to_string());
+        }
+        if let Some(challenge) = self.challenge.and_then(|c| c.to_possible_value()) {
+            set("challenge", challenge.get_name().to_string());
+        }
+        if let Some(seconds) = self.fade {
+            set("fade", seconds.to_string());
+        }
+
+        let flags = [
+            ("punctuation", self.punctuation),
+            ("numbers", self.numbers),
+            ("proofread", self.proofread),
+            ("reveal", self.reveal),
+            ("assist", self.assist),
+            ("forgive", self.forgive),
+        ];
+        for (key, _) in flags.iter().filter(|(_, is_on)| *is_on) {
+            options.insert(key.to_string(), toml::Value::Boolean(true));
+        }
+        if !self.number_template.is_empty() {
+            let templates = self
+                .number_template
+                .iter()
+                .map(|template| toml::Value::String(template.to_string()))
+                .collect();
+            options.insert("number-template".to_string(), toml::Value::Array(templates));
+        }
+        Some(options)
+    }
+
+    /// Where to sync the data directory to after the session, if anywhere.
+    pub fn sync_remote(&self) -> Option<SyncRemote> {
+        self.sync_git
+            .clone()
+            .map(SyncRemote::Git)
+            .or_else(|| self.sync_rclone.clone().map(SyncRemote::Rclone))
+    }
+
+    /// Options that change the text or how the test is taken, such as
+    /// `punctuation=0.3`, for recording along with the results.
+    pub fn mode_flags(&self) -> Vec<String> {
+        let mut flags = vec![
+            match self.duration {
+                Some(seconds) => format!("duration={}", seconds),
+                None => format!("num_words={}", self.num_words),
+            },
+            format!("layout={}", self.layout),
+        ];
+        if let Some(keyboard) = &self.keyboard {
+            flags.push(keyboard_comparison::flag(keyboard));
+        }
+        if self.punctuation {
+            flags.push(format!("punctuation={}", self.punctuation_chance));
+            if self.punctuation_cooldown > 0 {
+                flags.push(format!(
+                    "punctuation_cooldown={}",
+                    self.punctuation_cooldown
+                ));
+            }
+            if let Some(max_marks) = self.max_marks_per_word {
+                flags.push(format!("max_marks_per_word={}", max_marks));
+            }
+        }
+        if self.diff.is_some() {
+            flags.push("diff".to_string());
+        }
+        if let Some(challenge) = self.challenge.and_then(|c| c.to_possible_value()) {
+            flags.push(format!("challenge={}", challenge.get_name()));
+        }
+        if let Some(debounce_ms) = self.debounce_ms {
+            flags.push(format!("debounce_ms={}", debounce_ms));
+        }
+        if let Some(key) = self.sticky_shift {
+            flags.push(format!("sticky_shift={}", key));
+        }
+        if let Some(hand) = self.one_hand.and_then(|hand| hand.to_possible_value()) {
+            flags.push(format!("one_hand={}", hand.get_name()));
+        }
+        if self.reveal {
+            flags.push("reveal".to_string());
+        }
+        if let Some(seconds) = self.fade {
+            flags.push(format!("fade={}", seconds));
+        }
+        if self.assist {
+            flags.push("assist".to_string());
+        }
+        if self.pronounce().is_some() {
+            flags.push("pronounce".to_string());
+        }
+        if self.proofread {
+            flags.push(format!("proofread={}", self.typo_chance));
+        }
+        if self.numbers {
+            flags.push(format!(
+                "numbers={}:{}",
+                self.number_chance, self.number_max
+            ));
+            for template in &self.number_template {
+                flags.push(format!("number_template={}", template));
+            }
+        }
+        if let Some(mix) = &self.mix {
+            flags.push(format!("mix={}", mix));
+        }
+        if let Some(locale) = &self.locale {
+            flags.push(format!("locale={}", locale));
+        }
+        if let Some(lang) = &self.lang {
+            flags.push(format!("lang={}", lang));
+        }
+        if let Some(names) = self.generator_names() {
+            flags.push(format!("generators={}", names.join(",")));
+        }
+        if self.cover_all {
+            flags.push("cover_all".to_string());
+        }
+        if self.practice {
+            flags.push("practice".to_string());
+        }
+        if self.queue.is_some() {
+            flags.push("queue".to_string());
+        }
+        if let Some(seed) = self.seed {
+            flags.push(format!("seed={}", seed));
+        }
+        if let Some(window) = self.no_repeat_window {
+            flags.push(format!("no_repeat_window={}", window));
+        }
+        if self.is_quote_mode() {
+            flags.push("quote_mode".to_string());
+        }
+        if self.forgive {
+            flags.push("forgive".to_string());
+        }
+        if let Some(wpm) = self.bot {
+            flags.push(format!("bot={}:{}", wpm, self.bot_noise));
+        }
+        if let Some(wpm) = self.demo {
+            flags.push(format!("demo={}", wpm));
+        }
+        flags
+    }
+}
+
+/// Position in `args` (the name of the program first) of the name of the
+/// command, if any: the first argument that is neither an option nor the
+/// value of one.
+///
+/// Found without parsing `args`, so that it works when they or the config
+/// file are invalid.
+pub fn command_index(args: &[String]) -> Option<usize> {
+    let config = ToipeConfig::command();
+    let takes_value = |is_option: &dyn Fn(&clap::Arg) -> bool| {
+        config
+            .get_arguments()
+            .any(|arg| is_option(arg) && arg.is_takes_value_set())
+    };
+
+    let mut args = args.iter().enumerate().skip(1).peekable();
+    while let Some((index, arg)) = args.next() {
+        let value_follows = if arg == "--" {
+            // only values follow
+            return None;
+        } else if let Some(long) = arg.strip_prefix("--") {
+            !long.contains('=') && takes_value(&|option| option.get_long() == Some(long))
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            // the first short option taking a value takes the rest of the
+            // argument, or the next one
+            shorts
+                .char_indices()
+                .find(|&(_, c)| takes_value(&|option| option.get_short() == Some(c)))
+                .is_some_and(|(at, c)| at + c.len_utf8() == shorts.len())
+        } else {
+            return Some(index);
+        };
+        // values do not start with `-`, and optional ones are left out then
+        if value_follows {
+            args.next_if(|(_, value)| !value.starts_with('-'));
+        }
+    }
+    None
+}
+
+/// Reads the TOML file at `path` as a table.
+pub fn read_toml_file(path: &Path) -> Result<toml::value::Table, Error> {
+    let contents = fs::read_to_string(path)?;
+    toml::from_str(&contents).map_err(|err| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), err),
+        )
+    })
+}
+
+/// `table` as a TOML document with a `key = value` line for each of its
+/// keys, and its nested tables last as TOML requires.
+pub fn to_toml_document(table: &toml::value::Table) -> String {
+    toml::to_string(&toml::Value::Table(table.clone())).expect("TOML tables can be written")
+}
+
+/// Arguments setting the options in `table`, keyed by their long names,
+/// except the ones given in `matches`.
+fn table_args(
+    table: &toml::value::Table,
+    matches: &ArgMatches,
+    invalid: impl Fn(String) -> Error,
+) -> Result<Vec<OsString>, Error> {
+    let command = ToipeConfig::command();
+    let mut args = Vec::new();
+    for (key, value) in table {
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(key.as_str()) && key != "config")
+            .ok_or_else(|| invalid(format!("unknown option `{}`", key)))?;
+        if matches.occurrences_of(arg.get_id()) > 0 {
+            continue;
+        }
+
+        let values = match value {
+            toml::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            let value = match value {
+                toml::Value::Boolean(false) => continue,
+                toml::Value::Boolean(true) => None,
+                toml::Value::String(s) => Some(s.clone()),
+                toml::Value::Integer(i) => Some(i.to_string()),
+                toml::Value::Float(f) => Some(f.to_string()),
+                value => {
+                    return Err(invalid(format!(
+                        "option `{}` cannot be {}",
+                        key,
+                        value.type_str()
+                    )))
+                }
+            };
+            args.push(OsString::from(format!("--{}", key)));
+            args.extend(value.map(OsString::from));
+        }
+    }
+    Ok(args)
+}
+
+/// Parses a speed in words per minute, which must be above 0.
+fn parse_wpm(wpm: &str) -> Result<f64, String> {
+    match wpm.parse::<f64>() {
+        Ok(wpm) if wpm.is_finite() && wpm > 0.0 => Ok(wpm),
+        Ok(_) => Err("must be a number above 0".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parses a probability from 0 to 1.
+fn parse_probability(chance: &str) -> Result<f64, String> {
+    match chance.parse::<f64>() {
+        Ok(chance) if (0.0..=1.0).contains(&chance) => Ok(chance),
+        Ok(_) => Err("must be from 0 to 1".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::theme::ThemeColor;
+
+    #[test]
+    fn finds_the_command() {
+        let index = |args: &[&str]| {
+            command_index(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+        };
+        assert_eq!(index(&["toipe", "doctor"]), Some(1));
+        assert_eq!(index(&["toipe", "-f", "doctor"]), None);
+        assert_eq!(index(&["toipe", "--file", "doctor", "doctor"]), Some(3));
+        assert_eq!(index(&["toipe", "--file=doctor", "doctor"]), Some(2));
+        assert_eq!(index(&["toipe", "-pn", "30", "schedule", "add"]), Some(3));
+        assert_eq!(index(&["toipe", "-pn30", "schedule"]), Some(2));
+        assert_eq!(index(&["toipe", "-p", "--", "doctor"]), None);
+        assert_eq!(index(&["toipe", "-p"]), None);
+        // without its optional value
+        assert_eq!(
+            index(&["toipe", "--git-log", "-n", "30", "doctor"]),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn loads_defaults_from_config_file() {
+        let path = std::env::temp_dir().join(format!("toipe-config-{}.toml", std::process::id()));
+        let load = |contents: &str, args: &[&str]| {
+            fs::write(&path, contents).unwrap();
+            let path = path.to_string_lossy();
+            ToipeConfig::load_from([&["toipe", "--config", &path], args].concat())
+        };
+
+        let config = load(
+            "num-words = 50\npunctuation = true\nwpm-formula = 'gross'\n[theme]\ncorrect = 'cyan'\n",
+            &["-n", "5"],
+        )
+        .unwrap();
+        assert_eq!(config.num_words, 5);
+        assert!(config.punctuation);
+        assert_eq!(config.wpm_formula, WpmFormula::Gross);
+        assert_eq!(config.theme.correct, ThemeColor::Ansi(6));
+
+        assert!(load("nope = 1", &[]).is_err());
+        assert!(load("theme = 'red'", &[]).is_err());
+        assert!(load("num-words = 'many'", &[]).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn validates_speeds_and_chances() {
+        let load = |args: &[&str]| ToipeConfig::try_parse_from([&["toipe"], args].concat());
+        assert_eq!(load(&["--bot", "60"]).unwrap().bot, Some(60.0));
+        for wpm in ["0", "-5", "inf", "NaN", "fast"] {
+            assert!(load(&["--bot", wpm]).is_err(), "--bot {}", wpm);
+            assert!(load(&["--demo", wpm]).is_err(), "--demo {}", wpm);
+        }
+        assert_eq!(load(&["--typo-chance", "1"]).unwrap().typo_chance, 1.0);
+        for chance in ["-0.1", "1.5", "NaN"] {
+            assert!(load(&["--typo-chance", chance]).is_err(), "{}", chance);
+        }
+    }
+
+    #[test]
+    fn challenge_codes_take_the_same_test() {
+        let path = std::env::temp_dir().join(format!("toipe-code-{}.toml", std::process::id()));
+        fs::write(&path, "num-words = 50\npunctuation-chance = 0.5\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+        let load = |args: &[&str]| {
+            let mut config =
+                ToipeConfig::load_from([&["toipe", "--config", &path], args].concat()).unwrap();
+            config.is_stdin_tty = true;
+            config
+        };
+
+        let config = load(&["-p", "-n", "12", "--one-hand", "left", "--reveal"]);
+        let code = challenge_code::encode(&config.challenge_options(7).unwrap());
+        let taken = load(&["--challenge-code", &code]);
+        assert_eq!(taken.seed, Some(7));
+        assert_eq!(taken.num_words, 12);
+        assert!(taken.punctuation);
+        assert_eq!(taken.one_hand, Some(Hand::Left));
+        assert!(taken.reveal);
+        assert_eq!(
+            taken.mode_flags(),
+            [config.mode_flags(), vec!["seed=7".to_string()]].concat()
+        );
+
+        let mut practice = config;
+        practice.practice = true;
+        assert!(practice.challenge_options(7).is_none());
+        assert!(ToipeConfig::load_from(["toipe", "--challenge-code", "oops"]).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn challenge_codes_only_set_test_options() {
+        let load = |document: &str| {
+            let options = toml::from_str(document).unwrap();
+            let code = challenge_code::encode(&options);
+            ToipeConfig::load_from(["toipe", "--challenge-code", &code])
+        };
+        for document in [
+            "command = \"echo pwned > /tmp/toipe_pwned\"\n",
+            "sync-git = \"https://example.com/data.git\"\n",
+            "log-file = \"/tmp/toipe.log\"\n",
+        ] {
+            let err = load(document).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput, "{}", document);
+        }
+
+        let err = load("num-words = \"many\"\n").err().unwrap();
+        assert!(err.to_string().starts_with("challenge code: "), "{}", err);
+        assert_eq!(load("num-words = \"12\"\n").unwrap().num_words, 12);
+    }
 }
@@ -0,0 +1,150 @@
+//! Debug log of what toipe does, see `--log-file`.
+//!
+//! Every entry is a line of JSON, with the milliseconds since the log was
+//! opened and the kind of event:
+//!
+//! ```text
+//! {"event":"key","key":"char","ms":1532,"position":12,"redraw":true}
+//! {"chars":1,"event":"flush","ms":1533,"scrolled":false}
+//! ```
+//!
+//! Events are input (`key`), what is drawn on the screen (`flush`,
+//! `redraw`, `reset_screen`, ...) and how the text is picked
+//! (`selector`, `words`), so that rendering bugs in terminals toipe was
+//! not tested with can be looked into from the log of a user. Typed chars
+//! are only logged as `char`, unless `--log-keys` is given.
+
+use std::{
+    env,
+    io::{Error, LineWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    backend::{terminal_size, Key},
+    storage,
+    theme::ColorDepth,
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static LOG: Mutex<Option<DebugLog>> = Mutex::new(None);
+
+struct DebugLog {
+    file: LineWriter<Box<dyn Write + Send>>,
+    opened_at: Instant,
+    log_keys: bool,
+}
+
+/// Logs the rest of the process to `path`, replacing it. Typed chars are
+/// logged as they are only with `log_keys`.
+pub fn open(path: &Path, log_keys: bool) -> Result<(), Error> {
+    let file = LineWriter::new(storage::create(path)?);
+    *LOG.lock().unwrap_or_else(|err| err.into_inner()) = Some(DebugLog {
+        file,
+        opened_at: Instant::now(),
+        log_keys,
+    });
+    ENABLED.store(true, Ordering::SeqCst);
+
+    // what the output depends on, the terminal most of all
+    let var = |name| env::var(name).ok().into();
+    let size = terminal_size()
+        .ok()
+        .map(|(width, height)| vec![width as u64, height as u64]);
+    log(
+        "open",
+        [
+            ("version", env!("CARGO_PKG_VERSION").into()),
+            ("os", env::consts::OS.into()),
+            ("term", var("TERM")),
+            ("term_program", var("TERM_PROGRAM")),
+            ("colorterm", var("COLORTERM")),
+            ("lang", var("LANG")),
+            ("depth", format!("{:?}", ColorDepth::detect()).into()),
+            ("size", size.into()),
+        ],
+    );
+    Ok(())
+}
+
+/// Whether events are logged, to skip working out their fields
+/// otherwise.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Logs `event` with `fields`, if the log is open.
+///
+/// Failing to write the log does not stop the test, the entry is lost.
+pub fn log<'a>(event: &str, fields: impl IntoIterator<Item = (&'a str, Value)>) {
+    if !is_enabled() {
+        return;
+    }
+    let mut log = LOG.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(log) = log.as_mut() {
+        let ms = log.opened_at.elapsed().as_millis() as u64;
+        writeln!(log.file, "{}", entry(event, ms, fields)).ok();
+    }
+}
+
+fn entry<'a>(event: &str, ms: u64, fields: impl IntoIterator<Item = (&'a str, Value)>) -> Value {
+    let mut object = Map::new();
+    object.insert("event".to_string(), event.into());
+    object.insert("ms".to_string(), ms.into());
+    for (name, value) in fields {
+        object.insert(name.to_string(), value);
+    }
+    object.into()
+}
+
+/// `key` as logged: shortcuts and keys that do not type anything as
+/// they are, typed chars only with `--log-keys`.
+pub fn key(key: Key) -> Value {
+    let log_keys = LOG
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .as_ref()
+        .is_some_and(|log| log.log_keys);
+    describe_key(key, log_keys).into()
+}
+
+fn describe_key(key: Key, log_keys: bool) -> String {
+    match key {
+        Key::Char('\n') => "enter".to_string(),
+        Key::Char('\t') => "tab".to_string(),
+        Key::Char(c) if log_keys => format!("char {:?}", c),
+        Key::Char(_) => "char".to_string(),
+        Key::Alt(c) => format!("alt-{}", c.escape_default()),
+        Key::Ctrl(c) => format!("ctrl-{}", c.escape_default()),
+        Key::F(n) => format!("f{}", n),
+        key => format!("{:?}", key).to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_chars_are_hidden() {
+        assert_eq!(describe_key(Key::Char('a'), false), "char");
+        assert_eq!(describe_key(Key::Char('a'), true), "char 'a'");
+        assert_eq!(describe_key(Key::Char('\n'), false), "enter");
+        assert_eq!(describe_key(Key::Ctrl('r'), false), "ctrl-r");
+        assert_eq!(describe_key(Key::Backspace, false), "backspace");
+    }
+
+    #[test]
+    fn entries_are_json_lines() {
+        let entry = entry("flush", 12, [("chars", 3usize.into())]);
+        assert_eq!(entry.to_string(), r#"{"chars":3,"event":"flush","ms":12}"#);
+    }
+}
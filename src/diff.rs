@@ -0,0 +1,265 @@
+//! Unified diffs for practicing typing code changes, see `--diff`.
+//!
+//! A random hunk of the diff is shown on each test. Only the added lines
+//! are typed, the `+`/`-` prefixes and the other lines are only shown.
+
+use std::{
+    io::{Error, ErrorKind},
+    ops::Range,
+};
+
+use crate::{
+    ansi::color,
+    tui::{DecoratedLine, Text},
+};
+use rand::{seq::SliceRandom, Rng};
+
+/// Number of columns a tab is expanded to.
+const TAB_WIDTH: usize = 4;
+
+/// Most lines a hunk can have to be shown.
+pub const MAX_HUNK_LINES: usize = 20;
+
+/// A line in a hunk of a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Added(String),
+    Removed(String),
+    Context(String),
+}
+
+/// A hunk of a unified diff: the `@@` header and the lines after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub header: String,
+    pub lines: Vec<DiffLine>,
+}
+
+impl Hunk {
+    /// The added lines, which are to be typed. Empty lines are left out
+    /// since there is nothing to type.
+    pub fn added_lines(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                DiffLine::Added(text) if !text.trim().is_empty() => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Line number of the first line of the hunk in the new file, from
+    /// the `@@ -a,b +c,d @@` header.
+    pub fn new_start(&self) -> Option<usize> {
+        let new_range = self.header.split(' ').find(|part| part.starts_with('+'))?;
+        new_range[1..].split(',').next()?.parse().ok()
+    }
+
+    /// Indices of the chars in the indentation of each added line, in the
+    /// added lines joined like the lines of [`Hunk::decorated_lines`].
+    pub fn indentation(&self) -> Vec<Range<usize>> {
+        let mut indentation = Vec::new();
+        let mut line_start = 0;
+        for line in self.added_lines() {
+            let indent = line.chars().take_while(|c| *c == ' ').count();
+            indentation.push(line_start..line_start + indent);
+            // the lines are joined with a space
+            line_start += line.chars().count() + 1;
+        }
+        indentation
+    }
+
+    /// Indices of the chars of the added lines where an indentation guide
+    /// is shown in place of a space: every [`TAB_WIDTH`] columns of the
+    /// indentation.
+    pub fn indent_guides(&self) -> Vec<usize> {
+        self.indentation()
+            .into_iter()
+            .flat_map(|indent| indent.step_by(TAB_WIDTH))
+            .collect()
+    }
+
+    /// Lines to display, with the added lines to be typed.
+    ///
+    /// All typed lines but the last end with a space, like the lines of
+    /// words. With `line_numbers`, the added and context lines are
+    /// numbered as in the new file.
+    pub fn decorated_lines(&self, line_numbers: bool) -> Vec<DecoratedLine> {
+        let num_typed = self.added_lines().len();
+        let mut typed = 0;
+        let mut line_number = self.new_start().unwrap_or(1);
+        // numbers are right-aligned to the widest one
+        let number_width = (line_number + self.lines.len()).to_string().len();
+
+        let mut lines = vec![DecoratedLine {
+            decoration: vec![Text::from(self.header.as_str())
+                .with_color(color::Cyan)
+                .with_faint()],
+            text: None,
+            ending: Vec::new(),
+        }];
+        for line in &self.lines {
+            let mut decorated = match line {
+                DiffLine::Added(text) if !text.trim().is_empty() => {
+                    typed += 1;
+                    let text = if typed < num_typed {
+                        format!("{} ", text)
+                    } else {
+                        text.clone()
+                    };
+                    DecoratedLine {
+                        decoration: vec![Text::from("+").with_color(color::Green)],
+                        text: Some(Text::from(text).with_faint()),
+                        ending: Vec::new(),
+                    }
+                }
+                DiffLine::Added(_) => DecoratedLine {
+                    decoration: vec![Text::from("+").with_color(color::Green)],
+                    text: None,
+                    ending: Vec::new(),
+                },
+                DiffLine::Removed(text) => DecoratedLine {
+                    decoration: vec![Text::from(format!("-{}", text))
+                        .with_color(color::Red)
+                        .with_faint()],
+                    text: None,
+                    ending: Vec::new(),
+                },
+                DiffLine::Context(text) => DecoratedLine {
+                    decoration: vec![Text::from(format!(" {}", text)).with_faint()],
+                    text: None,
+                    ending: Vec::new(),
+                },
+            };
+            if line_numbers {
+                // removed lines are not in the new file
+                let number = match line {
+                    DiffLine::Removed(_) => String::new(),
+                    _ => {
+                        line_number += 1;
+                        (line_number - 1).to_string()
+                    }
+                };
+                decorated.decoration.insert(
+                    0,
+                    Text::from(format!("{:>1$} ", number, number_width)).with_faint(),
+                );
+            }
+            lines.push(decorated);
+        }
+        lines
+    }
+}
+
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::new();
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - expanded.chars().count() % TAB_WIDTH;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
+
+/// Parses the hunks of a unified diff, such as the output of `git diff`.
+///
+/// File headers and other lines outside of hunks are ignored. Tabs are
+/// expanded and trailing whitespace is removed.
+pub fn parse(diff: &str) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            hunks.extend(current.take());
+            current = Some(Hunk {
+                header: line.to_string(),
+                lines: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some(hunk) = &mut current else {
+            continue;
+        };
+        let text = || expand_tabs(&line[1..]).trim_end().to_string();
+        match line.chars().next() {
+            Some('+') if !line.starts_with("+++ ") => hunk.lines.push(DiffLine::Added(text())),
+            Some('-') if !line.starts_with("--- ") => hunk.lines.push(DiffLine::Removed(text())),
+            Some(' ') => hunk.lines.push(DiffLine::Context(text())),
+            Some('\\') => {} // "\ No newline at end of file"
+            None => hunk.lines.push(DiffLine::Context(String::new())),
+            // the next file starts
+            _ => hunks.extend(current.take()),
+        }
+    }
+    hunks.extend(current);
+
+    hunks
+}
+
+/// Picks a random hunk that adds lines and is small enough to be shown.
+pub fn random_hunk<'a>(hunks: &'a [Hunk], rng: &mut impl Rng) -> Result<&'a Hunk, Error> {
+    let candidates: Vec<&Hunk> = hunks
+        .iter()
+        .filter(|hunk| !hunk.added_lines().is_empty() && hunk.lines.len() < MAX_HUNK_LINES)
+        .collect();
+    candidates.choose(rng).copied().ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "The diff has no hunks with added lines and at most {} lines",
+                MAX_HUNK_LINES
+            ),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_diffs() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1234567..89abcde 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
+ fn main() {
+-    old();
++\tnew();  
++
+ }
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -1 +1 @@
+-Hello
++Hello, world
+\\ No newline at end of file
+";
+        let hunks = parse(diff);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(
+            hunks[0].lines,
+            vec![
+                DiffLine::Context("fn main() {".to_string()),
+                DiffLine::Removed("    old();".to_string()),
+                DiffLine::Added("    new();".to_string()),
+                DiffLine::Added(String::new()),
+                DiffLine::Context("}".to_string()),
+            ]
+        );
+        assert_eq!(hunks[0].added_lines(), vec!["    new();"]);
+        assert_eq!(hunks[0].new_start(), Some(1));
+        assert_eq!(hunks[0].indentation(), vec![0..4]);
+        assert_eq!(hunks[0].indent_guides(), vec![0]);
+        assert_eq!(hunks[1].header, "@@ -1 +1 @@");
+        assert_eq!(hunks[1].added_lines(), vec!["Hello, world"]);
+    }
+}
@@ -0,0 +1,68 @@
+//! Locations of the files toipe stores on disk.
+//!
+//! Follows the [XDG Base Directory
+//! Specification](https://specifications.freedesktop.org/basedir-spec/latest/)
+//! on all platforms.
+
+use std::{
+    env,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+/// Name of the directory created inside the base directories.
+const APP_DIR_NAME: &str = "toipe";
+
+fn home_dir() -> Result<PathBuf, Error> {
+    // Windows has no $HOME
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Could not determine home directory"))
+}
+
+/// Returns `$<env_var>` if it is set to an absolute path, otherwise
+/// `$HOME/<fallback>`.
+fn base_dir(env_var: &str, fallback: &str) -> Result<PathBuf, Error> {
+    match env::var_os(env_var).map(PathBuf::from) {
+        Some(path) if path.is_absolute() => Ok(path),
+        _ => Ok(home_dir()?.join(fallback)),
+    }
+}
+
+/// Directory for user configuration such as keyboard layouts.
+///
+/// Usually `~/.config/toipe`. Not created if missing.
+pub fn config_dir() -> Result<PathBuf, Error> {
+    Ok(base_dir("XDG_CONFIG_HOME", ".config")?.join(APP_DIR_NAME))
+}
+
+/// Directory containing custom keyboard layout files.
+///
+/// Usually `~/.config/toipe/layouts`. Not created if missing.
+pub fn layouts_dir() -> Result<PathBuf, Error> {
+    Ok(config_dir()?.join("layouts"))
+}
+
+/// Directory for user data such as installed word lists.
+///
+/// Usually `~/.local/share/toipe`. Not created if missing.
+pub fn data_dir() -> Result<PathBuf, Error> {
+    Ok(base_dir("XDG_DATA_HOME", ".local/share")?.join(APP_DIR_NAME))
+}
+
+/// Directory for files that can be downloaded or computed again, such
+/// as feeds.
+///
+/// Usually `~/.cache/toipe`. Not created if missing.
+pub fn cache_dir() -> Result<PathBuf, Error> {
+    Ok(base_dir("XDG_CACHE_HOME", ".cache")?.join(APP_DIR_NAME))
+}
+
+/// Directory containing word lists installed with `toipe wordlist install`.
+///
+/// Usually `~/.local/share/toipe/wordlists`. Not created if missing.
+pub fn wordlists_dir() -> Result<PathBuf, Error> {
+    Ok(data_dir()?.join("wordlists"))
+}
@@ -0,0 +1,342 @@
+//! Checks of what toipe needs to run well, see `toipe doctor`.
+//!
+//! Each check reports what it found and, when something is wrong, how to
+//! fix it. Problems with the terminal usually show up as a garbled UI,
+//! so they are checked first.
+
+use std::{
+    env, fs,
+    io::{stdin, stdout, Error, IsTerminal},
+    path::Path,
+};
+
+use crate::{
+    backend::{terminal_size, Backend, Terminal},
+    config::ToipeConfig,
+    dirs,
+    theme::ColorDepth,
+    user_wordlists, Toipe,
+};
+
+/// Smallest terminal the usual layout fits in, see `--mini` for smaller
+/// ones.
+const MIN_SIZE: (u16, u16) = (50, 10);
+
+/// Outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// toipe runs, but not as well as it could
+    Warning,
+    /// toipe does not run, or not as intended
+    Failed,
+}
+
+/// What a check found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub found: String,
+    /// what to do about it, if it is not ok
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, found: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Ok,
+            found: found.into(),
+            fix: None,
+        }
+    }
+
+    fn warning(name: &'static str, found: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warning,
+            found: found.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn failed(name: &'static str, found: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Failed,
+            found: found.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Runs the checks and prints what they found, with `config` as loaded
+/// from the command line and the config file.
+///
+/// Fails if any check failed.
+pub fn run(config: Result<&ToipeConfig, &Error>) -> Result<(), Error> {
+    let checks = checks(config);
+    for check in &checks {
+        let mark = match check.status {
+            Status::Ok => "✓",
+            Status::Warning => "!",
+            Status::Failed => "✗",
+        };
+        println!("{} {}: {}", mark, check.name, check.found);
+        if let Some(fix) = &check.fix {
+            println!("    {}", fix);
+        }
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == Status::Failed)
+        .count();
+    match failed {
+        0 => Ok(()),
+        1 => Err(Error::other("1 check failed")),
+        _ => Err(Error::other(format!("{} checks failed", failed))),
+    }
+}
+
+/// All checks, in the order they are shown.
+pub fn checks(config: Result<&ToipeConfig, &Error>) -> Vec<Check> {
+    let var = |name| env::var(name).unwrap_or_default();
+    let mut checks = vec![terminal_check()];
+    if stdout().is_terminal() {
+        checks.push(raw_mode_check());
+        checks.push(size_check(terminal_size().ok()));
+    }
+    checks.push(term_check(&var("TERM")));
+    checks.push(color_check(ColorDepth::detect()));
+    checks.push(unicode_check(&locale()));
+    checks.push(config_check(config));
+    if let Ok(config) = config {
+        checks.push(wordlist_check(config));
+    }
+    checks.push(data_dir_check());
+    checks
+}
+
+fn terminal_check() -> Check {
+    let name = "Terminal";
+    if !stdout().is_terminal() {
+        Check::failed(
+            name,
+            "output is not a terminal",
+            "run toipe directly in a terminal, without redirecting its output",
+        )
+    } else if !stdin().is_terminal() && !Path::new("/dev/tty").exists() && !Terminal::READS_CONSOLE
+    {
+        Check::failed(
+            name,
+            "keys cannot be read, input is not a terminal",
+            "run toipe with input from a terminal",
+        )
+    } else {
+        Check::ok(name, "output is a terminal")
+    }
+}
+
+fn raw_mode_check() -> Check {
+    let name = "Raw mode";
+    // raw mode is turned off again when dropped
+    match Terminal::raw_stdout() {
+        Ok(_) => Check::ok(name, "supported"),
+        Err(err) => Check::failed(
+            name,
+            format!("could not be turned on: {}", err),
+            "run toipe in a terminal emulator rather than through a pipe or an IDE console",
+        ),
+    }
+}
+
+fn size_check(size: Option<(u16, u16)>) -> Check {
+    let name = "Size";
+    match size {
+        Some((width, height)) if width >= MIN_SIZE.0 && height >= MIN_SIZE.1 => {
+            Check::ok(name, format!("{}×{}", width, height))
+        }
+        Some((width, height)) => Check::warning(
+            name,
+            format!(
+                "{}×{} is smaller than {}×{}",
+                width, height, MIN_SIZE.0, MIN_SIZE.1
+            ),
+            "make the window larger, or use --mini to type in a single row",
+        ),
+        None => Check::failed(
+            name,
+            "unknown",
+            "run toipe in a terminal emulator that reports its size",
+        ),
+    }
+}
+
+/// Whether the terminal type supports moving the cursor and clearing the
+/// screen, which toipe draws its UI with.
+fn term_check(term: &str) -> Check {
+    let name = "Terminal type";
+    match term {
+        "" if cfg!(windows) => Check::ok(name, "Windows console"),
+        "" => Check::warning(
+            name,
+            "$TERM is not set",
+            "set $TERM to the type of your terminal, e.g. TERM=xterm-256color",
+        ),
+        "dumb" => Check::failed(
+            name,
+            "dumb, without cursor movement",
+            "run toipe in a terminal emulator, or set $TERM to its type",
+        ),
+        _ => Check::ok(name, term),
+    }
+}
+
+fn color_check(depth: ColorDepth) -> Check {
+    let name = "Colors";
+    match depth {
+        ColorDepth::TrueColor => Check::ok(name, "24-bit"),
+        ColorDepth::Ansi256 => Check::ok(name, "256 colors"),
+        ColorDepth::Ansi16 => Check::warning(
+            name,
+            "16 colors, RGB colors of themes are approximated",
+            "set COLORTERM=truecolor if your terminal supports 24-bit colors",
+        ),
+    }
+}
+
+/// The locale of chars, as in `$LC_ALL`, `$LC_CTYPE` and `$LANG`.
+fn locale() -> String {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+}
+
+/// Whether chars outside of ASCII, such as the cursor and the on-screen
+/// keyboard, are shown one column wide as toipe expects.
+fn unicode_check(locale: &str) -> Check {
+    let name = "Unicode";
+    let normalized = locale.to_lowercase().replace('-', "");
+    if normalized.contains("utf8") || cfg!(windows) {
+        Check::ok(name, "UTF-8")
+    } else {
+        Check::warning(
+            name,
+            match locale {
+                "" => "no locale set".to_string(),
+                locale => format!("locale {} is not UTF-8", locale),
+            },
+            "set a UTF-8 locale, e.g. LANG=en_US.UTF-8, or symbols may be misaligned",
+        )
+    }
+}
+
+fn config_check(config: Result<&ToipeConfig, &Error>) -> Check {
+    let name = "Config";
+    let path = config
+        .ok()
+        .and_then(|config| config.config.clone())
+        .map(Into::into)
+        .or_else(|| Some(dirs::config_dir().ok()?.join("config.toml")));
+    match (config, path) {
+        (Err(err), _) => Check::failed(
+            name,
+            err.to_string(),
+            "fix or remove the option in the config file",
+        ),
+        (Ok(_), Some(path)) if path.exists() => Check::ok(name, path.display().to_string()),
+        (Ok(_), _) => Check::ok(name, "no config file, the defaults are used"),
+    }
+}
+
+/// Whether the text of a test can be picked with the options in
+/// `config`, such as from the word list it uses.
+fn wordlist_check(config: &ToipeConfig) -> Check {
+    let name = "Word list";
+    let installed = user_wordlists::list_installed().unwrap_or_default().len();
+    match Toipe::generate(config) {
+        Ok(_) => Check::ok(
+            name,
+            format!(
+                "{} available, {} installed with `toipe wordlist install`",
+                config.text_name(),
+                installed
+            ),
+        ),
+        Err(err) => Check::failed(
+            name,
+            format!("no text for a test: {}", err),
+            "pick another one with --wordlist, or list installed ones with `toipe wordlist list`",
+        ),
+    }
+}
+
+/// Whether results can be saved to the data directory.
+fn data_dir_check() -> Check {
+    let name = "Data directory";
+    let dir = match dirs::data_dir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            return Check::failed(
+                name,
+                err.to_string(),
+                "set $HOME or $XDG_DATA_HOME to a directory you can write to",
+            )
+        }
+    };
+    let probe = dir.join(".doctor");
+    let written = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .and_then(|_| fs::remove_file(&probe));
+    match written {
+        Ok(()) => Check::ok(name, format!("{} is writable", dir.display())),
+        Err(err) => Check::failed(
+            name,
+            format!("cannot write to {}: {}", dir.display(), err),
+            format!(
+                "make {} writable, or use --incognito to save nothing",
+                dir.display()
+            ),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terminal_checks() {
+        assert_eq!(size_check(Some((80, 24))).status, Status::Ok);
+        let small = size_check(Some((40, 5)));
+        assert_eq!(small.status, Status::Warning);
+        assert!(small.fix.unwrap().contains("--mini"));
+        assert_eq!(size_check(None).status, Status::Failed);
+
+        assert_eq!(term_check("dumb").status, Status::Failed);
+        assert_eq!(term_check("xterm-256color").status, Status::Ok);
+        assert_eq!(color_check(ColorDepth::Ansi16).status, Status::Warning);
+    }
+
+    #[test]
+    fn unicode_locales() {
+        assert_eq!(unicode_check("en_US.UTF-8").status, Status::Ok);
+        assert_eq!(unicode_check("C.utf8").status, Status::Ok);
+        if !cfg!(windows) {
+            assert_eq!(unicode_check("C").status, Status::Warning);
+            assert_eq!(unicode_check("").found, "no locale set");
+        }
+    }
+
+    #[test]
+    fn invalid_configs_fail() {
+        let err = Error::other("config.toml: unknown option `nope`");
+        let check = config_check(Err(&err));
+        assert_eq!(check.status, Status::Failed);
+        assert!(check.found.contains("nope"));
+    }
+}
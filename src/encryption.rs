@@ -0,0 +1,176 @@
+//! At-rest encryption of stored data.
+//!
+//! Data is encrypted with XChaCha20-Poly1305, using a key derived with
+//! Argon2 from either
+//!
+//! - the passphrase in `$TOIPE_PASSPHRASE`, if set, or
+//! - a random key stored in [`key_file`], created by [`create_key_file`].
+//!
+//! Keys are not read from the keychain of the OS, only from these two
+//! places.
+//!
+//! Encrypted data starts with [`MAGIC`], followed by the random salt of
+//! the key and the random nonce. Being authenticated, decrypting with the
+//! wrong key or tampered data fails instead of returning garbage.
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    XChaCha20Poly1305, XNonce,
+};
+use rand::RngCore;
+
+use crate::{dirs, storage};
+
+/// Environment variable holding the passphrase to use instead of the key
+/// file.
+pub const PASSPHRASE_VAR: &str = "TOIPE_PASSPHRASE";
+
+/// Start of encrypted data, with the version of its format.
+const MAGIC: &[u8] = b"toipe-encrypted-1\n";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// File holding the random key used when no passphrase is given.
+///
+/// Usually `~/.config/toipe/encryption.key`.
+pub fn key_file() -> Result<PathBuf, Error> {
+    Ok(dirs::config_dir()?.join("encryption.key"))
+}
+
+/// Creates [`key_file`] with a random key, readable only by the user.
+///
+/// Does nothing if a passphrase is set or the key file already exists.
+pub fn create_key_file() -> Result<(), Error> {
+    let path = key_file()?;
+    if std::env::var_os(PASSPHRASE_VAR).is_some() || path.exists() {
+        return Ok(());
+    }
+
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    let key: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+
+    storage::write_private(&path, key.as_bytes())
+}
+
+/// The passphrase, or else the contents of the key file.
+fn secret() -> Result<Vec<u8>, Error> {
+    if let Some(passphrase) = std::env::var_os(PASSPHRASE_VAR) {
+        return Ok(passphrase.to_string_lossy().into_owned().into_bytes());
+    }
+
+    let path = key_file()?;
+    fs::read(&path).map_err(|err| match err.kind() {
+        ErrorKind::NotFound => Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "No encryption key found. Set ${} or restore {}",
+                PASSPHRASE_VAR,
+                path.display()
+            ),
+        ),
+        _ => err,
+    })
+}
+
+/// The cipher keyed with `secret` and `salt`.
+fn cipher(secret: &[u8], salt: &[u8]) -> Result<XChaCha20Poly1305, Error> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|err| Error::new(ErrorKind::InvalidInput, err.to_string()))?;
+    Ok(XChaCha20Poly1305::new(&key.into()))
+}
+
+/// Encrypts `data` with a key derived from `secret`.
+fn encrypt_with(secret: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let encrypted = cipher(secret, &salt)?
+        .encrypt(XNonce::from_slice(&nonce), data)
+        .map_err(|_| Error::other("Could not encrypt"))?;
+    Ok([MAGIC, &salt, &nonce, &encrypted].concat())
+}
+
+/// Decrypts data encrypted by [`encrypt_with`] with the same `secret`.
+fn decrypt_with(secret: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    let data = data
+        .strip_prefix(MAGIC)
+        .filter(|data| data.len() >= SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Not encrypted by toipe"))?;
+    let (salt, data) = data.split_at(SALT_LEN);
+    let (nonce, encrypted) = data.split_at(NONCE_LEN);
+
+    cipher(secret, salt)?
+        .decrypt(XNonce::from_slice(nonce), encrypted)
+        .map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                "Wrong encryption key, or the data was modified",
+            )
+        })
+}
+
+/// Encrypts `data` with the current key.
+pub fn encrypt(data: &[u8]) -> Result<Vec<u8>, Error> {
+    encrypt_with(&secret()?, data)
+}
+
+/// Decrypts data encrypted by [`encrypt`].
+pub fn decrypt(data: &[u8]) -> Result<Vec<u8>, Error> {
+    decrypt_with(&secret()?, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = b"2022-01-01T00:00:00Z\t42.0\n";
+        let encrypted = encrypt_with(b"secret", data).unwrap();
+        assert!(!encrypted.windows(data.len()).any(|w| w == data));
+        assert_eq!(decrypt_with(b"secret", &encrypted).unwrap(), data);
+        assert_eq!(
+            decrypt_with(b"secret", &encrypt_with(b"secret", b"").unwrap()).unwrap(),
+            b""
+        );
+    }
+
+    #[test]
+    fn fails_with_the_wrong_key() {
+        let encrypted = encrypt_with(b"secret", b"history").unwrap();
+        let err = decrypt_with(b"guess", &encrypted).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn fails_with_tampered_data() {
+        let encrypted = encrypt_with(b"secret", b"history").unwrap();
+        // a byte of the salt, nonce, ciphertext and tag
+        let salt = MAGIC.len();
+        for i in [
+            salt,
+            salt + SALT_LEN,
+            salt + SALT_LEN + NONCE_LEN,
+            encrypted.len() - 1,
+        ] {
+            let mut tampered = encrypted.clone();
+            tampered[i] ^= 1;
+            assert!(decrypt_with(b"secret", &tampered).is_err(), "byte {}", i);
+        }
+        assert!(decrypt_with(b"secret", &encrypted[..encrypted.len() - 1]).is_err());
+        assert!(decrypt_with(b"secret", b"history").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! Input events for the typing test.
+//!
+//! Keys are read on a separate thread so that the typing test can also
+//! be woken up periodically by [`ToipeEvent::Tick`]s, e.g. to animate
+//! things while the user is not typing.
+
+use std::{
+    collections::HashMap,
+    io,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::{Key, Keys},
+    debug_log,
+    layout::KeyboardLayout,
+};
+
+/// How often the number of wakeups is written to the debug log.
+const WAKEUPS_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Something that happened during a typing test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToipeEvent {
+    /// a key was pressed
+    Key(Key),
+    /// some time passed without a key being pressed
+    Tick,
+}
+
+impl From<Key> for ToipeEvent {
+    fn from(key: Key) -> Self {
+        ToipeEvent::Key(key)
+    }
+}
+
+/// A key that shifts the next key pressed, for typists who cannot hold
+/// shift while pressing another key.
+pub struct StickyShift {
+    key: char,
+    /// chars typed with shift, by the char typed by the same key without
+    shifted: HashMap<char, char>,
+    /// whether the next key is shifted
+    armed: bool,
+}
+
+impl StickyShift {
+    /// Makes `key` shift the next key, as it would be shifted in
+    /// `layout`.
+    pub fn new(key: char, layout: &KeyboardLayout) -> Self {
+        let shifted = layout
+            .rows
+            .iter()
+            .flat_map(|row| &row.keys)
+            .filter_map(|key| Some((key.key, key.shifted?)))
+            .collect();
+        Self {
+            key,
+            shifted,
+            armed: false,
+        }
+    }
+
+    /// The key typed when `key` is pressed, or `None` if it only shifts
+    /// the next key.
+    fn remap(&mut self, key: Key) -> Option<Key> {
+        let armed = std::mem::take(&mut self.armed);
+        match key {
+            Key::Char(c) if c == self.key => {
+                // pressed twice to type the key itself
+                self.armed = !armed;
+                armed.then_some(key)
+            }
+            Key::Char(c) if armed => Some(Key::Char(self.shifted.get(&c).copied().unwrap_or(c))),
+            key => Some(key),
+        }
+    }
+}
+
+/// Stream of [`ToipeEvent`]s from a terminal.
+///
+/// Yields a [`ToipeEvent::Tick`] whenever no key is pressed for the
+/// tick rate. Without a tick rate, only key events are yielded.
+pub struct EventStream {
+    /// keys with when they were read
+    keys: Receiver<(Instant, io::Result<Key>)>,
+    /// key received by [`EventStream::has_pending`] but not yielded yet
+    pending: Option<io::Result<Key>>,
+    tick_rate: Option<Duration>,
+    /// presses of the same key within this window are ignored
+    debounce: Option<Duration>,
+    /// last key yielded and when it was read
+    last_key: Option<(Instant, Key)>,
+    sticky_shift: Option<StickyShift>,
+    /// events yielded since `counted_since`, for the debug log
+    wakeups: u64,
+    counted_since: Instant,
+}
+
+impl EventStream {
+    /// Starts reading `source` keys on a new thread, such as the keys of
+    /// a terminal from [`Backend::keys`](crate::backend::Backend::keys).
+    ///
+    /// The thread keeps reading until `source` ends or the stream is
+    /// dropped (and another key is read), so only one stream should be
+    /// created for a source.
+    pub fn new(source: Keys) -> Self {
+        let (sender, keys) = mpsc::channel();
+
+        thread::spawn(move || {
+            for key in source {
+                if sender.send((Instant::now(), key)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            keys,
+            pending: None,
+            tick_rate: None,
+            debounce: None,
+            last_key: None,
+            sticky_shift: None,
+            wakeups: 0,
+            counted_since: Instant::now(),
+        }
+    }
+
+    /// Whether a key was pressed and is waiting to be yielded, without
+    /// blocking.
+    ///
+    /// Lets the screen be redrawn only once for keys that arrive in quick
+    /// succession, e.g. while backspace is held down.
+    pub fn has_pending(&mut self) -> bool {
+        while self.pending.is_none() {
+            let Ok((at, key)) = self.keys.try_recv() else {
+                break;
+            };
+            if !self.is_bounce(at, &key) {
+                self.pending = self.remap(key);
+            }
+        }
+        self.pending.is_some()
+    }
+
+    /// Sets how often ticks are yielded while no key is pressed.
+    ///
+    /// `None` disables ticks.
+    pub fn set_tick_rate(&mut self, tick_rate: Option<Duration>) {
+        self.tick_rate = tick_rate;
+    }
+
+    /// Counts a wakeup of the typing test, writing how many there were
+    /// per second to the debug log at most every
+    /// [`WAKEUPS_LOG_INTERVAL`]. Shows that idle screens wake up only for
+    /// keys, see [`EventStream::set_tick_rate`].
+    fn count_wakeup(&mut self) {
+        if !debug_log::is_enabled() {
+            return;
+        }
+        self.wakeups += 1;
+        let elapsed = self.counted_since.elapsed();
+        if elapsed < WAKEUPS_LOG_INTERVAL {
+            return;
+        }
+        debug_log::log(
+            "wakeups",
+            [
+                ("count", self.wakeups.into()),
+                (
+                    "per_second",
+                    (self.wakeups as f64 / elapsed.as_secs_f64()).into(),
+                ),
+                (
+                    "tick_rate_ms",
+                    self.tick_rate
+                        .map(|tick_rate| tick_rate.as_millis() as u64)
+                        .into(),
+                ),
+            ],
+        );
+        self.wakeups = 0;
+        self.counted_since = Instant::now();
+    }
+
+    /// Sets the window within which another press of the same key is
+    /// ignored, for chattering switches or tremors.
+    ///
+    /// `None` disables debouncing.
+    pub fn set_debounce(&mut self, debounce: Option<Duration>) {
+        self.debounce = debounce;
+    }
+
+    /// Sets the key that shifts the next key, see [`StickyShift`].
+    ///
+    /// `None` disables sticky shift.
+    pub fn set_sticky_shift(&mut self, sticky_shift: Option<StickyShift>) {
+        self.sticky_shift = sticky_shift;
+    }
+
+    /// The key to yield for `key`, after sticky shift, or `None` if it is
+    /// swallowed.
+    fn remap(&mut self, key: io::Result<Key>) -> Option<io::Result<Key>> {
+        match (&mut self.sticky_shift, key) {
+            (Some(sticky_shift), Ok(key)) => sticky_shift.remap(key).map(Ok),
+            (_, key) => Some(key),
+        }
+    }
+
+    /// Whether `key`, read at `at`, repeats the last key within the
+    /// debounce window and is to be ignored. Remembers it otherwise.
+    fn is_bounce(&mut self, at: Instant, key: &io::Result<Key>) -> bool {
+        let Ok(key) = key else {
+            return false;
+        };
+        let is_bounce = matches!(
+            (self.debounce, self.last_key),
+            (Some(debounce), Some((last_at, last_key)))
+                if last_key == *key && at.saturating_duration_since(last_at) < debounce
+        );
+        if !is_bounce {
+            self.last_key = Some((at, *key));
+        }
+        is_bounce
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = io::Result<ToipeEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.next_event();
+        if event.is_some() {
+            self.count_wakeup();
+        }
+        event
+    }
+}
+
+impl EventStream {
+    /// The next event, see [`Iterator::next`].
+    fn next_event(&mut self) -> Option<io::Result<ToipeEvent>> {
+        if let Some(key) = self.pending.take() {
+            return Some(key.map(ToipeEvent::Key));
+        }
+
+        loop {
+            let (at, key) = match self.tick_rate {
+                Some(tick_rate) => match self.keys.recv_timeout(tick_rate) {
+                    Ok(key) => key,
+                    Err(RecvTimeoutError::Timeout) => return Some(Ok(ToipeEvent::Tick)),
+                    Err(RecvTimeoutError::Disconnected) => return None,
+                },
+                None => self.keys.recv().ok()?,
+            };
+            if self.is_bounce(at, &key) {
+                continue;
+            }
+            if let Some(key) = self.remap(key) {
+                return Some(key.map(ToipeEvent::Key));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys typing `text`.
+    fn typed(text: &'static str) -> Keys {
+        Box::new(text.chars().map(|c| Ok(Key::Char(c))))
+    }
+
+    #[test]
+    fn debounces_repeated_keys() {
+        let mut events = EventStream::new(typed("aab"));
+        events.set_debounce(Some(Duration::from_secs(60)));
+        let keys: Vec<ToipeEvent> = events.map(Result::unwrap).collect();
+        assert_eq!(
+            keys,
+            [
+                ToipeEvent::Key(Key::Char('a')),
+                ToipeEvent::Key(Key::Char('b'))
+            ]
+        );
+
+        let events = EventStream::new(typed("aab"));
+        assert_eq!(events.count(), 3);
+    }
+
+    #[test]
+    fn sticky_shift_shifts_next_key() {
+        let mut events = EventStream::new(typed(";a;1;;b"));
+        events.set_sticky_shift(Some(StickyShift::new(';', &KeyboardLayout::qwerty())));
+        let keys: String = events
+            .map(|event| match event.unwrap() {
+                ToipeEvent::Key(Key::Char(c)) => c,
+                event => panic!("unexpected {:?}", event),
+            })
+            .collect();
+        assert_eq!(keys, "A!;b");
+    }
+}
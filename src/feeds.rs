@@ -0,0 +1,215 @@
+//! Headlines and summaries from RSS and Atom feeds, as quotes.
+//!
+//! Only built with the `feeds` feature. Feeds are downloaded with
+//! [`http::download`] and the extracted quotes are cached in
+//! [`dirs::cache_dir`] for [`CACHE_TTL`], so that restarting does not
+//! download the feed again. A stale cache is used if the download fails.
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use crate::{checksum::sha256_hex, dirs, http, storage};
+
+/// How long a downloaded feed is used before downloading it again.
+pub const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Longest quote taken from a summary, in chars.
+const MAX_SUMMARY_LEN: usize = 200;
+
+fn cache_file(url: &str) -> Result<PathBuf, Error> {
+    Ok(dirs::cache_dir()?
+        .join("feeds")
+        .join(&sha256_hex(url.as_bytes())[..16]))
+}
+
+/// Quotes from the feed at `url`, one per line.
+pub fn quotes(url: &str) -> Result<String, Error> {
+    let cache = cache_file(url)?;
+    let cached = fs::read_to_string(&cache).ok();
+    let is_fresh = fs::metadata(&cache)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < CACHE_TTL);
+
+    if let Some(cached) = &cached {
+        if is_fresh {
+            return Ok(cached.clone());
+        }
+    }
+
+    let downloaded = http::download(url).and_then(|feed| {
+        let quotes = parse(&String::from_utf8_lossy(&feed));
+        if quotes.is_empty() {
+            Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("No headlines found in the feed at {}", url),
+            ))
+        } else {
+            Ok(quotes.join("\n") + "\n")
+        }
+    });
+
+    match (downloaded, cached) {
+        (Ok(quotes), _) => {
+            storage::write(&cache, quotes.as_bytes())?;
+            Ok(quotes)
+        }
+        (Err(_), Some(cached)) => Ok(cached),
+        (Err(err), None) => Err(err),
+    }
+}
+
+/// Extracts the titles and summaries of the items of an RSS or Atom feed.
+pub fn parse(feed: &str) -> Vec<String> {
+    let mut quotes = Vec::new();
+    for item in elements(feed, "item").chain(elements(feed, "entry")) {
+        if let Some(title) = elements(item, "title").next() {
+            quotes.push(text(title));
+        }
+        let summary = elements(item, "description")
+            .chain(elements(item, "summary"))
+            .next();
+        if let Some(summary) = summary {
+            let summary = text(summary);
+            quotes.push(match summary.char_indices().nth(MAX_SUMMARY_LEN) {
+                Some((end, _)) => summary[..end].trim_end().to_string() + "...",
+                None => summary,
+            });
+        }
+    }
+    quotes.retain(|quote| !quote.is_empty());
+    quotes
+}
+
+/// Contents of the elements with the given tag name.
+///
+/// This is not a full XML parser, just enough for feeds.
+fn elements<'a>(xml: &'a str, tag: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut rest = xml;
+
+    std::iter::from_fn(move || loop {
+        let start = rest.find(&open)?;
+        let after_name = &rest[start + open.len()..];
+        // skip longer tag names with the same prefix, such as <items>
+        if !after_name.starts_with(['>', ' ', '\t', '\r', '\n', '/']) {
+            rest = after_name;
+            continue;
+        }
+        let content_start = after_name.find('>')? + 1;
+        if after_name[..content_start].ends_with("/>") {
+            rest = &after_name[content_start..];
+            return Some("");
+        }
+        let content = &after_name[content_start..];
+        let end = content.find(&close)?;
+        rest = &content[end + close.len()..];
+        return Some(&content[..end]);
+    })
+}
+
+/// Plain text of the contents of an element: unwraps CDATA, decodes
+/// entities, strips HTML tags and collapses whitespace.
+fn text(contents: &str) -> String {
+    let contents = contents.trim();
+    let contents = match contents
+        .strip_prefix("<![CDATA[")
+        .and_then(|contents| contents.strip_suffix("]]>"))
+    {
+        Some(cdata) => cdata.to_string(),
+        None => decode_entities(contents),
+    };
+
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in contents.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    let mut text = decode_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    // tags were replaced with spaces, which may end up before punctuation
+    for punctuation in [".", ",", ";", ":", "!", "?"] {
+        text = text.replace(&format!(" {}", punctuation), punctuation);
+    }
+    text
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let c = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        });
+
+        match (entity, c) {
+            (Some(entity), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_feeds() {
+        let rss = r#"<?xml version="1.0"?>
+            <rss><channel><title>News</title>
+            <item>
+              <title>Rust 2.0 &amp; beyond</title>
+              <description>&lt;p&gt;It is &lt;b&gt;fast&lt;/b&gt;.&lt;/p&gt;</description>
+            </item>
+            <item><title><![CDATA[<i>Tabs</i> & spaces]]></title></item>
+            </channel></rss>"#;
+        assert_eq!(
+            parse(rss),
+            vec!["Rust 2.0 & beyond", "It is fast.", "Tabs & spaces"]
+        );
+
+        let atom = r#"<feed><title>Blog</title>
+            <entry><title type="text">Hello&#x2c; world&#33;</title>
+            <summary/><link href="x"/></entry></feed>"#;
+        assert_eq!(parse(atom), vec!["Hello, world!"]);
+    }
+}
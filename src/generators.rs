@@ -0,0 +1,249 @@
+//! Word selectors by name, for specifying the text generator pipeline
+//! declaratively, such as `--generators top250,numbers,punctuation`.
+//!
+//! The first name of a pipeline picks the words, such as a built-in word
+//! list, and the rest change them, in order. See
+//! [`SelectorRegistry::build`].
+
+use std::{collections::BTreeMap, fs, io};
+
+use clap::ArgEnum;
+
+use crate::{
+    config::ToipeConfig,
+    textgen::{fork_rng, Selector, SelectorRng, WordSelector},
+    wordlists::{find_os_wordlist, BuiltInWordlist},
+};
+
+/// Number of recent words not repeated by the `no-repeat` generator when
+/// `--no-repeat-window` is not given.
+pub const DEFAULT_NO_REPEAT_WINDOW: usize = 5;
+
+/// Builds the selector that starts a pipeline.
+pub type SourceFn = dyn Fn(&ToipeConfig, SelectorRng) -> Result<Selector, io::Error>;
+
+/// Builds a selector around the selector built so far.
+pub type WrapperFn = dyn Fn(Selector, &ToipeConfig) -> Result<Selector, io::Error>;
+
+/// Builds a stage of a pipeline: a source of words, or a selector
+/// changing the words of the stages before it.
+pub enum SelectorConstructor {
+    /// starts the pipeline
+    Source(Box<SourceFn>),
+    /// wraps the selector built so far
+    Wrapper(Box<WrapperFn>),
+}
+
+/// Maps names to the constructors of selectors.
+pub struct SelectorRegistry {
+    constructors: BTreeMap<String, SelectorConstructor>,
+}
+
+impl SelectorRegistry {
+    /// A registry without any selectors.
+    pub fn empty() -> Self {
+        Self {
+            constructors: BTreeMap::new(),
+        }
+    }
+
+    /// A registry with the built-in word lists by their names (such as
+    /// `top250`) and the `numbers`, `punctuation` and `no-repeat`
+    /// selectors, which take their options from the config (such as
+    /// `--number-chance`).
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        for &wordlist in BuiltInWordlist::value_variants() {
+            if let Some(name) = wordlist.to_possible_value() {
+                registry.register(
+                    name.get_name(),
+                    SelectorConstructor::Source(Box::new(move |_, rng| {
+                        wordlist_selector(wordlist, rng)
+                    })),
+                );
+            }
+        }
+        registry.register(
+            "numbers",
+            SelectorConstructor::Wrapper(Box::new(|selector, config| {
+                Ok(if config.number_template.is_empty() {
+                    selector.with_numbers_up_to(config.number_chance, config.number_max)
+                } else {
+                    selector.with_formatted_numbers(
+                        config.number_chance,
+                        config.number_template.clone(),
+                    )
+                })
+            })),
+        );
+        registry.register(
+            "punctuation",
+            SelectorConstructor::Wrapper(Box::new(|selector, config| {
+                Ok(selector.with_limited_punctuation(
+                    config.punctuation_chance,
+                    config.punctuation_cooldown,
+                    config.max_marks_per_word,
+                ))
+            })),
+        );
+        registry.register(
+            "no-repeat",
+            SelectorConstructor::Wrapper(Box::new(|selector, config| {
+                Ok(selector.no_repeat(config.no_repeat_window.unwrap_or(DEFAULT_NO_REPEAT_WINDOW)))
+            })),
+        );
+        registry
+    }
+
+    /// Adds a selector, replacing any selector with the same name.
+    pub fn register(&mut self, name: &str, constructor: SelectorConstructor) {
+        self.constructors.insert(name.to_string(), constructor);
+    }
+
+    /// Names of all selectors, in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.constructors.keys().map(String::as_str)
+    }
+
+    /// Builds the pipeline of selectors with the given names, with
+    /// generators forked from `rng`.
+    ///
+    /// Fails if a name is unknown, or the first name is not a source of
+    /// words or any other one is.
+    pub fn build<S: AsRef<str>>(
+        &self,
+        names: &[S],
+        config: &ToipeConfig,
+        rng: &mut SelectorRng,
+    ) -> Result<Box<dyn WordSelector>, io::Error> {
+        let mut selector: Option<Selector> = None;
+        for name in names.iter().map(AsRef::as_ref) {
+            let constructor = self.constructors.get(name).ok_or_else(|| {
+                let names: Vec<&str> = self.names().collect();
+                invalid_input(format!(
+                    "unknown generator `{}`, expected one of: {}",
+                    name,
+                    names.join(", ")
+                ))
+            })?;
+            selector = Some(match (constructor, selector) {
+                (SelectorConstructor::Source(source), None) => {
+                    let mut selector = source(config, fork_rng(rng))?;
+                    if let Some(locale) = &config.locale {
+                        selector = selector.with_locale(locale.clone());
+                    }
+                    if let Some(language) = &config.lang {
+                        selector = selector.with_language(language.clone());
+                    }
+                    selector
+                }
+                (SelectorConstructor::Wrapper(wrapper), Some(selector)) => {
+                    wrapper(selector, config)?
+                }
+                (SelectorConstructor::Source(_), Some(_)) => {
+                    return Err(invalid_input(format!(
+                        "generator `{}` picks the words, so it must come first",
+                        name
+                    )))
+                }
+                (SelectorConstructor::Wrapper(_), None) => {
+                    return Err(invalid_input(format!(
+                        "generator `{}` changes words, so it cannot come first",
+                        name
+                    )))
+                }
+            });
+        }
+
+        selector
+            .map(Selector::build)
+            .ok_or_else(|| invalid_input("no generators given".to_string()))
+    }
+}
+
+impl Default for SelectorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Selector of the words of a built-in word list.
+fn wordlist_selector(wordlist: BuiltInWordlist, rng: SelectorRng) -> Result<Selector, io::Error> {
+    match wordlist.contents() {
+        Some(contents) => Selector::raw_with_rng(contents.split_whitespace(), rng),
+        None => {
+            let contents = fs::read_to_string(find_os_wordlist()?)?;
+            Selector::raw_with_rng(contents.split_whitespace(), rng)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::textgen::selector_rng;
+
+    fn config(args: &[&str]) -> ToipeConfig {
+        ToipeConfig::parse_from([&["toipe"], args].concat())
+    }
+
+    #[test]
+    fn builds_pipelines_by_name() {
+        let config = config(&["--number-chance", "1"]);
+        let mut selector = SelectorRegistry::new()
+            .build(&["top250", "numbers"], &config, &mut selector_rng(Some(1)))
+            .unwrap();
+        let words = selector.new_words(10).unwrap();
+        assert!(words.iter().all(|word| word.parse::<u64>().is_ok()));
+    }
+
+    #[test]
+    fn rejects_invalid_pipelines() {
+        let registry = SelectorRegistry::new();
+        let config = config(&[]);
+        let mut rng = selector_rng(Some(1));
+        for names in [
+            &["top250", "nope"][..],
+            &["numbers"],
+            &["top250", "top500"],
+            &[],
+        ] {
+            assert!(registry.build(names, &config, &mut rng).is_err());
+        }
+    }
+
+    #[test]
+    fn registers_custom_generators() {
+        let mut registry = SelectorRegistry::empty();
+        registry.register(
+            "abc",
+            SelectorConstructor::Source(Box::new(|_, rng| {
+                Selector::raw_with_rng(["a", "b", "c"], rng)
+            })),
+        );
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["abc"]);
+
+        let mut selector = registry
+            .build(&["abc"], &config(&[]), &mut selector_rng(Some(1)))
+            .unwrap();
+        let words = selector.new_words(10).unwrap();
+        assert!(words
+            .iter()
+            .all(|word| ["a", "b", "c"].contains(&&word[..])));
+    }
+
+    #[test]
+    fn parses_generator_names() {
+        let config = config(&["--generators", "top250, numbers,,punctuation"]);
+        assert_eq!(
+            config.generator_names().unwrap(),
+            ["top250", "numbers", "punctuation"]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! HTTP requests, made through `curl` (or `wget` if `curl` is not
+//! available) rather than an HTTP client built in.
+
+use std::{
+    io::{Error, ErrorKind, Write},
+    process::{Command, Stdio},
+};
+
+use serde_json::Value;
+
+/// Downloads the contents of `url` using `curl` or `wget`.
+pub fn download(url: &str) -> Result<Vec<u8>, Error> {
+    let downloaders: [(&str, &[&str]); 2] = [("curl", &["-fsSL"]), ("wget", &["-qO-"])];
+
+    for (program, args) in downloaders {
+        // `--` so that a URL starting with `-` is not read as an option
+        match Command::new(program).args(args).arg("--").arg(url).output() {
+            Ok(output) if output.status.success() => return Ok(output.stdout),
+            Ok(output) => {
+                return Err(Error::other(format!(
+                    "Could not download {}: {} exited with {}",
+                    url, program, output.status
+                )))
+            }
+            // try the next downloader
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(Error::new(
+        ErrorKind::NotFound,
+        format!(
+            "Could not download {}: neither curl nor wget is installed",
+            url
+        ),
+    ))
+}
+
+/// Sends `body` to `url` in a POST request, using `curl`.
+pub fn post_json(url: &str, body: &Value) -> Result<(), Error> {
+    let mut curl = Command::new("curl")
+        .args(["-fsS", "-X", "POST", "-H", "Content-Type: application/json"])
+        .args(["--max-time", "10", "--data-binary", "@-", "--", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => Error::new(ErrorKind::NotFound, "curl is not installed"),
+            _ => err,
+        })?;
+    if let Some(mut stdin) = curl.stdin.take() {
+        stdin.write_all(body.to_string().as_bytes())?;
+    }
+    let output = curl.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
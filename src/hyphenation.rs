@@ -0,0 +1,161 @@
+//! Where words can be broken across lines, see `--hyphenate`.
+//!
+//! Words are broken between syllables, found with the vowel–consonant
+//! rules taught in school rather than with the patterns of a language:
+//! a syllable starts with the consonants before its vowel that can start
+//! a word together, so a single consonant between vowels starts the next
+//! syllable (`ba-sic`) and clusters are split before their last
+//! consonants (`num-ber`, `con-struct`). Pairs of letters read as one
+//! sound, such as `ng`, are never split. This is usually right for English
+//! and other languages written in the Latin alphabet, and never breaks a
+//! word in an impossible place.
+
+/// Smallest number of letters before a break.
+const MIN_LEFT: usize = 2;
+
+/// Smallest number of letters after a break.
+const MIN_RIGHT: usize = 3;
+
+/// Pairs of consonants read as a single sound.
+const DIGRAPHS: [&str; 8] = ["ch", "ck", "gh", "ng", "ph", "sh", "th", "wh"];
+
+/// Clusters of consonants that can start a syllable, besides single
+/// consonants.
+const ONSETS: [&str; 32] = [
+    "bl", "br", "ch", "cl", "cr", "dr", "fl", "fr", "gl", "gr", "ph", "pl", "pr", "sc", "sh", "sk",
+    "sl", "sm", "sn", "sp", "st", "sw", "th", "tr", "tw", "wh", "chr", "scr", "shr", "spl", "spr",
+    "str",
+];
+
+/// A place where a word can be broken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Break {
+    /// number of chars before the break
+    pub index: usize,
+    /// whether a hyphen is shown at the break, which is not needed after
+    /// a hyphen of the word itself
+    pub adds_hyphen: bool,
+}
+
+fn is_vowel(c: char) -> bool {
+    "aeiouyàáâäæèéêëìíîïòóôöøùúûü".contains(c.to_lowercase().next().unwrap_or(c))
+}
+
+/// Places where `word` can be broken, in order.
+///
+/// Punctuation around the word, such as `(` or `,`, stays with the
+/// letters next to it.
+pub fn breaks(word: &str) -> Vec<Break> {
+    let chars: Vec<char> = word.chars().collect();
+    let start = chars
+        .iter()
+        .position(|c| c.is_alphabetic())
+        .unwrap_or(chars.len());
+    let end = chars
+        .iter()
+        .rposition(|c| c.is_alphabetic())
+        .map_or(start, |end| end + 1);
+    let letters = &chars[start..end];
+
+    let mut breaks = Vec::new();
+    if letters.contains(&'-') {
+        // compounds are only broken after their own hyphens
+        for (index, c) in letters.iter().enumerate() {
+            let (left, right) = (index + 1, letters.len() - index - 1);
+            if *c == '-' && left >= MIN_LEFT && right >= MIN_RIGHT {
+                breaks.push(Break {
+                    index: start + index + 1,
+                    adds_hyphen: false,
+                });
+            }
+        }
+        return breaks;
+    }
+    if !letters.iter().all(|c| c.is_alphabetic()) {
+        return breaks;
+    }
+
+    let vowels: Vec<usize> = (0..letters.len())
+        .filter(|index| is_vowel(letters[*index]))
+        .collect();
+    let lowercase = |range: std::ops::Range<usize>| -> String {
+        letters[range].iter().collect::<String>().to_lowercase()
+    };
+    for pair in vowels.windows(2) {
+        // the consonants between two vowels, of which the longest onset
+        // starts the next syllable
+        let (first, next_vowel) = (pair[0] + 1, pair[1]);
+        // vowels next to each other are usually one syllable
+        if first == next_vowel {
+            continue;
+        }
+        let onset = (2..=3.min(next_vowel - first))
+            .rev()
+            .find(|len| ONSETS.contains(&lowercase(next_vowel - len..next_vowel).as_str()))
+            .unwrap_or(1);
+        let mut index = next_vowel - onset;
+        if index > first && DIGRAPHS.contains(&lowercase(index - 1..index + 1).as_str()) {
+            // the pair stays in the syllable before
+            index += 1;
+        }
+        if index >= MIN_LEFT && letters.len() - index >= MIN_RIGHT {
+            breaks.push(Break {
+                index: start + index,
+                adds_hyphen: true,
+            });
+        }
+    }
+    breaks
+}
+
+/// Breaks `word` so that its first part, with any hyphen shown at the
+/// break, is at most `width` chars, as late in the word as possible.
+///
+/// Returns the two parts and whether a hyphen is shown between them.
+pub fn split(word: &str, width: usize) -> Option<(&str, &str, bool)> {
+    let best = breaks(word)
+        .into_iter()
+        .rfind(|at| at.index + at.adds_hyphen as usize <= width)?;
+    let byte_index = word
+        .char_indices()
+        .nth(best.index)
+        .map_or(word.len(), |(i, _)| i);
+    let (first, rest) = word.split_at(byte_index);
+    Some((first, rest, best.adds_hyphen))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hyphenated(word: &str) -> String {
+        let mut hyphenated = word.to_string();
+        for at in breaks(word).iter().rev().filter(|at| at.adds_hyphen) {
+            let byte_index = word.char_indices().nth(at.index).unwrap().0;
+            hyphenated.insert(byte_index, '-');
+        }
+        hyphenated
+    }
+
+    #[test]
+    fn breaks_between_syllables() {
+        assert_eq!(hyphenated("number"), "num-ber");
+        assert_eq!(hyphenated("construct"), "con-struct");
+        assert_eq!(hyphenated("government"), "go-vern-ment");
+        assert_eq!(hyphenated("mother"), "mo-ther");
+        assert_eq!(hyphenated("singing"), "sing-ing");
+        // too short to leave enough letters on both lines
+        assert_eq!(hyphenated("the"), "the");
+        assert_eq!(hyphenated("into"), "into");
+        assert_eq!(hyphenated("(basic),"), "(ba-sic),");
+    }
+
+    #[test]
+    fn splits_to_fit() {
+        assert_eq!(split("government", 5), Some(("go", "vernment", true)));
+        assert_eq!(split("government", 7), Some(("govern", "ment", true)));
+        assert_eq!(split("government", 2), None);
+        assert_eq!(split("well-known", 6), Some(("well-", "known", false)));
+        assert_eq!(split("r2d2", 10), None);
+    }
+}
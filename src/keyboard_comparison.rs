@@ -0,0 +1,268 @@
+//! Comparisons of typing on different keyboards, see
+//! `toipe compare-keyboards`.
+//!
+//! Tests are tagged with the keyboard they were typed on by `--keyboard`,
+//! which is recorded in the history. The guided comparison runs rounds
+//! of one test per keyboard, with the same text for every keyboard of a
+//! round and the order of the keyboards alternating between rounds, so
+//! that neither warming up nor tiring favors a keyboard.
+//!
+//! Keyboards are compared by their mean speed, with a 95% confidence
+//! interval from Student's t-distribution, and the difference between
+//! two keyboards with Welch's t-interval, which does not assume that
+//! both are typed on equally consistently.
+
+use crate::results::history::HistoryEntry;
+
+/// Seed of the text of the first round, see [`seed`].
+const SEED: u64 = 0x6b65_7962;
+
+/// Two-sided 95% critical values of Student's t-distribution, for 1 to
+/// 30 degrees of freedom.
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// The critical value for more degrees of freedom, from the normal
+/// distribution.
+const Z_95: f64 = 1.96;
+
+/// Seed of the text typed on every keyboard in `round`.
+pub fn seed(round: usize) -> u64 {
+    SEED + round as u64
+}
+
+/// The order `keyboards` are typed on in `round`, reversed every other
+/// round.
+pub fn order(keyboards: &[String], round: usize) -> Vec<&String> {
+    match round % 2 {
+        0 => keyboards.iter().collect(),
+        _ => keyboards.iter().rev().collect(),
+    }
+}
+
+/// The flag of tests typed on `keyboard`, see
+/// [`crate::results::history::TestMetadata::flags`].
+pub fn flag(keyboard: &str) -> String {
+    format!("keyboard={}", keyboard)
+}
+
+fn t_95(degrees_of_freedom: f64) -> f64 {
+    match degrees_of_freedom.floor() as usize {
+        0 => f64::INFINITY,
+        df if df <= T_95.len() => T_95[df - 1],
+        _ => Z_95,
+    }
+}
+
+/// Speed and mistakes of the tests typed on a keyboard.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyboardStats {
+    pub keyboard: String,
+    pub tests: usize,
+    pub mean_wpm: f64,
+    /// sample variance of the speed
+    pub wpm_variance: f64,
+    /// mistakes per char typed, over all tests
+    pub error_rate: f64,
+}
+
+impl KeyboardStats {
+    /// Stats of the tests among `entries` typed on `keyboard`.
+    pub fn from_entries(entries: &[HistoryEntry], keyboard: &str) -> Self {
+        let flag = flag(keyboard);
+        let tagged: Vec<&HistoryEntry> = entries
+            .iter()
+            .filter(|entry| entry.metadata.flags.contains(&flag))
+            .collect();
+
+        let tests = tagged.len();
+        let mean_wpm = tagged.iter().map(|entry| entry.wpm).sum::<f64>() / tests.max(1) as f64;
+        let wpm_variance = tagged
+            .iter()
+            .map(|entry| (entry.wpm - mean_wpm).powi(2))
+            .sum::<f64>()
+            / tests.saturating_sub(1).max(1) as f64;
+        let errors: usize = tagged.iter().map(|entry| entry.total_char_errors).sum();
+        let typed: usize = tagged.iter().map(|entry| entry.total_chars_typed).sum();
+        Self {
+            keyboard: keyboard.to_string(),
+            tests,
+            mean_wpm,
+            wpm_variance,
+            error_rate: errors as f64 / typed.max(1) as f64,
+        }
+    }
+
+    /// Half the width of the 95% confidence interval of the mean speed,
+    /// or `None` with fewer than two tests.
+    pub fn wpm_margin(&self) -> Option<f64> {
+        if self.tests < 2 {
+            return None;
+        }
+        let n = self.tests as f64;
+        Some(t_95(n - 1.0) * (self.wpm_variance / n).sqrt())
+    }
+}
+
+/// How much faster one keyboard is than another, negative if it is
+/// slower, with the 95% confidence interval of the difference.
+///
+/// `None` with fewer than two tests of either keyboard.
+pub fn wpm_difference(a: &KeyboardStats, b: &KeyboardStats) -> Option<(f64, f64, f64)> {
+    if a.tests < 2 || b.tests < 2 {
+        return None;
+    }
+    let va = a.wpm_variance / a.tests as f64;
+    let vb = b.wpm_variance / b.tests as f64;
+    let difference = a.mean_wpm - b.mean_wpm;
+    let total = va + vb;
+    let margin = if total > 0.0 {
+        // Welch–Satterthwaite degrees of freedom
+        let df =
+            total.powi(2) / (va.powi(2) / (a.tests - 1) as f64 + vb.powi(2) / (b.tests - 1) as f64);
+        t_95(df) * total.sqrt()
+    } else {
+        0.0
+    };
+    Some((difference, difference - margin, difference + margin))
+}
+
+/// A table of the stats of `keyboards` in `entries`, followed by how
+/// every other keyboard compares with the first.
+pub fn report(entries: &[HistoryEntry], keyboards: &[String]) -> String {
+    let stats: Vec<KeyboardStats> = keyboards
+        .iter()
+        .map(|keyboard| KeyboardStats::from_entries(entries, keyboard))
+        .collect();
+    let width = keyboards
+        .iter()
+        .map(|keyboard| keyboard.chars().count())
+        .chain(["Keyboard".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut report = format!(
+        "{:<width$}  {:>5}  {:>8}  {:>15}  {:>6}\n",
+        "Keyboard",
+        "Tests",
+        "Mean wpm",
+        "95% CI",
+        "Errors",
+        width = width
+    );
+    for stats in &stats {
+        let interval = match stats.wpm_margin() {
+            Some(margin) => format!(
+                "{:.1} to {:.1}",
+                stats.mean_wpm - margin,
+                stats.mean_wpm + margin
+            ),
+            None => "-".to_string(),
+        };
+        let (mean, errors) = match stats.tests {
+            0 => ("-".to_string(), "-".to_string()),
+            _ => (
+                format!("{:.1}", stats.mean_wpm),
+                format!("{:.1}%", stats.error_rate * 100.0),
+            ),
+        };
+        report += &format!(
+            "{:<width$}  {:>5}  {:>8}  {:>15}  {:>6}\n",
+            stats.keyboard,
+            stats.tests,
+            mean,
+            interval,
+            errors,
+            width = width
+        );
+    }
+
+    let Some((first, others)) = stats.split_first() else {
+        return report;
+    };
+    report.push('\n');
+    for other in others {
+        report += &match wpm_difference(other, first) {
+            Some((difference, low, high)) => format!(
+                "{} vs {}: {:+.1} wpm (95% CI {:+.1} to {:+.1}), {}\n",
+                other.keyboard,
+                first.keyboard,
+                difference,
+                low,
+                high,
+                if low > 0.0 || high < 0.0 {
+                    "a significant difference"
+                } else {
+                    "not a significant difference"
+                }
+            ),
+            None => format!(
+                "{} vs {}: needs at least 2 tests of each\n",
+                other.keyboard, first.keyboard
+            ),
+        };
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::history::test_entry;
+
+    fn entry(keyboard: &str, wpm: f64, errors: usize) -> HistoryEntry {
+        let mut entry = test_entry(1_700_000_000, wpm);
+        // 100 chars with `errors` of them typed wrong
+        entry.total_chars_typed = 100;
+        entry.total_char_errors = errors;
+        entry.metadata.flags = vec![flag(keyboard)];
+        entry
+    }
+
+    #[test]
+    fn compares_keyboards() {
+        let entries = [
+            entry("laptop", 60.0, 2),
+            entry("laptop", 62.0, 4),
+            entry("laptop", 64.0, 0),
+            entry("split", 70.0, 1),
+            entry("split", 72.0, 1),
+            entry("split", 74.0, 1),
+            entry("other", 90.0, 0),
+        ];
+        let laptop = KeyboardStats::from_entries(&entries, "laptop");
+        assert_eq!(laptop.tests, 3);
+        assert_eq!(laptop.mean_wpm, 62.0);
+        assert_eq!(laptop.wpm_variance, 4.0);
+        assert_eq!(laptop.error_rate, 0.02);
+        // t = 4.303 for 2 degrees of freedom
+        let margin = laptop.wpm_margin().unwrap();
+        assert!((margin - 4.303 * (4.0f64 / 3.0).sqrt()).abs() < 1e-9);
+
+        let split = KeyboardStats::from_entries(&entries, "split");
+        let (difference, low, high) = wpm_difference(&split, &laptop).unwrap();
+        assert_eq!(difference, 10.0);
+        assert!(low > 0.0 && high > low);
+
+        let other = KeyboardStats::from_entries(&entries, "other");
+        assert_eq!(other.wpm_margin(), None);
+        assert_eq!(wpm_difference(&other, &laptop), None);
+
+        let keyboards = ["laptop", "split", "other"].map(String::from);
+        let report = report(&entries, &keyboards);
+        assert!(report.contains("split vs laptop: +10.0 wpm"));
+        assert!(report.contains("a significant difference"));
+        assert!(report.contains("other vs laptop: needs at least 2 tests of each"));
+    }
+
+    #[test]
+    fn alternates_the_order() {
+        let keyboards = ["a", "b"].map(String::from);
+        assert_eq!(order(&keyboards, 0), ["a", "b"]);
+        assert_eq!(order(&keyboards, 1), ["b", "a"]);
+        assert_ne!(seed(0), seed(1));
+    }
+}
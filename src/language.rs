@@ -0,0 +1,241 @@
+//! The language of the words, see `--lang`.
+//!
+//! The language of a custom word list is guessed from its chars: the
+//! script they are written in, such as Cyrillic, and for scripts used by
+//! several languages, the letters only some of them use, such as `ß` in
+//! German. The guess is usually right for word lists of a single
+//! language, and only decides how the text is capitalized, separated and
+//! punctuated, so a wrong guess is harmless and can be overridden.
+
+use std::{collections::HashMap, str::FromStr};
+
+use crate::locale::Locale;
+
+/// Number of words of a word list looked at to guess its language.
+const SAMPLE_WORDS: usize = 1000;
+
+/// A writing system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    Hangul,
+    /// hiragana and katakana, written along with Han in Japanese
+    Kana,
+    Han,
+}
+
+impl Script {
+    /// The script `c` is written in, if it is a letter of one of them.
+    pub fn of(c: char) -> Option<Self> {
+        let script = match c {
+            'a'..='z' | 'A'..='Z' | '\u{c0}'..='\u{24f}' if c.is_alphabetic() => Self::Latin,
+            '\u{370}'..='\u{3ff}' => Self::Greek,
+            '\u{400}'..='\u{4ff}' => Self::Cyrillic,
+            '\u{590}'..='\u{5ff}' => Self::Hebrew,
+            '\u{600}'..='\u{6ff}' => Self::Arabic,
+            '\u{900}'..='\u{97f}' => Self::Devanagari,
+            '\u{e00}'..='\u{e7f}' => Self::Thai,
+            '\u{1100}'..='\u{11ff}' | '\u{ac00}'..='\u{d7af}' => Self::Hangul,
+            '\u{3040}'..='\u{30ff}' => Self::Kana,
+            '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' => Self::Han,
+            _ => return None,
+        };
+        Some(script)
+    }
+
+    /// Whether words are separated by spaces.
+    pub fn separates_words(self) -> bool {
+        !matches!(self, Self::Thai | Self::Kana | Self::Han)
+    }
+}
+
+/// Supported languages, with their script and the letters telling them
+/// apart from the other languages of the script. The first language of
+/// each script is the one guessed without any such letter.
+const LANGUAGES: [(&str, Script, &str); 21] = [
+    ("en", Script::Latin, ""),
+    ("cs", Script::Latin, "ěřůčšž"),
+    ("de", Script::Latin, "äöüß"),
+    ("es", Script::Latin, "ñ¿¡"),
+    ("fr", Script::Latin, "çèêœëîâû"),
+    ("it", Script::Latin, "ìò"),
+    ("nl", Script::Latin, ""),
+    ("pl", Script::Latin, "ąęłśźżń"),
+    ("pt", Script::Latin, "ãõ"),
+    ("sv", Script::Latin, "å"),
+    ("tr", Script::Latin, "ğış"),
+    ("el", Script::Greek, ""),
+    ("ru", Script::Cyrillic, ""),
+    ("uk", Script::Cyrillic, "іїєґ"),
+    ("he", Script::Hebrew, ""),
+    ("ar", Script::Arabic, ""),
+    ("hi", Script::Devanagari, ""),
+    ("th", Script::Thai, ""),
+    ("ko", Script::Hangul, ""),
+    ("ja", Script::Kana, ""),
+    ("zh", Script::Han, ""),
+];
+
+/// A language, optionally in a region, such as `pt` or `pt-BR`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Language {
+    tag: String,
+    /// the language without the region, one of [`LANGUAGES`]
+    code: &'static str,
+    script: Script,
+}
+
+impl Language {
+    fn new(tag: &str, code: &'static str, script: Script) -> Self {
+        Self {
+            tag: tag.to_string(),
+            code,
+            script,
+        }
+    }
+
+    /// Guesses the language `words` are written in, or `None` if they
+    /// have no letters.
+    pub fn detect<S: AsRef<str>>(words: &[S]) -> Option<Self> {
+        let words = &words[..words.len().min(SAMPLE_WORDS)];
+        let mut scripts = HashMap::<Script, usize>::new();
+        for c in words.iter().flat_map(|word| word.as_ref().chars()) {
+            if let Some(script) = Script::of(c) {
+                *scripts.entry(script).or_default() += 1;
+            }
+        }
+        let count = |script| scripts.get(&script).copied().unwrap_or_default();
+        let mut script = *scripts.iter().max_by_key(|(_, count)| **count)?.0;
+        if matches!(script, Script::Han | Script::Kana) {
+            // Japanese is mostly written in Han, with a few kana in most
+            // words
+            let cjk = count(Script::Han) + count(Script::Kana);
+            script = match count(Script::Kana) * 20 >= cjk {
+                true => Script::Kana,
+                false => Script::Han,
+            };
+        }
+
+        let marks = |letters: &str| {
+            words
+                .iter()
+                .flat_map(|word| word.as_ref().chars())
+                .filter(|c| c.to_lowercase().any(|c| letters.contains(c)))
+                .count()
+        };
+        let candidates = LANGUAGES.iter().filter(|(_, s, _)| *s == script);
+        let (code, _, _) = candidates
+            .clone()
+            .map(|language| (language, marks(language.2)))
+            .filter(|(_, marks)| *marks > 0)
+            .max_by_key(|(_, marks)| *marks)
+            .map(|(language, _)| language)
+            .or_else(|| candidates.clone().next())?;
+        Some(Self::new(code, code, script))
+    }
+
+    pub fn script(&self) -> Script {
+        self.script
+    }
+
+    /// `word` with its first letter in upper case, as at the start of a
+    /// sentence.
+    pub fn capitalize(&self, word: &str) -> String {
+        let mut chars = word.chars();
+        let Some(first) = chars.next() else {
+            return String::new();
+        };
+        let rest = chars.as_str();
+        match (self.code, first) {
+            // the upper case of the dotted i is dotted too
+            ("tr", 'i') => format!("İ{}", rest),
+            // the digraph is capitalized as a whole
+            ("nl", 'i') if rest.starts_with('j') => format!("IJ{}", &rest[1..]),
+            _ => first.to_uppercase().chain(rest.chars()).collect(),
+        }
+    }
+
+    /// Conventions of the numbers and quotes of the language, if they
+    /// are known and differ from the English ones used by default.
+    pub fn locale(&self) -> Option<Locale> {
+        match self.code {
+            "en" => None,
+            _ => self.tag.parse().ok(),
+        }
+    }
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Self::new("en", "en", Script::Latin)
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    /// Parses a tag such as `de`, `pt-BR` or `ja_JP.UTF-8`.
+    fn from_str(tag: &str) -> Result<Self, Self::Err> {
+        let code = tag
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match LANGUAGES.iter().find(|(known, _, _)| *known == code) {
+            Some((code, script, _)) => Ok(Self::new(tag, code, *script)),
+            None => Err(format!(
+                "unknown language `{}`, expected one of: {}",
+                tag,
+                LANGUAGES.map(|(code, _, _)| code).join(", ")
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected(words: &str) -> Option<String> {
+        let words: Vec<&str> = words.split(' ').collect();
+        Language::detect(&words).map(|language| language.to_string())
+    }
+
+    #[test]
+    fn detects_scripts_and_languages() {
+        assert_eq!(detected("the quick brown fox").as_deref(), Some("en"));
+        assert_eq!(detected("der größte Fuß über").as_deref(), Some("de"));
+        assert_eq!(detected("kız ağaç şimdi").as_deref(), Some("tr"));
+        assert_eq!(detected("привет мир").as_deref(), Some("ru"));
+        assert_eq!(detected("привіт їжак").as_deref(), Some("uk"));
+        assert_eq!(detected("日本語 ひらがな 漢字").as_deref(), Some("ja"));
+        assert_eq!(detected("中文 汉字 简体").as_deref(), Some("zh"));
+        assert_eq!(detected("123 456 !?").as_deref(), None);
+    }
+
+    #[test]
+    fn capitalizes_by_language() {
+        let language = |tag: &str| tag.parse::<Language>().unwrap();
+        assert_eq!(language("tr").capitalize("istanbul"), "İstanbul");
+        assert_eq!(language("nl").capitalize("ijsland"), "IJsland");
+        assert_eq!(language("en").capitalize("island"), "Island");
+        assert_eq!(language("de_DE.UTF-8").capitalize("über"), "Über");
+        assert!(!language("ja").script().separates_words());
+        assert!(language("ko").script().separates_words());
+        assert_eq!(language("pt-BR").locale().unwrap().quotes, ('“', '”'));
+        assert_eq!(language("en").locale(), None);
+        assert!("xx".parse::<Language>().is_err());
+    }
+}
@@ -0,0 +1,559 @@
+//! Keyboard layouts: where each character is on the keyboard and which
+//! finger types it.
+//!
+//! A few common layouts are built in (see [`BUILT_IN_LAYOUTS`]). Other
+//! layouts can be defined in TOML files:
+//!
+//! ```toml
+//! name = "my layout"
+//!
+//! [rows.top]
+//! # keys in the row from left to right
+//! keys = "qwertyuiop[]\\"
+//! # (optional) characters typed with shift, one for each key
+//! shifted = "QWERTYUIOP{}|"
+//! # (optional) finger that types each key, from 0 (left pinky) to 9
+//! # (right pinky). 4 and 5 are the left and right thumbs.
+//! fingers = "0123366789999"
+//! ```
+//!
+//! The rows are `number`, `top`, `home` and `bottom`. When `fingers` is
+//! not given, the usual touch typing fingers for a row-staggered
+//! keyboard are used. The space bar is always added, typed by the right
+//! thumb.
+
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use clap::ArgEnum;
+
+use crate::{config::read_toml_file, dirs};
+
+/// A hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ArgEnum)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+/// A finger, ordered from the left pinky to the right pinky.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Finger {
+    LeftPinky,
+    LeftRing,
+    LeftMiddle,
+    LeftIndex,
+    LeftThumb,
+    RightThumb,
+    RightIndex,
+    RightMiddle,
+    RightRing,
+    RightPinky,
+}
+
+impl Finger {
+    /// All fingers, ordered from the left pinky to the right pinky.
+    pub const ALL: [Finger; 10] = [
+        Finger::LeftPinky,
+        Finger::LeftRing,
+        Finger::LeftMiddle,
+        Finger::LeftIndex,
+        Finger::LeftThumb,
+        Finger::RightThumb,
+        Finger::RightIndex,
+        Finger::RightMiddle,
+        Finger::RightRing,
+        Finger::RightPinky,
+    ];
+
+    /// The finger numbered `index`, from 0 (left pinky) to 9 (right
+    /// pinky).
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    /// The hand this finger is on.
+    pub fn hand(&self) -> Hand {
+        if *self <= Finger::LeftThumb {
+            Hand::Left
+        } else {
+            Hand::Right
+        }
+    }
+
+    /// Name of the finger in messages, such as `left pinky`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Finger::LeftPinky => "left pinky",
+            Finger::LeftRing => "left ring finger",
+            Finger::LeftMiddle => "left middle finger",
+            Finger::LeftIndex => "left index finger",
+            Finger::LeftThumb => "left thumb",
+            Finger::RightThumb => "right thumb",
+            Finger::RightIndex => "right index finger",
+            Finger::RightMiddle => "right middle finger",
+            Finger::RightRing => "right ring finger",
+            Finger::RightPinky => "right pinky",
+        }
+    }
+}
+
+/// A row of keys on the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Row {
+    Number,
+    Top,
+    Home,
+    Bottom,
+    Space,
+}
+
+impl Row {
+    /// Rows that can be defined in a layout, from top to bottom.
+    pub const DEFINABLE: [Row; 4] = [Row::Number, Row::Top, Row::Home, Row::Bottom];
+
+    /// Name of the row in layout files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Row::Number => "number",
+            Row::Top => "top",
+            Row::Home => "home",
+            Row::Bottom => "bottom",
+            Row::Space => "space",
+        }
+    }
+
+    /// Touch typing fingers for each column of this row on a
+    /// row-staggered keyboard.
+    fn default_fingers(&self) -> &'static [usize] {
+        match self {
+            Row::Number => &[0, 0, 1, 2, 3, 3, 6, 6, 7, 8, 9, 9, 9],
+            Row::Top => &[0, 1, 2, 3, 3, 6, 6, 7, 8, 9, 9, 9, 9],
+            Row::Home => &[0, 1, 2, 3, 3, 6, 6, 7, 8, 9, 9],
+            Row::Bottom => &[0, 1, 2, 3, 3, 6, 6, 7, 8, 9],
+            Row::Space => &[5],
+        }
+    }
+}
+
+/// Where a character is typed on the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPosition {
+    pub row: Row,
+    /// 0-based column of the key in its row
+    pub column: usize,
+    pub finger: Finger,
+    /// whether shift must be held to type the character
+    pub shifted: bool,
+}
+
+impl KeyPosition {
+    /// The hand that types the character.
+    pub fn hand(&self) -> Hand {
+        self.finger.hand()
+    }
+}
+
+/// A physical key in a [`LayoutRow`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutKey {
+    /// character typed without shift
+    pub key: char,
+    /// character typed with shift, if any
+    pub shifted: Option<char>,
+    pub finger: Finger,
+}
+
+/// A row of keys in a [`KeyboardLayout`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutRow {
+    pub row: Row,
+    /// keys from left to right
+    pub keys: Vec<LayoutKey>,
+}
+
+/// A keyboard layout.
+#[derive(Debug, Clone)]
+pub struct KeyboardLayout {
+    pub name: String,
+    /// rows from top to bottom, including the space bar
+    pub rows: Vec<LayoutRow>,
+    positions: HashMap<char, KeyPosition>,
+}
+
+/// Definition of a built-in layout: name and the unshifted and shifted
+/// keys of each row from top to bottom.
+type LayoutDefinition = (&'static str, [(&'static str, &'static str); 4]);
+
+/// Layouts that are built in and can be selected by name.
+pub const BUILT_IN_LAYOUTS: [LayoutDefinition; 3] = [
+    (
+        "qwerty",
+        [
+            ("`1234567890-=", "~!@#$%^&*()_+"),
+            ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+            ("asdfghjkl;'", "ASDFGHJKL:\""),
+            ("zxcvbnm,./", "ZXCVBNM<>?"),
+        ],
+    ),
+    (
+        "dvorak",
+        [
+            ("`1234567890[]", "~!@#$%^&*(){}"),
+            ("',.pyfgcrl/=\\", "\"<>PYFGCRL?+|"),
+            ("aoeuidhtns-", "AOEUIDHTNS_"),
+            (";qjkxbmwvz", ":QJKXBMWVZ"),
+        ],
+    ),
+    (
+        "colemak",
+        [
+            ("`1234567890-=", "~!@#$%^&*()_+"),
+            ("qwfpgjluy;[]\\", "QWFPGJLUY:{}|"),
+            ("arstdhneio'", "ARSTDHNEIO\""),
+            ("zxcvbkm,./", "ZXCVBKM<>?"),
+        ],
+    ),
+];
+
+fn invalid(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+impl KeyboardLayout {
+    /// Builds a layout from its rows (from top to bottom), adding the
+    /// space bar.
+    pub fn from_rows(name: &str, mut rows: Vec<LayoutRow>) -> Self {
+        rows.retain(|row| row.row != Row::Space);
+        rows.push(LayoutRow {
+            row: Row::Space,
+            keys: vec![LayoutKey {
+                key: ' ',
+                shifted: None,
+                finger: Finger::RightThumb,
+            }],
+        });
+
+        let mut positions = HashMap::new();
+        for row in &rows {
+            for (column, key) in row.keys.iter().enumerate() {
+                let position = |shifted| KeyPosition {
+                    row: row.row,
+                    column,
+                    finger: key.finger,
+                    shifted,
+                };
+                positions.entry(key.key).or_insert_with(|| position(false));
+                if let Some(shifted) = key.shifted {
+                    positions.entry(shifted).or_insert_with(|| position(true));
+                }
+            }
+        }
+
+        Self {
+            name: name.to_string(),
+            rows,
+            positions,
+        }
+    }
+
+    /// Builds one row of a layout.
+    ///
+    /// `shifted` and `fingers` must have as many chars as `keys`, if
+    /// given. See the [module documentation](self) for the format.
+    fn build_row(
+        row: Row,
+        keys: &str,
+        shifted: Option<&str>,
+        fingers: Option<&str>,
+    ) -> Result<LayoutRow, Error> {
+        let num_keys = keys.chars().count();
+
+        let shifted: Vec<Option<char>> = match shifted {
+            Some(shifted) if shifted.chars().count() != num_keys => {
+                return Err(invalid(format!(
+                    "row `{}` has {} keys but {} shifted keys",
+                    row.name(),
+                    num_keys,
+                    shifted.chars().count()
+                )))
+            }
+            Some(shifted) => shifted.chars().map(Some).collect(),
+            None => vec![None; num_keys],
+        };
+
+        let fingers: Vec<Finger> = match fingers {
+            Some(fingers) => {
+                let fingers = fingers
+                    .chars()
+                    .map(|c| {
+                        c.to_digit(10)
+                            .and_then(|d| Finger::from_index(d as usize))
+                            .ok_or_else(|| {
+                                invalid(format!(
+                                    "invalid finger `{}` in row `{}`, expected a digit",
+                                    c,
+                                    row.name()
+                                ))
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if fingers.len() != num_keys {
+                    return Err(invalid(format!(
+                        "row `{}` has {} keys but {} fingers",
+                        row.name(),
+                        num_keys,
+                        fingers.len()
+                    )));
+                }
+                fingers
+            }
+            None => {
+                let defaults = row.default_fingers();
+                (0..num_keys)
+                    .map(|column| defaults[column.min(defaults.len() - 1)])
+                    .map(|index| Finger::from_index(index).expect("default fingers are valid"))
+                    .collect()
+            }
+        };
+
+        Ok(LayoutRow {
+            row,
+            keys: keys
+                .chars()
+                .zip(shifted)
+                .zip(fingers)
+                .map(|((key, shifted), finger)| LayoutKey {
+                    key,
+                    shifted,
+                    finger,
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns the built-in layout with the given name.
+    pub fn built_in(name: &str) -> Option<Self> {
+        let (name, rows) = BUILT_IN_LAYOUTS
+            .iter()
+            .find(|(layout_name, _)| layout_name.eq_ignore_ascii_case(name))?;
+
+        let rows = Row::DEFINABLE
+            .iter()
+            .zip(rows)
+            .map(|(row, (keys, shifted))| Self::build_row(*row, keys, Some(shifted), None))
+            .collect::<Result<Vec<_>, _>>()
+            .expect("built-in layouts are valid");
+
+        Some(Self::from_rows(name, rows))
+    }
+
+    /// The default layout, QWERTY.
+    pub fn qwerty() -> Self {
+        Self::built_in("qwerty").expect("qwerty is a built-in layout")
+    }
+
+    /// Parses a layout definition in TOML.
+    ///
+    /// See the [module documentation](self) for the format.
+    pub fn from_toml_str(contents: &str) -> Result<Self, Error> {
+        Self::from_table(&toml::from_str(contents).map_err(|err| invalid(format!("{}", err)))?)
+    }
+
+    /// Reads a layout definition from a TOML file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        Self::from_table(&read_toml_file(path)?)
+            .map_err(|err| invalid(format!("{}: {}", path.display(), err)))
+    }
+
+    fn from_table(table: &toml::value::Table) -> Result<Self, Error> {
+        let name = match table.get("name") {
+            Some(name) => name
+                .as_str()
+                .ok_or_else(|| invalid("`name` must be a string".to_string()))?,
+            None => "custom",
+        };
+
+        let rows_table = table
+            .get("rows")
+            .and_then(toml::Value::as_table)
+            .ok_or_else(|| invalid("missing `[rows]` table".to_string()))?;
+
+        if let Some(unknown) = rows_table
+            .keys()
+            .find(|key| !Row::DEFINABLE.iter().any(|row| row.name() == *key))
+        {
+            return Err(invalid(format!(
+                "unknown row `{}`, expected one of number, top, home, bottom",
+                unknown
+            )));
+        }
+
+        let mut rows = Vec::new();
+        for row in Row::DEFINABLE {
+            let row_table = match rows_table.get(row.name()) {
+                Some(toml::Value::Table(row_table)) => row_table,
+                Some(_) => return Err(invalid(format!("`rows.{}` must be a table", row.name()))),
+                None => continue,
+            };
+
+            let get_str = |key: &str| -> Result<Option<&str>, Error> {
+                match row_table.get(key) {
+                    Some(value) => value.as_str().map(Some).ok_or_else(|| {
+                        invalid(format!("`rows.{}.{}` must be a string", row.name(), key))
+                    }),
+                    None => Ok(None),
+                }
+            };
+
+            let keys = get_str("keys")?
+                .ok_or_else(|| invalid(format!("missing `rows.{}.keys`", row.name())))?;
+            rows.push(Self::build_row(
+                row,
+                keys,
+                get_str("shifted")?,
+                get_str("fingers")?,
+            )?);
+        }
+
+        Ok(Self::from_rows(name, rows))
+    }
+
+    /// Finds a layout by name or path.
+    ///
+    /// `name_or_path` is tried as (in order):
+    /// - a built-in layout name (see [`BUILT_IN_LAYOUTS`])
+    /// - the name of a layout file `<name>.toml` in [`dirs::layouts_dir`]
+    /// - a path to a layout file
+    pub fn find(name_or_path: &str) -> Result<Self, Error> {
+        if let Some(layout) = Self::built_in(name_or_path) {
+            return Ok(layout);
+        }
+
+        if let Ok(dir) = dirs::layouts_dir() {
+            let path = dir.join(format!("{}.toml", name_or_path));
+            if path.is_file() {
+                return Self::from_file(path);
+            }
+        }
+
+        if Path::new(name_or_path).is_file() {
+            return Self::from_file(name_or_path);
+        }
+
+        Err(Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "Unknown keyboard layout `{}`. Use one of {} or a path to a layout file",
+                name_or_path,
+                BUILT_IN_LAYOUTS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        ))
+    }
+
+    /// Where the given character is typed, if it is in this layout.
+    pub fn position(&self, c: char) -> Option<KeyPosition> {
+        self.positions.get(&c).copied()
+    }
+
+    /// The finger that types the given character, if it is in this
+    /// layout.
+    pub fn finger(&self, c: char) -> Option<Finger> {
+        self.position(c).map(|position| position.finger)
+    }
+
+    /// The hand that types the given character, if it is in this layout.
+    pub fn hand(&self, c: char) -> Option<Hand> {
+        self.position(c).map(|position| position.hand())
+    }
+
+    /// Whether all characters of the word are typed by the given hand in
+    /// this layout.
+    pub fn is_typed_with(&self, word: &str, hand: Hand) -> bool {
+        word.chars().all(|c| self.hand(c) == Some(hand))
+    }
+
+    /// The character typed by the same key with shift (or without, if
+    /// `c` is itself a shifted character).
+    pub fn shift_pair(&self, c: char) -> Option<char> {
+        let position = self.position(c)?;
+        let row = self.rows.iter().find(|row| row.row == position.row)?;
+        let key = row.keys.get(position.column)?;
+        if position.shifted {
+            Some(key.key)
+        } else {
+            key.shifted
+        }
+    }
+}
+
+impl Default for KeyboardLayout {
+    fn default() -> Self {
+        Self::qwerty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_layouts() {
+        for (name, _) in BUILT_IN_LAYOUTS {
+            let layout = KeyboardLayout::built_in(name).unwrap();
+            // every letter of the alphabet can be typed, in both cases
+            for c in 'a'..='z' {
+                assert!(layout.position(c).is_some(), "{} missing {}", name, c);
+                assert_eq!(layout.shift_pair(c), Some(c.to_ascii_uppercase()));
+            }
+        }
+
+        let qwerty = KeyboardLayout::qwerty();
+        assert_eq!(qwerty.finger('f'), Some(Finger::LeftIndex));
+        assert_eq!(qwerty.finger('j'), Some(Finger::RightIndex));
+        assert_eq!(qwerty.finger('p'), Some(Finger::RightPinky));
+        assert_eq!(qwerty.hand(' '), Some(Hand::Right));
+        assert_eq!(
+            qwerty.position('!'),
+            Some(KeyPosition {
+                row: Row::Number,
+                column: 1,
+                finger: Finger::LeftPinky,
+                shifted: true,
+            })
+        );
+    }
+
+    #[test]
+    fn layout_from_toml() {
+        let layout = KeyboardLayout::from_toml_str(
+            r#"
+            name = "tiny"
+            [rows.home]
+            keys = "auie"
+            shifted = "AUIE"
+            fingers = "0167"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(layout.name, "tiny");
+        assert_eq!(layout.finger('u'), Some(Finger::LeftRing));
+        assert_eq!(layout.hand('e'), Some(Hand::Right));
+        assert_eq!(layout.shift_pair('I'), Some('i'));
+        assert_eq!(layout.position('q'), None);
+
+        assert!(
+            KeyboardLayout::from_toml_str("[rows.home]\nkeys = \"ab\"\nfingers = \"0\"").is_err()
+        );
+        assert!(KeyboardLayout::from_toml_str("[rows.middle]\nkeys = \"ab\"").is_err());
+    }
+}
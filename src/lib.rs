@@ -9,36 +9,155 @@
 //!
 //! See [`RawWordSelector`] if you're looking for the word selection
 //! algorithm.
+//!
+//! To draw the typing test in part of the terminal, next to a UI of your
+//! own, set [`ToipeConfig::region`].
+//! To keep the results of tests somewhere other than the history file,
+//! see [`results::sink`].
 
+pub mod analytics;
+pub mod ansi;
+pub mod backend;
+pub mod bot;
+pub mod breaks;
+pub mod challenge_board;
+pub mod challenge_code;
+pub mod checksum;
+pub mod clock;
+pub mod coach;
+pub mod confetti;
 pub mod config;
+pub mod debug_log;
+pub mod diff;
+pub mod dirs;
+pub mod doctor;
+pub mod encryption;
+pub mod events;
+#[cfg(feature = "feeds")]
+pub mod feeds;
+pub mod generators;
+pub mod http;
+pub mod hyphenation;
+pub mod keyboard_comparison;
+pub mod language;
+pub mod layout;
+pub mod locale;
+pub mod metronome;
+pub mod migrations;
+pub mod notify;
+pub mod profile;
+pub mod pronounce;
+pub mod queue;
 pub mod results;
+pub mod schedule;
+pub mod session;
+pub mod setup;
+pub mod storage;
+pub mod sync;
+pub mod telemetry;
+pub mod testing;
 pub mod textgen;
+pub mod theme;
 pub mod trie;
 pub mod tty;
 pub mod tui;
+pub mod user_wordlists;
+pub mod wordlist_lint;
+pub mod wordlist_picker;
 pub mod wordlists;
 pub mod wordstream;
 
-use std::time::Instant;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufReader;
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use analytics::{ErrorPositions, HandBalance};
+use backend::{Backend, Key, Terminal};
+use bot::Bot;
+use breaks::BreakReminder;
+use clock::{Clock, SystemClock};
+use coach::Drill;
+use confetti::Confetti;
 use config::ToipeConfig;
-use results::ToipeResults;
-use termion::input::{Keys, TermRead};
-use termion::{color, event::Key};
+use diff::Hunk;
+use events::{EventStream, StickyShift, ToipeEvent};
+use language::Language;
+use layout::KeyboardLayout;
+use metronome::Metronome;
+use notify::Notifier;
+use pronounce::Pronouncer;
+use queue::Queued;
+use results::{
+    char_stats,
+    history::{self, HistoryEntry, HistoryStats, TestMetadata},
+    keystroke_log,
+    plan::{self, Date},
+    sink::{FileSink, ResultsSink},
+    CharOutcome, ToipeResults,
+};
+use session::{SessionEnd, SessionUpdate, TypingSession};
+use testing::{ScriptPlayer, ScriptQueue, SimulatedTypist};
 use textgen::{
-    NumberGeneratingWordSelector, PunctuatedWordSelector, RawWordSelector, WordSelector,
+    fork_rng, RawWordSelector, Selector, SelectorRng, ShuffledDeckWordSelector,
+    WeightedWordSelector, WordSelector,
 };
-use tui::{Text, ToipeTui};
+use theme::{ColorDepth, Theme};
+use trie::Trie;
+use tui::{graphics, progress_title, Text, ToipeTui};
+use wordstream::RandomSectionSelector;
 
 use anyhow::Result;
+use rand::Rng;
 
 /// Typing test terminal UI and logic.
 pub struct Toipe {
     tui: ToipeTui,
     text: Vec<Text>,
     words: Vec<String>,
+    /// the words as displayed, with deliberate typos in proofreading mode
+    shown_words: Vec<String>,
+    /// hunks of the diff given with `--diff`
+    hunks: Vec<Hunk>,
+    /// hunk of the diff being typed, whose added lines are the words
+    hunk: Option<Hunk>,
+    /// items of `--queue` mixed into the words
+    queued: Vec<Queued>,
     word_selector: Box<dyn WordSelector>,
     config: ToipeConfig,
+    layout: KeyboardLayout,
+    bot: Option<Bot>,
+    /// see `--break-after`
+    break_reminder: Option<BreakReminder>,
+    /// see `--metronome`
+    metronome: Option<Metronome>,
+    /// tells the time while typing, see [`Toipe::set_clock`]
+    clock: Rc<dyn Clock>,
+    /// where finished tests are stored, see [`Toipe::set_results_sink`]
+    results_sink: Box<dyn ResultsSink>,
+    /// SHA-256 of the words in the word list, see
+    /// [`TestMetadata::wordlist_hash`]
+    wordlist_hash: Option<String>,
+    /// shown at the bottom of the screen along with the hint
+    warning: Option<String>,
+    /// language of the words, see `--lang`
+    language: Option<Language>,
+    /// picks the hunks and typos, and seeds the word selectors
+    rng: SelectorRng,
+    /// seed `rng` was made from for the current test, for its challenge
+    /// code (see [`ToipeConfig::challenge_options`])
+    seed: u64,
+    /// whether the last test was finished rather than quit
+    finished: bool,
+    /// results to submit to the challenge board, see
+    /// [`Toipe::take_submissions`]
+    submissions: Vec<challenge_board::PendingSubmission>,
 }
 
 /// Represents any error caught in Toipe.
@@ -70,55 +189,352 @@ impl std::fmt::Display for ToipeError {
 
 impl std::error::Error for ToipeError {}
 
-impl<'a> Toipe {
+/// Minimum number of distinct words a word list must have for a
+/// meaningful typing test.
+///
+/// Does not apply in quote mode, where each "word" is a whole line.
+pub const MIN_DISTINCT_WORDS: u64 = 10;
+
+/// Error returned when the word list has fewer than
+/// [`MIN_DISTINCT_WORDS`] distinct words.
+#[derive(Debug)]
+pub struct SmallWordlistError {
+    /// Name of the word list, see [`ToipeConfig::text_name`].
+    pub source_name: String,
+    /// Number of distinct words found in the word list.
+    pub distinct_words: u64,
+    /// Minimum number of distinct words required.
+    pub min_distinct_words: u64,
+}
+
+impl std::fmt::Display for SmallWordlistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Word list {} has only {} distinct words, at least {} are required. \
+            Use --allow-small-wordlist to use it anyway",
+            self.source_name, self.distinct_words, self.min_distinct_words
+        )
+    }
+}
+
+impl std::error::Error for SmallWordlistError {}
+
+/// Error returned when the word list has no words at all, such as an
+/// empty file or one with only blank lines.
+#[derive(Debug)]
+pub struct EmptyWordlistError {
+    /// Name of the word list, see [`ToipeConfig::text_name`].
+    pub source_name: String,
+}
+
+impl std::fmt::Display for EmptyWordlistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Word list {} has no words", self.source_name)
+    }
+}
+
+impl std::error::Error for EmptyWordlistError {}
+
+impl Toipe {
     /// Initializes a new typing test on the standard output.
     ///
     /// See [`ToipeConfig`] for configuration options.
     ///
     /// Initializes the word selector.
     /// Also invokes [`Toipe::restart()`].
-    pub fn new(config: ToipeConfig) -> Result<Self> {
-        let stream = wordstream::WordStream::new(&config)?;
-
-        let mut word_selector: Box<dyn WordSelector> =
-            Box::new(RawWordSelector::from_iter(stream.into_iter())?);
-
-        if config.numbers {
-            word_selector = Box::new(NumberGeneratingWordSelector::from_word_selector(
-                word_selector,
-                config.number_chance,
-                config.number_max,
-            ));
-        }
-
-        if config.punctuation {
-            word_selector = Box::new(PunctuatedWordSelector::from_word_selector(
-                word_selector,
-                config.punctuation_chance,
-            ));
-        }
+    pub fn new(mut config: ToipeConfig) -> Result<Self> {
+        config.theme = config.theme.for_depth(ColorDepth::detect());
+        let layout = KeyboardLayout::find(&config.layout)?;
+        let seed = config.seed.unwrap_or_else(rand::random);
+        let mut rng = textgen::selector_rng(Some(seed));
+        let (word_selector, wordlist_hash, warning, language) =
+            Self::word_selector(&config, &layout, &mut rng)?;
+        let hunks = match &config.diff {
+            Some(path) => diff::parse(&std::fs::read_to_string(path)?),
+            None => Vec::new(),
+        };
 
         let mut toipe = Toipe {
             tui: ToipeTui::new(),
             words: Vec::new(),
+            shown_words: Vec::new(),
+            hunks,
+            hunk: None,
+            queued: Vec::new(),
             text: Vec::new(),
             word_selector,
+            bot: config.bot.map(|wpm| Bot::new(wpm, config.bot_noise)),
+            break_reminder: config
+                .break_after
+                .filter(|minutes| *minutes > 0)
+                .map(|minutes| BreakReminder::new(Duration::from_secs(minutes * 60))),
+            metronome: config.metronome.map(Metronome::new),
+            clock: Rc::new(SystemClock),
+            results_sink: Box::new(FileSink::default()),
             config,
+            layout,
+            wordlist_hash,
+            warning,
+            language,
+            rng,
+            seed,
+            finished: false,
+            submissions: Vec::new(),
         };
 
+        toipe.tui.set_region(toipe.config.region);
+        toipe.tui.set_challenge(toipe.config.challenge);
+        toipe.tui.set_theme(toipe.config.theme);
+        toipe.tui.set_low_vision(toipe.config.low_vision);
+        toipe.tui.set_hyphenate(toipe.config.hyphenate);
+        toipe
+            .tui
+            .set_keyboard(toipe.config.show_keyboard.then(|| toipe.layout.clone()));
+        toipe.tui.set_mini(toipe.config.mini);
+        toipe.tui.set_window_title(toipe.config.window_title)?;
         toipe.restart()?;
 
         Ok(toipe)
     }
 
+    /// Builds the word selector for the text source and options in
+    /// `config`, with generators forked from `rng`.
+    ///
+    /// Also returns the hash of the word list (see
+    /// [`TestMetadata::wordlist_hash`]), a warning to show and the
+    /// language of the words, if any.
+    #[allow(clippy::type_complexity)]
+    fn word_selector(
+        config: &ToipeConfig,
+        layout: &KeyboardLayout,
+        rng: &mut SelectorRng,
+    ) -> Result<(
+        Box<dyn WordSelector>,
+        Option<String>,
+        Option<String>,
+        Option<Language>,
+    )> {
+        if let Some(names) = config.generator_names() {
+            let word_selector = generators::SelectorRegistry::new().build(&names, config, rng)?;
+            debug_log::log("selector", [("generators", names.into())]);
+            return Ok((word_selector, None, None, config.lang.clone()));
+        }
+
+        let (word_selector, wordlist_hash, warning, detected) = match &config.file_random_section {
+            Some(path) => {
+                let reader = BufReader::new(File::open(path)?);
+                if !wordstream::has_words(reader)? {
+                    return Err(EmptyWordlistError {
+                        source_name: config.text_name(),
+                    }
+                    .into());
+                }
+                (
+                    Box::new(RandomSectionSelector::new(
+                        path.into(),
+                        config.num_words,
+                        fork_rng(rng),
+                    )) as Box<dyn WordSelector>,
+                    None,
+                    None,
+                    None,
+                )
+            }
+            None => Self::wordlist_selector(config, layout, rng)?,
+        };
+        let language = config.lang.clone().or(detected);
+
+        // what is added to the words, for the debug log
+        let mut modifiers = Vec::new();
+        let mut selector = Selector::from_word_selector(word_selector, fork_rng(rng));
+        if let Some(locale) = config
+            .locale
+            .clone()
+            .or_else(|| language.as_ref()?.locale())
+        {
+            selector = selector.with_locale(locale);
+        }
+        if let Some(language) = &language {
+            selector = selector.with_language(language.clone());
+        }
+        if let Some(window) = config.no_repeat_window {
+            selector = selector.no_repeat(window);
+            modifiers.push("no_repeat");
+        }
+        if let Some(mix) = &config.mix {
+            selector = selector.with_mix(mix, config.number_max)?;
+            modifiers.push("mix");
+        } else if config.numbers && !config.number_template.is_empty() {
+            selector = selector
+                .with_formatted_numbers(config.number_chance, config.number_template.clone());
+            modifiers.push("formatted_numbers");
+        } else if config.numbers {
+            selector = selector.with_numbers_up_to(config.number_chance, config.number_max);
+            modifiers.push("numbers");
+        }
+        if config.punctuation {
+            modifiers.push("punctuation");
+            selector = selector.with_limited_punctuation(
+                config.punctuation_chance,
+                config.punctuation_cooldown,
+                config.max_marks_per_word,
+            );
+        }
+
+        if let Some(hand) = config.one_hand {
+            // numbers and punctuation are not filtered like the word list
+            let layout = layout.clone();
+            selector = selector.filtered(move |word| layout.is_typed_with(word, hand));
+            modifiers.push("one_hand");
+        }
+
+        debug_log::log(
+            "selector",
+            [
+                ("source", config.text_name().into()),
+                (
+                    "random_section",
+                    config.file_random_section.is_some().into(),
+                ),
+                ("modifiers", modifiers.into()),
+                ("warning", warning.clone().into()),
+                (
+                    "language",
+                    language.as_ref().map(ToString::to_string).into(),
+                ),
+            ],
+        );
+        Ok((selector.build(), wordlist_hash, warning, language))
+    }
+
+    /// Builds the word selector picking random words from the word list,
+    /// before any of the options changing the words are applied.
+    ///
+    /// The language of a custom word list is guessed from its words, the
+    /// built-in ones are in English.
+    #[allow(clippy::type_complexity)]
+    fn wordlist_selector(
+        config: &ToipeConfig,
+        layout: &KeyboardLayout,
+        rng: &mut SelectorRng,
+    ) -> Result<(
+        Box<dyn WordSelector>,
+        Option<String>,
+        Option<String>,
+        Option<Language>,
+    )> {
+        let stream = wordstream::WordStream::new(config)?;
+        let words = stream
+            .into_iter()
+            .filter(|word| match (config.one_hand, word) {
+                (Some(hand), Ok(word)) => layout.is_typed_with(word, hand),
+                _ => true,
+            });
+
+        let raw_word_selector = RawWordSelector::from_iter(words, fork_rng(rng))?;
+
+        let all_words = raw_word_selector.all_words().ok();
+        let wordlist_hash = all_words
+            .as_ref()
+            .map(|words| checksum::sha256_hex(words.join("\n").as_bytes()));
+        let language = all_words
+            .filter(|_| config.wordlist_file.is_some())
+            .and_then(|words| Language::detect(&words));
+
+        let mut warning = None;
+        let distinct_words = raw_word_selector.num_distinct_words();
+        if distinct_words == 0 {
+            return Err(EmptyWordlistError {
+                source_name: config.text_name(),
+            }
+            .into());
+        }
+        if !config.is_quote_mode() && distinct_words < MIN_DISTINCT_WORDS {
+            let error = SmallWordlistError {
+                source_name: config.text_name(),
+                distinct_words,
+                min_distinct_words: MIN_DISTINCT_WORDS,
+            };
+            if !config.allow_small_wordlist {
+                return Err(error.into());
+            }
+            warning = Some(format!(
+                "{} has only {} distinct words",
+                error.source_name, error.distinct_words
+            ));
+        }
+
+        debug_log::log(
+            "wordlist",
+            [
+                ("distinct_words", distinct_words.into()),
+                ("cover_all", config.cover_all.into()),
+                ("practice", config.practice.into()),
+            ],
+        );
+        let word_selector: Box<dyn WordSelector> = if config.cover_all {
+            Box::new(ShuffledDeckWordSelector::from_raw_word_selector(
+                &raw_word_selector,
+                fork_rng(rng),
+            )?)
+        } else if config.practice {
+            let stats = char_stats::load()?;
+            Box::new(WeightedWordSelector::from_raw_word_selector(
+                &raw_word_selector,
+                |word| stats.word_weight(word),
+                fork_rng(rng),
+            )?)
+        } else {
+            Box::new(raw_word_selector)
+        };
+
+        Ok((word_selector, wordlist_hash, warning, language))
+    }
+
+    /// The keyboard layout used for this typing test.
+    pub fn layout(&self) -> &KeyboardLayout {
+        &self.layout
+    }
+
+    /// Stores finished tests in `sink` instead of the history file, and
+    /// compares the results with the tests in it.
+    pub fn set_results_sink(&mut self, sink: impl ResultsSink + 'static) {
+        self.results_sink = Box::new(sink);
+    }
+
+    /// Results of the finished tests to submit to the challenge board,
+    /// which is left to the caller so the results screen is not held up.
+    pub fn take_submissions(&mut self) -> Vec<challenge_board::PendingSubmission> {
+        std::mem::take(&mut self.submissions)
+    }
+
+    /// Tells the time with `clock` instead of the real time, such as a
+    /// [`ManualClock`](clock::ManualClock) to play a test back.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Rc::new(clock);
+    }
+
     fn display_hint(&mut self) -> Result<()> {
+        let mut lines = Vec::<Vec<Text>>::new();
+        if let Some(warning) = &self.warning {
+            lines.push(vec![
+                Text::from("warning: ").with_color(self.config.theme.warning),
+                Text::from(warning.as_str()).with_faint(),
+            ]);
+        }
         if self.config.show_hint {
-            self.tui.display_lines_bottom(&[&[
-                Text::from("ctrl-r").with_color(color::Blue),
+            lines.push(vec![
+                Text::from("ctrl-r").with_color(self.config.theme.hint),
                 Text::from(" to restart, ").with_faint(),
-                Text::from("ctrl-c").with_color(color::Blue),
+                Text::from("ctrl-u").with_color(self.config.theme.hint),
+                Text::from(" to retry, ").with_faint(),
+                Text::from("ctrl-c").with_color(self.config.theme.hint),
                 Text::from(" to quit ").with_faint(),
-            ]])?;
+            ]);
+        }
+        if !lines.is_empty() {
+            self.tui.display_lines_bottom(&lines)?;
         }
         Ok(())
     }
@@ -128,15 +544,116 @@ impl<'a> Toipe {
     /// Clears the screen, generates new words and displays them on the
     /// UI.
     pub fn restart(&mut self) -> Result<()> {
+        if !self.words.is_empty() && self.config.is_reproducible() {
+            // every test has a seed of its own, so that its challenge code
+            // does not depend on the tests before it
+            let seed = self.rng.gen();
+            self.reseed(seed)?;
+        }
+        self.new_words()
+    }
+
+    /// Shows new words, from the word selector as it is.
+    fn new_words(&mut self) -> Result<()> {
+        if self.config.text_command.is_some() {
+            // fresh output for every test
+            let (word_selector, wordlist_hash, warning, language) =
+                Self::word_selector(&self.config, &self.layout, &mut self.rng)?;
+            self.word_selector = word_selector;
+            self.wordlist_hash = wordlist_hash;
+            self.warning = warning;
+            self.language = language;
+        }
+        let (words, shown_words, hunk, queued) = pick_words(
+            &self.config,
+            self.word_selector.as_mut(),
+            &self.hunks,
+            &mut self.rng,
+        )?;
+        self.words = words;
+        self.shown_words = shown_words;
+        self.hunk = hunk;
+        self.queued = queued;
+        self.retry()
+    }
+
+    /// Picks the words of the next tests with `seed`.
+    fn reseed(&mut self, seed: u64) -> Result<()> {
+        self.seed = seed;
+        self.rng = textgen::selector_rng(Some(seed));
+        self.word_selector = Self::word_selector(&self.config, &self.layout, &mut self.rng)?.0;
+        Ok(())
+    }
+
+    /// The text of the first test with `config`, as it would be shown,
+    /// without running the test. See `toipe generate`.
+    ///
+    /// The text is the same on every call if `config` has a seed.
+    pub fn generate(config: &ToipeConfig) -> Result<String> {
+        let layout = KeyboardLayout::find(&config.layout)?;
+        let mut rng = textgen::selector_rng(config.seed);
+        let (mut word_selector, ..) = Self::word_selector(config, &layout, &mut rng)?;
+        if config.text_command.is_some() {
+            // as in `Toipe::restart`
+            word_selector = Self::word_selector(config, &layout, &mut rng)?.0;
+        }
+        let hunks = match &config.diff {
+            Some(path) => diff::parse(&std::fs::read_to_string(path)?),
+            None => Vec::new(),
+        };
+
+        let (_, shown_words, hunk, _) =
+            pick_words(config, word_selector.as_mut(), &hunks, &mut rng)?;
+        // the words of a hunk are its lines
+        match hunk {
+            Some(_) => Ok(shown_words.join("\n")),
+            None => Ok(textgen::join_words(&shown_words)),
+        }
+    }
+
+    /// Make the terminal ready for another attempt at the same words.
+    ///
+    /// Clears the screen and displays the current words again.
+    pub fn retry(&mut self) -> Result<()> {
         self.tui.reset_screen()?;
-        self.words = self.word_selector.new_words(self.config.num_words)?;
+        if let Some(bot) = &mut self.bot {
+            bot.reset();
+        }
         self.display_hint()?;
         self.show_words()?;
         Ok(())
     }
 
     fn show_words(&mut self) -> Result<()> {
-        self.text = self.tui.display_words(&self.words)?;
+        self.text = match &self.hunk {
+            Some(hunk) => self
+                .tui
+                .display_decorated_lines(&hunk.decorated_lines(self.config.line_numbers))?,
+            None => self.tui.display_words(&self.shown_words)?,
+        };
+
+        let displayed_text = self.displayed_text();
+        if let Some(hunk) = self.hunk.as_ref().filter(|_| self.config.indent_guides) {
+            for index in hunk.indent_guides() {
+                self.tui
+                    .display_at_char(index, &Text::from(displayed_text[index]).with_faint())?;
+            }
+            self.tui.flush()?;
+        }
+
+        if self.config.reveal {
+            let original_text = self.original_text();
+            let hidden = original_text
+                .iter()
+                .enumerate()
+                .skip(reveal_end(&original_text, 0))
+                .filter(|(_, c)| !c.is_whitespace());
+            for (index, _) in hidden {
+                self.tui
+                    .display_at_char(index, &Text::from(MASK).with_faint())?;
+            }
+            self.tui.flush()?;
+        }
         Ok(())
     }
 
@@ -147,212 +664,1209 @@ impl<'a> Toipe {
     /// If the test completes successfully, returns a boolean indicating
     /// whether the user wants to do another test and the
     /// [`ToipeResults`] for this test.
-    pub fn test<T: std::io::Read>(&mut self, mut keys: Keys<T>) -> Result<(bool, ToipeResults)> {
-        let mut input = Vec::<char>::new();
-        let original_text = self
-            .text
-            .iter()
-            .fold(Vec::<char>::new(), |mut chars, text| {
-                chars.extend(text.text().chars());
-                chars
-            });
-        let mut num_errors = 0;
-        let mut num_chars_typed = 0;
+    pub fn test(&mut self, events: &mut EventStream) -> Result<(bool, ToipeResults)> {
+        let mut original_text = self.original_text();
+        let mut displayed_text = self.displayed_text();
+        let mut typed_text = self.typed_text();
+        let theme = self.config.theme;
+        let clock = Rc::clone(&self.clock);
 
-        enum TestStatus {
-            // last key press did not quit/restart - more keys to be entered
-            NotDone,
-            // last letter was typed
-            Done,
-            // user wants to quit test
-            Quit,
-            // user wants to restart test
-            Restart,
+        let mut session = TypingSession::new(self.words.clone())
+            .with_forgive(self.config.forgive)
+            .with_spaces_skipped(
+                self.language
+                    .as_ref()
+                    .is_some_and(|language| !language.script().separates_words()),
+            );
+        if let Some(hunk) = self.hunk.as_ref().filter(|_| !self.config.type_indentation) {
+            session = session.with_indentation(hunk.indentation().into_iter().flatten());
         }
 
-        impl TestStatus {
-            fn to_process_more_keys(&self) -> bool {
-                matches!(self, TestStatus::NotDone)
-            }
+        // the indentation of the first line is skipped before the test
+        // starts
+        let updates = session.skip_indentation();
+        display_updates(
+            &mut self.tui,
+            &theme,
+            &updates,
+            &typed_text,
+            &displayed_text,
+        )?;
+        self.tui.flush()?;
 
-            fn to_display_results(&self) -> bool {
-                matches!(self, TestStatus::Done)
+        // the words of the text to complete the typed start of a word
+        // from, and the chars brightened as its completion
+        let mut assist = self.config.assist.then(|| {
+            let mut trie = Trie::new();
+            for word in original_text.iter().collect::<String>().split_whitespace() {
+                trie.insert(word).ok();
             }
+            trie
+        });
+        let mut assisted = Range::default();
 
-            fn to_restart(&self) -> bool {
-                matches!(self, TestStatus::Restart)
-            }
+        // each word is spoken when the cursor reaches it, see
+        // `--pronounce`
+        let mut pronouncer = self.config.pronounce().map(|command| {
+            Pronouncer::new(command.map_or_else(
+                || pronounce::default_command(self.language.as_ref()),
+                str::to_string,
+            ))
+        });
+        if let Some(pronouncer) = &mut pronouncer {
+            pronouncer.tick(&original_text, session.position())?;
         }
 
-        let mut process_key = |key: Key| -> Result<TestStatus> {
-            match key {
-                Key::Ctrl('c') => {
-                    return Ok(TestStatus::Quit);
-                }
-                Key::Ctrl('r') | Key::Char('\n') => {
-                    return Ok(TestStatus::Restart);
+        // the text is revealed up to the end of the word after the one
+        // being typed, see `--reveal`
+        let reveal = self.config.reveal;
+        let mut revealed = reveal_end(&original_text, session.position());
+
+        // the words shown so far fade out after a while, see `--fade`
+        let mut fading = self.config.fade.map(|seconds| {
+            let mut fading = Fading::new(Duration::try_from_secs_f64(seconds).unwrap_or_default());
+            fading.show(
+                if reveal {
+                    revealed
+                } else {
+                    original_text.len()
+                },
+                clock.now(),
+            );
+            fading
+        });
+
+        // timed tests end after this long, and more words are added to
+        // the text while typing, see `--duration`
+        let time_limit = self.config.duration.map(Duration::from_secs);
+        // the status shows the seconds left and the beat of the
+        // metronome, see `--metronome`, and is redrawn when they change
+        let mut shown_status = None;
+        if time_limit.is_some() || self.metronome.is_some() {
+            let beat = self.metronome.as_ref().map(|_| false);
+            self.tui
+                .display_status(&status_line(&theme, time_limit, beat))?;
+            self.tui.flush()?;
+        }
+
+        // the key of the first char is shown on the keyboard, which lights
+        // up the keys pressed in this test, see `--show-keyboard`
+        self.tui.clear_keyboard_heat();
+        self.tui
+            .display_keyboard(original_text.get(session.position()).copied())?;
+        self.tui.display_title(&progress_title(0.0, 0.0))?;
+        // the chars ahead of the cursor fade into the untyped ones, unless
+        // they are hidden, see the `gradient` of the theme
+        let gradient = theme.shows_gradient() && !reveal && fading.is_none();
+        if gradient {
+            display_gradient(&mut self.tui, &theme, &displayed_text, session.position())?;
+        }
+        self.tui.flush()?;
+
+        // read first key, nothing but fading happens before it, so ticks
+        // are only needed for that
+        events.set_tick_rate(
+            (fading.is_some() || pronouncer.is_some()).then(|| self.frame_interval()),
+        );
+        let key = loop {
+            match events.next() {
+                Some(Ok(ToipeEvent::Key(key))) => break key,
+                Some(Ok(ToipeEvent::Tick)) => {
+                    if let Some(pronouncer) = &mut pronouncer {
+                        pronouncer.tick(&original_text, session.position())?;
+                    }
+                    if let Some(fading) = &mut fading {
+                        fading.fade(
+                            &mut self.tui,
+                            &original_text,
+                            session.position(),
+                            clock.now(),
+                        )?;
+                        self.tui.flush()?;
+                    }
                 }
-                Key::Ctrl('w') => {
-                    // delete last word
-                    if input.len() > 0
-                        && matches!(original_text.get(input.len() - 1), Some(' ') | None)
-                    {
-                        if input.pop().is_some() {
-                            self.tui.replace_text(
-                                Text::from(original_text[input.len()]).with_faint(),
-                            )?;
+                Some(Err(err)) => return Err(err.into()),
+                None => return Err(ToipeError::from("Input was closed".to_string()).into()),
+            }
+        };
+
+        // start the timer
+        let started_at = clock.now();
+        session.start(started_at);
+        if let Some(metronome) = &mut self.metronome {
+            metronome.start(started_at);
+        }
+
+        let idle_tick_rate = self.tick_rate();
+        let frame_interval = self.frame_interval();
+        // when the screen was last redrawn
+        let mut last_frame: Option<Instant> = None;
+        // when the break taken during the test started, if one is being
+        // taken, see `--break-after`
+        let mut on_break: Option<Instant> = None;
+        // whether the test waits for the end of a break, when nothing
+        // happens until a key is pressed
+        let paused = Cell::new(false);
+
+        // `redraw` is false when more keys are waiting to be processed or
+        // the screen was redrawn less than a frame ago, the screen is then
+        // only updated later
+        let mut process_event = |event: ToipeEvent, redraw: bool| -> Result<Option<SessionEnd>> {
+            let now = clock.now();
+            // the timer is paused during a break, until enter is pressed
+            if let Some(break_started_at) = on_break {
+                match event {
+                    ToipeEvent::Key(Key::Char('\n')) => {
+                        let pause = now.saturating_duration_since(break_started_at);
+                        session.pause(pause);
+                        if let Some(bot) = &mut self.bot {
+                            bot.pause();
                         }
-                    }
-                    while input.len() > 0
-                        && !matches!(original_text.get(input.len() - 1), Some(' ') | None)
-                    {
-                        if input.pop().is_some() {
-                            self.tui.replace_text(
-                                Text::from(original_text[input.len()]).with_faint(),
-                            )?;
+                        if let Some(reminder) = &mut self.break_reminder {
+                            reminder.took_break();
                         }
+                        if let Some(metronome) = &mut self.metronome {
+                            metronome.start(now);
+                        }
+                        on_break = None;
+                        paused.set(false);
+                        self.tui.hide_overlay()?;
+                        return Ok(None);
                     }
+                    // quitting still works
+                    ToipeEvent::Key(Key::Ctrl('c')) => {}
+                    _ => return Ok(None),
                 }
-                Key::Char(c) => {
-                    input.push(c);
-
-                    if input.len() >= original_text.len() {
-                        return Ok(TestStatus::Done);
+            } else if let Some(reminder) = &mut self.break_reminder {
+                match event {
+                    ToipeEvent::Key(_) => reminder.typed(now),
+                    ToipeEvent::Tick if reminder.is_due(now) => {
+                        on_break = Some(now);
+                        paused.set(true);
+                        self.tui.display_overlay(&[
+                            vec![Text::from(format!(
+                                "You have been typing for {} minutes, time for a break.",
+                                reminder.after().as_secs() / 60
+                            ))],
+                            vec![Text::from(
+                                "Rest your hands and look away from the screen for a while.",
+                            )],
+                            vec![],
+                            vec![
+                                Text::from("enter").with_color(theme.hint),
+                                Text::from(" to continue, the timer is paused until then")
+                                    .with_faint(),
+                            ],
+                        ])?;
+                        return Ok(None);
                     }
+                    ToipeEvent::Tick => {}
+                }
+            }
 
-                    num_chars_typed += 1;
+            let time_left =
+                time_limit.map(|time_limit| time_limit.saturating_sub(session.elapsed(now)));
+            if time_left.is_some_and(|time_left| time_left.is_zero()) {
+                return Ok(Some(SessionEnd::Done));
+            }
+            let beat = self.metronome.as_mut().map(|metronome| metronome.tick(now));
+            let status = (time_left.map(|time_left| time_left.as_secs()), beat);
+            if (time_left.is_some() || beat.is_some()) && shown_status != Some(status) {
+                self.tui
+                    .display_status(&status_line(&theme, time_left, beat))?;
+                shown_status = Some(status);
+            }
+            // the window title follows the progress, see `--window-title`
+            if self.config.window_title {
+                self.tui
+                    .display_title(&live_title(&session, now, time_limit))?;
+            }
 
-                    if original_text[input.len() - 1] == c {
-                        self.tui
-                            .display_raw_text(&Text::from(c).with_color(color::LightGreen))?;
-                        self.tui.move_to_next_char()?;
+            if let Some(bot) = &mut self.bot {
+                let old_position = bot.position().min(original_text.len() - 1);
+                bot.advance(now);
+                let position = bot.position().min(original_text.len() - 1);
+
+                if position != old_position {
+                    // chars not typed yet are shown as displayed
+                    let shown_char = if old_position < session.position() {
+                        typed_text[old_position]
                     } else {
-                        self.tui.display_raw_text(
-                            &Text::from(original_text[input.len() - 1])
-                                .with_underline()
-                                .with_color(color::Red),
-                        )?;
-                        self.tui.move_to_next_char()?;
-                        num_errors += 1;
+                        displayed_text[old_position]
+                    };
+                    self.tui.display_at_char(
+                        old_position,
+                        &char_text(&theme, shown_char, session.outcome(old_position)),
+                    )?;
+                }
+                self.tui.display_at_char(
+                    position,
+                    &Text::from(displayed_text[position])
+                        .with_underline()
+                        .with_color(theme.cursor),
+                )?;
+            }
+
+            if let Some(fading) = &mut fading {
+                fading.fade(&mut self.tui, &original_text, session.position(), now)?;
+            }
+            if let Some(pronouncer) = &mut pronouncer {
+                pronouncer.tick(&original_text, session.position())?;
+            }
+
+            let key = match event {
+                ToipeEvent::Key(key) => key,
+                ToipeEvent::Tick => {
+                    if redraw {
+                        self.tui
+                            .display_keyboard(original_text.get(session.position()).copied())?;
+                        self.tui.flush()?;
                     }
+                    return Ok(None);
                 }
-                Key::Backspace | Key::Ctrl('h') => {
-                    if input.pop().is_some() {
+            };
+            if debug_log::is_enabled() {
+                debug_log::log(
+                    "key",
+                    [
+                        ("key", debug_log::key(key)),
+                        ("position", session.position().into()),
+                        ("redraw", redraw.into()),
+                    ],
+                );
+            }
+            if let Key::Char(c) = key {
+                self.tui.press_key(c);
+            }
+
+            let updates = session.handle_key(key, now);
+            display_updates(
+                &mut self.tui,
+                &theme,
+                &updates,
+                &typed_text,
+                &displayed_text,
+            )?;
+            if self.config.window_title {
+                self.tui
+                    .display_title(&live_title(&session, now, time_limit))?;
+            }
+            if gradient {
+                display_gradient(&mut self.tui, &theme, &displayed_text, session.position())?;
+            }
+            if let Some(end) = session.end() {
+                return Ok(Some(end));
+            }
+
+            // timed tests never run out of words
+            if time_limit.is_some() && original_text.len() - session.position() < MIN_CHARS_AHEAD {
+                let words = self.word_selector.new_words(self.config.num_words)?;
+                if let Some(trie) = &mut assist {
+                    for word in &words {
+                        trie.insert(word).ok();
+                    }
+                }
+                self.words.extend(words.iter().cloned());
+                session.extend(words.iter().cloned());
+                self.shown_words.extend(words);
+                self.show_words()?;
+                original_text = self.original_text();
+                displayed_text = self.displayed_text();
+                typed_text = self.typed_text();
+
+                // the text is shown from scratch, along with what was typed
+                for (index, typed) in typed_text.iter().enumerate().take(session.position()) {
+                    self.tui
+                        .type_text(&char_text(&theme, *typed, session.outcome(index)))?;
+                }
+                if reveal {
+                    for (index, c) in displayed_text
+                        .iter()
+                        .enumerate()
+                        .take(revealed)
+                        .skip(session.position())
+                    {
                         self.tui
-                            .replace_text(Text::from(original_text[input.len()]).with_faint())?;
+                            .display_at_char(index, &char_text(&theme, *c, CharOutcome::Skipped))?;
                     }
                 }
-                _ => {}
+                if let Some(fading) = &mut fading {
+                    fading.mask_faded(&mut self.tui, &original_text, session.position())?;
+                    if !reveal {
+                        fading.show(original_text.len(), now);
+                    }
+                }
+                assisted = Range::default();
+                shown_status = None;
             }
 
-            self.tui.flush()?;
+            if reveal {
+                let end = reveal_end(&original_text, session.position());
+                for (index, c) in displayed_text
+                    .iter()
+                    .enumerate()
+                    .take(end)
+                    .skip(revealed.max(session.position()))
+                {
+                    self.tui
+                        .display_at_char(index, &char_text(&theme, *c, CharOutcome::Skipped))?;
+                }
+                revealed = revealed.max(end);
+                if let Some(fading) = &mut fading {
+                    fading.show(end, now);
+                }
+            }
+
+            if let Some(trie) = &assist {
+                let completion =
+                    assisted_chars(trie, &original_text, session.input()).unwrap_or_default();
+                for index in assisted.clone() {
+                    if index >= session.position() && !completion.contains(&index) {
+                        self.tui.display_at_char(
+                            index,
+                            &char_text(&theme, displayed_text[index], CharOutcome::Skipped),
+                        )?;
+                    }
+                }
+                for index in completion.clone() {
+                    self.tui
+                        .display_at_char(index, &Text::from(displayed_text[index]))?;
+                }
+                assisted = completion;
+            }
 
-            Ok(TestStatus::NotDone)
+            if redraw {
+                self.tui
+                    .display_keyboard(original_text.get(session.position()).copied())?;
+                self.tui.flush()?;
+            }
+
+            Ok(None)
         };
 
-        // read first key
-        let key = keys.next().unwrap()?;
-        // start the timer
-        let started_at = Instant::now();
         // process first key
-        let mut status = process_key(key)?;
+        let mut event = Some(Ok(ToipeEvent::Key(key)));
+        let mut end = None;
 
-        if status.to_process_more_keys() {
-            for key in &mut keys {
-                status = process_key(key?)?;
-                if !status.to_process_more_keys() {
-                    break;
-                }
+        while let Some(current_event) = event {
+            let redraw = !events.has_pending()
+                && last_frame.is_none_or(|last_frame| last_frame.elapsed() >= frame_interval);
+            if redraw {
+                last_frame = Some(Instant::now());
+            }
+
+            end = process_event(current_event?, redraw)?;
+            if end.is_some() {
+                break;
             }
+
+            // wake up to do a skipped redraw once the frame is over
+            events.set_tick_rate(if paused.get() {
+                None
+            } else if redraw {
+                idle_tick_rate
+            } else {
+                Some(frame_interval)
+            });
+            event = events.next();
+        }
+        events.set_tick_rate(idle_tick_rate);
+
+        if end == Some(SessionEnd::Retry) {
+            // start over with the timer and input reset
+            self.retry()?;
+            return self.test(events);
         }
 
         // stop the timer
-        let ended_at = Instant::now();
-
-        let (final_chars_typed_correctly, final_uncorrected_errors) =
-            input.iter().zip(original_text.iter()).fold(
-                (0, 0),
-                |(total_chars_typed_correctly, total_uncorrected_errors),
-                 (typed_char, orig_char)| {
-                    if typed_char == orig_char {
-                        (total_chars_typed_correctly + 1, total_uncorrected_errors)
-                    } else {
-                        (total_chars_typed_correctly, total_uncorrected_errors + 1)
-                    }
-                },
+        let ended_at = session.ended_at(clock.now(), time_limit);
+        let results = session.results(ended_at);
+
+        // the history before this test, to compare the results with
+        let mut previous = None;
+        let done = end == Some(SessionEnd::Done);
+        self.finished = done;
+        if done {
+            previous = self
+                .results_sink
+                .recent(usize::MAX)
+                .ok()
+                .map(|entries| HistoryStats::from_entries(&entries));
+            let metadata = TestMetadata::current(
+                self.config.text_name(),
+                self.wordlist_hash.clone(),
+                self.config.mode_flags(),
             );
+            if let Err(err) = self
+                .results_sink
+                .append(&HistoryEntry::new(&results, metadata))
+            {
+                self.warning = Some(format!("could not save results: {}", err));
+            }
+            if let Err(err) = char_stats::record(&results) {
+                self.warning = Some(format!("could not save char stats: {}", err));
+            }
+            if self.config.telemetry_endpoint.is_some() {
+                if let Err(err) = telemetry::record(&self.config.mode_flags()) {
+                    self.warning = Some(format!("could not count the test: {}", err));
+                }
+            }
+            if let Some(path) = &self.config.queue {
+                let recorded = queue::record(
+                    Path::new(path),
+                    &self.queued,
+                    &self.words,
+                    &results,
+                    self.config.queue_wpm.unwrap_or_else(queue::default_wpm),
+                    self.config.queue_times,
+                );
+                match recorded {
+                    Ok(mastered) => debug_log::log("queue", [("mastered", mastered.into())]),
+                    Err(err) => self.warning = Some(format!("could not update the queue: {}", err)),
+                }
+            }
 
-        let results = ToipeResults {
-            total_words: self.words.len(),
-            total_chars_typed: num_chars_typed,
-            total_chars_in_text: input.len(),
-            total_char_errors: num_errors,
-            final_chars_typed_correctly,
-            final_uncorrected_errors,
-            started_at,
-            ended_at,
-        };
+            let logged = if self.config.keystroke_log {
+                keystroke_log::save(&results)
+            } else {
+                Ok(())
+            };
+            if let Err(err) = logged.and_then(|_| keystroke_log::prune(self.config.keep_logs_days))
+            {
+                self.warning = Some(format!("could not save keystroke log: {}", err));
+            }
+            if let Some(path) = &self.config.export_keystrokes {
+                if let Err(err) = keystroke_log::export_csv(&results, Path::new(path)) {
+                    self.warning = Some(format!("could not export keystrokes: {}", err));
+                }
+            }
+            if let (Some(server), Some(options)) = (
+                &self.config.challenge_server,
+                self.config.challenge_options(self.seed),
+            ) {
+                let name = self
+                    .config
+                    .name
+                    .clone()
+                    .unwrap_or_else(challenge_board::default_name);
+                let code = challenge_code::encode(&options);
+                self.submissions.push(challenge_board::PendingSubmission {
+                    server: server.clone(),
+                    code,
+                    submission: challenge_board::Submission::new(&name, &results),
+                });
+            }
+        }
 
-        let to_restart = if status.to_display_results() {
-            self.display_results(results.clone(), keys)?
+        let to_restart = if done {
+            if !self.config.no_animations {
+                self.tui.collapse_text(TRANSITION_DURATION)?;
+            }
+            // nothing changes on the results but for confetti until a key
+            // is pressed, so no ticks wake toipe up meanwhile
+            events.set_tick_rate(None);
+            let review = if self.config.review {
+                self.display_review(&results, events)?
+            } else {
+                None
+            };
+            let to_restart = match review {
+                Some(to_restart) => to_restart,
+                None => self.display_results(results.clone(), previous.as_ref(), events)?,
+            };
+            events.set_tick_rate(self.tick_rate());
+            to_restart
         } else {
-            status.to_restart()
+            end == Some(SessionEnd::Restart)
         };
 
         Ok((to_restart, results))
     }
 
+    /// Turns on the options of `drill` for the next tests.
+    fn start_drill(&mut self, drill: Drill) -> Result<()> {
+        drill.apply(&mut self.config);
+        let (word_selector, wordlist_hash, warning, language) =
+            Self::word_selector(&self.config, &self.layout, &mut self.rng)?;
+        self.word_selector = word_selector;
+        self.wordlist_hash = wordlist_hash;
+        self.warning = warning;
+        self.language = language;
+        Ok(())
+    }
+
+    /// How often the typing test needs to be woken up while no key is
+    /// pressed, if at all.
+    fn tick_rate(&self) -> Option<Duration> {
+        (self.bot.is_some()
+            || self.config.fade.is_some()
+            || self.config.duration.is_some()
+            || self.break_reminder.is_some()
+            || self.metronome.is_some()
+            || self.config.pronounce().is_some()
+            || self.config.window_title)
+            .then(|| self.frame_interval())
+    }
+
+    /// Minimum time between redraws of the screen while typing.
+    fn frame_interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.config.fps.max(1) as f64)
+    }
+
+    /// The text to be typed.
+    ///
+    /// Differs from [`Toipe::displayed_text`] only in proofreading mode
+    /// and with indentation guides.
+    fn original_text(&self) -> Vec<char> {
+        // the lines of the displayed text join the words with spaces
+        textgen::join_words(&self.words).chars().collect()
+    }
+
+    /// The text as displayed, with deliberate typos in proofreading
+    /// mode.
+    fn displayed_text(&self) -> Vec<char> {
+        let mut chars = self
+            .text
+            .iter()
+            .fold(Vec::<char>::new(), |mut chars, text| {
+                chars.extend(text.text().chars());
+                chars
+            });
+        self.show_indent_guides(&mut chars);
+        chars
+    }
+
+    /// The text as displayed once typed.
+    fn typed_text(&self) -> Vec<char> {
+        let mut chars = self.original_text();
+        for c in chars.iter_mut().filter(|c| **c == '\n') {
+            *c = tui::PARAGRAPH_MARK;
+        }
+        self.show_indent_guides(&mut chars);
+        chars
+    }
+
+    /// Replaces the spaces where an indentation guide is shown, see
+    /// `--indent-guides`.
+    fn show_indent_guides(&self, chars: &mut [char]) {
+        let Some(hunk) = self.hunk.as_ref().filter(|_| self.config.indent_guides) else {
+            return;
+        };
+        for index in hunk.indent_guides() {
+            if let Some(c @ ' ') = chars.get_mut(index) {
+                *c = INDENT_GUIDE;
+            }
+        }
+    }
+
     pub fn run(&mut self, tty: &mut tty::Tty) -> Result<()> {
-        while tty
-            .map(|source| self.test(source.keys()))
-            .map_or(false, |(restart, _)| restart)
-        {
+        if let Some(wpm) = self.config.demo {
+            return self.run_demo(wpm, tty);
+        }
+
+        if self.config.stats {
+            let mut events = EventStream::new(Terminal::keys(tty.try_clone()?));
+            return self.display_stats(&mut events);
+        }
+        let mut events = self.events(tty)?;
+
+        let mut notifier = self.config.notify().then(Notifier::start);
+        while let Ok((restart, _)) = self.test(&mut events) {
+            if let Some(notifier) = &mut notifier {
+                notifier.test_ended();
+            }
+            if !restart {
+                break;
+            }
+            self.restart()?;
+        }
+        if let Some(notifier) = notifier {
+            notifier.session_ended();
+        }
+        Ok(())
+    }
+
+    /// Runs a single test, then shows the results as usual.
+    ///
+    /// Returns the results if the test was finished rather than quit.
+    pub fn run_once(&mut self, tty: &mut tty::Tty) -> Result<Option<ToipeResults>> {
+        let mut events = self.events(tty)?;
+        let (_, results) = self.test(&mut events)?;
+        Ok(self.finished.then_some(results))
+    }
+
+    /// Keys typed in `tty`, with the options for reading them.
+    fn events(&self, tty: &mut tty::Tty) -> Result<EventStream> {
+        let mut events = EventStream::new(Terminal::keys(tty.try_clone()?));
+        events.set_tick_rate(self.tick_rate());
+        events.set_debounce(self.config.debounce_ms.map(Duration::from_millis));
+        events.set_sticky_shift(
+            self.config
+                .sticky_shift
+                .map(|key| StickyShift::new(key, &self.layout)),
+        );
+        Ok(events)
+    }
+
+    /// Runs a single test typed by a [`SimulatedTypist`], then shows the
+    /// results as usual.
+    fn run_demo(&mut self, wpm: f64, tty: &mut tty::Tty) -> Result<()> {
+        let text: String = self.original_text().into_iter().collect();
+        let script = SimulatedTypist::new(wpm).script(&text);
+
+        // keys from the terminal are only read once the script is over
+        let mut events = EventStream::new(Box::new(
+            ScriptPlayer::new(script).chain(Terminal::keys(tty.try_clone()?)),
+        ));
+        events.set_tick_rate(self.tick_rate());
+
+        self.test(&mut events)?;
+        Ok(())
+    }
+
+    /// Runs `rounds` of one test on each of `keyboards`, asking to switch
+    /// keyboards before every test, see `toipe compare-keyboards`.
+    ///
+    /// Stops early when a test or the results are quit.
+    pub fn compare_keyboards(
+        &mut self,
+        tty: &mut tty::Tty,
+        keyboards: &[String],
+        rounds: usize,
+    ) -> Result<()> {
+        let mut events = self.events(tty)?;
+        for round in 0..rounds {
+            for keyboard in keyboard_comparison::order(keyboards, round) {
+                let lines = vec![
+                    vec![Text::from(format!("Round {} of {}", round + 1, rounds))],
+                    Vec::new(),
+                    vec![
+                        Text::from("Switch to "),
+                        Text::from(keyboard.as_str()).with_color(self.config.theme.results),
+                    ],
+                    Vec::new(),
+                    vec![
+                        Text::from("enter").with_color(self.config.theme.hint),
+                        Text::from(" to start, ").with_faint(),
+                        Text::from("ctrl-c").with_color(self.config.theme.hint),
+                        Text::from(" to stop").with_faint(),
+                    ],
+                ];
+                self.tui.reset_screen()?;
+                self.tui.display_lines(&lines)?;
+                self.tui.hide_cursor()?;
+                let mut start = false;
+                for event in events.by_ref() {
+                    match event? {
+                        ToipeEvent::Key(Key::Char('\n')) => {
+                            start = true;
+                            break;
+                        }
+                        ToipeEvent::Key(Key::Ctrl('c') | Key::Esc) => break,
+                        _ => {}
+                    }
+                }
+                self.tui.show_cursor()?;
+                if !start {
+                    return Ok(());
+                }
+
+                // every keyboard of a round gets the same text
+                let seed = keyboard_comparison::seed(round);
+                self.config.keyboard = Some(keyboard.clone());
+                self.config.seed = Some(seed);
+                self.reseed(seed)?;
+                self.new_words()?;
+                let (to_restart, _) = self.test(&mut events)?;
+                if !self.finished || !to_restart {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Has a [`SimulatedTypist`] typing at `wpm` take test after test,
+    /// with the results of each shown for `pause`, until a key is
+    /// pressed. See `toipe demo`.
+    pub fn run_screensaver(&mut self, tty: &mut tty::Tty, wpm: f64, pause: Duration) -> Result<()> {
+        let (keyboard, pressed) = mpsc::channel();
+        let keys = Terminal::keys(tty.try_clone()?);
+        thread::spawn(move || {
+            for key in keys {
+                if keyboard.send(key).is_err() {
+                    break;
+                }
+            }
+        });
+        let (scripts, received) = mpsc::channel();
+        let mut events = EventStream::new(Box::new(ScriptQueue::new(received, pressed)));
+        events.set_tick_rate(self.tick_rate());
+
+        let mut typist = SimulatedTypist::new(wpm);
+        loop {
+            let text: String = self.original_text().into_iter().collect();
+            let mut script = typist.script(&text);
+            // enter on the results goes on to the next test
+            script.push((pause, Key::Char('\n')));
+            if scripts.send(script).is_err() {
+                return Ok(());
+            }
+            let (to_restart, _) = self.test(&mut events)?;
+            if !to_restart {
+                return Ok(());
+            }
             self.restart()?;
         }
+    }
+
+    /// Shows a summary of the history until a key is pressed, see
+    /// `--stats`.
+    fn display_stats(&mut self, events: &mut EventStream) -> Result<()> {
+        let entries = self.results_sink.recent(usize::MAX)?;
+        let stats = HistoryStats::from_entries(&entries);
+        let results_color = self.config.theme.results;
+        let wpm = |wpm: f64| Text::from(format!("{:.1} wpm", wpm)).with_color(results_color);
+
+        let mut lines: Vec<Vec<Text>> = stats
+            .summary()
+            .into_iter()
+            .map(|(text, speed)| {
+                let mut line = vec![Text::from(text)];
+                line.extend(speed.map(wpm));
+                line
+            })
+            .collect();
+
+        // how closely the plan made with `toipe plan` is followed
+        if let Some(plan) = plan::load()? {
+            let today = Date::today();
+            let done = plan.sessions_done(&entries);
+            let expected = plan.sessions_expected(today);
+            lines.push(Vec::new());
+            lines.push(vec![
+                Text::from("Plan: "),
+                wpm(plan.target_wpm),
+                Text::from(format!(" by {}", plan.by)),
+            ]);
+            lines.push(vec![Text::from(format!(
+                "Sessions done: {} of {} so far ({:.0}%)",
+                done,
+                expected,
+                100.0 * done as f64 / expected.max(1) as f64
+            ))]);
+            lines.push(vec![
+                Text::from("On track today at: "),
+                wpm(plan.milestone(today)),
+            ]);
+        }
+
+        self.tui.reset_screen()?;
+        self.tui.display_lines(&lines)?;
+        self.tui.hide_cursor()?;
+        for event in events.by_ref() {
+            if let ToipeEvent::Key(_) = event? {
+                break;
+            }
+        }
+        self.tui.show_cursor()?;
         Ok(())
     }
 
-    fn display_results<T: std::io::Read>(
+    /// Lines of the text with every char marked by what happened to it
+    /// in the test.
+    fn annotated_lines(&self, results: &ToipeResults) -> Vec<Vec<Text>> {
+        let mut outcomes = results.char_outcomes().into_iter();
+        self.text
+            .iter()
+            .map(|line| {
+                line.text()
+                    .chars()
+                    .zip(&mut outcomes)
+                    .map(|(c, outcome)| char_text(&self.config.theme, c, outcome))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Shows the typed text with all mistakes marked and the speed of
+    /// each word on the selected line. Lines are selected with the arrow
+    /// keys.
+    ///
+    /// Returns whether the user wants to do another test if they
+    /// restarted or quit from here, or `None` to go on to the results.
+    fn display_review(
+        &mut self,
+        results: &ToipeResults,
+        events: &mut EventStream,
+    ) -> Result<Option<bool>> {
+        let annotated_lines = self.annotated_lines(results);
+        let mut word_wpms = results.word_wpms().into_iter();
+        // speeds of the words on each line, wrapped to the width of the
+        // text
+        let width = self.text.iter().map(|line| line.text().len()).max();
+        let width = width.unwrap_or_default();
+        // start of a word broken at the end of the line before, see
+        // `--hyphenate`
+        let mut broken_start = String::new();
+        let num_lines = self.text.len();
+        let line_speeds: Vec<Vec<Vec<Text>>> = self
+            .text
+            .iter()
+            .enumerate()
+            .map(|(line_index, line)| {
+                let mut speed_lines = vec![String::from("wpm:")];
+                let mut words: Vec<String> = line
+                    .text()
+                    .split(' ')
+                    .filter(|word| !word.is_empty())
+                    .map(|word| word.trim_end_matches(tui::PARAGRAPH_MARK).to_string())
+                    .collect();
+                if let Some(first) = words.first_mut() {
+                    first.insert_str(0, &std::mem::take(&mut broken_start));
+                }
+                if line_index + 1 < num_lines && !line.text().ends_with([' ', tui::PARAGRAPH_MARK])
+                {
+                    // the word is timed on the line it ends on
+                    broken_start = words.pop().unwrap_or_default();
+                }
+                for (word, wpm) in words.iter().zip(&mut word_wpms) {
+                    let speed = match wpm {
+                        Some(wpm) => format!("{} {:.0}", word, wpm),
+                        None => format!("{} -", word),
+                    };
+                    let last = speed_lines.last_mut().unwrap();
+                    if last.len() + speed.len() + 2 <= width {
+                        last.push_str(if last.ends_with(':') { " " } else { ", " });
+                        last.push_str(&speed);
+                    } else {
+                        speed_lines.push(speed);
+                    }
+                }
+                speed_lines
+                    .into_iter()
+                    .map(|speeds| vec![Text::from(speeds).with_faint()])
+                    .collect()
+            })
+            .collect();
+
+        let mut selected = 0;
+        loop {
+            let mut lines: Vec<Vec<Text>> = annotated_lines
+                .iter()
+                .enumerate()
+                .map(|(line_no, line)| {
+                    let marker = if line_no == selected { "> " } else { "  " };
+                    std::iter::once(Text::from(marker))
+                        .chain(line.iter().cloned())
+                        .collect()
+                })
+                .collect();
+            lines.push(Vec::new());
+            lines.extend(line_speeds[selected].iter().cloned());
+
+            self.tui.reset_screen()?;
+            self.tui.display_lines(&lines)?;
+            self.tui.display_lines_bottom(&[[
+                Text::from("up/down").with_color(self.config.theme.hint),
+                Text::from(" to pick a line, ").with_faint(),
+                Text::from("enter").with_color(self.config.theme.hint),
+                Text::from(" to see the results").with_faint(),
+            ]])?;
+            self.tui.hide_cursor()?;
+
+            let key = loop {
+                match events.next() {
+                    Some(Ok(ToipeEvent::Key(key))) => break key,
+                    Some(Ok(ToipeEvent::Tick)) => continue,
+                    Some(Err(err)) => return Err(err.into()),
+                    None => return Ok(Some(false)),
+                }
+            };
+            match key {
+                Key::Up => selected = selected.saturating_sub(1),
+                Key::Down => selected = (selected + 1).min(annotated_lines.len() - 1),
+                Key::Char('\n') | Key::Right => return Ok(None),
+                Key::Ctrl('r') => return Ok(Some(true)),
+                Key::Ctrl('c') => return Ok(Some(false)),
+                _ => {}
+            }
+        }
+    }
+
+    fn display_results(
         &mut self,
         results: ToipeResults,
-        mut keys: Keys<T>,
+        previous: Option<&HistoryStats>,
+        events: &mut EventStream,
     ) -> Result<bool> {
         self.tui.reset_screen()?;
 
-        self.tui.display_lines::<&[Text], _>(&[
-            &[Text::from(format!(
+        let precision = self.config.precision;
+        let formula = self.config.wpm_formula;
+        let theme = self.config.theme;
+        let mut speed_line = vec![
+            Text::from("Speed: "),
+            Text::from(format!("{:.*} wpm", precision, results.wpm_with(formula)))
+                .with_color(theme.results),
+            Text::from(format!(" ({})", formula.description())),
+        ];
+        if self.config.normalized_wpm {
+            speed_line.push(
+                Text::from(format!(
+                    ", {:.*} normalized",
+                    precision,
+                    results.normalized_wpm()
+                ))
+                .with_faint(),
+            );
+        }
+
+        let mut mistakes_line = vec![Text::from(format!(
+            "Mistakes: {} out of {} characters",
+            results.total_char_errors, results.total_chars_in_text
+        ))];
+        if results.final_chars_skipped > 0 {
+            mistakes_line.push(
+                Text::from(format!(", {} skipped", results.final_chars_skipped)).with_faint(),
+            );
+        }
+
+        let mut lines: Vec<Vec<Text>> = vec![
+            vec![Text::from(format!(
                 "Took {}s for {} words of {}",
                 results.duration().as_secs(),
                 results.total_words,
                 self.config.text_name(),
             ))],
-            &[
-                Text::from(format!("Accuracy: {:.1}%", results.accuracy() * 100.0))
-                    .with_color(color::Blue),
-            ],
-            &[Text::from(format!(
-                "Mistakes: {} out of {} characters",
-                results.total_char_errors, results.total_chars_in_text
-            ))],
-            &[
-                Text::from("Speed: "),
-                Text::from(format!("{:.1} wpm", results.wpm())).with_color(color::Green),
-                Text::from(" (words per minute)"),
-            ],
-        ])?;
+            vec![Text::from(format!(
+                "Accuracy: {:.*}%",
+                precision,
+                results.accuracy() * 100.0
+            ))
+            .with_color(theme.accuracy)],
+            mistakes_line,
+            speed_line,
+            vec![Text::from(format!(
+                "Difficulty: {:.2} ({:.1} chars per word, {:.0}% punctuation)",
+                results.difficulty.score(),
+                results.difficulty.avg_word_length,
+                results.difficulty.punctuation_density * 100.0,
+            ))
+            .with_faint()],
+        ];
+        let personal_best =
+            previous.is_some_and(|previous| previous.is_personal_best(results.wpm()));
+        if let Some(previous) = previous {
+            let wpm = results.wpm();
+            if personal_best {
+                lines.push(vec![
+                    Text::from("New personal best!").with_color(theme.results)
+                ]);
+            } else if let Some(average) = previous.average_wpm {
+                lines.push(vec![Text::from(format!(
+                    "{:+.1} wpm compared to your average of the last {} tests",
+                    wpm - average,
+                    previous.num_tests.min(history::ROLLING_WINDOW),
+                ))
+                .with_faint()]);
+            }
+        }
+        if self.config.practice {
+            let weakest = char_stats::load()
+                .map(|stats| stats.weakest_chars(NUM_PRACTICED_CHARS))
+                .unwrap_or_default();
+            if !weakest.is_empty() {
+                let weakest: Vec<String> = weakest.iter().map(char::to_string).collect();
+                lines.push(vec![Text::from(format!(
+                    "Practicing: {}",
+                    weakest.join(" ")
+                ))
+                .with_faint()]);
+            }
+        }
+        if let Some(bot) = &self.bot {
+            let bot_duration = bot.time_to_type(results.total_chars_in_text);
+            let duration = results.duration();
+            let outcome = if duration <= bot_duration {
+                Text::from(format!(
+                    "You beat the bot ({:.0} wpm) by {:.1}s",
+                    bot.wpm(),
+                    (bot_duration - duration).as_secs_f64(),
+                ))
+                .with_color(theme.results)
+            } else {
+                Text::from(format!(
+                    "The bot ({:.0} wpm) won the race by {:.1}s",
+                    bot.wpm(),
+                    (duration - bot_duration).as_secs_f64(),
+                ))
+                .with_color(theme.wrong)
+            };
+            lines.push(vec![outcome]);
+        }
+        let tip = coach::tip(&results, &self.layout, &self.config);
+        if let Some(tip) = &tip {
+            lines.push(vec![
+                Text::from("Tip: ").with_color(theme.hint),
+                Text::from(tip.message.as_str()),
+            ]);
+            if let Some(drill) = tip.drill {
+                lines.push(vec![
+                    Text::from("ctrl-t").with_color(theme.hint),
+                    Text::from(format!(" to start a test with {}", drill.option())).with_faint(),
+                ]);
+            }
+        }
+        let drill = tip.and_then(|tip| tip.drill);
+        if let Some(options) = self.config.challenge_options(self.seed) {
+            lines.push(vec![
+                Text::from("Challenge code: "),
+                Text::from(challenge_code::encode(&options)).with_color(theme.results),
+                Text::from(" (see --challenge-code)").with_faint(),
+            ]);
+        }
+        if self.config.detailed_results {
+            if let (Some(burst), Some(sustained)) = (results.burst_wpm(), results.sustained_wpm()) {
+                lines.push(vec![Text::from(format!(
+                    "Burst: {:.1} wpm peak over {}s, {:.1} wpm sustained",
+                    burst,
+                    results::BURST_WINDOW.as_secs(),
+                    sustained,
+                ))
+                .with_faint()]);
+            }
+            if let (Some(reaction), Some(interval)) = (
+                results.avg_reaction_latency(),
+                results.avg_in_word_interval(),
+            ) {
+                lines.push(vec![Text::from(format!(
+                    "Reaction: {}ms per word, {}ms between keys within words",
+                    reaction.as_millis(),
+                    interval.as_millis(),
+                ))
+                .with_faint()]);
+            }
+            let error_positions = ErrorPositions::from_results(&results);
+            if error_positions.total() > 0 {
+                let percent = |n| n as f64 / error_positions.total() as f64 * 100.0;
+                lines.push(vec![Text::from(format!(
+                    "Mistake positions: {:.0}% word start, {:.0}% middle, {:.0}% end",
+                    percent(error_positions.start),
+                    percent(error_positions.middle),
+                    percent(error_positions.end),
+                ))
+                .with_faint()]);
+            }
+            if let Some(balance) = HandBalance::from_results(&results, &self.layout) {
+                lines.push(vec![Text::from(format!(
+                    "Hands: {:.0}% left, {:.0}% alternation, {:.1}% same-finger bigrams",
+                    balance.left_hand_ratio * 100.0,
+                    balance.alternation_rate * 100.0,
+                    balance.same_finger_bigram_rate * 100.0,
+                ))
+                .with_faint()]);
+            }
+        }
+        // the speed over the test as a chart drawn over empty lines at the
+        // end, or else as a sparkline
+        let window = (results.duration() / MAX_CHART_COLUMNS).max(Duration::from_secs(1));
+        let speeds = results.wpm_per_window(window);
+        let chart = self.config.detailed_results && !self.config.mini && speeds.len() > 1;
+        let chart_image = chart && self.tui.supports_images();
+        if chart_image {
+            lines.push(vec![Text::from("Speed over time:").with_faint()]);
+            lines.extend(std::iter::repeat_n(Vec::new(), CHART_ROWS as usize));
+        } else if chart {
+            lines.push(vec![
+                Text::from("Speed over time: ").with_faint(),
+                Text::from(graphics::sparkline(&speeds)).with_color(theme.results),
+            ]);
+        }
+
+        // the minimal layout only has room for the speed and accuracy,
+        // see `--mini`
+        if self.config.mini {
+            lines = vec![vec![
+                Text::from(format!("{:.*} wpm", precision, results.wpm_with(formula)))
+                    .with_color(theme.results),
+                Text::from(format!(", {:.*}%", precision, results.accuracy() * 100.0))
+                    .with_color(theme.accuracy),
+                Text::from(" · enter to restart, ctrl-c to quit").with_faint(),
+            ]];
+        }
+
+        // the typed text with all mistakes marked, above the summary if
+        // there is room for it
+        let mut annotated_lines = self.annotated_lines(&results);
+        let (_, terminal_height) = self.tui.size()?;
+        if !self.config.mini && annotated_lines.len() + lines.len() + 4 <= terminal_height as usize
+        {
+            annotated_lines.push(Vec::new());
+            annotated_lines.append(&mut lines);
+            lines = annotated_lines;
+        }
+
+        self.tui.display_lines(&lines)?;
+        if chart_image {
+            self.tui.display_chart(
+                lines.len(),
+                lines.len() - CHART_ROWS as usize,
+                CHART_ROWS,
+                &speeds,
+                theme.results,
+            )?;
+        }
         self.display_hint()?;
         // no cursor on results page
         self.tui.hide_cursor()?;
 
+        // confetti falls over a new personal best, moved on every tick
+        let mut confetti = if personal_best && !self.config.no_animations {
+            let (width, height) = self.tui.size()?;
+            events.set_tick_rate(Some(self.frame_interval()));
+            Some(Confetti::new(
+                width,
+                height,
+                &mut rand::thread_rng(),
+                self.clock.now(),
+            ))
+        } else {
+            None
+        };
+
         // TODO: make this a bit more general
         // perhaps use a `known_keys_pressed` flag?
         let mut to_restart: Option<bool> = None;
         while to_restart.is_none() {
-            match keys.next().unwrap()? {
+            let key = match events.next() {
+                Some(Ok(ToipeEvent::Key(key))) => key,
+                Some(Ok(ToipeEvent::Tick)) => {
+                    if let Some(falling) = &confetti {
+                        let now = self.clock.now();
+                        self.tui.display_confetti(&falling.pieces(now))?;
+                        if falling.is_over(now) {
+                            confetti = None;
+                            events.set_tick_rate(None);
+                        }
+                    }
+                    continue;
+                }
+                Some(Err(err)) => return Err(err.into()),
+                None => break,
+            };
+            match key {
                 // press ctrl + 'r' to restart
                 Key::Ctrl('r') | Key::Char('\n') => to_restart = Some(true),
                 // press ctrl + 'c' to quit
                 Key::Ctrl('c') => to_restart = Some(false),
+                // press ctrl + 't' to try the drill of the tip
+                Key::Ctrl('t') => {
+                    if let Some(drill) = drill {
+                        self.start_drill(drill)?;
+                        to_restart = Some(true);
+                    }
+                }
                 _ => {}
             }
         }
@@ -362,3 +1876,308 @@ impl<'a> Toipe {
         Ok(to_restart.unwrap_or(false))
     }
 }
+
+/// Number of the chars most often typed wrong shown on the results page
+/// of `--practice`.
+const NUM_PRACTICED_CHARS: usize = 5;
+
+/// Time taken by the text to collapse at the end of a test, see
+/// `--no-animations`.
+const TRANSITION_DURATION: Duration = Duration::from_millis(200);
+
+/// Number of chars ahead of the cursor that fade into the untyped chars,
+/// see [`Theme::gradient`].
+const GRADIENT_CHARS: usize = 8;
+
+/// Most bars in the chart of the speed over a test on the results page of
+/// `--detailed-results`, each for the same part of the test.
+const MAX_CHART_COLUMNS: u32 = 60;
+
+/// Rows taken by the chart of the speed over a test, when drawn as an
+/// image.
+const CHART_ROWS: u16 = 4;
+
+/// More words are added to the text of a timed test once fewer chars than
+/// this are left to type, see `--duration`.
+const MIN_CHARS_AHEAD: usize = 64;
+
+/// Shown in place of the spaces of the indentation, see `--indent-guides`.
+const INDENT_GUIDE: char = '│';
+
+/// Shown in place of the chars of the words not revealed yet or faded
+/// out, see `--reveal` and `--fade`.
+const MASK: char = '·';
+
+/// Chars of the text counting down to fade out after they are shown, see
+/// `--fade`.
+struct Fading {
+    after: Duration,
+    /// when each range of chars fades out, in order
+    pending: VecDeque<(Instant, Range<usize>)>,
+    /// end of the chars shown so far
+    shown: usize,
+}
+
+impl Fading {
+    fn new(after: Duration) -> Self {
+        Self {
+            after,
+            pending: VecDeque::new(),
+            shown: 0,
+        }
+    }
+
+    /// Starts the countdown of the chars up to `end` that were not shown
+    /// before.
+    fn show(&mut self, end: usize, now: Instant) {
+        if end > self.shown {
+            self.pending.push_back((now + self.after, self.shown..end));
+            self.shown = end;
+        }
+    }
+
+    /// Masks the chars that faded out before, unless they were typed
+    /// already, for when the text was shown again.
+    fn mask_faded(&self, tui: &mut ToipeTui, original_text: &[char], typed: usize) -> Result<()> {
+        let faded = self
+            .pending
+            .front()
+            .map_or(self.shown, |(_, range)| range.start);
+        let faded_chars = original_text
+            .iter()
+            .enumerate()
+            .take(faded)
+            .skip(typed)
+            .filter(|(_, c)| !c.is_whitespace());
+        for (index, _) in faded_chars {
+            tui.display_at_char(index, &Text::from(MASK).with_faint())?;
+        }
+        Ok(())
+    }
+
+    /// Masks the chars whose time is up, unless they were typed already.
+    fn fade(
+        &mut self,
+        tui: &mut ToipeTui,
+        original_text: &[char],
+        typed: usize,
+        now: Instant,
+    ) -> Result<()> {
+        while let Some((_, range)) = self.pending.front().filter(|(at, _)| *at <= now) {
+            for index in range.clone().skip_while(|index| *index < typed) {
+                if !original_text[index].is_whitespace() {
+                    tui.display_at_char(index, &Text::from(MASK).with_faint())?;
+                }
+            }
+            self.pending.pop_front();
+        }
+        Ok(())
+    }
+}
+
+/// Time left in a timed test, as shown above the text, see `--duration`.
+/// Shows the chars of the text that changed in a typing test.
+///
+/// `typed_text` and `displayed_text` are the text as shown once typed and
+/// before, see [`Toipe::typed_text`] and [`Toipe::displayed_text`].
+fn display_updates(
+    tui: &mut ToipeTui,
+    theme: &Theme,
+    updates: &[SessionUpdate],
+    typed_text: &[char],
+    displayed_text: &[char],
+) -> Result<()> {
+    for update in updates {
+        match *update {
+            SessionUpdate::Typed { index, outcome } => {
+                tui.type_text(&char_text(theme, typed_text[index], outcome))?;
+            }
+            SessionUpdate::Deleted { index } => {
+                tui.replace_text(char_text(
+                    theme,
+                    displayed_text[index],
+                    CharOutcome::Skipped,
+                ))?;
+            }
+            SessionUpdate::WordEnd { .. } | SessionUpdate::Ended(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Shows the chars from `position` fading from the color of correct
+/// chars to that of untyped chars over [`GRADIENT_CHARS`] chars.
+fn display_gradient(
+    tui: &mut ToipeTui,
+    theme: &Theme,
+    displayed_text: &[char],
+    position: usize,
+) -> Result<()> {
+    let end = (position + GRADIENT_CHARS + 1).min(displayed_text.len());
+    for (k, index) in (position..end).enumerate() {
+        let c = displayed_text[index];
+        // the char after the gradient was in it before a char was deleted
+        let text = if k == GRADIENT_CHARS {
+            char_text(theme, c, CharOutcome::Skipped)
+        } else {
+            let t = (k + 1) as f64 / (GRADIENT_CHARS + 1) as f64;
+            Text::from(c).with_faint().with_color(theme.correct.blend(
+                theme.untyped,
+                t,
+                theme.depth,
+            ))
+        };
+        tui.display_at_char(index, &text)?;
+    }
+    Ok(())
+}
+
+/// Picks the words of the next test with `config`, from `hunks` with
+/// `--diff` or else from `word_selector`.
+///
+/// Returns the words to type, the words as shown, which differ in
+/// proofreading mode, and the hunk they are from, if any.
+#[allow(clippy::type_complexity)]
+fn pick_words(
+    config: &ToipeConfig,
+    word_selector: &mut dyn WordSelector,
+    hunks: &[Hunk],
+    rng: &mut SelectorRng,
+) -> Result<(Vec<String>, Vec<String>, Option<Hunk>, Vec<Queued>)> {
+    let mut hunk = None;
+    let mut queued = Vec::new();
+    let words = if config.diff.is_some() {
+        let random_hunk = diff::random_hunk(hunks, rng)?.clone();
+        let words = random_hunk.added_lines();
+        hunk = Some(random_hunk);
+        words
+    } else {
+        let mut words = word_selector.new_words(config.num_words)?;
+        // quotes are typed as they are
+        if let Some(path) = config.queue.as_ref().filter(|_| !config.is_quote_mode()) {
+            queued = queue::mix_due(Path::new(path), &mut words, rng)?;
+        }
+        words
+    };
+    let shown_words = if config.proofread {
+        words
+            .iter()
+            .map(|word| {
+                rng.gen_bool(config.typo_chance)
+                    .then(|| textgen::with_typo(word, rng))
+                    .flatten()
+                    .unwrap_or_else(|| word.clone())
+            })
+            .collect()
+    } else {
+        words.clone()
+    };
+    debug_log::log(
+        "words",
+        [
+            ("count", words.len().into()),
+            (
+                "typos",
+                words
+                    .iter()
+                    .zip(&shown_words)
+                    .filter(|(word, shown)| word != shown)
+                    .count()
+                    .into(),
+            ),
+            ("hunk", hunk.is_some().into()),
+            ("queued", queued.len().into()),
+        ],
+    );
+    Ok((words, shown_words, hunk, queued))
+}
+
+/// Status shown above the text, with the beat of the metronome and the
+/// seconds left, if any.
+fn status_line(theme: &Theme, time_left: Option<Duration>, beat: Option<bool>) -> Vec<Text> {
+    let mut line = Vec::new();
+    match beat {
+        Some(true) => line.push(Text::from("● ").with_color(theme.hint)),
+        Some(false) => line.push(Text::from("○ ").with_faint()),
+        None => {}
+    }
+    if let Some(time_left) = time_left {
+        line.push(Text::from(format!("{}s", time_left.as_secs())).with_faint());
+    }
+    line
+}
+
+/// Window title with the progress through `session` so far, see
+/// `--window-title`. Timed tests progress with time rather than text.
+fn live_title(session: &TypingSession, now: Instant, time_limit: Option<Duration>) -> String {
+    let elapsed = session.elapsed(now);
+    let progress = match time_limit {
+        Some(time_limit) => elapsed.as_secs_f64() / time_limit.as_secs_f64(),
+        None => session.position() as f64 / session.text().len().max(1) as f64,
+    };
+    let correct = (0..session.position())
+        .filter(|index| session.outcome(*index) == CharOutcome::Correct)
+        .count();
+    let minutes = elapsed.as_secs_f64() / 60.0;
+    let wpm = if minutes > 0.0 {
+        correct as f64 / 5.0 / minutes
+    } else {
+        0.0
+    };
+    progress_title(progress, wpm)
+}
+
+/// End of the word after the one at `index`, up to which the text is
+/// shown with `--reveal`.
+fn reveal_end(original_text: &[char], index: usize) -> usize {
+    let mut end = index;
+    for _ in 0..2 {
+        end += original_text[end..]
+            .iter()
+            .take_while(|c| c.is_whitespace())
+            .count();
+        end += original_text[end..]
+            .iter()
+            .take_while(|c| !c.is_whitespace())
+            .count();
+    }
+    end
+}
+
+/// Chars of the current word left to type, if its start is typed
+/// correctly and no other word in `trie` starts the same way, see
+/// `--assist`.
+fn assisted_chars(trie: &Trie, original_text: &[char], input: &[char]) -> Option<Range<usize>> {
+    let start = original_text[..input.len()]
+        .iter()
+        .rposition(|c| c.is_whitespace())
+        .map_or(0, |index| index + 1);
+    let end = original_text[input.len()..]
+        .iter()
+        .position(|c| c.is_whitespace())
+        .map_or(original_text.len(), |index| input.len() + index);
+    if start == input.len() || input[start..] != original_text[start..input.len()] {
+        return None;
+    }
+
+    let prefix: String = input[start..].iter().collect();
+    let word: String = original_text[start..end].iter().collect();
+    (trie.completion(&prefix)? == word).then_some(input.len()..end)
+}
+
+/// How a char of the text is displayed given what happened to it.
+///
+/// Mistakes that were fixed are shown in a dim red, those left in the
+/// text in a bright underlined red.
+fn char_text(theme: &Theme, original: char, outcome: CharOutcome) -> Text {
+    match outcome {
+        CharOutcome::Correct => Text::from(original).with_color(theme.correct),
+        CharOutcome::Corrected => Text::from(original)
+            .with_faint()
+            .with_color(theme.corrected),
+        CharOutcome::Uncorrected => Text::from(original)
+            .with_underline()
+            .with_color(theme.wrong),
+        CharOutcome::Skipped => Text::from(original).with_faint().with_color(theme.untyped),
+    }
+}
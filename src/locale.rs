@@ -0,0 +1,148 @@
+//! Conventions of languages and regions for writing numbers and quotes,
+//! see `--locale`.
+//!
+//! Only what shows up in generated text is covered: the decimal
+//! separator, how digits are grouped and which quotation marks are used.
+//! Groups are separated by a plain space where the convention is a thin
+//! space, since that is what is typed.
+
+use std::str::FromStr;
+
+/// Conventions of a language, optionally in a region, such as `de` or
+/// `de-CH`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    tag: String,
+    /// separates the decimals, as in `3.14` or `3,14`
+    pub decimal_separator: char,
+    /// separates groups of three digits, as in `12,500` or `12.500`
+    pub group_separator: char,
+    /// whether numbers of four digits are grouped too, as in `1.250`
+    /// rather than `1250`
+    pub groups_four_digits: bool,
+    /// opening and closing quotation marks
+    pub quotes: (char, char),
+}
+
+impl Locale {
+    /// Tags of the supported languages.
+    pub const LANGUAGES: [&'static str; 13] = [
+        "cs", "de", "en", "es", "fr", "it", "ja", "nl", "pl", "pt", "ru", "sv", "uk",
+    ];
+
+    fn new(
+        tag: &str,
+        decimal_separator: char,
+        group_separator: char,
+        groups_four_digits: bool,
+        quotes: (char, char),
+    ) -> Self {
+        Self {
+            tag: tag.to_string(),
+            decimal_separator,
+            group_separator,
+            groups_four_digits,
+            quotes,
+        }
+    }
+
+    /// `number` with its digits grouped.
+    pub fn format_integer(&self, number: u64) -> String {
+        let digits = number.to_string();
+        if digits.len() < 4 || (digits.len() == 4 && !self.groups_four_digits) {
+            return digits;
+        }
+        let mut formatted = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                formatted.push(self.group_separator);
+            }
+            formatted.push(digit);
+        }
+        formatted
+    }
+
+    /// `whole` with `decimals` after the decimal separator, such as
+    /// `12,05` for 12 and "05".
+    pub fn format_decimal(&self, whole: u64, decimals: &str) -> String {
+        format!(
+            "{}{}{}",
+            self.format_integer(whole),
+            self.decimal_separator,
+            decimals
+        )
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Parses a tag such as `fr`, `pt-BR` or `de_CH.UTF-8`.
+    fn from_str(tag: &str) -> Result<Self, Self::Err> {
+        let normalized = tag
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .replace('_', "-")
+            .to_lowercase();
+        let (language, region) = match normalized.split_once('-') {
+            Some((language, region)) => (language, Some(region)),
+            None => (normalized.as_str(), None),
+        };
+        let locale = match (language, region) {
+            ("de", Some("ch" | "li")) => Self::new(tag, '.', '\'', true, ('«', '»')),
+            ("de", _) => Self::new(tag, ',', '.', true, ('„', '“')),
+            ("en", _) => Self::new(tag, '.', ',', true, ('"', '"')),
+            ("es", _) => Self::new(tag, ',', '.', false, ('«', '»')),
+            ("fr", _) => Self::new(tag, ',', ' ', true, ('«', '»')),
+            ("it", _) => Self::new(tag, ',', '.', true, ('«', '»')),
+            ("ja", _) => Self::new(tag, '.', ',', true, ('「', '」')),
+            ("nl", _) => Self::new(tag, ',', '.', true, ('“', '”')),
+            ("pl", _) => Self::new(tag, ',', ' ', false, ('„', '”')),
+            ("pt", Some("br")) => Self::new(tag, ',', '.', true, ('“', '”')),
+            ("pt", _) => Self::new(tag, ',', '.', true, ('«', '»')),
+            ("cs", _) => Self::new(tag, ',', ' ', true, ('„', '“')),
+            ("ru" | "uk", _) => Self::new(tag, ',', ' ', true, ('«', '»')),
+            ("sv", _) => Self::new(tag, ',', ' ', true, ('”', '”')),
+            _ => {
+                return Err(format!(
+                    "unknown locale `{}`, expected one of: {}",
+                    tag,
+                    Self::LANGUAGES.join(", ")
+                ))
+            }
+        };
+        Ok(locale)
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_follow_the_locale() {
+        let locale = |tag: &str| tag.parse::<Locale>().unwrap();
+        assert_eq!(locale("en").format_integer(1250), "1,250");
+        assert_eq!(locale("de").format_integer(1234567), "1.234.567");
+        assert_eq!(locale("de-CH").format_integer(12500), "12'500");
+        assert_eq!(
+            locale("fr_FR.UTF-8").format_decimal(12500, "05"),
+            "12 500,05"
+        );
+        // four digits are not grouped in Spanish
+        assert_eq!(locale("es").format_integer(1250), "1250");
+        assert_eq!(locale("es").format_integer(12500), "12.500");
+        assert_eq!(locale("en").format_integer(999), "999");
+
+        assert_eq!(locale("pt-BR").quotes, ('“', '”'));
+        assert_eq!(locale("pt").quotes, ('«', '»'));
+        assert!("xx".parse::<Locale>().is_err());
+    }
+}
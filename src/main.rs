@@ -1,13 +1,177 @@
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
 use anyhow::Result;
-use clap::StructOpt;
+use clap::CommandFactory;
 
-use toipe::config::ToipeConfig;
-use toipe::Toipe;
+use toipe::config::{self, ToipeCommand, ToipeConfig};
+use toipe::results::{baseline, history, plan};
+use toipe::{
+    challenge_board, debug_log, doctor, keyboard_comparison, profile, schedule, setup, storage,
+    sync, telemetry, user_wordlists, wordlist_picker,
+};
+use toipe::{EmptyWordlistError, Toipe};
 
 fn main() -> Result<()> {
-    let config = ToipeConfig::parse();
+    let args: Vec<String> = std::env::args().collect();
+    let command_index = config::command_index(&args);
+    let loaded = ToipeConfig::load();
+    // the doctor reports an invalid config rather than failing on it
+    if let Err(err) = &loaded {
+        if command_index.is_some_and(|index| args[index] == "doctor") {
+            doctor::run(Err(err))?;
+            return Ok(());
+        }
+    }
+    let mut config = loaded?;
+    if config.incognito {
+        storage::enable_incognito();
+    }
+    if let Some(path) = &config.log_file {
+        debug_log::open(path.as_ref(), config.log_keys)?;
+        debug_log::log("options", [("mode", config.mode_flags().into())]);
+    }
+
+    if let Some(ToipeCommand::Generate { seed, words }) = config.command {
+        config.seed = seed.or(config.seed);
+        config.num_words = words.unwrap_or(config.num_words);
+        println!("{}", Toipe::generate(&config)?);
+        return Ok(());
+    }
+
+    if let Some(ToipeCommand::Demo { wpm, pause }) = config.command {
+        // the tests of the typist are not results of the user
+        storage::enable_incognito();
+        let mut tty = toipe::tty::Tty::new(&config)?;
+        Toipe::new(config)?.run_screensaver(&mut tty, wpm, Duration::from_secs(pause))?;
+        return Ok(());
+    }
+
+    if let Some(ToipeCommand::Calibrate) = config.command {
+        let calibration = config.calibration();
+        let mut tty = toipe::tty::Tty::new(&calibration)?;
+        let results = Toipe::new(calibration)?.run_once(&mut tty)?;
+        // the terminal is restored once the test is dropped
+        match results {
+            Some(results) => baseline::run(&results)?,
+            None => println!("The test was quit before the end, no baseline was measured"),
+        }
+        return Ok(());
+    }
+
+    if let Some(ToipeCommand::Setup) = config.command {
+        let mut tty = toipe::tty::Tty::new(&config)?;
+        // the terminal is restored once the wizard returns
+        if let Some(path) = setup::run(&config, &mut tty)? {
+            println!("Saved the settings to {}", path.display());
+        }
+        return Ok(());
+    }
+
+    if let Some(ToipeCommand::CompareKeyboards {
+        keyboards,
+        rounds,
+        report,
+    }) = &config.command
+    {
+        let (keyboards, rounds, report) = (keyboards.clone(), *rounds, *report);
+        if !report {
+            let mut tty = toipe::tty::Tty::new(&config)?;
+            Toipe::new(config)?.compare_keyboards(&mut tty, &keyboards, rounds)?;
+        }
+        print!(
+            "{}",
+            keyboard_comparison::report(&history::load()?, &keyboards)
+        );
+        return Ok(());
+    }
+
+    if let Some(command) = &config.command {
+        match command {
+            ToipeCommand::Wordlist(command) => user_wordlists::run(command)?,
+            ToipeCommand::History(command) => history::run(command)?,
+            ToipeCommand::Stats { merge } => {
+                let others: Vec<PathBuf> = merge.iter().map(PathBuf::from).collect();
+                print!("{}", history::lifetime_report(&others)?)
+            }
+            ToipeCommand::Backup { archive } => profile::backup(archive.as_ref())?,
+            ToipeCommand::Restore { archive } => profile::restore(archive.as_ref())?,
+            ToipeCommand::Plan { target, by } => plan::run(target.map(|target| target.0), *by)?,
+            ToipeCommand::Challenge(command) => {
+                challenge_board::run(command, config.challenge_server.as_deref())?
+            }
+            ToipeCommand::Doctor => doctor::run(Ok(&config))?,
+            ToipeCommand::Schedule(command) => {
+                // the options before the command are those of the test
+                let index = command_index.expect("a command was given");
+                schedule::run(command, args[1..index].to_vec())?
+            }
+            ToipeCommand::Telemetry => telemetry::run(config.telemetry_endpoint.as_deref())?,
+            ToipeCommand::Completions { shell } => clap_complete::generate(
+                *shell,
+                &mut ToipeConfig::command(),
+                "toipe",
+                &mut io::stdout(),
+            ),
+            ToipeCommand::Manpage => {
+                clap_mangen::Man::new(ToipeConfig::command()).render(&mut io::stdout())?
+            }
+            ToipeCommand::Generate { .. }
+            | ToipeCommand::Demo { .. }
+            | ToipeCommand::Calibrate
+            | ToipeCommand::Setup
+            | ToipeCommand::CompareKeyboards { .. } => {
+                unreachable!("handled above")
+            }
+        }
+        return Ok(());
+    }
+
     let mut tty = toipe::tty::Tty::new(&config)?;
-    let mut toipe = Toipe::new(config)?;
+    let sync_remote = config.sync_remote();
+    let telemetry_endpoint = config.telemetry_endpoint.clone();
+    let can_pick_wordlist = config.is_stdin_tty;
+    let theme = config.theme;
+    let mut toipe = match Toipe::new(config) {
+        Ok(toipe) => toipe,
+        Err(err) => match err.downcast::<EmptyWordlistError>() {
+            // piped text would be read again, so only the error is shown
+            Ok(error) if can_pick_wordlist => {
+                match wordlist_picker::run(&error, theme, &mut tty)? {
+                    Some(wordlist) => {
+                        // the config went with the failed test
+                        let mut config = ToipeConfig::load()?;
+                        config.use_built_in_wordlist(wordlist);
+                        Toipe::new(config)?
+                    }
+                    None => return Err(error.into()),
+                }
+            }
+            Ok(error) => return Err(error.into()),
+            Err(err) => return Err(err),
+        },
+    };
     toipe.run(&mut tty)?;
+    let submissions = toipe.take_submissions();
+    // restore the terminal before submitting and syncing, which can take
+    // a while
+    drop(toipe);
+
+    for submission in submissions {
+        if let Err(err) = submission.submit() {
+            eprintln!("warning: {}", err);
+        }
+    }
+
+    if let Some(remote) = sync_remote {
+        sync::sync(&remote)?;
+    }
+    if let Some(endpoint) = telemetry_endpoint {
+        // usage statistics are not worth failing over
+        if let Err(err) = telemetry::send_due(&endpoint) {
+            debug_log::log("telemetry", [("error", err.to_string().into())]);
+        }
+    }
     Ok(())
 }
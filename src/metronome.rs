@@ -0,0 +1,116 @@
+//! A metronome for typing at an even pace, see `--metronome`.
+//!
+//! Each beat lights up an indicator above the text. When built with the
+//! `audio` feature, each beat also clicks.
+
+use std::time::{Duration, Instant};
+
+/// A metronome beating at a number of keystrokes per minute.
+pub struct Metronome {
+    /// time between beats
+    interval: Duration,
+    /// when the first beat was, if the metronome is running
+    started_at: Option<Instant>,
+    /// number of beats clicked so far
+    beats: u64,
+    #[cfg(feature = "audio")]
+    click: Option<audio::Click>,
+}
+
+impl Metronome {
+    /// A metronome beating `strokes_per_minute` times a minute, stopped
+    /// until [`Metronome::start`].
+    pub fn new(strokes_per_minute: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(60) / strokes_per_minute.max(1),
+            started_at: None,
+            beats: 0,
+            #[cfg(feature = "audio")]
+            click: audio::Click::new(),
+        }
+    }
+
+    /// Starts beating at `at`, or over from `at` if already running.
+    pub fn start(&mut self, at: Instant) {
+        self.started_at = Some(at);
+        self.beats = 0;
+    }
+
+    /// Whether the indicator is lit at `now`, which it is for the first
+    /// half of each beat. Clicks if a beat started since the last call.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let Some(started_at) = self.started_at else {
+            return false;
+        };
+        let elapsed = now.saturating_duration_since(started_at);
+        let beats = (elapsed.as_nanos() / self.interval.as_nanos()) as u64 + 1;
+        if beats > self.beats {
+            self.beats = beats;
+            #[cfg(feature = "audio")]
+            if let Some(click) = &self.click {
+                click.play();
+            }
+        }
+        elapsed.as_nanos() % self.interval.as_nanos() < self.interval.as_nanos() / 2
+    }
+}
+
+#[cfg(feature = "audio")]
+mod audio {
+    use std::time::Duration;
+
+    use rodio::{source::SineWave, OutputStream, OutputStreamHandle, Source};
+
+    /// Length of a click.
+    const CLICK_LENGTH: Duration = Duration::from_millis(15);
+
+    /// Plays clicks on the default audio output.
+    pub struct Click {
+        // the output stops when the stream is dropped
+        _stream: OutputStream,
+        handle: OutputStreamHandle,
+    }
+
+    impl Click {
+        /// `None` if there is no audio output to play clicks on.
+        pub fn new() -> Option<Self> {
+            let (stream, handle) = OutputStream::try_default().ok()?;
+            Some(Self {
+                _stream: stream,
+                handle,
+            })
+        }
+
+        /// Plays a click, without waiting for it to end. A click that
+        /// cannot be played is skipped.
+        pub fn play(&self) {
+            let click = SineWave::new(1000.0)
+                .take_duration(CLICK_LENGTH)
+                .amplify(0.3);
+            self.handle.play_raw(click).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beats_at_the_pace() {
+        // a beat every 200ms
+        let mut metronome = Metronome::new(300);
+        let start = Instant::now();
+        let at = |millis: u64| start + Duration::from_millis(millis);
+        assert!(!metronome.tick(start));
+
+        metronome.start(start);
+        assert!(metronome.tick(at(0)));
+        assert!(metronome.tick(at(99)));
+        assert!(!metronome.tick(at(100)));
+        assert!(metronome.tick(at(200)));
+        assert_eq!(metronome.beats, 2);
+        assert!(!metronome.tick(at(1150)));
+        assert_eq!(metronome.beats, 6);
+    }
+}
@@ -0,0 +1,165 @@
+//! Versioned formats for the data toipe stores on disk.
+//!
+//! Every stored JSON object records the version of its [`Schema`] in a
+//! `schema_version` field. When the format changes, the schema gains a
+//! migration step, and objects written by older versions of toipe are
+//! upgraded step by step when read, and should then be written back in
+//! the latest format so the steps only run once.
+
+use std::{
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use serde_json::{Map, Value};
+
+/// Name of the field holding the schema version of a stored object.
+pub const VERSION_FIELD: &str = "schema_version";
+
+/// A step upgrading an object from one schema version to the next.
+pub type Migration = fn(&mut Map<String, Value>) -> Result<(), Error>;
+
+/// Format of a kind of stored object, such as history entries.
+pub struct Schema {
+    /// used in error messages
+    pub name: &'static str,
+    /// steps upgrading objects from version 1 to 2, 2 to 3, and so on
+    pub migrations: &'static [Migration],
+}
+
+impl Schema {
+    /// Version of objects written by this version of toipe.
+    ///
+    /// Starts at 1 and grows with every migration step.
+    pub const fn version(&self) -> u64 {
+        self.migrations.len() as u64 + 1
+    }
+
+    /// Upgrades an object written with any version of this schema to
+    /// [`Schema::version`].
+    ///
+    /// Returns whether the object was changed.
+    pub fn migrate(&self, value: &mut Value) -> Result<bool, Error> {
+        let object = value.as_object_mut().ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{} entry is not an object", self.name),
+            )
+        })?;
+        let version = object
+            .get(VERSION_FIELD)
+            .and_then(Value::as_u64)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} entry has no {}", self.name, VERSION_FIELD),
+                )
+            })?;
+
+        if version == 0 || version > self.version() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} entry has schema version {}, but this version of toipe only supports up to {}",
+                    self.name,
+                    version,
+                    self.version()
+                ),
+            ));
+        }
+
+        let steps = &self.migrations[version as usize - 1..];
+        for step in steps {
+            step(object)?;
+        }
+        object.insert(VERSION_FIELD.into(), self.version().into());
+
+        Ok(!steps.is_empty())
+    }
+}
+
+/// Parses one JSON object per line, upgrading each object to the latest
+/// version of `schema`.
+///
+/// `source` is only used in error messages. Returns whether any object
+/// had to be upgraded, in which case the objects should be written back
+/// in the latest format.
+pub fn upgrade_json_lines(
+    contents: &str,
+    source: &Path,
+    schema: &Schema,
+) -> Result<(Vec<Value>, bool), Error> {
+    let mut upgraded = false;
+    let values = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_no, line)| {
+            serde_json::from_str::<Value>(line)
+                .map_err(Error::from)
+                .and_then(|mut value| {
+                    upgraded |= schema.migrate(&mut value)?;
+                    Ok(value)
+                })
+                .map_err(|err| {
+                    Error::new(
+                        err.kind(),
+                        format!("{}:{}: {}", source.display(), line_no + 1, err),
+                    )
+                })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok((values, upgraded))
+}
+
+/// Formats objects as one JSON object per line.
+pub fn to_json_lines(values: &[Value]) -> String {
+    values.iter().map(|value| format!("{}\n", value)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename_score(object: &mut Map<String, Value>) -> Result<(), Error> {
+        if let Some(score) = object.remove("score") {
+            object.insert("wpm".into(), score);
+        }
+        Ok(())
+    }
+
+    fn add_tags(object: &mut Map<String, Value>) -> Result<(), Error> {
+        object.insert("tags".into(), Vec::<String>::new().into());
+        Ok(())
+    }
+
+    const SCHEMA: Schema = Schema {
+        name: "Test",
+        migrations: &[rename_score, add_tags],
+    };
+
+    #[test]
+    fn upgrades_json_lines() {
+        let source = Path::new("test.jsonl");
+        let (values, upgraded) = upgrade_json_lines(
+            "{\"schema_version\":1,\"score\":50}\n\n{\"schema_version\":3,\"wpm\":60,\"tags\":[]}\n",
+            source,
+            &SCHEMA,
+        )
+        .unwrap();
+        assert!(upgraded);
+        assert_eq!(values.len(), 2);
+        assert!(values[0].get("score").is_none());
+        assert_eq!(
+            to_json_lines(&values),
+            "{\"schema_version\":3,\"tags\":[],\"wpm\":50}\n{\"schema_version\":3,\"tags\":[],\"wpm\":60}\n"
+        );
+
+        let (_, upgraded) = upgrade_json_lines(&to_json_lines(&values), source, &SCHEMA).unwrap();
+        assert!(!upgraded);
+
+        let err = upgrade_json_lines("{\"schema_version\":4}", source, &SCHEMA).unwrap_err();
+        assert!(err.to_string().starts_with("test.jsonl:1: "));
+    }
+}
@@ -0,0 +1,140 @@
+//! Desktop notifications, see `--notify`.
+//!
+//! Shown when a session of tests ends, with a summary of it, and when
+//! the tests a day of the plan made with `toipe plan` are done (see
+//! [`Plan::tests_per_day`]).
+//!
+//! Notifications are only shown when built with the `notifications`
+//! feature.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::results::{
+    history::{self, HistoryEntry},
+    plan::{self, Date, Plan},
+};
+
+/// Shows notifications for a session of tests, using the history to
+/// tell which tests were done.
+pub struct Notifier {
+    /// when the session started, in seconds since the Unix epoch
+    started_at: u64,
+    /// tests to be taken today, if there is a plan and they are not done
+    /// yet
+    daily_goal: Option<usize>,
+}
+
+impl Notifier {
+    /// Starts a session now.
+    pub fn start() -> Self {
+        let entries = history::load().unwrap_or_default();
+        let daily_goal = plan::load()
+            .ok()
+            .flatten()
+            .map(|plan: Plan| plan.tests_per_day())
+            .filter(|goal| tests_on(&entries, Date::today()) < *goal);
+        Self {
+            started_at: now(),
+            daily_goal,
+        }
+    }
+
+    /// Notifies if the test that just ended met the daily goal.
+    pub fn test_ended(&mut self) {
+        let Some(goal) = self.daily_goal else {
+            return;
+        };
+        let entries = history::load().unwrap_or_default();
+        if tests_on(&entries, Date::today()) >= goal {
+            show(
+                "Daily goal met",
+                &format!("You took the {} tests of your plan for today.", goal),
+            );
+            self.daily_goal = None;
+        }
+    }
+
+    /// Notifies with a summary of the tests done in the session.
+    pub fn session_ended(self) {
+        let entries = history::load().unwrap_or_default();
+        let session: Vec<&HistoryEntry> = entries
+            .iter()
+            .filter(|entry| entry.timestamp >= self.started_at)
+            .collect();
+        if let Some(body) = summary(&session) {
+            show("Session over", &body);
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Number of tests in `entries` taken on `day`.
+fn tests_on(entries: &[HistoryEntry], day: Date) -> usize {
+    entries
+        .iter()
+        .filter(|entry| Date::from_timestamp(entry.timestamp) == day)
+        .count()
+}
+
+/// Summary of the tests of a session, if any were done.
+fn summary(entries: &[&HistoryEntry]) -> Option<String> {
+    let best = entries.iter().map(|entry| entry.wpm).reduce(f64::max)?;
+    let average = entries.iter().map(|entry| entry.wpm).sum::<f64>() / entries.len() as f64;
+    let tests = if entries.len() == 1 { "test" } else { "tests" };
+    Some(format!(
+        "{} {}, {:.1} wpm on average, {:.1} wpm at best",
+        entries.len(),
+        tests,
+        average,
+        best
+    ))
+}
+
+/// Shows a desktop notification. Failing to is not worth stopping for,
+/// as on a system without a notification daemon.
+#[cfg(feature = "notifications")]
+fn show(summary: &str, body: &str) {
+    notify_rust::Notification::new()
+        .appname("toipe")
+        .summary(summary)
+        .body(body)
+        .show()
+        .ok();
+}
+
+#[cfg(not(feature = "notifications"))]
+fn show(_summary: &str, _body: &str) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::history::test_entry;
+
+    #[test]
+    fn summarizes_sessions() {
+        let entries = [
+            test_entry(1_700_000_000, 60.0),
+            test_entry(1_700_000_100, 70.0),
+        ];
+        let session: Vec<&HistoryEntry> = entries.iter().collect();
+        assert_eq!(
+            summary(&session).unwrap(),
+            "2 tests, 65.0 wpm on average, 70.0 wpm at best"
+        );
+        assert_eq!(
+            summary(&session[..1]).unwrap(),
+            "1 test, 60.0 wpm on average, 60.0 wpm at best"
+        );
+        assert_eq!(summary(&[]), None);
+
+        let day = Date::from_timestamp(1_700_000_000);
+        assert_eq!(tests_on(&entries, day), 2);
+        assert_eq!(tests_on(&entries, "2023-11-15".parse().unwrap()), 0);
+    }
+}
@@ -0,0 +1,201 @@
+//! Backup and restore of everything toipe stores on disk.
+//!
+//! A backup is a gzipped tarball with the contents of
+//! [`dirs::config_dir`] under `config/` and the contents of
+//! [`dirs::data_dir`] (history, installed word lists, ...) under `data/`.
+//! The permissions of the files are kept, so that the encryption key and
+//! keystroke logs stay readable only by the user.
+//!
+//! Both are refused in incognito mode, where nothing would be written.
+
+use std::{
+    fs::{self, Metadata},
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::{dirs, results::history, storage, sync::HISTORY_FILES};
+
+/// The directories included in a backup, along with their names in the
+/// archive.
+fn profile_dirs() -> Result<[(&'static str, PathBuf); 2], Error> {
+    Ok([("config", dirs::config_dir()?), ("data", dirs::data_dir()?)])
+}
+
+/// Fails in incognito mode, which discards the writes of a backup or
+/// restore.
+fn refuse_incognito() -> Result<(), Error> {
+    match storage::is_incognito() {
+        true => Err(Error::new(
+            ErrorKind::Unsupported,
+            "backup/restore is disabled in incognito",
+        )),
+        false => Ok(()),
+    }
+}
+
+/// Writes a backup of the configuration and data directories to
+/// `archive`.
+pub fn backup(archive: &Path) -> Result<(), Error> {
+    refuse_incognito()?;
+    backup_dirs(archive, &profile_dirs()?)
+}
+
+/// Writes the contents of `dirs` under their names to `archive`, which is
+/// only readable by the user.
+fn backup_dirs(archive: &Path, dirs: &[(&str, PathBuf)]) -> Result<(), Error> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mut found = false;
+    for (name, dir) in dirs {
+        if dir.is_dir() {
+            builder.append_dir_all(name, dir)?;
+            found = true;
+        }
+    }
+    if !found {
+        return Err(Error::new(ErrorKind::NotFound, "Nothing to back up"));
+    }
+
+    let contents = builder.into_inner()?.finish()?;
+    storage::write_private(archive, &contents)
+}
+
+/// Restores a backup written by [`backup`].
+///
+/// Files in the backup replace existing files with the same name, except
+/// for the history, whose entries are merged with the existing ones (see
+/// [`history::merge`]). Other existing files are kept.
+pub fn restore(archive: &Path) -> Result<(), Error> {
+    refuse_incognito()?;
+    restore_dirs(archive, &profile_dirs()?)
+}
+
+/// Restores the contents of `dirs` from `archive`, see [`restore`].
+fn restore_dirs(archive: &Path, dirs: &[(&str, PathBuf)]) -> Result<(), Error> {
+    // only readable by the user, with a name that can not be guessed
+    let scratch = tempfile::tempdir()?;
+    let mut unpacked = tar::Archive::new(GzDecoder::new(fs::File::open(archive)?));
+    unpacked.set_preserve_permissions(true);
+    unpacked.unpack(scratch.path()).map_err(|err| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{} is not a toipe backup: {}", archive.display(), err),
+        )
+    })?;
+
+    let mut restored = false;
+    for (name, dir) in dirs {
+        let source = scratch.path().join(name);
+        if source.is_dir() {
+            for &history_name in HISTORY_FILES {
+                let history = source.join(history_name);
+                if history.is_file() {
+                    history::merge_into(&dir.join(history_name), &history)?;
+                    fs::remove_file(history)?;
+                }
+            }
+            copy_dir(&source, dir)?;
+            restored = true;
+        }
+    }
+    if !restored {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{} is not a toipe backup", archive.display()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Permissions of a file, to copy them.
+fn mode(metadata: &Metadata) -> u32 {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777
+    }
+    #[cfg(not(unix))]
+    {
+        if metadata.permissions().readonly() {
+            0o444
+        } else {
+            0o666
+        }
+    }
+}
+
+/// Recursively copies the files in `from` into `to`, with their
+/// permissions. Links are skipped.
+fn copy_dir(from: &Path, to: &Path) -> Result<(), Error> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            storage::write_with_mode(&target, &fs::read(entry.path())?, mode(&entry.metadata()?))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::history::test_entry;
+
+    fn append(dir: &Path, timestamp: u64) {
+        history::append_to(&dir.join("history.jsonl"), &test_entry(timestamp, 60.0)).unwrap();
+    }
+
+    #[test]
+    fn backup_and_restore() {
+        let backed_up = tempfile::tempdir().unwrap();
+        let config = backed_up.path().join("config");
+        let data = backed_up.path().join("data");
+        storage::write_private(&config.join("encryption.key"), b"key").unwrap();
+        storage::write(&config.join("config.toml"), b"num-words = 30\n").unwrap();
+        storage::write(&data.join("wordlists/mine.txt"), b"some words\n").unwrap();
+        append(&data, 1);
+        let archive = backed_up.path().join("backup.tar.gz");
+        backup_dirs(
+            &archive,
+            &[("config", config.clone()), ("data", data.clone())],
+        )
+        .unwrap();
+
+        let restored = tempfile::tempdir().unwrap();
+        let dirs = [
+            ("config", restored.path().join("config")),
+            ("data", restored.path().join("data")),
+        ];
+        storage::write(&dirs[1].1.join("char_stats.json"), b"kept\n").unwrap();
+        append(&dirs[1].1, 2);
+        restore_dirs(&archive, &dirs).unwrap();
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(dirs[0].1.join("encryption.key")), "key");
+        assert_eq!(read(dirs[0].1.join("config.toml")), "num-words = 30\n");
+        assert_eq!(read(dirs[1].1.join("wordlists/mine.txt")), "some words\n");
+        assert_eq!(read(dirs[1].1.join("char_stats.json")), "kept\n");
+        let timestamps: Vec<_> = history::load_file(&dirs[1].1.join("history.jsonl"))
+            .unwrap()
+            .iter()
+            .map(|entry| entry.timestamp)
+            .collect();
+        assert_eq!(timestamps, [1, 2]);
+
+        #[cfg(unix)]
+        {
+            let mode = |path: &Path| mode(&fs::metadata(path).unwrap());
+            assert_eq!(mode(&archive), 0o600);
+            assert_eq!(mode(&dirs[0].1.join("encryption.key")), 0o600);
+        }
+
+        let err = restore_dirs(&config.join("config.toml"), &dirs).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
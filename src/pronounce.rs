@@ -0,0 +1,135 @@
+//! Pronunciation of the words while typing them, see `--pronounce`.
+//!
+//! Each word is spoken by a text-to-speech command once the cursor
+//! reaches it. A word is only spoken after the previous one was, so when
+//! typing faster than the words are spoken, the words passed meanwhile
+//! are skipped and the one at the cursor is spoken next.
+//!
+//! Words are only spoken when built with the `audio` feature.
+
+use std::{
+    io::Error,
+    ops::Range,
+    process::{Child, Command, Stdio},
+};
+
+use crate::language::Language;
+
+/// The text-to-speech command used without one given to `--pronounce`:
+/// `say` on Mac OS, and `espeak-ng` with the voice of `language`
+/// elsewhere.
+pub fn default_command(language: Option<&Language>) -> String {
+    if cfg!(target_os = "macos") {
+        return "say".to_string();
+    }
+    match language {
+        Some(language) => format!("espeak-ng -v {}", language),
+        None => "espeak-ng".to_string(),
+    }
+}
+
+/// Speaks the word at the cursor with a text-to-speech command.
+pub struct Pronouncer {
+    /// shell command given the word as its last argument
+    command: String,
+    /// chars of the word spoken last in the text, if any
+    spoken: Option<Range<usize>>,
+    /// the command speaking a word, until it is found to have exited
+    speaking: Option<Child>,
+}
+
+impl Pronouncer {
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            spoken: None,
+            speaking: None,
+        }
+    }
+
+    /// Speaks the word at `position` in `text` unless it was just
+    /// spoken, once the previous word is done. Called on every event, so
+    /// that a word left waiting is spoken on a later tick.
+    pub fn tick(&mut self, text: &[char], position: usize) -> Result<(), Error> {
+        if let Some(child) = &mut self.speaking {
+            if child.try_wait()?.is_none() {
+                return Ok(());
+            }
+            self.speaking = None;
+        }
+        let Some(word) = word_at(text, position) else {
+            return Ok(());
+        };
+        if self.spoken.as_ref() == Some(&word) {
+            return Ok(());
+        }
+        let spoken: String = text[word.clone()].iter().collect();
+        self.spoken = Some(word);
+        self.speaking = Some(self.speak(&spoken)?);
+        Ok(())
+    }
+
+    /// Starts speaking `word`, without waiting for it to be spoken.
+    fn speak(&self, word: &str) -> Result<Child, Error> {
+        Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", self.command))
+            .arg("toipe")
+            .arg(word)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    }
+}
+
+impl Drop for Pronouncer {
+    /// Stops speaking when the test is over.
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.speaking {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+}
+
+/// Chars of the word at `position` in `text`, or of the word after it
+/// when `position` is between words.
+fn word_at(text: &[char], position: usize) -> Option<Range<usize>> {
+    let start = match text.get(position) {
+        Some(c) if !c.is_whitespace() => text[..position]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map_or(0, |index| index + 1),
+        _ => {
+            position
+                + text
+                    .get(position..)?
+                    .iter()
+                    .position(|c| !c.is_whitespace())?
+        }
+    };
+    let end = start
+        + text[start..]
+            .iter()
+            .take_while(|c| !c.is_whitespace())
+            .count();
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_word_at_the_cursor() {
+        let text: Vec<char> = "one two  three".chars().collect();
+        assert_eq!(word_at(&text, 0), Some(0..3));
+        assert_eq!(word_at(&text, 2), Some(0..3));
+        assert_eq!(word_at(&text, 3), Some(4..7));
+        assert_eq!(word_at(&text, 7), Some(9..14));
+        assert_eq!(word_at(&text, 13), Some(9..14));
+        assert_eq!(word_at(&text, 14), None);
+        assert_eq!(word_at(&"one ".chars().collect::<Vec<_>>(), 3), None);
+    }
+}
@@ -0,0 +1,413 @@
+//! Words and phrases to master, see `--queue`.
+//!
+//! The queue is a text file with one item per line, such as a list of
+//! words that are hard to type, kept by the user. Items that are due are
+//! mixed into the text of every test. Typing an item without mistakes at
+//! `--queue-wpm` makes it due again only some time later, so that it has
+//! to be typed well on different occasions rather than in a row. Once it
+//! was typed well `--queue-times` times, it is removed from the file.
+//!
+//! The progress of the items is kept in [`state_file`], next to the
+//! history.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Error, ErrorKind},
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rand::Rng;
+use serde_json::{Map, Value};
+
+use crate::{
+    dirs,
+    results::{baseline, CharOutcome, ToipeResults},
+    storage, textgen,
+};
+
+const SCHEMA_VERSION: u64 = 1;
+
+/// Most items mixed into a test.
+const MAX_ITEMS_PER_TEST: usize = 3;
+
+/// Speed items must be typed at without a baseline, see
+/// [`default_wpm`].
+const DEFAULT_WPM: f64 = 40.0;
+
+/// Seconds until an item typed well is due again, doubled for every time
+/// it was typed well before.
+const FIRST_INTERVAL: u64 = 10 * 60;
+
+/// How an item of the queue is doing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// times the item was typed well
+    pub successes: u64,
+    /// when the item is due again, in seconds since the Unix epoch
+    pub due: u64,
+}
+
+impl Progress {
+    /// Adds a test at `now` in which the item was typed well or not.
+    fn add(&mut self, success: bool, now: u64) {
+        self.due = now;
+        if success {
+            self.due += FIRST_INTERVAL << self.successes.min(16);
+            self.successes += 1;
+        }
+    }
+}
+
+/// [`Progress`] of the items typed so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueueState {
+    pub items: BTreeMap<String, Progress>,
+}
+
+impl QueueState {
+    /// Whether `item` is due at `now`. Items never typed are.
+    pub fn is_due(&self, item: &str, now: u64) -> bool {
+        self.items
+            .get(item)
+            .is_none_or(|progress| progress.due <= now)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let items: Map<String, Value> = self
+            .items
+            .iter()
+            .map(|(item, progress)| {
+                let mut object = Map::new();
+                object.insert("successes".into(), progress.successes.into());
+                object.insert("due".into(), progress.due.into());
+                (item.clone(), object.into())
+            })
+            .collect();
+
+        let mut object = Map::new();
+        object.insert("schema_version".into(), SCHEMA_VERSION.into());
+        object.insert("items".into(), items.into());
+        object.into()
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, Error> {
+        let items = value
+            .get("items")
+            .and_then(Value::as_object)
+            .ok_or_else(|| invalid("items"))?;
+
+        let mut state = Self::default();
+        for (item, progress) in items {
+            let field = |name| {
+                progress
+                    .get(name)
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| invalid(item))
+            };
+            state.items.insert(
+                item.clone(),
+                Progress {
+                    successes: field("successes")?,
+                    due: field("due")?,
+                },
+            );
+        }
+        Ok(state)
+    }
+}
+
+fn invalid(field: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Missing or invalid `{}` in queue state", field),
+    )
+}
+
+/// An item mixed into the words of a test.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Queued {
+    pub item: String,
+    /// indices of its words among the words of the test
+    pub words: Range<usize>,
+}
+
+impl Queued {
+    /// Indices of the chars of the item in the text of `words`.
+    fn chars(&self, words: &[String]) -> Range<usize> {
+        let end = textgen::join_words(&words[..self.words.end])
+            .chars()
+            .count();
+        let len = textgen::join_words(&words[self.words.clone()])
+            .chars()
+            .count();
+        end - len..end
+    }
+}
+
+/// File the progress of the items is stored in.
+///
+/// Usually `~/.local/share/toipe/queue.json`.
+pub fn state_file() -> Result<PathBuf, Error> {
+    Ok(dirs::data_dir()?.join("queue.json"))
+}
+
+/// The progress of the items so far, or none if nothing was typed yet.
+pub fn load() -> Result<QueueState, Error> {
+    match fs::read_to_string(state_file()?) {
+        Ok(contents) => QueueState::from_json(&serde_json::from_str::<Value>(&contents)?),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(QueueState::default()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Items of the queue file at `path`, in order. Blank lines and lines
+/// starting with `#` are left out.
+pub fn read_items(path: &Path) -> Result<Vec<String>, Error> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|item| !item.is_empty() && !item.starts_with('#'))
+        .collect())
+}
+
+/// Speed items must be typed at when `--queue-wpm` is not given: the
+/// speed of the baseline, if `toipe calibrate` was run.
+pub fn default_wpm() -> f64 {
+    baseline::load()
+        .ok()
+        .flatten()
+        .map_or(DEFAULT_WPM, |baseline| baseline.wpm)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Mixes the first items of the queue file at `path` that are due into
+/// `words`, each at a random place between them.
+pub fn mix_due(
+    path: &Path,
+    words: &mut Vec<String>,
+    rng: &mut impl Rng,
+) -> Result<Vec<Queued>, Error> {
+    let state = load()?;
+    let now = now();
+    let due: Vec<String> = read_items(path)?
+        .into_iter()
+        .filter(|item| state.is_due(item, now))
+        .take(MAX_ITEMS_PER_TEST)
+        .collect();
+    Ok(mix(&due, words, rng))
+}
+
+fn mix(items: &[String], words: &mut Vec<String>, rng: &mut impl Rng) -> Vec<Queued> {
+    let mut places: Vec<(usize, &String)> = items
+        .iter()
+        .map(|item| (rng.gen_range(0..=words.len()), item))
+        .collect();
+    places.sort_by_key(|(place, _)| *place);
+    let mut places = places.into_iter().peekable();
+
+    let mut mixed = Vec::new();
+    let mut queued = Vec::new();
+    for index in 0..=words.len() {
+        while let Some((_, item)) = places.next_if(|(place, _)| *place == index) {
+            let start = mixed.len();
+            mixed.extend(item.split(' ').map(String::from));
+            queued.push(Queued {
+                item: item.clone(),
+                words: start..mixed.len(),
+            });
+        }
+        mixed.extend(words.get(index).cloned());
+    }
+    *words = mixed;
+    queued
+}
+
+/// Adds the `queued` items of a test of `words` that ended now, with
+/// `results`, to their progress. Items typed well `times` times at
+/// `wpm` are removed from the queue file at `path`.
+///
+/// Returns the items removed.
+pub fn record(
+    path: &Path,
+    queued: &[Queued],
+    words: &[String],
+    results: &ToipeResults,
+    wpm: f64,
+    times: u64,
+) -> Result<Vec<String>, Error> {
+    let mut state = load()?;
+    let mastered = add(&mut state, queued, words, results, wpm, times, now());
+
+    // items no longer in the file are forgotten
+    let items = read_items(path)?;
+    state.items.retain(|item, _| items.contains(item));
+    if !mastered.is_empty() {
+        let contents: String = fs::read_to_string(path)?
+            .lines()
+            .filter(|line| {
+                let item = line.split_whitespace().collect::<Vec<_>>().join(" ");
+                !mastered.contains(&item)
+            })
+            .map(|line| format!("{}\n", line))
+            .collect();
+        storage::write(path, contents.as_bytes())?;
+    }
+    storage::write(&state_file()?, format!("{}\n", state.to_json()).as_bytes())?;
+    Ok(mastered)
+}
+
+fn add(
+    state: &mut QueueState,
+    queued: &[Queued],
+    words: &[String],
+    results: &ToipeResults,
+    wpm: f64,
+    times: u64,
+    now: u64,
+) -> Vec<String> {
+    let outcomes = results.char_outcomes();
+    let mut mastered = Vec::new();
+    for queued in queued {
+        let chars = queued.chars(words);
+        // items after the end of a timed test were not typed
+        if chars.end > results.total_chars_in_text {
+            continue;
+        }
+        let correct = outcomes[chars.clone()]
+            .iter()
+            .all(|outcome| *outcome == CharOutcome::Correct);
+        let fast = results.span_wpm(chars).is_some_and(|speed| speed >= wpm);
+        let progress = state.items.entry(queued.item.clone()).or_default();
+        progress.add(correct && fast, now);
+        if progress.successes >= times {
+            state.items.remove(&queued.item);
+            mastered.push(queued.item.clone());
+        }
+    }
+    mastered
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    /// Results of typing `text` as `typed`, one char every 100 ms.
+    fn results(text: &str, typed: &str) -> ToipeResults {
+        ToipeResults::from_typed(text, typed, Duration::from_millis(100))
+    }
+
+    #[test]
+    fn mixes_items_between_words() {
+        let mut words: Vec<String> = ["a", "b", "c"].map(String::from).to_vec();
+        let items = ["x y".to_string(), "z".to_string()];
+        let queued = mix(&items, &mut words, &mut StdRng::seed_from_u64(0));
+
+        assert_eq!(words.len(), 6);
+        assert_eq!(queued.len(), 2);
+        for queued in &queued {
+            assert_eq!(words[queued.words.clone()].join(" "), queued.item);
+        }
+        let rest: Vec<&String> = words
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !queued.iter().any(|queued| queued.words.contains(index)))
+            .map(|(_, word)| word)
+            .collect();
+        assert_eq!(rest, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn items_are_mastered_over_time() {
+        let words: Vec<String> = ["ab", "cd"].map(String::from).to_vec();
+        let queued = [Queued {
+            item: "cd".to_string(),
+            words: 1..2,
+        }];
+        let mut state = QueueState::default();
+
+        // 2 chars in 200 ms is 120 wpm
+        let mastered = add(
+            &mut state,
+            &queued,
+            &words,
+            &results("ab cd", "ab cd"),
+            100.0,
+            2,
+            0,
+        );
+        assert!(mastered.is_empty());
+        assert_eq!(state.items["cd"].successes, 1);
+        assert!(!state.is_due("cd", 0));
+        assert!(state.is_due("cd", FIRST_INTERVAL));
+
+        // too slow
+        add(
+            &mut state,
+            &queued,
+            &words,
+            &results("ab cd", "ab cd"),
+            200.0,
+            2,
+            1,
+        );
+        assert_eq!(state.items["cd"].successes, 1);
+        // a mistake
+        add(
+            &mut state,
+            &queued,
+            &words,
+            &results("ab cd", "ab cx"),
+            100.0,
+            2,
+            2,
+        );
+        assert_eq!(state.items["cd"].successes, 1);
+
+        let mastered = add(
+            &mut state,
+            &queued,
+            &words,
+            &results("ab cd", "ab cd"),
+            100.0,
+            2,
+            3,
+        );
+        assert_eq!(mastered, ["cd"]);
+        assert!(state.items.is_empty());
+
+        // not reached before the end of the test
+        add(
+            &mut state,
+            &queued,
+            &words,
+            &results("ab cd", "ab"),
+            100.0,
+            2,
+            4,
+        );
+        assert!(state.items.is_empty());
+
+        let mut state = QueueState::default();
+        state.items.insert(
+            "a b".to_string(),
+            Progress {
+                successes: 2,
+                due: 60,
+            },
+        );
+        assert_eq!(QueueState::from_json(&state.to_json()).unwrap(), state);
+    }
+}
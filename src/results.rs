@@ -1,8 +1,72 @@
+use std::ops::Range;
 use std::time::{Duration, Instant};
 
+use clap::ArgEnum;
+
+use crate::textgen::TextDifficulty;
+
+pub mod baseline;
+pub mod char_stats;
+pub mod history;
+pub mod keystroke_log;
+pub mod plan;
+pub mod sink;
+
+/// Length of the windows used for [`ToipeResults::burst_wpm`] and
+/// [`ToipeResults::sustained_wpm`].
+pub const BURST_WINDOW: Duration = Duration::from_secs(5);
+
+/// A character typed during a typing test.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keystroke {
+    /// time since the start of the test
+    pub at: Duration,
+    /// position of the typed char in the text
+    pub index: usize,
+    /// char in the text at this position
+    pub expected: char,
+    /// char that was actually typed
+    pub typed: char,
+}
+
+impl Keystroke {
+    /// Whether the right char was typed.
+    pub fn is_correct(&self) -> bool {
+        self.expected == self.typed
+    }
+}
+
+/// How a char of the text ended up after a typing test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharOutcome {
+    /// typed correctly on the first try
+    Correct,
+    /// typed wrong at some point but fixed later
+    Corrected,
+    /// typed wrong and left that way
+    Uncorrected,
+    /// never typed
+    Skipped,
+}
+
+impl CharOutcome {
+    /// Outcome of a char given what is currently typed in its place and
+    /// whether it was ever typed wrong.
+    pub fn of(expected: char, typed: Option<char>, had_mistake: bool) -> Self {
+        match typed {
+            None => Self::Skipped,
+            Some(typed) if typed != expected => Self::Uncorrected,
+            Some(_) if had_mistake => Self::Corrected,
+            Some(_) => Self::Correct,
+        }
+    }
+}
+
 /// Stores stats from a typing test.
 #[derive(Clone)]
 pub struct ToipeResults {
+    /// the given text
+    pub text: String,
     /// number of words in given text
     pub total_words: usize,
     /// number of chars typed including those typed before being cleared
@@ -17,27 +81,42 @@ pub struct ToipeResults {
     pub final_chars_typed_correctly: usize,
     /// number of chars in given text that were wrongly typed at the end of the test
     pub final_uncorrected_errors: usize,
+    /// number of chars in given text that were skipped over instead of
+    /// being typed (see [`ToipeConfig::forgive`](crate::config::ToipeConfig::forgive))
+    pub final_chars_skipped: usize,
     pub started_at: Instant,
     pub ended_at: Instant,
+    /// estimated difficulty of the given text
+    pub difficulty: TextDifficulty,
+    /// every char typed during the test, in order, including those that
+    /// were later cleared
+    pub keystrokes: Vec<Keystroke>,
 }
 
 impl ToipeResults {
     /// Duration of the test.
     ///
     /// i.e., the time between the user pressing the first key and them
-    /// typing the last letter.
+    /// typing the last letter, or the time limit of a timed test (see
+    /// [`ToipeConfig::duration`](crate::config::ToipeConfig::duration)).
     pub fn duration(&self) -> Duration {
         self.ended_at.duration_since(self.started_at)
     }
 
+    /// Speed and accuracy of the test, see [`score`].
+    pub fn score(&self) -> Score {
+        score(
+            self.total_chars_typed,
+            self.total_char_errors,
+            self.final_chars_typed_correctly,
+            self.final_uncorrected_errors,
+            self.duration(),
+        )
+    }
+
     /// Percentage of letters that were typed correctly.
     pub fn accuracy(&self) -> f64 {
-        if self.total_chars_typed == 0 {
-            return 0.0;
-        }
-
-        (self.total_chars_typed as isize - self.total_char_errors as isize) as f64
-            / self.total_chars_typed as f64
+        self.score().accuracy
     }
 
     /// Speed in (correctly typed) words per minute.
@@ -50,30 +129,363 @@ impl ToipeResults {
     ///
     /// Uncorrected errors are penalized to encourage correcting errors.
     pub fn wpm(&self) -> f64 {
-        (self.final_chars_typed_correctly as f64 / 5.0 - self.final_uncorrected_errors as f64)
-            .max(0.0)
-            / (self.duration().as_secs_f64() / 60.0)
+        self.score().wpm
+    }
+
+    /// Speed in words per minute using the given formula.
+    ///
+    /// [`ToipeResults::wpm`] is the same as [`WpmFormula::Net`].
+    pub fn wpm_with(&self, formula: WpmFormula) -> f64 {
+        let minutes = self.duration().as_secs_f64() / 60.0;
+        match formula {
+            WpmFormula::Gross => {
+                (self.total_chars_in_text - self.final_chars_skipped) as f64 / 5.0 / minutes
+            }
+            WpmFormula::Net => self.wpm(),
+            WpmFormula::Words => self.words_typed_correctly() as f64 / minutes,
+        }
+    }
+
+    /// Number of words of the text in which every char was typed
+    /// correctly by the end of the test.
+    fn words_typed_correctly(&self) -> usize {
+        let outcomes = self.char_outcomes();
+        let mut outcomes = outcomes.iter();
+        self.text
+            .split([' ', '\n'])
+            .filter(|word| {
+                // the space after the word is not part of it
+                let word_outcomes: Vec<_> = outcomes.by_ref().take(word.chars().count()).collect();
+                outcomes.next();
+                !word.is_empty()
+                    && word_outcomes.iter().all(|outcome| {
+                        matches!(outcome, CharOutcome::Correct | CharOutcome::Corrected)
+                    })
+            })
+            .count()
+    }
+
+    /// Speed in words per minute, adjusted for the difficulty of the text.
+    ///
+    /// Measured as [`ToipeResults::wpm`] multiplied by the
+    /// [difficulty score](TextDifficulty::score) of the text, so that
+    /// speeds on easy and hard texts can be compared. [`ToipeResults::wpm`]
+    /// is still the actual speed.
+    pub fn normalized_wpm(&self) -> f64 {
+        self.wpm() * self.difficulty.score()
+    }
+
+    /// Reaction latency for each word, i.e., the time between a word
+    /// becoming the current word and its first char being typed.
+    ///
+    /// A word becomes current when the space before it is typed. The
+    /// first word is skipped since the test only starts when it is typed.
+    pub fn reaction_latencies(&self) -> Vec<Duration> {
+        let mut latencies = Vec::new();
+        // index of the first char of the current word and when the word
+        // became current
+        let mut current_word: Option<(usize, Duration)> = None;
+
+        for keystroke in &self.keystrokes {
+            if let Some((index, since)) = current_word {
+                if keystroke.index == index {
+                    latencies.push(keystroke.at.saturating_sub(since));
+                    current_word = None;
+                }
+            }
+            if matches!(keystroke.expected, ' ' | '\n') {
+                current_word = Some((keystroke.index + 1, keystroke.at));
+            }
+        }
+
+        latencies
+    }
+
+    /// Average of [`ToipeResults::reaction_latencies`], if any.
+    pub fn avg_reaction_latency(&self) -> Option<Duration> {
+        average(&self.reaction_latencies())
+    }
+
+    /// Number of correctly typed chars with `start <= keystroke.at < end`.
+    fn correct_chars_between(&self, start: Duration, end: Duration) -> usize {
+        self.keystrokes
+            .iter()
+            .filter(|keystroke| {
+                keystroke.is_correct() && start <= keystroke.at && keystroke.at < end
+            })
+            .count()
+    }
+
+    /// Peak speed in words per minute over any [`BURST_WINDOW`] of the test.
+    ///
+    /// Only correctly typed chars are counted. Returns `None` if the test
+    /// was shorter than [`BURST_WINDOW`].
+    pub fn burst_wpm(&self) -> Option<f64> {
+        if self.duration() < BURST_WINDOW {
+            return None;
+        }
+
+        self.keystrokes
+            .iter()
+            .map(|keystroke| self.correct_chars_between(keystroke.at, keystroke.at + BURST_WINDOW))
+            .max()
+            .map(|num_chars| chars_to_wpm(num_chars, BURST_WINDOW))
+    }
+
+    /// Typical speed in words per minute that was kept up over the test.
+    ///
+    /// The test is split into consecutive [`BURST_WINDOW`]s, and the
+    /// median of their speeds is taken. Unlike [`ToipeResults::wpm`], this
+    /// is not skewed by short bursts or pauses. Only correctly typed chars
+    /// are counted. Returns `None` if the test was shorter than
+    /// [`BURST_WINDOW`].
+    pub fn sustained_wpm(&self) -> Option<f64> {
+        let mut speeds = self.wpm_per_window(BURST_WINDOW);
+        if speeds.is_empty() {
+            return None;
+        }
+        speeds.sort_by(f64::total_cmp);
+
+        let mid = speeds.len() / 2;
+        Some(if speeds.len().is_multiple_of(2) {
+            (speeds[mid - 1] + speeds[mid]) / 2.0
+        } else {
+            speeds[mid]
+        })
+    }
+
+    /// Speed in words per minute over each whole `window` of the test,
+    /// in order. Only correctly typed chars are counted.
+    pub fn wpm_per_window(&self, window: Duration) -> Vec<f64> {
+        let num_windows = (self.duration().as_secs_f64() / window.as_secs_f64()) as u32;
+        (0..num_windows)
+            .map(|i| {
+                let num_chars = self.correct_chars_between(window * i, window * (i + 1));
+                chars_to_wpm(num_chars, window)
+            })
+            .collect()
+    }
+
+    /// What happened to each char of the text, see [`CharOutcome`].
+    pub fn char_outcomes(&self) -> Vec<CharOutcome> {
+        let mut typed = vec![None; self.text.chars().count()];
+        let mut had_mistake = vec![false; typed.len()];
+        for keystroke in &self.keystrokes {
+            if keystroke.index < typed.len() {
+                // later keystrokes at the same index replace earlier ones
+                typed[keystroke.index] = Some(keystroke.typed);
+                had_mistake[keystroke.index] |= !keystroke.is_correct();
+            }
+        }
+
+        self.text
+            .chars()
+            .zip(typed)
+            .zip(had_mistake)
+            .map(|((expected, typed), had_mistake)| CharOutcome::of(expected, typed, had_mistake))
+            .collect()
+    }
+
+    /// Speed in words per minute for each word of the text, with words
+    /// split on spaces and paragraph breaks.
+    ///
+    /// A word is timed from when the space before it was typed until
+    /// its last char was typed, so the time taken to react to the word
+    /// is included. `None` for words that were not fully typed.
+    pub fn word_wpms(&self) -> Vec<Option<f64>> {
+        let last_typed_at = self.last_typed_at();
+        let mut wpms = Vec::new();
+        let mut start = 0;
+        for word in self.text.split([' ', '\n']) {
+            let end = start + word.chars().count();
+            if end > start {
+                wpms.push(span_wpm(&last_typed_at, start..end));
+            }
+            start = end + 1;
+        }
+
+        wpms
+    }
+
+    /// Speed in words per minute of typing the chars at `span`, timed
+    /// like the words of [`ToipeResults::word_wpms`].
+    pub fn span_wpm(&self, span: Range<usize>) -> Option<f64> {
+        span_wpm(&self.last_typed_at(), span)
+    }
+
+    /// When each char of the text was last typed, if it was.
+    fn last_typed_at(&self) -> Vec<Option<Duration>> {
+        let mut last_typed_at = vec![None; self.text.chars().count()];
+        for keystroke in &self.keystrokes {
+            if keystroke.index < last_typed_at.len() {
+                last_typed_at[keystroke.index] = Some(keystroke.at);
+            }
+        }
+        last_typed_at
+    }
+
+    /// Average time between consecutive keystrokes within a word.
+    ///
+    /// This is the steady-state typing speed, without the time taken to
+    /// react to each new word.
+    pub fn avg_in_word_interval(&self) -> Option<Duration> {
+        let intervals: Vec<Duration> = self
+            .keystrokes
+            .windows(2)
+            .filter(|pair| {
+                pair[1].index == pair[0].index + 1 && !matches!(pair[0].expected, ' ' | '\n')
+            })
+            .map(|pair| pair[1].at.saturating_sub(pair[0].at))
+            .collect();
+        average(&intervals)
+    }
+}
+
+#[cfg(test)]
+impl ToipeResults {
+    /// Results of typing each char of `typed` over the char at the same
+    /// position of `text`, one every `interval`, without any corrections.
+    pub fn from_typed(text: &str, typed: &str, interval: Duration) -> Self {
+        let keystrokes: Vec<Keystroke> = text
+            .chars()
+            .zip(typed.chars())
+            .enumerate()
+            .map(|(index, (expected, typed))| Keystroke {
+                at: interval * index as u32,
+                index,
+                expected,
+                typed,
+            })
+            .collect();
+        let num_errors = keystrokes.iter().filter(|k| !k.is_correct()).count();
+        let words: Vec<String> = text.split_whitespace().map(String::from).collect();
+        let started_at = Instant::now();
+        Self {
+            text: text.to_string(),
+            total_words: words.len(),
+            total_chars_typed: keystrokes.len(),
+            // the test ends where the typing stopped, as in a timed test
+            total_chars_in_text: keystrokes.len(),
+            total_char_errors: num_errors,
+            final_chars_typed_correctly: keystrokes.len() - num_errors,
+            final_uncorrected_errors: num_errors,
+            final_chars_skipped: 0,
+            started_at,
+            ended_at: started_at + keystrokes.last().map_or(Duration::ZERO, |k| k.at),
+            difficulty: TextDifficulty::from_words(&words),
+            keystrokes,
+        }
+    }
+}
+
+/// A way of calculating typing speed in words per minute.
+///
+/// Different typing tests and communities define words per minute
+/// differently. A "word" is 5 chars, except for [`WpmFormula::Words`].
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum, Debug)]
+pub enum WpmFormula {
+    /// All chars left in the text, whether right or wrong, per minute.
+    Gross,
+    /// Correctly typed chars per minute, minus a word for each
+    /// uncorrected error. See [`ToipeResults::wpm`].
+    Net,
+    /// Actual words of the text typed without uncorrected errors per
+    /// minute, regardless of their length.
+    Words,
+}
+
+impl WpmFormula {
+    /// Short description for displaying next to a speed.
+    pub fn description(&self) -> &'static str {
+        match self {
+            WpmFormula::Gross => "gross words per minute",
+            WpmFormula::Net => "words per minute",
+            WpmFormula::Words => "actual words per minute",
+        }
+    }
+}
+
+/// Speed and accuracy of a typing test.
+///
+/// The speed is calculated with [`WpmFormula::Net`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Score {
+    /// see [`ToipeResults::wpm`]
+    pub wpm: f64,
+    /// see [`ToipeResults::accuracy`]
+    pub accuracy: f64,
+}
+
+/// Scores a typing test from the counts in [`ToipeResults`] with the
+/// same names.
+///
+/// This is how toipe scores every test, exposed so that other tools can
+/// score tests in exactly the same way.
+pub fn score(
+    total_chars_typed: usize,
+    total_char_errors: usize,
+    final_chars_typed_correctly: usize,
+    final_uncorrected_errors: usize,
+    duration: Duration,
+) -> Score {
+    let accuracy = if total_chars_typed == 0 {
+        0.0
+    } else {
+        (total_chars_typed as isize - total_char_errors as isize) as f64 / total_chars_typed as f64
+    };
+
+    let wpm = (final_chars_typed_correctly as f64 / 5.0 - final_uncorrected_errors as f64).max(0.0)
+        / (duration.as_secs_f64() / 60.0);
+
+    Score { wpm, accuracy }
+}
+
+/// Speed in words per minute of `num_correct_chars` typed in `duration`.
+fn chars_to_wpm(num_correct_chars: usize, duration: Duration) -> f64 {
+    num_correct_chars as f64 / 5.0 / (duration.as_secs_f64() / 60.0)
+}
+
+/// Speed of typing the chars at `span`, from when the char before them
+/// was typed until the last of them was, with the chars typed at the
+/// times of `last_typed_at`.
+fn span_wpm(last_typed_at: &[Option<Duration>], span: Range<usize>) -> Option<f64> {
+    let started_at = match span.start {
+        0 => Some(Duration::ZERO),
+        start => *last_typed_at.get(start - 1)?,
+    };
+    let ended_at = last_typed_at.get(span.clone())?.iter().flatten().max();
+    match (started_at, ended_at) {
+        (Some(started_at), Some(&ended_at)) if ended_at > started_at => {
+            Some(chars_to_wpm(span.len(), ended_at - started_at))
+        }
+        _ => None,
     }
 }
 
+fn average(durations: &[Duration]) -> Option<Duration> {
+    if durations.is_empty() {
+        return None;
+    }
+    Some(durations.iter().sum::<Duration>() / durations.len() as u32)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use approx::assert_ulps_eq;
+    use proptest::prelude::*;
 
     #[test]
     fn sanity() {
-        let started_at = Instant::now();
-        let ended_at = started_at + Duration::new(10, 0);
+        let base = ToipeResults::from_typed("", "", Duration::ZERO);
         let results = ToipeResults {
-            total_words: 0,
             total_chars_typed: 100,
             total_chars_in_text: 120,
             total_char_errors: 10,
             final_chars_typed_correctly: 80,
             final_uncorrected_errors: 2,
-            started_at,
-            ended_at,
+            ended_at: base.started_at + Duration::new(10, 0),
+            ..base
         };
 
         assert_eq!(results.duration(), Duration::new(10, 0));
@@ -86,14 +498,9 @@ mod tests {
     fn accuracy() {
         fn get_toipe_results(total_chars_typed: usize, total_char_errors: usize) -> ToipeResults {
             ToipeResults {
-                total_words: 0,
                 total_chars_typed,
-                total_chars_in_text: 0,
                 total_char_errors,
-                final_chars_typed_correctly: 0,
-                final_uncorrected_errors: 0,
-                started_at: Instant::now(),
-                ended_at: Instant::now(),
+                ..ToipeResults::from_typed("", "", Duration::ZERO)
             }
         }
 
@@ -134,19 +541,14 @@ mod tests {
             final_uncorrected_errors: usize,
             duration: f64,
         ) -> ToipeResults {
-            let started_at = Instant::now();
+            let base = ToipeResults::from_typed("", "", Duration::ZERO);
             let seconds = duration.round();
             let nanoseconds = (duration - seconds) * 1_000_000_000.0;
-            let ended_at = started_at + Duration::new(seconds as u64, nanoseconds as u32);
             ToipeResults {
-                total_words: 0,
-                total_chars_typed: 0,
-                total_chars_in_text: 0,
-                total_char_errors: 0,
                 final_chars_typed_correctly,
                 final_uncorrected_errors,
-                started_at,
-                ended_at,
+                ended_at: base.started_at + Duration::new(seconds as u64, nanoseconds as u32),
+                ..base
             }
         }
 
@@ -201,4 +603,221 @@ mod tests {
         );
         // we don't consider the case of duration = 0 because that seems impossible
     }
+
+    #[test]
+    fn latencies() {
+        let keystroke = |at_ms, index, c| Keystroke {
+            at: Duration::from_millis(at_ms),
+            index,
+            expected: c,
+            typed: c,
+        };
+        let results = ToipeResults {
+            // "ab cd", with the "c" typed wrong and corrected
+            keystrokes: vec![
+                keystroke(0, 0, 'a'),
+                keystroke(100, 1, 'b'),
+                keystroke(200, 2, ' '),
+                Keystroke {
+                    typed: 'x',
+                    ..keystroke(600, 3, 'c')
+                },
+                keystroke(900, 3, 'c'),
+                keystroke(1000, 4, 'd'),
+            ],
+            ..ToipeResults::from_typed("ab cd", "ab cd", Duration::ZERO)
+        };
+
+        assert_eq!(
+            results.reaction_latencies(),
+            vec![Duration::from_millis(400)]
+        );
+        assert_eq!(
+            results.avg_in_word_interval(),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn char_outcomes() {
+        let keystroke = |index, expected, typed| Keystroke {
+            at: Duration::ZERO,
+            index,
+            expected,
+            typed,
+        };
+        // "b" is corrected, "c" is left wrong and "d" is never typed
+        let results = ToipeResults {
+            keystrokes: vec![
+                keystroke(0, 'a', 'a'),
+                keystroke(1, 'b', 'x'),
+                keystroke(1, 'b', 'b'),
+                keystroke(2, 'c', 'y'),
+            ],
+            ..ToipeResults::from_typed("abcd", "aby", Duration::ZERO)
+        };
+
+        assert_eq!(
+            results.char_outcomes(),
+            vec![
+                CharOutcome::Correct,
+                CharOutcome::Corrected,
+                CharOutcome::Uncorrected,
+                CharOutcome::Skipped,
+            ]
+        );
+    }
+
+    #[test]
+    fn word_wpms() {
+        let keystroke = |millis, index, c| Keystroke {
+            at: Duration::from_millis(millis),
+            index,
+            expected: c,
+            typed: c,
+        };
+        let results = ToipeResults {
+            // "ef" is never typed
+            keystrokes: vec![
+                keystroke(0, 0, 'a'),
+                keystroke(200, 1, 'b'),
+                keystroke(300, 2, ' '),
+                keystroke(500, 3, 'c'),
+                keystroke(700, 4, 'd'),
+            ],
+            ..ToipeResults::from_typed("ab cd ef", "ab cd", Duration::ZERO)
+        };
+
+        // 2 chars in 200ms and 400ms
+        assert_eq!(results.word_wpms(), vec![Some(120.0), Some(60.0), None]);
+    }
+
+    #[test]
+    fn wpm_formulas() {
+        // "abcd efgh" with the "f" left wrong, in 6 seconds
+        let base = ToipeResults::from_typed("abcd efgh", "abcd exgh", Duration::ZERO);
+        let results = ToipeResults {
+            ended_at: base.started_at + Duration::from_secs(6),
+            ..base
+        };
+
+        assert_ulps_eq!(results.wpm_with(WpmFormula::Gross), 18.0, max_ulps = 1);
+        assert_ulps_eq!(results.wpm_with(WpmFormula::Net), 6.0, max_ulps = 1);
+        assert_ulps_eq!(results.wpm_with(WpmFormula::Words), 10.0, max_ulps = 1);
+    }
+
+    #[test]
+    fn burst_and_sustained() {
+        // 20 seconds: 10 chars/s for 5s, 5 chars/s for 10s, 20 chars/s for 5s
+        let keystrokes = (0..50)
+            .map(|i| Duration::from_millis(i * 100))
+            .chain((0..50).map(|i| Duration::from_millis(5000 + i * 200)))
+            .chain((0..100).map(|i| Duration::from_millis(15000 + i * 50)))
+            .enumerate()
+            .map(|(index, at)| Keystroke {
+                at,
+                index,
+                expected: 'a',
+                typed: 'a',
+            })
+            .collect();
+        let base = ToipeResults::from_typed("", "", Duration::ZERO);
+        let started_at = base.started_at;
+        let results = ToipeResults {
+            ended_at: started_at + Duration::from_secs(20),
+            keystrokes,
+            ..base
+        };
+
+        // 100 chars in 5s
+        assert_ulps_eq!(results.burst_wpm().unwrap(), 240.0, max_ulps = 1);
+        // windows are 120, 60, 60 and 240 wpm
+        assert_ulps_eq!(results.sustained_wpm().unwrap(), 90.0, max_ulps = 1);
+
+        let short = ToipeResults {
+            ended_at: started_at + Duration::from_secs(4),
+            ..results
+        };
+        assert_eq!(short.burst_wpm(), None);
+        assert_eq!(short.sustained_wpm(), None);
+    }
+
+    #[test]
+    fn normalized_wpm() {
+        let base = ToipeResults::from_typed("", "", Duration::ZERO);
+        let mut results = ToipeResults {
+            final_chars_typed_correctly: 200,
+            ended_at: base.started_at + Duration::new(30, 0),
+            difficulty: TextDifficulty {
+                avg_word_length: TextDifficulty::REFERENCE_WORD_LENGTH,
+                rare_letter_ratio: TextDifficulty::REFERENCE_RARE_LETTER_RATIO,
+                punctuation_density: 0.0,
+            },
+            ..base
+        };
+
+        // reference text is neither easier nor harder
+        assert_ulps_eq!(results.normalized_wpm(), 80.0, max_ulps = 1);
+
+        // harder text gives a higher normalized speed, raw speed is unchanged
+        results.difficulty.punctuation_density = 0.1;
+        assert!(results.normalized_wpm() > 80.0);
+        assert_ulps_eq!(results.wpm(), 80.0, max_ulps = 1);
+
+        // easier text gives a lower normalized speed
+        results.difficulty.punctuation_density = 0.0;
+        results.difficulty.avg_word_length = 3.0;
+        assert!(results.normalized_wpm() < 80.0);
+    }
+
+    /// Inputs for [`score`], with no more errors than chars typed.
+    fn score_input() -> impl Strategy<Value = (usize, usize, usize, usize, Duration)> {
+        (0..1000usize)
+            .prop_flat_map(|typed| (Just(typed), 0..=typed, 0..=typed))
+            .prop_flat_map(|(typed, errors, final_chars)| {
+                (
+                    Just(typed),
+                    Just(errors),
+                    Just(final_chars),
+                    0..=final_chars,
+                    1..600_000u64,
+                )
+            })
+            .prop_map(|(typed, errors, final_chars, uncorrected, millis)| {
+                (
+                    typed,
+                    errors,
+                    final_chars - uncorrected,
+                    uncorrected,
+                    Duration::from_millis(millis),
+                )
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn score_invariants((typed, errors, correct, uncorrected, duration) in score_input()) {
+            let base = score(typed, errors, correct, uncorrected, duration);
+
+            // bounds
+            prop_assert!((0.0..=1.0).contains(&base.accuracy), "{:?}", base);
+            prop_assert!(base.wpm.is_finite() && base.wpm >= 0.0, "{:?}", base);
+            prop_assert!(base.wpm <= correct as f64 / 5.0 / (duration.as_secs_f64() / 60.0));
+
+            // more correct chars in the same time is never slower
+            let more_correct = score(typed + 1, errors, correct + 1, uncorrected, duration);
+            prop_assert!(more_correct.wpm >= base.wpm);
+            prop_assert!(more_correct.accuracy >= base.accuracy);
+
+            // leaving more errors is never faster
+            let more_uncorrected = score(typed + 1, errors + 1, correct, uncorrected + 1, duration);
+            prop_assert!(more_uncorrected.wpm <= base.wpm);
+            prop_assert!(more_uncorrected.accuracy <= base.accuracy);
+
+            // taking longer is never faster
+            let slower = score(typed, errors, correct, uncorrected, duration * 2);
+            prop_assert!(slower.wpm <= base.wpm);
+            prop_assert_eq!(slower.accuracy, base.accuracy);
+        }
+    }
 }
@@ -0,0 +1,182 @@
+//! Baseline of a profile, measured by `toipe calibrate`.
+//!
+//! The calibration test is the same for everyone: [`NUM_WORDS`] words of
+//! the top250 word list picked with [`SEED`], without punctuation. Its
+//! speed and the chars typed wrong in it are kept in [`baseline_file`],
+//! and stand in for the average typist assumed by the defaults of
+//! `--queue-wpm` and the target of `toipe plan`. The test is recorded in
+//! the history and [`super::char_stats`] like any other, so that plans
+//! and `--practice` can start from it right away.
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value};
+
+use super::{char_stats::CharStats, ToipeResults};
+use crate::{dirs, storage};
+
+const SCHEMA_VERSION: u64 = 1;
+
+/// Seed of the text of the calibration test.
+pub const SEED: u64 = 0x6361_6c69;
+
+/// Number of words of the calibration test.
+pub const NUM_WORDS: usize = 40;
+
+/// Number of chars typed wrong most often kept as problem keys.
+const NUM_PROBLEM_CHARS: usize = 5;
+
+/// How much faster than the baseline the default target of `toipe plan`
+/// is.
+const TARGET_GAIN: f64 = 1.2;
+
+/// Speed and problem keys measured by the calibration test.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Baseline {
+    /// when the test ended, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// see [`ToipeResults::wpm`]
+    pub wpm: f64,
+    /// see [`ToipeResults::accuracy`]
+    pub accuracy: f64,
+    /// chars typed wrong, most often wrong first
+    pub problem_chars: Vec<char>,
+}
+
+impl Baseline {
+    /// Baseline from the `results` of a calibration test that ended at
+    /// `timestamp`.
+    pub fn new(results: &ToipeResults, timestamp: u64) -> Self {
+        let mut stats = CharStats::default();
+        stats.add(results);
+        Self {
+            timestamp,
+            wpm: results.wpm(),
+            accuracy: results.accuracy(),
+            problem_chars: stats.weakest_chars(NUM_PROBLEM_CHARS),
+        }
+    }
+
+    /// Default speed to reach with `toipe plan`, a fifth faster than the
+    /// baseline, rounded up to a multiple of 5.
+    pub fn target_wpm(&self) -> f64 {
+        (self.wpm * TARGET_GAIN / 5.0).ceil() * 5.0
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("schema_version".into(), SCHEMA_VERSION.into());
+        object.insert("timestamp".into(), self.timestamp.into());
+        object.insert("wpm".into(), self.wpm.into());
+        object.insert("accuracy".into(), self.accuracy.into());
+        object.insert(
+            "problem_chars".into(),
+            self.problem_chars.iter().collect::<String>().into(),
+        );
+        object.into()
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, Error> {
+        let number = |field| {
+            value
+                .get(field)
+                .and_then(Value::as_f64)
+                .ok_or_else(|| invalid(field))
+        };
+        Ok(Self {
+            timestamp: value
+                .get("timestamp")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| invalid("timestamp"))?,
+            wpm: number("wpm")?,
+            accuracy: number("accuracy")?,
+            problem_chars: value
+                .get("problem_chars")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid("problem_chars"))?
+                .chars()
+                .collect(),
+        })
+    }
+}
+
+fn invalid(field: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Missing or invalid `{}` in the baseline", field),
+    )
+}
+
+/// File the baseline is stored in.
+///
+/// Usually `~/.local/share/toipe/baseline.json`.
+pub fn baseline_file() -> Result<PathBuf, Error> {
+    Ok(dirs::data_dir()?.join("baseline.json"))
+}
+
+/// The baseline, if `toipe calibrate` was run.
+pub fn load() -> Result<Option<Baseline>, Error> {
+    match fs::read_to_string(baseline_file()?) {
+        Ok(contents) => Baseline::from_json(&serde_json::from_str::<Value>(&contents)?).map(Some),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Finishes `toipe calibrate` with the `results` of the calibration
+/// test: replaces the baseline and prints it.
+pub fn run(results: &ToipeResults) -> Result<(), Error> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let baseline = Baseline::new(results, timestamp);
+    storage::write(
+        &baseline_file()?,
+        format!("{}\n", baseline.to_json()).as_bytes(),
+    )?;
+
+    println!(
+        "Baseline: {:.1} wpm at {:.1}% accuracy",
+        baseline.wpm,
+        baseline.accuracy * 100.0
+    );
+    if !baseline.problem_chars.is_empty() {
+        let chars: Vec<String> = baseline.problem_chars.iter().map(char::to_string).collect();
+        println!(
+            "Problem keys: {}, drilled with `toipe --practice`",
+            chars.join(" ")
+        );
+    }
+    println!(
+        "Suggested goal: `toipe plan --by <YYYY-MM-DD>` plans to reach {:.0} wpm",
+        baseline.target_wpm()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn measures_the_baseline() {
+        let results = ToipeResults::from_typed("tax box", "tax bix", Duration::from_secs(1));
+
+        let baseline = Baseline::new(&results, 1_700_000_000);
+        assert_eq!(baseline.problem_chars, ['o']);
+        assert_eq!(baseline.wpm, results.wpm());
+        assert_eq!(
+            baseline.target_wpm(),
+            (results.wpm() * 1.2 / 5.0).ceil() * 5.0
+        );
+        assert_eq!(Baseline::from_json(&baseline.to_json()).unwrap(), baseline);
+    }
+}
@@ -0,0 +1,203 @@
+//! How often each char was typed wrong across tests, for `--practice`.
+//!
+//! The counts of all tests are kept in [`stats_file`] as a JSON object,
+//! next to the history, and updated after every test by [`record`].
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+use serde_json::{Map, Value};
+
+use super::{CharOutcome, ToipeResults};
+use crate::{dirs, storage};
+
+const SCHEMA_VERSION: u64 = 1;
+
+/// Times a char is assumed to have been typed right before it was first
+/// typed, so that a single mistake on a rare char does not make it look
+/// like the weakest one.
+const PRIOR_TYPED: f64 = 10.0;
+
+/// How much more likely a word is picked by `--practice` for each char
+/// in it that is always typed wrong, see [`CharStats::word_weight`].
+const PRACTICE_BIAS: f64 = 20.0;
+
+/// Times a char was typed, and typed wrong at least once before moving
+/// on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CharCount {
+    pub typed: u64,
+    pub errors: u64,
+}
+
+/// [`CharCount`]s of every char typed so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CharStats {
+    pub counts: BTreeMap<char, CharCount>,
+}
+
+impl CharStats {
+    /// Adds the chars of a test. Whitespace and skipped chars are not
+    /// counted.
+    pub fn add(&mut self, results: &ToipeResults) {
+        for (c, outcome) in results.text.chars().zip(results.char_outcomes()) {
+            if c.is_whitespace() || outcome == CharOutcome::Skipped {
+                continue;
+            }
+            let count = self.counts.entry(c).or_default();
+            count.typed += 1;
+            if matches!(outcome, CharOutcome::Corrected | CharOutcome::Uncorrected) {
+                count.errors += 1;
+            }
+        }
+    }
+
+    /// Fraction of the times `c` was typed wrong, pulled towards 0 for
+    /// chars rarely typed.
+    pub fn error_rate(&self, c: char) -> f64 {
+        let count = self.counts.get(&c).copied().unwrap_or_default();
+        count.errors as f64 / (count.typed as f64 + PRIOR_TYPED)
+    }
+
+    /// Chars with the highest error rates, most often wrong first.
+    pub fn weakest_chars(&self, n: usize) -> Vec<char> {
+        let mut chars: Vec<char> = self
+            .counts
+            .iter()
+            .filter(|(_, count)| count.errors > 0)
+            .map(|(&c, _)| c)
+            .collect();
+        chars.sort_by(|a, b| self.error_rate(*b).total_cmp(&self.error_rate(*a)));
+        chars.truncate(n);
+        chars
+    }
+
+    /// How likely `word` is to be picked by `--practice` relative to a
+    /// word without any char ever typed wrong, which has a weight of 1.
+    pub fn word_weight(&self, word: &str) -> f64 {
+        let chars: HashSet<char> = word.chars().collect();
+        1.0 + PRACTICE_BIAS * chars.into_iter().map(|c| self.error_rate(c)).sum::<f64>()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let chars: Map<String, Value> = self
+            .counts
+            .iter()
+            .map(|(c, count)| {
+                let mut object = Map::new();
+                object.insert("typed".into(), count.typed.into());
+                object.insert("errors".into(), count.errors.into());
+                (c.to_string(), object.into())
+            })
+            .collect();
+
+        let mut object = Map::new();
+        object.insert("schema_version".into(), SCHEMA_VERSION.into());
+        object.insert("chars".into(), chars.into());
+        object.into()
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, Error> {
+        let chars = value
+            .get("chars")
+            .and_then(Value::as_object)
+            .ok_or_else(|| invalid("chars"))?;
+
+        let mut counts = BTreeMap::new();
+        for (key, count) in chars {
+            let mut key_chars = key.chars();
+            let c = match (key_chars.next(), key_chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(invalid(key)),
+            };
+            let field = |name| {
+                count
+                    .get(name)
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| invalid(key))
+            };
+            counts.insert(
+                c,
+                CharCount {
+                    typed: field("typed")?,
+                    errors: field("errors")?,
+                },
+            );
+        }
+        Ok(Self { counts })
+    }
+}
+
+fn invalid(field: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Missing or invalid `{}` in char stats", field),
+    )
+}
+
+/// File the stats are stored in.
+///
+/// Usually `~/.local/share/toipe/char_stats.json`.
+pub fn stats_file() -> Result<PathBuf, Error> {
+    Ok(dirs::data_dir()?.join("char_stats.json"))
+}
+
+/// The stats of all tests so far, or empty stats if there are none.
+pub fn load() -> Result<CharStats, Error> {
+    match fs::read_to_string(stats_file()?) {
+        Ok(contents) => CharStats::from_json(&serde_json::from_str::<Value>(&contents)?),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(CharStats::default()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Adds the chars of a test that ended now to the stats.
+pub fn record(results: &ToipeResults) -> Result<(), Error> {
+    let mut stats = load()?;
+    stats.add(results);
+    storage::write(&stats_file()?, format!("{}\n", stats.to_json()).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn results(text: &str, typed: &str) -> ToipeResults {
+        ToipeResults::from_typed(text, typed, Duration::ZERO)
+    }
+
+    #[test]
+    fn counts_errors_per_char() {
+        let mut stats = CharStats::default();
+        stats.add(&results("ab ab", "ax ab"));
+        stats.add(&results("ba", "b"));
+
+        assert_eq!(
+            stats.counts[&'a'],
+            CharCount {
+                typed: 2,
+                errors: 0
+            }
+        );
+        assert_eq!(
+            stats.counts[&'b'],
+            CharCount {
+                typed: 3,
+                errors: 1
+            }
+        );
+        assert!(!stats.counts.contains_key(&' '));
+        assert_eq!(stats.weakest_chars(5), ['b']);
+
+        assert!(stats.word_weight("bob") > stats.word_weight("aaa"));
+        assert_eq!(stats.word_weight("xyz"), 1.0);
+
+        assert_eq!(CharStats::from_json(&stats.to_json()).unwrap(), stats);
+    }
+}
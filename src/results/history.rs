@@ -0,0 +1,704 @@
+//! History of past typing tests.
+//!
+//! Each finished test is appended to [`history_file`] as a line of JSON
+//! (see [`HistoryEntry::to_json`]). Every entry records the
+//! [`SCHEMA`] version it was written with, and entries written by older
+//! versions of toipe are upgraded when read (see [`crate::migrations`]).
+//!
+//! The history can be encrypted at rest with `toipe history encrypt`
+//! (see [`crate::encryption`]), after which it is transparently
+//! decrypted when read.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value};
+
+use super::ToipeResults;
+use crate::{
+    checksum,
+    config::HistoryCommand,
+    dirs, encryption,
+    migrations::{self, Schema},
+    storage,
+};
+
+/// Format of history entries.
+///
+/// Add a migration step whenever the format changes.
+pub const SCHEMA: Schema = Schema {
+    name: "History",
+    migrations: &[],
+};
+
+/// Where and how a test was taken, so that old entries can still be
+/// interpreted after the configuration changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestMetadata {
+    pub toipe_version: String,
+    /// `$TERM`
+    pub term: Option<String>,
+    /// (columns, rows) of the terminal
+    pub terminal_size: Option<(u16, u16)>,
+    /// human-readable name of the word list or other source of text
+    pub wordlist: String,
+    /// SHA-256 of the words in the word list, to tell apart different
+    /// word lists with the same name
+    pub wordlist_hash: Option<String>,
+    /// seed used to generate the text, if it can be reproduced
+    pub seed: Option<u64>,
+    /// options that change the text or how it is typed, such as
+    /// `punctuation` or `bot=60`
+    pub flags: Vec<String>,
+}
+
+impl TestMetadata {
+    /// Metadata for a test taken now, in the current terminal.
+    pub fn current(wordlist: String, wordlist_hash: Option<String>, flags: Vec<String>) -> Self {
+        Self {
+            toipe_version: env!("CARGO_PKG_VERSION").to_string(),
+            term: std::env::var("TERM").ok(),
+            terminal_size: crate::backend::terminal_size().ok(),
+            wordlist,
+            wordlist_hash,
+            seed: None,
+            flags,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("toipe_version".into(), self.toipe_version.as_str().into());
+        object.insert("term".into(), self.term.clone().into());
+        object.insert(
+            "terminal_size".into(),
+            self.terminal_size
+                .map(|(columns, rows)| vec![columns as u64, rows as u64])
+                .into(),
+        );
+        object.insert("wordlist".into(), self.wordlist.as_str().into());
+        object.insert("wordlist_hash".into(), self.wordlist_hash.clone().into());
+        object.insert("seed".into(), self.seed.into());
+        object.insert("flags".into(), self.flags.clone().into());
+        object.into()
+    }
+
+    fn from_json(value: &Value) -> Result<Self, Error> {
+        let terminal_size = match value.get("terminal_size").and_then(Value::as_array) {
+            Some(size) => match size.as_slice() {
+                [columns, rows] => Some((
+                    columns.as_u64().ok_or_else(|| invalid("terminal_size"))? as u16,
+                    rows.as_u64().ok_or_else(|| invalid("terminal_size"))? as u16,
+                )),
+                _ => return Err(invalid("terminal_size")),
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            toipe_version: string_field(value, "toipe_version")?,
+            term: value.get("term").and_then(Value::as_str).map(String::from),
+            terminal_size,
+            wordlist: string_field(value, "wordlist")?,
+            wordlist_hash: value
+                .get("wordlist_hash")
+                .and_then(Value::as_str)
+                .map(String::from),
+            seed: value.get("seed").and_then(Value::as_u64),
+            flags: value
+                .get("flags")
+                .and_then(Value::as_array)
+                .ok_or_else(|| invalid("flags"))?
+                .iter()
+                .map(|flag| {
+                    flag.as_str()
+                        .map(String::from)
+                        .ok_or_else(|| invalid("flags"))
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// A finished test as stored in the history.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// when the test ended, in seconds since the Unix epoch
+    pub timestamp: u64,
+    pub duration: Duration,
+    /// see [`ToipeResults::wpm`]
+    pub wpm: f64,
+    /// see [`ToipeResults::accuracy`]
+    pub accuracy: f64,
+    /// see the fields of [`ToipeResults`] with the same names
+    pub total_words: usize,
+    pub total_chars_typed: usize,
+    pub total_chars_in_text: usize,
+    pub total_char_errors: usize,
+    pub final_chars_typed_correctly: usize,
+    pub final_uncorrected_errors: usize,
+    pub final_chars_skipped: usize,
+    pub metadata: TestMetadata,
+}
+
+impl HistoryEntry {
+    /// Entry for a test that ended now.
+    pub fn new(results: &ToipeResults, metadata: TestMetadata) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            duration: results.duration(),
+            wpm: results.wpm(),
+            accuracy: results.accuracy(),
+            total_words: results.total_words,
+            total_chars_typed: results.total_chars_typed,
+            total_chars_in_text: results.total_chars_in_text,
+            total_char_errors: results.total_char_errors,
+            final_chars_typed_correctly: results.final_chars_typed_correctly,
+            final_uncorrected_errors: results.final_uncorrected_errors,
+            final_chars_skipped: results.final_chars_skipped,
+            metadata,
+        }
+    }
+
+    /// The entry as a JSON object, in the latest version of [`SCHEMA`].
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert(migrations::VERSION_FIELD.into(), SCHEMA.version().into());
+        object.insert("timestamp".into(), self.timestamp.into());
+        object.insert("duration".into(), self.duration.as_secs_f64().into());
+        object.insert("wpm".into(), self.wpm.into());
+        object.insert("accuracy".into(), self.accuracy.into());
+        for (name, count) in [
+            ("total_words", self.total_words),
+            ("total_chars_typed", self.total_chars_typed),
+            ("total_chars_in_text", self.total_chars_in_text),
+            ("total_char_errors", self.total_char_errors),
+            (
+                "final_chars_typed_correctly",
+                self.final_chars_typed_correctly,
+            ),
+            ("final_uncorrected_errors", self.final_uncorrected_errors),
+            ("final_chars_skipped", self.final_chars_skipped),
+        ] {
+            object.insert(name.into(), count.into());
+        }
+        object.insert("metadata".into(), self.metadata.to_json());
+        object.into()
+    }
+
+    /// Fails if the speed or accuracy is infinite or NaN, which JSON can
+    /// not represent, so that the entry could not be read back.
+    pub fn check_finite(&self) -> Result<(), Error> {
+        for (name, value) in [("speed", self.wpm), ("accuracy", self.accuracy)] {
+            if !value.is_finite() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Can not store a {} of {}", name, value),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Identifies the result, the same on every machine the entry is
+    /// copied to: the checksum of its JSON.
+    pub fn id(&self) -> String {
+        checksum::sha256_hex(self.to_json().to_string().as_bytes())
+    }
+
+    /// Reads an entry of any version of [`SCHEMA`].
+    pub fn from_json(mut value: Value) -> Result<Self, Error> {
+        SCHEMA.migrate(&mut value)?;
+        let count = |name| {
+            value
+                .get(name)
+                .and_then(Value::as_u64)
+                .map(|count| count as usize)
+                .ok_or_else(|| invalid(name))
+        };
+        let float = |name| {
+            value
+                .get(name)
+                .and_then(Value::as_f64)
+                .ok_or_else(|| invalid(name))
+        };
+
+        Ok(Self {
+            timestamp: value
+                .get("timestamp")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| invalid("timestamp"))?,
+            duration: Duration::from_secs_f64(float("duration")?.max(0.0)),
+            wpm: float("wpm")?,
+            accuracy: float("accuracy")?,
+            total_words: count("total_words")?,
+            total_chars_typed: count("total_chars_typed")?,
+            total_chars_in_text: count("total_chars_in_text")?,
+            total_char_errors: count("total_char_errors")?,
+            final_chars_typed_correctly: count("final_chars_typed_correctly")?,
+            final_uncorrected_errors: count("final_uncorrected_errors")?,
+            final_chars_skipped: count("final_chars_skipped")?,
+            metadata: TestMetadata::from_json(
+                value.get("metadata").ok_or_else(|| invalid("metadata"))?,
+            )?,
+        })
+    }
+}
+
+/// Entry for a test of 30 words typed without errors, ending at
+/// `timestamp` at `wpm`, to test what is done with the history.
+#[cfg(test)]
+pub(crate) fn test_entry(timestamp: u64, wpm: f64) -> HistoryEntry {
+    let text = "word ".repeat(30);
+    let results = ToipeResults::from_typed(&text, &text, Duration::from_millis(200));
+    HistoryEntry {
+        timestamp,
+        wpm,
+        ..HistoryEntry::new(
+            &results,
+            TestMetadata::current("top250".to_string(), None, Vec::new()),
+        )
+    }
+}
+
+fn invalid(field: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Missing or invalid `{}` in history entry", field),
+    )
+}
+
+fn string_field(value: &Value, field: &str) -> Result<String, Error> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .map(String::from)
+        .ok_or_else(|| invalid(field))
+}
+
+/// File the history is stored in.
+///
+/// Usually `~/.local/share/toipe/history.jsonl`, or
+/// `~/.local/share/toipe/history.jsonl.enc` after `toipe history
+/// encrypt`.
+pub fn history_file() -> Result<PathBuf, Error> {
+    let path = dirs::data_dir()?.join("history.jsonl");
+    let encrypted = encrypted_path(&path);
+    Ok(if encrypted.exists() { encrypted } else { path })
+}
+
+/// Whether the history is encrypted, see `toipe history encrypt`.
+pub fn is_encrypted() -> Result<bool, Error> {
+    Ok(is_encrypted_file(&history_file()?))
+}
+
+fn encrypted_path(path: &Path) -> PathBuf {
+    let mut encrypted = path.as_os_str().to_owned();
+    encrypted.push(".enc");
+    encrypted.into()
+}
+
+/// Whether the history file at `path` is encrypted, from its name.
+pub(crate) fn is_encrypted_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "enc")
+}
+
+/// Reads a history file, decrypting it if needed. A missing file is
+/// empty.
+fn read(path: &Path) -> Result<String, Error> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(String::new()),
+        Err(err) => return Err(err),
+    };
+    let data = if is_encrypted_file(path) {
+        encryption::decrypt(&data)?
+    } else {
+        data
+    };
+    String::from_utf8(data).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+/// Replaces the contents of a history file, encrypting it if needed.
+fn write(path: &Path, contents: &str) -> Result<(), Error> {
+    if is_encrypted_file(path) {
+        storage::write(path, &encryption::encrypt(contents.as_bytes())?)
+    } else {
+        storage::write(path, contents.as_bytes())
+    }
+}
+
+/// Appends an entry to the history, creating the file if needed.
+pub fn append(entry: &HistoryEntry) -> Result<(), Error> {
+    append_to(&history_file()?, entry)
+}
+
+/// Appends an entry to the history file at `path`, creating it if
+/// needed.
+pub(crate) fn append_to(path: &Path, entry: &HistoryEntry) -> Result<(), Error> {
+    entry.check_finite()?;
+    let line = format!("{}\n", entry.to_json());
+
+    if is_encrypted_file(path) {
+        // encrypted files can not be appended to in place
+        return write(path, &(read(path)? + &line));
+    }
+
+    storage::append(path, line.as_bytes())
+}
+
+/// All entries in the history, oldest first.
+///
+/// Upgrades the history file if it was written by an older version of
+/// toipe.
+pub fn load() -> Result<Vec<HistoryEntry>, Error> {
+    load_file(&history_file()?)
+}
+
+/// Like [`load`], for the history file at `path`.
+pub(crate) fn load_file(path: &Path) -> Result<Vec<HistoryEntry>, Error> {
+    parse_file(path, true)
+}
+
+/// Entries of the history file at `path`, oldest first, writing them
+/// back to it when upgraded and `upgrade_in_place`.
+fn parse_file(path: &Path, upgrade_in_place: bool) -> Result<Vec<HistoryEntry>, Error> {
+    let (values, upgraded) = migrations::upgrade_json_lines(&read(path)?, path, &SCHEMA)?;
+    if upgraded && upgrade_in_place {
+        write(path, &migrations::to_json_lines(&values))?;
+    }
+
+    let mut entries = values
+        .into_iter()
+        .map(HistoryEntry::from_json)
+        .collect::<Result<Vec<_>, _>>()?;
+    // entries merged from other machines may be out of order
+    entries.sort_by_key(|entry| entry.timestamp);
+    Ok(entries)
+}
+
+/// Combines two histories, keeping a single copy of entries found in
+/// both.
+pub fn merge(ours: Vec<HistoryEntry>, theirs: Vec<HistoryEntry>) -> Vec<HistoryEntry> {
+    let (entries, _) = merge_sources(vec![
+        ("ours".to_string(), ours),
+        ("theirs".to_string(), theirs),
+    ]);
+    entries
+}
+
+/// Merges the history stored in `other` (such as a copy from another
+/// machine) into the history file.
+///
+/// `other` is decrypted if its name ends with `.enc`.
+pub fn merge_file(other: &Path) -> Result<(), Error> {
+    merge_into(&history_file()?, other)
+}
+
+/// Like [`merge_file`], into the history file at `path`.
+pub(crate) fn merge_into(path: &Path, other: &Path) -> Result<(), Error> {
+    let entries = merge(load_file(path)?, load_file(other)?);
+    let values: Vec<_> = entries.iter().map(HistoryEntry::to_json).collect();
+    write(path, &migrations::to_json_lines(&values))
+}
+
+/// Tests found in a history merged by [`lifetime_report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceCount {
+    /// `this machine`, or the path of the file
+    pub name: String,
+    pub tests: usize,
+    /// tests not found in the sources before it
+    pub added: usize,
+}
+
+/// Combines the entries of several `sources`, keeping a single copy of
+/// the results found in more than one (see [`HistoryEntry::id`]).
+///
+/// Returns the entries, oldest first, and how many came from each
+/// source.
+pub fn merge_sources(
+    sources: Vec<(String, Vec<HistoryEntry>)>,
+) -> (Vec<HistoryEntry>, Vec<SourceCount>) {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    let mut counts = Vec::new();
+    for (name, source) in sources {
+        let tests = source.len();
+        let before = entries.len();
+        entries.extend(source.into_iter().filter(|entry| seen.insert(entry.id())));
+        counts.push(SourceCount {
+            name,
+            tests,
+            added: entries.len() - before,
+        });
+    }
+    entries.sort_by_key(|entry| entry.timestamp);
+    (entries, counts)
+}
+
+/// Summary of the history together with the histories exported from
+/// other machines at `others`, such as a copy of their history file,
+/// for `toipe stats`. The history is not changed.
+pub fn lifetime_report(others: &[PathBuf]) -> Result<String, Error> {
+    let mut sources = vec![("this machine".to_string(), load()?)];
+    for path in others {
+        if !path.exists() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("No history to merge at {}", path.display()),
+            ));
+        }
+        // the copy is left as it is, even when written by an older toipe
+        sources.push((path.display().to_string(), parse_file(path, false)?));
+    }
+    let (entries, counts) = merge_sources(sources);
+
+    // the tests of each history, then the summary of all of them
+    let mut report = String::new();
+    for count in &counts {
+        report += &format!("{}: {} tests", count.name, count.tests);
+        if count.added < count.tests {
+            report += &format!(", {} already counted", count.tests - count.added);
+        }
+        report += "\n";
+    }
+    report += "\n";
+    for (text, wpm) in HistoryStats::from_entries(&entries).summary() {
+        report += &text;
+        if let Some(wpm) = wpm {
+            report += &format!("{:.1} wpm", wpm);
+        }
+        report += "\n";
+    }
+    Ok(report)
+}
+
+/// Number of most recent tests averaged in [`HistoryStats::average_wpm`].
+pub const ROLLING_WINDOW: usize = 10;
+
+/// Summary of the history, shown by `--stats` and `toipe stats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HistoryStats {
+    pub num_tests: usize,
+    /// highest speed of any test, in wpm
+    pub best_wpm: Option<f64>,
+    /// average speed of the last [`ROLLING_WINDOW`] tests, in wpm
+    pub average_wpm: Option<f64>,
+    /// highest speed of the tests of each word list, by name
+    pub best_per_wordlist: BTreeMap<String, f64>,
+    /// time spent typing in all tests
+    pub total_duration: Duration,
+}
+
+impl HistoryStats {
+    /// Summarizes `entries`, oldest first.
+    pub fn from_entries(entries: &[HistoryEntry]) -> Self {
+        let mut best_per_wordlist = BTreeMap::new();
+        for entry in entries {
+            let best = best_per_wordlist
+                .entry(entry.metadata.wordlist.clone())
+                .or_insert(entry.wpm);
+            *best = entry.wpm.max(*best);
+        }
+
+        let recent = &entries[entries.len().saturating_sub(ROLLING_WINDOW)..];
+        Self {
+            num_tests: entries.len(),
+            best_wpm: entries.iter().map(|entry| entry.wpm).reduce(f64::max),
+            average_wpm: (!recent.is_empty())
+                .then(|| recent.iter().map(|entry| entry.wpm).sum::<f64>() / recent.len() as f64),
+            best_per_wordlist,
+            total_duration: entries.iter().map(|entry| entry.duration).sum(),
+        }
+    }
+
+    /// Lines of the summary shown by `--stats` and `toipe stats`, each
+    /// with the speed in wpm that ends it, if any.
+    pub fn summary(&self) -> Vec<(String, Option<f64>)> {
+        let minutes = self.total_duration.as_secs() / 60;
+        let mut lines = vec![
+            (format!("Tests taken: {}", self.num_tests), None),
+            (
+                format!("Time typing: {}h {:02}m", minutes / 60, minutes % 60),
+                None,
+            ),
+        ];
+        if let (Some(best), Some(average)) = (self.best_wpm, self.average_wpm) {
+            lines.push(("Personal best: ".to_string(), Some(best)));
+            lines.push((
+                format!(
+                    "Average of the last {} tests: ",
+                    self.num_tests.min(ROLLING_WINDOW)
+                ),
+                Some(average),
+            ));
+            lines.push((String::new(), None));
+            lines.push(("Best per word list:".to_string(), None));
+            for (wordlist, best) in &self.best_per_wordlist {
+                lines.push((format!("  {}: ", wordlist), Some(*best)));
+            }
+        }
+        lines
+    }
+
+    /// Whether `wpm` beats every test so far.
+    pub fn is_personal_best(&self, wpm: f64) -> bool {
+        self.best_wpm.is_some_and(|best| wpm > best)
+    }
+}
+
+/// Runs a `toipe history` command.
+pub fn run(command: &HistoryCommand) -> Result<(), Error> {
+    let path = history_file()?;
+    match command {
+        HistoryCommand::Encrypt => {
+            if is_encrypted_file(&path) {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    "The history is already encrypted",
+                ));
+            }
+            encryption::create_key_file()?;
+            write(&encrypted_path(&path), &read(&path)?)?;
+            remove_if_exists(&path)
+        }
+        HistoryCommand::Decrypt => {
+            if !is_encrypted_file(&path) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "The history is not encrypted",
+                ));
+            }
+            write(&path.with_extension(""), &read(&path)?)?;
+            remove_if_exists(&path)
+        }
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), Error> {
+    match storage::remove(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> HistoryEntry {
+        HistoryEntry {
+            timestamp: 1_700_000_000,
+            duration: Duration::from_millis(12_500),
+            wpm: 61.5,
+            accuracy: 0.97,
+            total_words: 30,
+            total_chars_typed: 160,
+            total_chars_in_text: 150,
+            total_char_errors: 5,
+            final_chars_typed_correctly: 149,
+            final_uncorrected_errors: 1,
+            final_chars_skipped: 0,
+            metadata: TestMetadata {
+                toipe_version: "0.5.0".to_string(),
+                term: Some("xterm-256color".to_string()),
+                terminal_size: Some((80, 24)),
+                wordlist: "top250".to_string(),
+                wordlist_hash: None,
+                seed: None,
+                flags: vec!["punctuation".to_string()],
+            },
+        }
+    }
+
+    #[test]
+    fn summarizes_history() {
+        let with = |wpm, wordlist: &str| {
+            let mut entry = entry();
+            entry.wpm = wpm;
+            entry.metadata.wordlist = wordlist.to_string();
+            entry
+        };
+        let mut entries = vec![with(20.0, "top250"), with(90.0, "top1000")];
+        entries.extend((0..ROLLING_WINDOW).map(|_| with(50.0, "top250")));
+
+        let stats = HistoryStats::from_entries(&entries);
+        assert_eq!(stats.num_tests, ROLLING_WINDOW + 2);
+        assert_eq!(stats.best_wpm, Some(90.0));
+        assert_eq!(stats.average_wpm, Some(50.0));
+        assert_eq!(stats.best_per_wordlist["top250"], 50.0);
+        assert_eq!(stats.best_per_wordlist["top1000"], 90.0);
+        assert!(stats.is_personal_best(91.0));
+        assert!(!stats.is_personal_best(90.0));
+
+        let summary = stats.summary();
+        assert_eq!(summary[0], ("Tests taken: 12".to_string(), None));
+        assert_eq!(summary[1], ("Time typing: 0h 02m".to_string(), None));
+        assert_eq!(summary[2], ("Personal best: ".to_string(), Some(90.0)));
+        // a blank line before the best of each word list
+        assert_eq!(summary[4], (String::new(), None));
+        assert_eq!(
+            summary.last(),
+            Some(&("  top250: ".to_string(), Some(50.0)))
+        );
+        assert_eq!(HistoryStats::default().summary().len(), 2);
+
+        assert_eq!(HistoryStats::from_entries(&[]), HistoryStats::default());
+        assert!(!HistoryStats::default().is_personal_best(10.0));
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let entry = entry();
+
+        let line = entry.to_json().to_string();
+        assert_eq!(
+            HistoryEntry::from_json(serde_json::from_str::<Value>(&line).unwrap()).unwrap(),
+            entry
+        );
+
+        let from_the_future = line.replace("\"schema_version\":1", "\"schema_version\":999");
+        assert!(
+            HistoryEntry::from_json(serde_json::from_str::<Value>(&from_the_future).unwrap())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn merging() {
+        let at = |timestamp| HistoryEntry {
+            timestamp,
+            ..entry()
+        };
+
+        assert_eq!(
+            merge(vec![at(1), at(3)], vec![at(2), at(3), at(4)]),
+            vec![at(1), at(2), at(3), at(4)]
+        );
+
+        let (entries, counts) = merge_sources(vec![
+            ("this machine".to_string(), vec![at(1), at(3)]),
+            ("laptop.jsonl".to_string(), vec![at(3), at(2), at(3)]),
+        ]);
+        assert_eq!(entries, vec![at(1), at(2), at(3)]);
+        assert_eq!(
+            counts[1],
+            SourceCount {
+                name: "laptop.jsonl".to_string(),
+                tests: 3,
+                added: 1,
+            }
+        );
+        assert_eq!(at(3).id(), at(3).id());
+        assert_ne!(at(3).id(), at(2).id());
+    }
+}
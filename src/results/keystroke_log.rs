@@ -0,0 +1,218 @@
+//! Logs of every key typed during tests, with timings.
+//!
+//! Keystroke logs are sensitive, so they are only written with
+//! `--keystroke-log`. Each test is logged to its own file in
+//! [`logs_dir`], named after the time the test ended, and logs older
+//! than `--keep-logs-days` are removed by [`prune`]. Logs are encrypted
+//! if the history is (see [`history::is_encrypted`]).
+//!
+//! Keystrokes can also be exported as CSV with `--export-keystrokes`,
+//! see [`export_csv`].
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value};
+
+use super::{history, ToipeResults};
+use crate::{dirs, encryption, storage};
+
+/// Version of the format of keystroke logs.
+pub const SCHEMA_VERSION: u64 = 1;
+
+const MILLIS_PER_DAY: u128 = 24 * 60 * 60 * 1000;
+
+/// Directory the keystroke logs are stored in.
+///
+/// Usually `~/.local/share/toipe/keystrokes`.
+pub fn logs_dir() -> Result<PathBuf, Error> {
+    Ok(dirs::data_dir()?.join("keystrokes"))
+}
+
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// The keystrokes of a test as a JSON object.
+pub fn to_json(results: &ToipeResults) -> Value {
+    let keystrokes: Vec<Value> = results
+        .keystrokes
+        .iter()
+        .map(|keystroke| {
+            let mut object = Map::new();
+            object.insert("at".into(), keystroke.at.as_secs_f64().into());
+            object.insert("index".into(), keystroke.index.into());
+            object.insert("expected".into(), keystroke.expected.to_string().into());
+            object.insert("typed".into(), keystroke.typed.to_string().into());
+            object.into()
+        })
+        .collect();
+
+    let mut object = Map::new();
+    object.insert("schema_version".into(), SCHEMA_VERSION.into());
+    object.insert("text".into(), results.text.as_str().into());
+    object.insert("keystrokes".into(), keystrokes.into());
+    object.into()
+}
+
+/// Writes the keystrokes of a test that ended now to a new log file,
+/// only readable by the user since it holds everything they typed.
+pub fn save(results: &ToipeResults) -> Result<(), Error> {
+    let contents = format!("{}\n", to_json(results));
+    let name = now_millis().to_string();
+
+    if history::is_encrypted()? {
+        storage::write_private(
+            &logs_dir()?.join(name + ".json.enc"),
+            &encryption::encrypt(contents.as_bytes())?,
+        )
+    } else {
+        storage::write_private(&logs_dir()?.join(name + ".json"), contents.as_bytes())
+    }
+}
+
+/// Header of the CSV written by [`export_csv`].
+const CSV_HEADER: &str = "timestamp,expected,typed,correct,latency_ms\n";
+
+/// A char as a CSV field, quoted if needed.
+fn csv_field(c: char) -> String {
+    match c {
+        '"' => "\"\"\"\"".to_string(),
+        ',' | '\n' | '\r' => format!("\"{}\"", c),
+        c => c.to_string(),
+    }
+}
+
+/// The keystrokes of a test as CSV rows, without the header. The test
+/// started at `started_at_millis`, in milliseconds since the Unix epoch.
+///
+/// The columns are the time of the keystroke in milliseconds since the
+/// Unix epoch, the char expected and typed, whether they are the same
+/// and the milliseconds since the previous keystroke, or since the start
+/// of the test for the first one.
+pub fn to_csv(results: &ToipeResults, started_at_millis: u128) -> String {
+    let mut csv = String::new();
+    let mut previous_at = Duration::ZERO;
+    for keystroke in &results.keystrokes {
+        csv += &format!(
+            "{},{},{},{},{}\n",
+            started_at_millis + keystroke.at.as_millis(),
+            csv_field(keystroke.expected),
+            csv_field(keystroke.typed),
+            keystroke.is_correct(),
+            keystroke.at.saturating_sub(previous_at).as_millis(),
+        );
+        previous_at = keystroke.at;
+    }
+    csv
+}
+
+/// Appends the keystrokes of a test to the CSV file at `path`, see
+/// [`to_csv`]. The header is written first if the file is new.
+pub fn export_csv(results: &ToipeResults, path: &Path) -> Result<(), Error> {
+    let started_at_millis = now_millis().saturating_sub(results.started_at.elapsed().as_millis());
+    let mut csv = to_csv(results, started_at_millis);
+    if !fs::metadata(path).is_ok_and(|metadata| metadata.len() > 0) {
+        csv.insert_str(0, CSV_HEADER);
+    }
+    storage::append_private(path, csv.as_bytes())
+}
+
+/// Whether the log file with this name was written more than
+/// `keep_days` days before `now_millis`.
+///
+/// Files not named like keystroke logs never expire.
+fn is_expired(file_name: &str, now_millis: u128, keep_days: u32) -> bool {
+    let written_at = file_name
+        .split('.')
+        .next()
+        .and_then(|millis| millis.parse::<u128>().ok());
+    match written_at {
+        Some(written_at) => {
+            now_millis.saturating_sub(written_at) > keep_days as u128 * MILLIS_PER_DAY
+        }
+        None => false,
+    }
+}
+
+/// Removes the logs written more than `keep_days` days ago.
+///
+/// `0` keeps all logs.
+pub fn prune(keep_days: u32) -> Result<(), Error> {
+    if keep_days == 0 {
+        return Ok(());
+    }
+
+    let entries = match fs::read_dir(logs_dir()?) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    let now = now_millis();
+    for entry in entries {
+        let entry = entry?;
+        if is_expired(&entry.file_name().to_string_lossy(), now, keep_days) {
+            storage::remove(&entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::Keystroke;
+
+    #[test]
+    fn expiry() {
+        let now = 100 * MILLIS_PER_DAY;
+
+        assert!(!is_expired(&format!("{}.json", now - 1), now, 30));
+        assert!(!is_expired(
+            &format!("{}.json.enc", now - 30 * MILLIS_PER_DAY),
+            now,
+            30
+        ));
+        assert!(is_expired(
+            &format!("{}.json", now - 31 * MILLIS_PER_DAY),
+            now,
+            30
+        ));
+        assert!(is_expired(&format!("{}.json.enc", 0), now, 30));
+        assert!(!is_expired("notes.txt", now, 30));
+    }
+
+    #[test]
+    fn csv() {
+        let keystroke = |at_ms, index, expected, typed| Keystroke {
+            at: Duration::from_millis(at_ms),
+            index,
+            expected,
+            typed,
+        };
+        let results = ToipeResults {
+            keystrokes: vec![
+                keystroke(0, 0, 'a', 'a'),
+                keystroke(150, 1, ',', '.'),
+                keystroke(400, 1, ',', ','),
+                keystroke(520, 3, '"', '"'),
+            ],
+            ..ToipeResults::from_typed("a, \"b\"", "", Duration::ZERO)
+        };
+        assert_eq!(
+            to_csv(&results, 1_000),
+            "1000,a,a,true,0\n\
+             1150,\",\",.,false,150\n\
+             1400,\",\",\",\",true,250\n\
+             1520,\"\"\"\",\"\"\"\",true,120\n"
+        );
+    }
+}
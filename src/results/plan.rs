@@ -0,0 +1,466 @@
+//! Training plans for reaching a typing speed by a date, see `toipe plan`.
+//!
+//! A plan starts from the average speed of the latest tests in the
+//! history and assumes the speed keeps improving as fast as it did over
+//! past tests. It is stored in [`plan_file`], and how many of its
+//! sessions were done so far is shown by `toipe --stats`.
+
+use std::{
+    fmt, fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value};
+
+use super::{
+    baseline,
+    char_stats::{self, CharStats},
+    history::{self, HistoryEntry, HistoryStats},
+};
+use crate::{dirs, storage};
+
+const SCHEMA_VERSION: u64 = 1;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Number of tests in a practice session.
+pub const TESTS_PER_SESSION: usize = 10;
+
+/// Most sessions a day a plan asks for.
+const MAX_SESSIONS_PER_DAY: u64 = 4;
+
+/// Number of latest tests the improvement per test is measured over.
+const TREND_WINDOW: usize = 100;
+
+/// Fewest tests the improvement per test is measured from.
+const MIN_TREND_TESTS: usize = 10;
+
+/// Improvement per test assumed while it cannot be measured, in wpm.
+const DEFAULT_GAIN_PER_TEST: f64 = 0.05;
+
+/// Number of weakest chars a plan focuses on.
+const NUM_FOCUS_CHARS: usize = 5;
+
+/// A day, counted from 1970-01-01.
+///
+/// Parsed from and displayed as `YYYY-MM-DD`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date(pub i64);
+
+impl Date {
+    pub fn today() -> Self {
+        Self::from_timestamp(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        )
+    }
+
+    /// The day of a time in seconds since the Unix epoch, in UTC.
+    pub fn from_timestamp(timestamp: u64) -> Self {
+        Self((timestamp / SECS_PER_DAY) as i64)
+    }
+
+    fn from_ymd(year: i64, month: i64, day: i64) -> Self {
+        // see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Self(era * 146_097 + day_of_era - 719_468)
+    }
+
+    fn ymd(self) -> (i64, i64, i64) {
+        // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = self.0 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        (year, month, day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid date `{}`, expected YYYY-MM-DD", s);
+        let parts: Vec<i64> = s
+            .split('-')
+            .map(|part| part.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        let [year, month, day] = parts[..] else {
+            return Err(invalid());
+        };
+
+        let date = Self::from_ymd(year, month, day);
+        // out of range months and days end up in other months
+        if date.ymd() != (year, month, day) {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// A typing speed to reach, parsed from e.g. `90wpm` or `90`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WpmTarget(pub f64);
+
+impl FromStr for WpmTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.trim().trim_end_matches("wpm").trim_end();
+        match number.parse::<f64>() {
+            Ok(wpm) if wpm.is_finite() && wpm > 0.0 => Ok(Self(wpm)),
+            _ => Err(format!("invalid speed `{}`, expected e.g. 90wpm", s)),
+        }
+    }
+}
+
+/// Daily practice sessions for reaching [`Plan::target_wpm`] by
+/// [`Plan::by`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    pub target_wpm: f64,
+    pub by: Date,
+    /// when the plan was made
+    pub start: Date,
+    /// average speed of the latest tests when the plan was made
+    pub start_wpm: f64,
+    /// how much faster each test is expected to get, in wpm
+    pub gain_per_test: f64,
+    pub sessions_per_day: u64,
+    /// chars most often typed wrong, drilled with `--practice`
+    pub focus_chars: Vec<char>,
+}
+
+impl Plan {
+    /// Plans the sessions from `today` on, given the tests taken so far.
+    pub fn new(
+        target_wpm: f64,
+        by: Date,
+        today: Date,
+        entries: &[HistoryEntry],
+        char_stats: &CharStats,
+    ) -> Result<Self, Error> {
+        if by <= today {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The plan must end after today, {}", today),
+            ));
+        }
+        let start_wpm = HistoryStats::from_entries(entries)
+            .average_wpm
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    "Take a few tests first, the plan starts from your current speed",
+                )
+            })?;
+        let gain_per_test = gain_per_test(entries)
+            .filter(|gain| *gain > 0.0)
+            .unwrap_or(DEFAULT_GAIN_PER_TEST);
+
+        let tests = ((target_wpm - start_wpm).max(0.0) / gain_per_test).ceil();
+        let sessions = (tests / TESTS_PER_SESSION as f64).ceil() as u64;
+        let days = (by.0 - today.0) as u64;
+        Ok(Self {
+            target_wpm,
+            by,
+            start: today,
+            start_wpm,
+            gain_per_test,
+            sessions_per_day: sessions.div_ceil(days).clamp(1, MAX_SESSIONS_PER_DAY),
+            focus_chars: char_stats.weakest_chars(NUM_FOCUS_CHARS),
+        })
+    }
+
+    /// Number of days from the start to the end of the plan.
+    fn days(&self) -> i64 {
+        self.by.0 - self.start.0
+    }
+
+    /// Speed expected by the end of the plan when all sessions are done,
+    /// lower than the target when more sessions a day would be needed.
+    pub fn projected_wpm(&self) -> f64 {
+        let tests = self.sessions_per_day * self.days() as u64 * TESTS_PER_SESSION as u64;
+        (self.start_wpm + self.gain_per_test * tests as f64).min(self.target_wpm)
+    }
+
+    /// Speed to reach by the end of `day` to be on track.
+    pub fn milestone(&self, day: Date) -> f64 {
+        let progress = (day.0 - self.start.0 + 1) as f64 / self.days() as f64;
+        self.start_wpm + (self.target_wpm - self.start_wpm) * progress.clamp(0.0, 1.0)
+    }
+
+    /// The first day of every week of the plan, with the speed to reach
+    /// by the end of that week.
+    pub fn weeks(&self) -> Vec<(Date, f64)> {
+        (self.start.0..self.by.0)
+            .step_by(7)
+            .map(|day| {
+                let last_day = Date((day + 6).min(self.by.0 - 1));
+                (Date(day), self.milestone(last_day))
+            })
+            .collect()
+    }
+
+    /// Sessions to be done from the start of the plan up to `today`.
+    pub fn sessions_expected(&self, today: Date) -> u64 {
+        let days = (today.0 - self.start.0 + 1).clamp(0, self.days());
+        days as u64 * self.sessions_per_day
+    }
+
+    /// Tests to be taken each day, the daily goal of the plan.
+    pub fn tests_per_day(&self) -> usize {
+        self.sessions_per_day as usize * TESTS_PER_SESSION
+    }
+
+    /// Sessions done since the start of the plan.
+    pub fn sessions_done(&self, entries: &[HistoryEntry]) -> u64 {
+        let tests = entries
+            .iter()
+            .filter(|entry| Date::from_timestamp(entry.timestamp) >= self.start)
+            .count();
+        (tests / TESTS_PER_SESSION) as u64
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("schema_version".into(), SCHEMA_VERSION.into());
+        object.insert("target_wpm".into(), self.target_wpm.into());
+        object.insert("by".into(), self.by.to_string().into());
+        object.insert("start".into(), self.start.to_string().into());
+        object.insert("start_wpm".into(), self.start_wpm.into());
+        object.insert("gain_per_test".into(), self.gain_per_test.into());
+        object.insert("sessions_per_day".into(), self.sessions_per_day.into());
+        object.insert(
+            "focus_chars".into(),
+            self.focus_chars.iter().collect::<String>().into(),
+        );
+        object.into()
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, Error> {
+        let number = |field| {
+            value
+                .get(field)
+                .and_then(Value::as_f64)
+                .ok_or_else(|| invalid(field))
+        };
+        let date = |field| {
+            value
+                .get(field)
+                .and_then(Value::as_str)
+                .and_then(|date| date.parse().ok())
+                .ok_or_else(|| invalid(field))
+        };
+        Ok(Self {
+            target_wpm: number("target_wpm")?,
+            by: date("by")?,
+            start: date("start")?,
+            start_wpm: number("start_wpm")?,
+            gain_per_test: number("gain_per_test")?,
+            sessions_per_day: value
+                .get("sessions_per_day")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| invalid("sessions_per_day"))?,
+            focus_chars: value
+                .get("focus_chars")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid("focus_chars"))?
+                .chars()
+                .collect(),
+        })
+    }
+}
+
+/// How much faster each of the latest tests got on average, from the
+/// slope of the line fitted through their speeds.
+fn gain_per_test(entries: &[HistoryEntry]) -> Option<f64> {
+    let recent = &entries[entries.len().saturating_sub(TREND_WINDOW)..];
+    if recent.len() < MIN_TREND_TESTS {
+        return None;
+    }
+
+    let n = recent.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = recent.iter().map(|entry| entry.wpm).sum::<f64>() / n;
+    let (covariance, variance) =
+        recent
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(covariance, variance), (x, entry)| {
+                let dx = x as f64 - mean_x;
+                (covariance + dx * (entry.wpm - mean_y), variance + dx * dx)
+            });
+    Some(covariance / variance)
+}
+
+fn invalid(field: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Missing or invalid `{}` in the plan", field),
+    )
+}
+
+/// File the plan is stored in.
+///
+/// Usually `~/.local/share/toipe/plan.json`.
+pub fn plan_file() -> Result<PathBuf, Error> {
+    Ok(dirs::data_dir()?.join("plan.json"))
+}
+
+/// The current plan, if any.
+pub fn load() -> Result<Option<Plan>, Error> {
+    match fs::read_to_string(plan_file()?) {
+        Ok(contents) => Plan::from_json(&serde_json::from_str::<Value>(&contents)?).map(Some),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Runs `toipe plan`: makes a plan to reach `target_wpm` by `by`,
+/// replacing the current one, and prints its schedule.
+///
+/// Without a target, the one suggested by the baseline is used.
+pub fn run(target_wpm: Option<f64>, by: Date) -> Result<(), Error> {
+    let target_wpm = match target_wpm {
+        Some(wpm) => wpm,
+        None => baseline::load()?
+            .map(|baseline| baseline.target_wpm())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    "No speed to reach, pass one with --target or run `toipe calibrate` first",
+                )
+            })?,
+    };
+    let entries = history::load()?;
+    let plan = Plan::new(
+        target_wpm,
+        by,
+        Date::today(),
+        &entries,
+        &char_stats::load()?,
+    )?;
+    storage::write(&plan_file()?, format!("{}\n", plan.to_json()).as_bytes())?;
+
+    println!(
+        "Plan to reach {:.0} wpm by {}, from {:.1} wpm now:",
+        plan.target_wpm, plan.by, plan.start_wpm
+    );
+    println!(
+        "{} session(s) of {} tests a day",
+        plan.sessions_per_day, TESTS_PER_SESSION
+    );
+    if plan.projected_wpm() < plan.target_wpm {
+        println!(
+            "warning: at {} sessions a day you would reach about {:.1} wpm, \
+            consider a later date",
+            MAX_SESSIONS_PER_DAY,
+            plan.projected_wpm()
+        );
+    }
+    if !plan.focus_chars.is_empty() {
+        let chars: Vec<String> = plan.focus_chars.iter().map(char::to_string).collect();
+        println!(
+            "Focus: start each session with `toipe --practice` to drill {}",
+            chars.join(" ")
+        );
+    }
+
+    println!();
+    for (week_start, wpm) in plan.weeks() {
+        println!("Week of {}: reach {:.1} wpm", week_start, wpm);
+    }
+    println!();
+    println!("Track your progress with `toipe --stats`");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_ulps_eq;
+
+    use super::*;
+    use crate::results::history::test_entry;
+
+    #[test]
+    fn dates() {
+        let date: Date = "2025-01-01".parse().unwrap();
+        assert_eq!(date, Date(20_089));
+        assert_eq!(date.to_string(), "2025-01-01");
+        assert_eq!(
+            "2024-02-29".parse::<Date>().unwrap().to_string(),
+            "2024-02-29"
+        );
+        assert_eq!(
+            Date::from_timestamp(1_700_000_000).to_string(),
+            "2023-11-14"
+        );
+        for invalid in ["2025-13-01", "2023-02-29", "2025-01", "tomorrow"] {
+            assert!(invalid.parse::<Date>().is_err(), "{}", invalid);
+        }
+
+        assert_eq!("90wpm".parse(), Ok(WpmTarget(90.0)));
+        assert_eq!("72.5".parse(), Ok(WpmTarget(72.5)));
+        assert!("-1wpm".parse::<WpmTarget>().is_err());
+    }
+
+    #[test]
+    fn plans_sessions() {
+        let today = Date(20_000);
+        let day = SECS_PER_DAY;
+        // 20 tests getting 0.1 wpm faster each, the last 10 average 60.45
+        let entries: Vec<HistoryEntry> = (0..20)
+            .map(|i| test_entry(19_990 * day + i * 60, 59.0 + i as f64 * 0.1))
+            .collect();
+
+        let plan = Plan::new(70.0, Date(20_010), today, &entries, &CharStats::default()).unwrap();
+        assert_ulps_eq!(plan.gain_per_test, 0.1, epsilon = 1e-9);
+        // 96 tests in 10 days
+        assert_eq!(plan.sessions_per_day, 1);
+        assert_ulps_eq!(plan.projected_wpm(), 70.0);
+        assert_eq!(plan.weeks().len(), 2);
+        assert_ulps_eq!(plan.milestone(Date(20_009)), 70.0);
+
+        let rushed = Plan::new(70.0, Date(20_001), today, &entries, &CharStats::default()).unwrap();
+        assert_eq!(rushed.sessions_per_day, MAX_SESSIONS_PER_DAY);
+        assert!(rushed.projected_wpm() < 70.0);
+
+        assert!(Plan::new(70.0, today, today, &entries, &CharStats::default()).is_err());
+        assert!(Plan::new(70.0, Date(20_010), today, &[], &CharStats::default()).is_err());
+
+        let mut done = entries.clone();
+        done.extend((0..25).map(|i| test_entry(20_001 * day + i, 61.0)));
+        assert_eq!(plan.sessions_done(&done), 2);
+        assert_eq!(plan.sessions_expected(Date(20_001)), 2);
+        assert_eq!(plan.sessions_expected(Date(20_100)), 10);
+
+        assert_eq!(Plan::from_json(&plan.to_json()).unwrap(), plan);
+    }
+}
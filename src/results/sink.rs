@@ -0,0 +1,207 @@
+//! Where the results of finished tests are kept.
+//!
+//! [`Toipe`](crate::Toipe) stores each finished test in a
+//! [`ResultsSink`], and reads it back to compare the results with past
+//! tests and for `--stats`. By default this is the history file, see
+//! [`FileSink`]. Applications embedding toipe can keep the results in a
+//! SQLite database with `SqliteSink` (with the `sqlite` feature), only in
+//! memory with [`MemorySink`], or anywhere else by implementing the
+//! trait, see [`Toipe::set_results_sink`](crate::Toipe::set_results_sink).
+
+use std::{io::Error, path::PathBuf};
+
+use serde_json::Value;
+
+use super::history::{self, HistoryEntry};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteSink;
+
+/// Stores finished tests and answers queries about them.
+pub trait ResultsSink {
+    /// Stores a finished test.
+    fn append(&mut self, entry: &HistoryEntry) -> Result<(), Error>;
+
+    /// The `count` most recent tests, oldest first. All of them for
+    /// `usize::MAX`.
+    fn recent(&self, count: usize) -> Result<Vec<HistoryEntry>, Error>;
+
+    /// The fastest test taken with the word list called `wordlist` and
+    /// the options `flags` (see
+    /// [`ToipeConfig::mode_flags`](crate::config::ToipeConfig::mode_flags)),
+    /// in any order.
+    fn best_for_mode(
+        &self,
+        wordlist: &str,
+        flags: &[String],
+    ) -> Result<Option<HistoryEntry>, Error>;
+}
+
+/// `flags` in a canonical order, to compare the modes of tests.
+fn mode_key(flags: &[String]) -> Value {
+    let mut flags = flags.to_vec();
+    flags.sort();
+    flags.into()
+}
+
+/// The fastest of `entries` with the mode of `wordlist` and `flags`.
+fn best_of(entries: Vec<HistoryEntry>, wordlist: &str, flags: &[String]) -> Option<HistoryEntry> {
+    let mode = mode_key(flags);
+    entries
+        .into_iter()
+        .filter(|entry| {
+            entry.metadata.wordlist == wordlist && mode_key(&entry.metadata.flags) == mode
+        })
+        .max_by(|a, b| a.wpm.total_cmp(&b.wpm))
+}
+
+/// The last `count` of `entries`.
+fn last(mut entries: Vec<HistoryEntry>, count: usize) -> Vec<HistoryEntry> {
+    entries.drain(..entries.len().saturating_sub(count));
+    entries
+}
+
+/// The history file (see [`history::history_file`]) by default, or
+/// another file in its format.
+#[derive(Debug, Clone, Default)]
+pub struct FileSink {
+    path: Option<PathBuf>,
+}
+
+impl FileSink {
+    /// Tests stored in the file at `path` instead of the history file.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    fn path(&self) -> Result<PathBuf, Error> {
+        match &self.path {
+            Some(path) => Ok(path.clone()),
+            None => history::history_file(),
+        }
+    }
+}
+
+impl ResultsSink for FileSink {
+    fn append(&mut self, entry: &HistoryEntry) -> Result<(), Error> {
+        history::append_to(&self.path()?, entry)
+    }
+
+    fn recent(&self, count: usize) -> Result<Vec<HistoryEntry>, Error> {
+        Ok(last(history::load_file(&self.path()?)?, count))
+    }
+
+    fn best_for_mode(
+        &self,
+        wordlist: &str,
+        flags: &[String],
+    ) -> Result<Option<HistoryEntry>, Error> {
+        Ok(best_of(history::load_file(&self.path()?)?, wordlist, flags))
+    }
+}
+
+/// Tests kept in memory only, forgotten when dropped.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    /// oldest first
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl ResultsSink for MemorySink {
+    fn append(&mut self, entry: &HistoryEntry) -> Result<(), Error> {
+        // refused like by the other sinks, which store the entry as JSON
+        entry.check_finite()?;
+        self.entries.push(entry.clone());
+        Ok(())
+    }
+
+    fn recent(&self, count: usize) -> Result<Vec<HistoryEntry>, Error> {
+        Ok(last(self.entries.clone(), count))
+    }
+
+    fn best_for_mode(
+        &self,
+        wordlist: &str,
+        flags: &[String],
+    ) -> Result<Option<HistoryEntry>, Error> {
+        Ok(best_of(self.entries.clone(), wordlist, flags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+    use crate::results::history::test_entry;
+
+    /// See [`test_entry`], with `flags`.
+    pub(super) fn entry(timestamp: u64, wpm: f64, flags: &[&str]) -> HistoryEntry {
+        let mut entry = test_entry(timestamp, wpm);
+        entry.metadata.flags = flags.iter().map(|flag| flag.to_string()).collect();
+        entry
+    }
+
+    /// Checks the queries of `sink`, which starts empty, and that it
+    /// refuses entries it could not read back.
+    pub(super) fn check_queries(sink: &mut dyn ResultsSink) {
+        assert_eq!(sink.recent(usize::MAX).unwrap(), []);
+        sink.append(&entry(1, 50.0, &["punctuation", "num_words=30"]))
+            .unwrap();
+        sink.append(&entry(2, 70.0, &[])).unwrap();
+        sink.append(&entry(3, 60.0, &["num_words=30", "punctuation"]))
+            .unwrap();
+
+        let recent = sink.recent(2).unwrap();
+        assert_eq!(
+            recent
+                .iter()
+                .map(|entry| entry.timestamp)
+                .collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert_eq!(sink.recent(usize::MAX).unwrap().len(), 3);
+
+        for wpm in [f64::NAN, f64::INFINITY] {
+            let err = sink.append(&entry(4, wpm, &[])).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+        let mut inaccurate = entry(4, 60.0, &[]);
+        inaccurate.accuracy = f64::NAN;
+        assert!(sink.append(&inaccurate).is_err());
+        assert_eq!(sink.recent(usize::MAX).unwrap().len(), 3);
+
+        let flags = ["punctuation".to_string(), "num_words=30".to_string()];
+        let best = sink.best_for_mode("top250", &flags).unwrap().unwrap();
+        assert_eq!(best.wpm, 60.0);
+        assert_eq!(sink.best_for_mode("top1000", &flags).unwrap(), None);
+        assert_eq!(
+            sink.best_for_mode("top250", &[]).unwrap().unwrap().wpm,
+            70.0
+        );
+    }
+
+    /// A path in the temporary directory, removed if it exists.
+    pub(super) fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("toipe-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn memory_sink_queries() {
+        check_queries(&mut MemorySink::default());
+    }
+
+    #[test]
+    fn file_sink_queries() {
+        let path = temp_path("sink.jsonl");
+        check_queries(&mut FileSink::at(&path));
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+//! [`SqliteSink`], with the `sqlite` feature.
+
+use std::{
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+use super::{mode_key, ResultsSink};
+use crate::{
+    migrations,
+    results::history::{self, HistoryEntry, SCHEMA},
+    storage,
+};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS history (
+    timestamp INTEGER NOT NULL,
+    wpm REAL NOT NULL,
+    wordlist TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    entry TEXT NOT NULL
+);";
+
+/// Tests stored in a SQLite database.
+///
+/// Each test is a row of the `history` table, with the entry as JSON
+/// (see [`HistoryEntry::to_json`]) and the columns it is queried by.
+///
+/// The database is not encrypted, so it can not be used once the history
+/// is encrypted with `toipe history encrypt`.
+#[derive(Debug, Clone)]
+pub struct SqliteSink {
+    path: PathBuf,
+}
+
+fn sqlite_error(err: rusqlite::Error) -> Error {
+    Error::other(format!("SQLite error: {}", err))
+}
+
+impl SqliteSink {
+    /// Tests stored in the database at `path`, which is created along
+    /// with the table on the first test stored.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Opens the database, creating the table if needed.
+    fn open(&self) -> Result<rusqlite::Connection, Error> {
+        if history::is_encrypted()? {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "The history is encrypted, but SQLite databases are not",
+            ));
+        }
+        let connection = rusqlite::Connection::open(&self.path).map_err(sqlite_error)?;
+        connection
+            .execute_batch(CREATE_TABLE)
+            .map_err(sqlite_error)?;
+        Ok(connection)
+    }
+
+    /// Entries of the rows picked by `query` with `params`, which selects
+    /// the `entry` column, upgraded to the latest version of [`SCHEMA`].
+    fn query(
+        &self,
+        query: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<HistoryEntry>, Error> {
+        // reading would create an empty database
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let connection = self.open()?;
+        let mut statement = connection.prepare(query).map_err(sqlite_error)?;
+        let lines = statement
+            .query_map(params, |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(sqlite_error)?;
+        let (values, _) = migrations::upgrade_json_lines(&lines.join("\n"), &self.path, &SCHEMA)?;
+        values.into_iter().map(HistoryEntry::from_json).collect()
+    }
+}
+
+impl ResultsSink for SqliteSink {
+    fn append(&mut self, entry: &HistoryEntry) -> Result<(), Error> {
+        if storage::is_incognito() {
+            return Ok(());
+        }
+        entry.check_finite()?;
+        if let Some(parent) = self.path.parent() {
+            storage::create_dir(parent)?;
+        }
+        self.open()?
+            .execute(
+                "INSERT INTO history VALUES (?1, ?2, ?3, ?4, ?5);",
+                rusqlite::params![
+                    entry.timestamp as i64,
+                    entry.wpm,
+                    entry.metadata.wordlist,
+                    mode_key(&entry.metadata.flags).to_string(),
+                    entry.to_json().to_string(),
+                ],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn recent(&self, count: usize) -> Result<Vec<HistoryEntry>, Error> {
+        let mut entries = self.query(
+            "SELECT entry FROM history ORDER BY timestamp DESC, rowid DESC LIMIT ?1;",
+            [count.min(i64::MAX as usize) as i64],
+        )?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    fn best_for_mode(
+        &self,
+        wordlist: &str,
+        flags: &[String],
+    ) -> Result<Option<HistoryEntry>, Error> {
+        let entries = self.query(
+            "SELECT entry FROM history WHERE wordlist = ?1 AND mode = ?2 \
+             ORDER BY wpm DESC LIMIT 1;",
+            [wordlist.to_string(), mode_key(flags).to_string()],
+        )?;
+        Ok(entries.into_iter().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::sink::tests::{check_queries, entry, temp_path};
+
+    #[test]
+    fn queries() {
+        let path = temp_path("sink.sqlite");
+        let mut sink = SqliteSink::new(&path);
+        check_queries(&mut sink);
+
+        // strings are bound, not put in the SQL
+        let mut quoted = entry(4, 40.0, &["it's"]);
+        quoted.metadata.wordlist = "'); DROP TABLE history; --".to_string();
+        sink.append(&quoted).unwrap();
+        let best = sink
+            .best_for_mode(&quoted.metadata.wordlist, &["it's".to_string()])
+            .unwrap();
+        assert_eq!(best, Some(quoted));
+        assert_eq!(sink.recent(usize::MAX).unwrap().len(), 4);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,397 @@
+//! Daily practice at a set time, see `toipe schedule`.
+//!
+//! `toipe schedule install --at 09:00` installs a user-level timer that
+//! opens a terminal running toipe every day, with the options given
+//! before the command, such as `toipe --practice schedule install`. The
+//! timer is a systemd timer and service in
+//! `~/.config/systemd/user`, or on macOS a launchd agent in
+//! `~/Library/LaunchAgents`. No root access is needed.
+//!
+//! The generated files start with a comment (or key) recording the time
+//! and the command, which `toipe schedule status` reads back.
+
+use std::{
+    env, fmt, fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    process::Command,
+    str::FromStr,
+};
+
+use crate::{config::ScheduleCommand, storage};
+
+/// Name of the systemd units, and label of the launchd agent.
+const NAME: &str = "toipe-practice";
+
+/// Terminal the test is run in with systemd, if `$TERMINAL` is not set.
+const DEFAULT_TERMINAL: &str = "x-terminal-emulator";
+
+/// A time of the day, parsed from `HH:MM`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeOfDay {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid time `{}`, expected HH:MM", s);
+        let (hour, minute) = s.split_once(':').ok_or_else(invalid)?;
+        let hour: u8 = hour.parse().map_err(|_| invalid())?;
+        let minute: u8 = minute.parse().map_err(|_| invalid())?;
+        if hour > 23 || minute > 59 {
+            return Err(invalid());
+        }
+        Ok(Self { hour, minute })
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+/// What runs every day: `program` with `args` at `at`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    pub at: TimeOfDay,
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Schedule {
+    /// The command run, as typed in a shell.
+    pub fn command_line(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|word| shell_quote(word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The systemd service and timer, running the test in `terminal`.
+    pub fn systemd_units(&self, terminal: &str) -> (String, String) {
+        let words: Vec<String> = [terminal, "-e", &self.program]
+            .into_iter()
+            .map(str::to_string)
+            .chain(self.args.iter().cloned())
+            .map(|word| systemd_quote(&word))
+            .collect();
+        let service = format!(
+            "# {}\n[Unit]\nDescription=Daily typing practice with toipe\n\n\
+             [Service]\nType=oneshot\nExecStart={}\n",
+            self.command_line(),
+            words.join(" ")
+        );
+        let timer = format!(
+            "# {}\n[Unit]\nDescription=Daily typing practice with toipe at {}\n\n\
+             [Timer]\nOnCalendar=*-*-* {}:00\nPersistent=false\n\n\
+             [Install]\nWantedBy=timers.target\n",
+            self.at, self.at, self.at
+        );
+        (service, timer)
+    }
+
+    /// The launchd agent, running the test in Terminal.app.
+    pub fn launchd_plist(&self) -> String {
+        let script = format!(
+            "tell application \"Terminal\"\nactivate\ndo script \"{}\"\nend tell",
+            self.command_line()
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+        );
+        let arguments: String = ["osascript", "-e", &script]
+            .iter()
+            .map(|arg| format!("        <string>{}</string>\n", xml_escape(arg)))
+            .collect();
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>Comment</key>
+    <string>{} {}</string>
+    <key>ProgramArguments</key>
+    <array>
+{}    </array>
+    <key>StartCalendarInterval</key>
+    <dict>
+        <key>Hour</key>
+        <integer>{}</integer>
+        <key>Minute</key>
+        <integer>{}</integer>
+    </dict>
+</dict>
+</plist>
+",
+            NAME,
+            self.at,
+            xml_escape(&self.command_line()),
+            arguments,
+            self.at.hour,
+            self.at.minute
+        )
+    }
+}
+
+/// Quotes `word` for a POSIX shell, unless it needs no quotes.
+fn shell_quote(word: &str) -> String {
+    let is_plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c));
+    match is_plain {
+        true => word.to_string(),
+        false => format!("'{}'", word.replace('\'', "'\\''")),
+    }
+}
+
+/// Quotes `word` for the command line of a systemd unit, where `%` and
+/// `$` are expanded too.
+fn systemd_quote(word: &str) -> String {
+    let escaped = word
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn home() -> Result<PathBuf, Error> {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Could not find the home directory"))
+}
+
+fn systemd_dir() -> Result<PathBuf, Error> {
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home()?.join(".config"),
+    };
+    Ok(config.join("systemd").join("user"))
+}
+
+fn launchd_plist_file() -> Result<PathBuf, Error> {
+    Ok(home()?
+        .join("Library")
+        .join("LaunchAgents")
+        .join(format!("{}.plist", NAME)))
+}
+
+/// Runs `program` with `args`, failing with its error output.
+fn run_command(program: &str, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => {
+                Error::new(ErrorKind::NotFound, format!("{} is not installed", program))
+            }
+            _ => err,
+        })?;
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn install(schedule: &Schedule, terminal: Option<&str>) -> Result<(), Error> {
+    if storage::is_incognito() {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "Practice can not be scheduled in incognito mode",
+        ));
+    }
+    if cfg!(target_os = "macos") {
+        let path = launchd_plist_file()?;
+        if path.exists() {
+            // replaces the agent installed before
+            let _ = run_command("launchctl", &["unload", &path.to_string_lossy()]);
+        }
+        storage::write(&path, schedule.launchd_plist().as_bytes())?;
+        run_command("launchctl", &["load", "-w", &path.to_string_lossy()])?;
+    } else if cfg!(unix) {
+        let terminal = match terminal {
+            Some(terminal) => terminal.to_string(),
+            None => env::var("TERMINAL").unwrap_or_else(|_| DEFAULT_TERMINAL.to_string()),
+        };
+        let (service, timer) = schedule.systemd_units(&terminal);
+        let dir = systemd_dir()?;
+        storage::write(&dir.join(format!("{}.service", NAME)), service.as_bytes())?;
+        storage::write(&dir.join(format!("{}.timer", NAME)), timer.as_bytes())?;
+        run_command("systemctl", &["--user", "daemon-reload"])?;
+        run_command(
+            "systemctl",
+            &["--user", "enable", "--now", &format!("{}.timer", NAME)],
+        )?;
+    } else {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "Scheduling practice needs systemd or launchd",
+        ));
+    }
+    println!(
+        "Scheduled `{}` every day at {}",
+        schedule.command_line(),
+        schedule.at
+    );
+    Ok(())
+}
+
+fn remove() -> Result<(), Error> {
+    if cfg!(target_os = "macos") {
+        let path = launchd_plist_file()?;
+        if !path.exists() {
+            println!("No practice is scheduled");
+            return Ok(());
+        }
+        run_command("launchctl", &["unload", "-w", &path.to_string_lossy()])?;
+        storage::remove(&path)?;
+    } else {
+        let dir = systemd_dir()?;
+        let timer = dir.join(format!("{}.timer", NAME));
+        if !timer.exists() {
+            println!("No practice is scheduled");
+            return Ok(());
+        }
+        run_command(
+            "systemctl",
+            &["--user", "disable", "--now", &format!("{}.timer", NAME)],
+        )?;
+        storage::remove(&timer)?;
+        storage::remove(&dir.join(format!("{}.service", NAME)))?;
+        run_command("systemctl", &["--user", "daemon-reload"])?;
+    }
+    println!("Removed the scheduled practice");
+    Ok(())
+}
+
+/// The time and command recorded in the first line of the unit files,
+/// see [`Schedule::systemd_units`].
+fn recorded(timer: &str, service: &str) -> Option<(String, String)> {
+    let at = timer.lines().next()?.strip_prefix("# ")?;
+    let command = service.lines().next()?.strip_prefix("# ")?;
+    Some((at.to_string(), command.to_string()))
+}
+
+fn status() -> Result<(), Error> {
+    if cfg!(target_os = "macos") {
+        let path = launchd_plist_file()?;
+        let Ok(plist) = fs::read_to_string(&path) else {
+            println!("No practice is scheduled");
+            return Ok(());
+        };
+        let comment = plist
+            .lines()
+            .skip_while(|line| !line.contains("<key>Comment</key>"))
+            .nth(1)
+            .map(|line| {
+                line.trim()
+                    .trim_start_matches("<string>")
+                    .trim_end_matches("</string>")
+            });
+        println!("Practice is scheduled: {}", comment.unwrap_or("unknown"));
+        let loaded = run_command("launchctl", &["list", NAME]).is_ok();
+        println!(
+            "The agent is {}",
+            if loaded { "loaded" } else { "not loaded" }
+        );
+    } else {
+        let dir = systemd_dir()?;
+        let Ok(timer) = fs::read_to_string(dir.join(format!("{}.timer", NAME))) else {
+            println!("No practice is scheduled");
+            return Ok(());
+        };
+        let service = fs::read_to_string(dir.join(format!("{}.service", NAME)))?;
+        match recorded(&timer, &service) {
+            Some((at, command)) => {
+                println!("Practice is scheduled every day at {}: `{}`", at, command)
+            }
+            None => println!("Practice is scheduled, but its unit files were changed"),
+        }
+        // `is-active` exits with an error for inactive units
+        let unit = format!("{}.timer", NAME);
+        let active = run_command("systemctl", &["--user", "is-active", &unit])
+            .unwrap_or_else(|_| "inactive".to_string());
+        println!("The timer is {}", active);
+    }
+    Ok(())
+}
+
+/// Runs a `toipe schedule` command, with `args` the options given
+/// before it.
+pub fn run(command: &ScheduleCommand, args: Vec<String>) -> Result<(), Error> {
+    match command {
+        ScheduleCommand::Install { at, terminal } => {
+            let schedule = Schedule {
+                at: *at,
+                program: env::current_exe()?.to_string_lossy().into_owned(),
+                args,
+            };
+            install(&schedule, terminal.as_deref())
+        }
+        ScheduleCommand::Remove => remove(),
+        ScheduleCommand::Status => status(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_times() {
+        assert_eq!("09:05".parse(), Ok(TimeOfDay { hour: 9, minute: 5 }));
+        assert_eq!("9:05".parse::<TimeOfDay>().unwrap().to_string(), "09:05");
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+        assert!("0905".parse::<TimeOfDay>().is_err());
+    }
+
+    #[test]
+    fn generates_timers() {
+        let schedule = Schedule {
+            at: TimeOfDay { hour: 9, minute: 0 },
+            program: "/usr/bin/toipe".to_string(),
+            args: vec![
+                "--practice".to_string(),
+                "--name".to_string(),
+                "it's me".to_string(),
+            ],
+        };
+        assert_eq!(
+            schedule.command_line(),
+            "/usr/bin/toipe --practice --name 'it'\\''s me'"
+        );
+
+        let (service, timer) = schedule.systemd_units("kitty");
+        assert!(service.contains(
+            "ExecStart=\"kitty\" \"-e\" \"/usr/bin/toipe\" \"--practice\" \"--name\" \"it's me\"\n"
+        ));
+        assert!(timer.contains("OnCalendar=*-*-* 09:00:00\n"));
+        assert_eq!(
+            recorded(&timer, &service),
+            Some(("09:00".to_string(), schedule.command_line()))
+        );
+
+        let plist = schedule.launchd_plist();
+        assert!(plist.contains("<integer>9</integer>"));
+        assert!(plist.contains("do script &quot;/usr/bin/toipe --practice"));
+    }
+}
@@ -0,0 +1,667 @@
+//! The logic of a typing test, without a terminal UI.
+//!
+//! A [`TypingSession`] is given the words to type and handles key presses
+//! one at a time. Each key press returns what changed as
+//! [`SessionUpdate`]s, for a UI (such as [`Toipe::test`](crate::Toipe::test))
+//! to show. Once the test is over, [`TypingSession::results`] gives its
+//! [`ToipeResults`].
+//!
+//! ```
+//! use std::time::Instant;
+//!
+//! use toipe::backend::Key;
+//! use toipe::session::{SessionEnd, TypingSession};
+//!
+//! let mut session = TypingSession::new(vec!["hi".to_string(), "there".to_string()]);
+//! for c in "hi thera".chars() {
+//!     session.handle_key(Key::Char(c), Instant::now());
+//! }
+//! assert_eq!(session.end(), Some(SessionEnd::Done));
+//!
+//! let results = session.results(Instant::now());
+//! assert_eq!(results.final_uncorrected_errors, 1);
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    backend::Key,
+    results::{CharOutcome, Keystroke, ToipeResults},
+    textgen::{self, TextDifficulty},
+};
+
+/// How a [`TypingSession`] ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEnd {
+    /// the last char was typed
+    Done,
+    /// ctrl-c
+    Quit,
+    /// ctrl-r or enter, for a test with new words
+    Restart,
+    /// ctrl-u, for another try at the same words
+    Retry,
+}
+
+/// Something that changed in a [`TypingSession`] on a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionUpdate {
+    /// the char at `index` was typed, or skipped over if the outcome is
+    /// [`CharOutcome::Skipped`]
+    Typed {
+        index: usize,
+        outcome: CharOutcome,
+    },
+    /// the char typed at `index` was deleted, e.g. by backspace
+    Deleted {
+        index: usize,
+    },
+    /// the space at `index` was typed, ending the word before it
+    WordEnd {
+        index: usize,
+    },
+    Ended(SessionEnd),
+}
+
+/// State of a typing test: the text, what was typed in its place and
+/// the mistakes made so far.
+pub struct TypingSession {
+    words: Vec<String>,
+    /// the words joined with spaces
+    text: Vec<char>,
+    /// chars typed in place of the start of the text
+    input: Vec<char>,
+    /// whether each char of the text was ever typed wrong
+    had_mistake: Vec<bool>,
+    /// whether each char of the text was skipped over in forgive mode or
+    /// as indentation
+    skipped: Vec<bool>,
+    /// whether each char of the text is in the indentation of a line of
+    /// code, or a space between words of a script written without them,
+    /// which is skipped over instead of typed
+    indentation: Vec<bool>,
+    /// whether space completes the rest of the word, see `--forgive`
+    forgive: bool,
+    /// whether the spaces between words are skipped over, see `--lang`
+    skip_spaces: bool,
+    num_errors: usize,
+    num_chars_typed: usize,
+    /// (time, index, typed char) of every char typed
+    keystrokes: Vec<(Instant, usize, char)>,
+    started_at: Option<Instant>,
+    end: Option<SessionEnd>,
+}
+
+impl TypingSession {
+    /// Starts a test of typing `words`, separated by spaces, or by enter
+    /// at paragraph breaks (see [`textgen::PARAGRAPH_BREAK`]).
+    pub fn new(words: Vec<String>) -> Self {
+        let text: Vec<char> = textgen::join_words(&words).chars().collect();
+        Self {
+            words,
+            input: Vec::new(),
+            had_mistake: vec![false; text.len()],
+            skipped: vec![false; text.len()],
+            indentation: vec![false; text.len()],
+            text,
+            forgive: false,
+            skip_spaces: false,
+            num_errors: 0,
+            num_chars_typed: 0,
+            keystrokes: Vec::new(),
+            started_at: None,
+            end: None,
+        }
+    }
+
+    /// Makes space complete the rest of the word without typing it.
+    pub fn with_forgive(mut self, forgive: bool) -> Self {
+        self.forgive = forgive;
+        self
+    }
+
+    /// Makes the spaces between words be skipped over instead of typed,
+    /// for scripts written without them, such as Japanese.
+    pub fn with_spaces_skipped(mut self, skip_spaces: bool) -> Self {
+        self.skip_spaces = skip_spaces;
+        self.skip_spaces_from(0);
+        self
+    }
+
+    /// Makes the chars at `indices` be skipped over instead of typed, for
+    /// the indentation of lines of code.
+    pub fn with_indentation(mut self, indices: impl IntoIterator<Item = usize>) -> Self {
+        for index in indices {
+            if let Some(indentation) = self.indentation.get_mut(index) {
+                *indentation = true;
+            }
+        }
+        self
+    }
+
+    /// The text to be typed.
+    pub fn text(&self) -> &[char] {
+        &self.text
+    }
+
+    /// The chars typed so far, in place of the start of the text.
+    pub fn input(&self) -> &[char] {
+        &self.input
+    }
+
+    /// Index of the next char to be typed.
+    pub fn position(&self) -> usize {
+        self.input.len()
+    }
+
+    /// How the test ended, if it did.
+    pub fn end(&self) -> Option<SessionEnd> {
+        self.end
+    }
+
+    /// How the char at `index` is typed so far.
+    pub fn outcome(&self, index: usize) -> CharOutcome {
+        if self.skipped[index] && index < self.input.len() {
+            return CharOutcome::Skipped;
+        }
+        CharOutcome::of(
+            self.text[index],
+            self.input.get(index).copied(),
+            self.had_mistake[index],
+        )
+    }
+
+    /// Adds `words` to the end of the text, for timed tests that never
+    /// run out of words.
+    pub fn extend(&mut self, words: impl IntoIterator<Item = String>) {
+        let old_len = self.text.len();
+        self.words.extend(words);
+        self.text = textgen::join_words(&self.words).chars().collect();
+        self.had_mistake.resize(self.text.len(), false);
+        self.skipped.resize(self.text.len(), false);
+        self.indentation.resize(self.text.len(), false);
+        self.skip_spaces_from(old_len);
+    }
+
+    /// Marks the spaces from `index` on to be skipped over, if they are.
+    fn skip_spaces_from(&mut self, index: usize) {
+        if !self.skip_spaces {
+            return;
+        }
+        for (c, indentation) in self.text.iter().zip(&mut self.indentation).skip(index) {
+            *indentation |= *c == ' ';
+        }
+    }
+
+    /// Starts the timer, unless it already started.
+    ///
+    /// The timer starts on the first key handled otherwise.
+    pub fn start(&mut self, at: Instant) {
+        self.started_at.get_or_insert(at);
+    }
+
+    /// Leaves `duration` out of the time the test takes, as if the test
+    /// so far happened that much later. Done after a break taken during
+    /// the test.
+    pub fn pause(&mut self, duration: Duration) {
+        if let Some(started_at) = &mut self.started_at {
+            *started_at += duration;
+        }
+        for (at, _, _) in &mut self.keystrokes {
+            *at += duration;
+        }
+    }
+
+    /// Time the test took up to `now`, leaving out pauses. Zero before
+    /// it started.
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        self.started_at.map_or(Duration::ZERO, |started_at| {
+            now.saturating_duration_since(started_at)
+        })
+    }
+
+    /// When the test ends if it is over at `now`, which is earlier if it
+    /// ran over `time_limit`.
+    pub fn ended_at(&self, now: Instant, time_limit: Option<Duration>) -> Instant {
+        let overtime = time_limit.map_or(Duration::ZERO, |time_limit| {
+            self.elapsed(now).saturating_sub(time_limit)
+        });
+        now - overtime
+    }
+
+    /// Skips over the indentation at the position, if any. Done after
+    /// every key press, and before the first one for the first line.
+    pub fn skip_indentation(&mut self) -> Vec<SessionUpdate> {
+        let mut updates = Vec::new();
+        while self.indentation.get(self.input.len()) == Some(&true) {
+            updates.push(self.skip());
+        }
+        updates
+    }
+
+    /// Handles `key` pressed at `now`, returning what changed in order.
+    ///
+    /// Keys are ignored once the test ended.
+    pub fn handle_key(&mut self, key: Key, now: Instant) -> Vec<SessionUpdate> {
+        let mut updates = Vec::new();
+        if self.end.is_some() {
+            return updates;
+        }
+        self.start(now);
+
+        if matches!(
+            key,
+            Key::Backspace | Key::Ctrl('h') | Key::Ctrl('w') | Key::Ctrl('\x7f') | Key::Alt('\x7f')
+        ) {
+            // skipped indentation is deleted along with the char before it
+            while !self.input.is_empty() && self.indentation[self.input.len() - 1] {
+                updates.push(self.delete());
+            }
+        }
+
+        match key {
+            Key::Ctrl('c') => return self.ended(SessionEnd::Quit, updates),
+            Key::Ctrl('r') => return self.ended(SessionEnd::Restart, updates),
+            // enter is typed at a paragraph break, and restarts otherwise
+            Key::Char('\n') if self.text.get(self.input.len()) != Some(&'\n') => {
+                return self.ended(SessionEnd::Restart, updates)
+            }
+            Key::Ctrl('u') => return self.ended(SessionEnd::Retry, updates),
+            // alt-backspace is sent as alt + DEL. Most terminals send
+            // ctrl-backspace as ctrl-h, which is also a plain backspace, so
+            // only crossterm reads it as ctrl + DEL.
+            Key::Ctrl('w') | Key::Ctrl('\x7f') | Key::Alt('\x7f') => {
+                // delete last word
+                if !self.input.is_empty() && self.after_word_end() {
+                    updates.push(self.delete());
+                }
+                while !self.input.is_empty() && !self.after_word_end() {
+                    updates.push(self.delete());
+                }
+            }
+            Key::Ctrl('e') => {
+                // go back to the first uncorrected error
+                let first_error = self
+                    .input
+                    .iter()
+                    .zip(self.text.iter())
+                    .position(|(typed, original)| typed != original);
+                if let Some(index) = first_error {
+                    while self.input.len() > index {
+                        updates.push(self.delete());
+                    }
+                }
+            }
+            Key::Char(c) => {
+                if self.forgive && c == ' ' {
+                    // complete the rest of the word without typing it, the
+                    // space is then typed as usual
+                    while self
+                        .text
+                        .get(self.input.len())
+                        .is_some_and(|c| !is_word_end(*c))
+                    {
+                        updates.push(self.skip());
+                        if self.is_complete() {
+                            return self.ended(SessionEnd::Done, updates);
+                        }
+                    }
+                }
+
+                let index = self.input.len();
+                self.skipped[index] = false;
+                self.keystrokes.push((now, index, c));
+                self.input.push(c);
+
+                if self.is_complete() {
+                    updates.push(self.typed(index));
+                    return self.ended(SessionEnd::Done, updates);
+                }
+
+                self.num_chars_typed += 1;
+                if self.text[index] != c {
+                    self.num_errors += 1;
+                    self.had_mistake[index] = true;
+                }
+                updates.push(self.typed(index));
+                if is_word_end(self.text[index]) {
+                    updates.push(SessionUpdate::WordEnd { index });
+                }
+            }
+            Key::Backspace | Key::Ctrl('h') if !self.input.is_empty() => {
+                updates.push(self.delete())
+            }
+            _ => {}
+        }
+
+        // skip over the indentation of the next line, or back over it at
+        // the start of the text
+        updates.extend(self.skip_indentation());
+        updates
+    }
+
+    /// Results of the test, which ended at `ended_at`.
+    pub fn results(&self, ended_at: Instant) -> ToipeResults {
+        let started_at = self.started_at.unwrap_or(ended_at);
+
+        let final_chars_skipped = self
+            .skipped
+            .iter()
+            .take(self.input.len())
+            .filter(|s| **s)
+            .count();
+        let (final_chars_typed_correctly, final_uncorrected_errors) = self
+            .input
+            .iter()
+            .zip(self.text.iter())
+            .zip(self.skipped.iter())
+            .filter(|(_, skipped)| !**skipped)
+            .fold(
+                (0, 0),
+                |(total_chars_typed_correctly, total_uncorrected_errors),
+                 ((typed_char, orig_char), _)| {
+                    if typed_char == orig_char {
+                        (total_chars_typed_correctly + 1, total_uncorrected_errors)
+                    } else {
+                        (total_chars_typed_correctly, total_uncorrected_errors + 1)
+                    }
+                },
+            );
+
+        ToipeResults {
+            text: self.text.iter().collect(),
+            total_words: self.words.len(),
+            total_chars_typed: self.num_chars_typed,
+            total_chars_in_text: self.input.len(),
+            total_char_errors: self.num_errors,
+            final_chars_typed_correctly,
+            final_uncorrected_errors,
+            final_chars_skipped,
+            started_at,
+            ended_at,
+            difficulty: TextDifficulty::from_words(&self.words),
+            keystrokes: self
+                .keystrokes
+                .iter()
+                .map(|&(at, index, typed)| Keystroke {
+                    at: at.saturating_duration_since(started_at),
+                    index,
+                    expected: self.text[index],
+                    typed,
+                })
+                .collect(),
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.input.len() >= self.text.len()
+    }
+
+    /// Whether the last char typed is in place of a space, a paragraph
+    /// break or past the text.
+    fn after_word_end(&self) -> bool {
+        self.text
+            .get(self.input.len() - 1)
+            .is_none_or(|c| is_word_end(*c))
+    }
+
+    fn typed(&self, index: usize) -> SessionUpdate {
+        SessionUpdate::Typed {
+            index,
+            outcome: self.outcome(index),
+        }
+    }
+
+    /// Skips over the char at the position as if it was typed correctly.
+    fn skip(&mut self) -> SessionUpdate {
+        let index = self.input.len();
+        self.skipped[index] = true;
+        self.input.push(self.text[index]);
+        self.typed(index)
+    }
+
+    /// Deletes the last char typed, which must exist.
+    fn delete(&mut self) -> SessionUpdate {
+        self.input.pop();
+        SessionUpdate::Deleted {
+            index: self.input.len(),
+        }
+    }
+
+    fn ended(&mut self, end: SessionEnd, mut updates: Vec<SessionUpdate>) -> Vec<SessionUpdate> {
+        self.end = Some(end);
+        updates.push(SessionUpdate::Ended(end));
+        updates
+    }
+}
+
+/// Whether `c` separates words, as a space or a paragraph break.
+fn is_word_end(c: char) -> bool {
+    matches!(c, ' ' | '\n')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::{Clock, ManualClock},
+        testing::SimulatedTypist,
+    };
+
+    fn session(text: &str) -> TypingSession {
+        TypingSession::new(text.split(' ').map(String::from).collect())
+    }
+
+    fn type_keys(session: &mut TypingSession, keys: &[Key]) -> Vec<SessionUpdate> {
+        keys.iter()
+            .flat_map(|key| session.handle_key(*key, Instant::now()))
+            .collect()
+    }
+
+    fn chars(text: &str) -> Vec<Key> {
+        text.chars().map(Key::Char).collect()
+    }
+
+    #[test]
+    fn counts_mistakes_and_corrections() {
+        let mut session = session("ab cd");
+        let updates = type_keys(&mut session, &chars("xb"));
+        assert_eq!(
+            updates,
+            [
+                SessionUpdate::Typed {
+                    index: 0,
+                    outcome: CharOutcome::Uncorrected
+                },
+                SessionUpdate::Typed {
+                    index: 1,
+                    outcome: CharOutcome::Correct
+                },
+            ]
+        );
+
+        let updates = type_keys(&mut session, &[Key::Ctrl('e'), Key::Char('a')]);
+        assert_eq!(
+            updates,
+            [
+                SessionUpdate::Deleted { index: 1 },
+                SessionUpdate::Deleted { index: 0 },
+                SessionUpdate::Typed {
+                    index: 0,
+                    outcome: CharOutcome::Corrected
+                },
+            ]
+        );
+
+        let updates = type_keys(&mut session, &chars("b cd"));
+        assert_eq!(updates[2], SessionUpdate::WordEnd { index: 2 });
+        assert_eq!(
+            updates.last(),
+            Some(&SessionUpdate::Ended(SessionEnd::Done))
+        );
+        assert_eq!(session.end(), Some(SessionEnd::Done));
+        assert!(session
+            .handle_key(Key::Char('x'), Instant::now())
+            .is_empty());
+
+        let results = session.results(Instant::now());
+        assert_eq!(results.total_char_errors, 1);
+        assert_eq!(results.final_chars_typed_correctly, 5);
+        assert_eq!(results.final_uncorrected_errors, 0);
+        assert_eq!(results.keystrokes.len(), 7);
+    }
+
+    #[test]
+    fn leaves_pauses_out() {
+        let mut session = session("ab");
+        let start = Instant::now();
+        session.handle_key(Key::Char('a'), start);
+        session.pause(Duration::from_secs(60));
+        session.handle_key(Key::Char('b'), start + Duration::from_secs(61));
+
+        let results = session.results(start + Duration::from_secs(61));
+        assert_eq!(results.duration(), Duration::from_secs(1));
+        assert_eq!(results.keystrokes[1].at, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn times_out() {
+        let clock = ManualClock::default();
+        let time_limit = Some(Duration::from_secs(15));
+        let mut session = session("ab cd");
+        assert_eq!(session.elapsed(clock.now()), Duration::ZERO);
+
+        session.start(clock.now());
+        clock.advance(Duration::from_secs(10));
+        session.handle_key(Key::Char('a'), clock.now());
+        // a break of a minute is left out of the time limit
+        session.pause(Duration::from_secs(60));
+        clock.advance(Duration::from_secs(64));
+        assert_eq!(session.elapsed(clock.now()), Duration::from_secs(14));
+
+        // noticed a while after the time ran out
+        clock.advance(Duration::from_millis(1500));
+        let ended_at = session.ended_at(clock.now(), time_limit);
+        assert_eq!(ended_at, clock.now() - Duration::from_millis(500));
+        assert_eq!(session.ended_at(clock.now(), None), clock.now());
+
+        let results = session.results(ended_at);
+        assert_eq!(results.duration(), Duration::from_secs(15));
+        assert_eq!(results.keystrokes[0].at, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn deletes_words() {
+        let mut session = session("ab cd ef");
+        type_keys(&mut session, &chars("ab cx"));
+        type_keys(&mut session, &[Key::Ctrl('w')]);
+        assert_eq!(session.input(), ['a', 'b', ' ']);
+        type_keys(&mut session, &[Key::Ctrl('w')]);
+        assert!(session.input().is_empty());
+
+        type_keys(&mut session, &chars("ab c"));
+        type_keys(&mut session, &[Key::Ctrl('h')]);
+        assert_eq!(session.input(), ['a', 'b', ' ']);
+        type_keys(&mut session, &[Key::Alt('\x7f')]);
+        assert!(session.input().is_empty());
+        assert_eq!(
+            type_keys(&mut session, &[Key::Ctrl('c')]),
+            [SessionUpdate::Ended(SessionEnd::Quit)]
+        );
+    }
+
+    #[test]
+    fn skips_words_and_indentation() {
+        let mut forgiving = session("abc de").with_forgive(true);
+        type_keys(&mut forgiving, &chars("a d"));
+        assert_eq!(forgiving.input(), ['a', 'b', 'c', ' ', 'd']);
+        assert_eq!(forgiving.outcome(1), CharOutcome::Skipped);
+        assert_eq!(forgiving.outcome(3), CharOutcome::Correct);
+
+        let mut code = session("a   b").with_indentation(1..4);
+        type_keys(&mut code, &chars("a"));
+        assert_eq!(code.position(), 4);
+        type_keys(&mut code, &[Key::Backspace]);
+        assert_eq!(code.position(), 0);
+
+        let results = forgiving.results(Instant::now());
+        assert_eq!(results.final_chars_skipped, 2);
+    }
+
+    #[test]
+    fn types_enter_at_paragraph_breaks() {
+        let words = ["ab", textgen::PARAGRAPH_BREAK, "cd"].map(String::from);
+        let mut session = TypingSession::new(words.to_vec());
+        assert_eq!(session.text(), ['a', 'b', '\n', 'c', 'd']);
+
+        let updates = type_keys(&mut session, &chars("ab\n"));
+        assert_eq!(updates[3], SessionUpdate::WordEnd { index: 2 });
+        // enter restarts anywhere else
+        assert_eq!(
+            type_keys(&mut session, &chars("c\n")).last(),
+            Some(&SessionUpdate::Ended(SessionEnd::Restart))
+        );
+    }
+
+    #[test]
+    fn skips_spaces_of_unspaced_scripts() {
+        let mut session = session("日本 語 です").with_spaces_skipped(true);
+        type_keys(&mut session, &chars("日本"));
+        assert_eq!(session.position(), 3);
+        type_keys(&mut session, &chars("x"));
+        assert_eq!(session.outcome(3), CharOutcome::Uncorrected);
+        type_keys(&mut session, &[Key::Backspace, Key::Backspace]);
+        assert_eq!(session.position(), 1);
+    }
+
+    #[test]
+    fn plays_a_simulated_typist() {
+        let text = "the quick brown fox jumps over the lazy dog";
+        let play = |typist: SimulatedTypist| {
+            let mut typist = typist.with_error_rate(0.2).with_seed(7);
+            typist.jitter = 0.0;
+            let script = typist.script(text);
+            let clock = ManualClock::default();
+            let mut session = session(text);
+            for (delay, key) in &script {
+                clock.advance(*delay);
+                session.handle_key(*key, clock.now());
+            }
+            assert_eq!(session.end(), Some(SessionEnd::Done));
+            (script, session.results(clock.now()))
+        };
+
+        // 60 wpm is a key press every 200ms, and the test starts at the
+        // first one
+        let (script, results) = play(SimulatedTypist::new(60.0).with_correction_probability(1.0));
+        let num_corrections = script
+            .iter()
+            .filter(|(_, key)| *key == Key::Backspace)
+            .count();
+        assert!(num_corrections > 0);
+        assert_eq!(results.text, text);
+        // the last char ends the test before being counted as typed
+        assert_eq!(results.total_chars_typed, text.len() + num_corrections - 1);
+        assert_eq!(results.total_char_errors, num_corrections);
+        assert_eq!(results.final_chars_typed_correctly, text.len());
+        assert_eq!(results.final_uncorrected_errors, 0);
+        assert_eq!(results.keystrokes.len(), text.len() + num_corrections);
+        assert_eq!(
+            results.duration(),
+            Duration::from_millis(200) * (script.len() - 1) as u32
+        );
+        assert_eq!(
+            results.wpm(),
+            text.len() as f64 / 5.0 / results.duration().as_secs_f64() * 60.0
+        );
+
+        let (script, results) = play(SimulatedTypist::new(60.0).with_correction_probability(0.0));
+        assert_eq!(script.len(), text.len());
+        assert!(results.final_uncorrected_errors > 0);
+        assert_eq!(results.total_char_errors, results.final_uncorrected_errors);
+        assert_eq!(
+            results.final_chars_typed_correctly + results.final_uncorrected_errors,
+            text.len()
+        );
+    }
+}
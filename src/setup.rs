@@ -0,0 +1,461 @@
+//! The setup wizard of `toipe setup`.
+//!
+//! New users pick a theme, a word list, the number of words of a test
+//! and their keyboard layout one step at a time, starting from the
+//! current settings, instead of editing the config file by hand (see
+//! [`ToipeConfig::load`]). The choices are then written to the config
+//! file, keeping its other options.
+
+use std::{fs, io::Error, path::PathBuf};
+
+use anyhow::Result;
+use clap::ArgEnum;
+
+use crate::{
+    backend::{Backend, Key, Terminal},
+    config::{read_toml_file, to_toml_document, ToipeConfig},
+    dirs,
+    events::{EventStream, ToipeEvent},
+    layout::BUILT_IN_LAYOUTS,
+    storage,
+    theme::{ColorDepth, Theme},
+    tty::Tty,
+    tui::{Text, ToipeTui},
+    user_wordlists,
+    wordlists::BuiltInWordlist,
+};
+
+/// Themes to pick from, with the `[theme]` section they write.
+const THEMES: [(&str, &str); 4] = [
+    ("default", ""),
+    (
+        "high contrast",
+        "corrected = 'light-yellow'
+        untyped = 'light-white'
+        cursor = 'light-magenta'
+        hint = 'light-cyan'
+        accuracy = 'light-cyan'
+        warning = 'light-yellow'",
+    ),
+    ("gradient", "gradient = true"),
+    (
+        "solarized",
+        "correct = '#859900'
+        corrected = '#cb4b16'
+        wrong = '#dc322f'
+        untyped = '#586e75'
+        cursor = '#d33682'
+        hint = '#268bd2'
+        results = '#859900'
+        accuracy = '#268bd2'
+        warning = '#b58900'",
+    ),
+];
+
+/// Numbers of words of a test to pick from.
+const NUM_WORDS: [usize; 6] = [10, 15, 25, 30, 50, 100];
+
+const THEME_STEP: usize = 0;
+
+/// An answer to a step of the wizard, with the options of the config
+/// file it sets, or removes when `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Choice {
+    pub label: String,
+    pub options: Vec<(&'static str, Option<toml::Value>)>,
+}
+
+impl Choice {
+    fn new(label: impl Into<String>, key: &'static str, value: toml::Value) -> Self {
+        Self {
+            label: label.into(),
+            options: vec![(key, Some(value))],
+        }
+    }
+
+    fn removing(mut self, key: &'static str) -> Self {
+        self.options.push((key, None));
+        self
+    }
+}
+
+/// A question of the wizard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub title: &'static str,
+    pub choices: Vec<Choice>,
+    pub selected: usize,
+}
+
+impl Step {
+    /// A step with the first of `choices` whose options `is_current`
+    /// selected, or else the first one.
+    fn new(
+        title: &'static str,
+        choices: Vec<Choice>,
+        is_current: impl Fn(&Choice) -> bool,
+    ) -> Self {
+        let selected = choices.iter().position(is_current).unwrap_or_default();
+        Self {
+            title,
+            choices,
+            selected,
+        }
+    }
+
+    fn choice(&self) -> &Choice {
+        &self.choices[self.selected]
+    }
+}
+
+/// How the wizard ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Save,
+    Quit,
+}
+
+/// The steps of the wizard and how far through them the user is.
+///
+/// After the last step comes a summary of the choices, confirmed with
+/// enter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wizard {
+    pub steps: Vec<Step>,
+    /// index of the current step, or the number of steps on the summary
+    pub step: usize,
+}
+
+impl Wizard {
+    /// The steps, starting from the settings of `config`, with the
+    /// `installed` word lists and `layouts` to pick from besides the
+    /// built-in ones.
+    pub fn new(config: &ToipeConfig, installed: &[String], layouts: &[String]) -> Self {
+        let themes = THEMES
+            .iter()
+            .map(|(name, table)| match table.is_empty() {
+                true => Choice {
+                    label: name.to_string(),
+                    options: vec![("theme", None)],
+                },
+                false => Choice::new(
+                    *name,
+                    "theme",
+                    toml::Value::Table(
+                        toml::from_str::<toml::value::Table>(table).expect("valid theme"),
+                    ),
+                ),
+            })
+            .collect();
+        let current_theme = config.theme;
+
+        let mut wordlists: Vec<Choice> = BuiltInWordlist::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+            .map(|value| {
+                let name = value.get_name();
+                Choice::new(name, "wordlist", toml::Value::String(name.to_string()))
+                    .removing("file")
+            })
+            .collect();
+        wordlists.extend(installed.iter().map(|name| {
+            Choice::new(
+                format!("{} (installed)", name),
+                "file",
+                toml::Value::String(name.clone()),
+            )
+            .removing("wordlist")
+        }));
+        let current_wordlist = match &config.wordlist_file {
+            Some(name) => ("file", name.clone()),
+            None => (
+                "wordlist",
+                config
+                    .wordlist
+                    .to_possible_value()
+                    .map_or_else(String::new, |value| value.get_name().to_string()),
+            ),
+        };
+
+        let num_words = NUM_WORDS
+            .iter()
+            .map(|n| Choice::new(n.to_string(), "num-words", toml::Value::Integer(*n as i64)))
+            .collect();
+
+        let layouts = BUILT_IN_LAYOUTS
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(layouts.iter().map(String::as_str))
+            .map(|name| Choice::new(name, "layout", toml::Value::String(name.to_string())))
+            .collect();
+
+        Self {
+            steps: vec![
+                Step::new("Theme", themes, |choice| {
+                    theme(choice).map(|theme| Theme {
+                        depth: current_theme.depth,
+                        ..theme
+                    }) == Some(current_theme)
+                }),
+                Step::new("Word list", wordlists, |choice| {
+                    let (key, value) = &current_wordlist;
+                    choice.options[0] == (*key, Some(toml::Value::String(value.clone())))
+                }),
+                Step::new("Words per test", num_words, |choice| {
+                    choice.label == config.num_words.to_string()
+                }),
+                Step::new("Keyboard layout", layouts, |choice| {
+                    choice.label.eq_ignore_ascii_case(&config.layout)
+                }),
+            ],
+            step: 0,
+        }
+    }
+
+    /// Whether the summary is shown, after the last step.
+    pub fn is_summary(&self) -> bool {
+        self.step == self.steps.len()
+    }
+
+    /// Moves through the wizard: up and down pick a choice, enter goes
+    /// to the next step and esc back to the previous one.
+    ///
+    /// Returns how the wizard ended, if it did.
+    pub fn handle(&mut self, key: Key) -> Option<Outcome> {
+        match key {
+            Key::Ctrl('c') => return Some(Outcome::Quit),
+            Key::Char('\n') if self.is_summary() => return Some(Outcome::Save),
+            Key::Char('\n') => self.step += 1,
+            Key::Esc | Key::Backspace | Key::Left => self.step = self.step.saturating_sub(1),
+            _ if self.is_summary() => {}
+            Key::Up | Key::Char('k') => {
+                let step = &mut self.steps[self.step];
+                step.selected = step
+                    .selected
+                    .checked_sub(1)
+                    .unwrap_or(step.choices.len() - 1);
+            }
+            Key::Down | Key::Char('j') | Key::Char('\t') => {
+                let step = &mut self.steps[self.step];
+                step.selected = (step.selected + 1) % step.choices.len();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// The theme picked so far.
+    pub fn theme(&self) -> Theme {
+        theme(self.steps[THEME_STEP].choice()).unwrap_or_default()
+    }
+
+    /// Sets the options picked in `table`, the contents of the config
+    /// file.
+    pub fn apply(&self, table: &mut toml::value::Table) {
+        for (key, value) in self.steps.iter().flat_map(|step| &step.choice().options) {
+            match value {
+                Some(value) => table.insert(key.to_string(), value.clone()),
+                None => table.remove(*key),
+            };
+        }
+    }
+}
+
+/// The theme of a choice of the theme step.
+fn theme(choice: &Choice) -> Option<Theme> {
+    match &choice.options[0] {
+        (_, Some(toml::Value::Table(table))) => Theme::default().with_table(table).ok(),
+        _ => Some(Theme::default()),
+    }
+}
+
+/// Names of the layout files in [`dirs::layouts_dir`].
+fn installed_layouts() -> Result<Vec<String>, Error> {
+    let entries = match fs::read_dir(dirs::layouts_dir()?) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            if let Some(name) = path.file_stem() {
+                names.push(name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn display(tui: &mut ToipeTui, wizard: &Wizard, path: &str) -> Result<()> {
+    let theme = wizard.theme().for_depth(ColorDepth::detect());
+    let key = |text: &str| Text::from(text.to_string()).with_color(theme.hint);
+    let faint = |text: &str| Text::from(text.to_string()).with_faint();
+    let mut lines: Vec<Vec<Text>> = Vec::new();
+
+    if wizard.is_summary() {
+        lines.push(vec![Text::from(format!("Save to {}?", path)).with_bold()]);
+        lines.push(Vec::new());
+        let width = wizard
+            .steps
+            .iter()
+            .map(|step| step.title.len())
+            .max()
+            .unwrap_or_default();
+        for step in &wizard.steps {
+            lines.push(vec![
+                faint(&format!("{:<width$}  ", step.title, width = width)),
+                Text::from(format!("{:<16}", step.choice().label)).with_color(theme.results),
+            ]);
+        }
+        lines.push(Vec::new());
+        lines.push(vec![
+            key("enter"),
+            faint(" to save, "),
+            key("esc"),
+            faint(" to go back, "),
+            key("ctrl-c"),
+            faint(" to quit without saving"),
+        ]);
+    } else {
+        let step = &wizard.steps[wizard.step];
+        lines.push(vec![Text::from(format!(
+            "Setup {}/{}: {}",
+            wizard.step + 1,
+            wizard.steps.len(),
+            step.title
+        ))
+        .with_bold()]);
+        lines.push(Vec::new());
+        let width = step
+            .choices
+            .iter()
+            .map(|choice| choice.label.chars().count())
+            .max()
+            .unwrap_or_default();
+        for (i, choice) in step.choices.iter().enumerate() {
+            let label = format!("{:<width$}", choice.label, width = width);
+            lines.push(match i == step.selected {
+                true => vec![
+                    Text::from("> ").with_color(theme.cursor),
+                    Text::from(label).with_color(theme.cursor).with_bold(),
+                ],
+                false => vec![Text::from("  "), Text::from(label)],
+            });
+        }
+        if wizard.step == THEME_STEP {
+            lines.push(Vec::new());
+            lines.push(vec![
+                Text::from("the quick ").with_color(theme.correct),
+                Text::from("brwn").with_color(theme.wrong),
+                Text::from(" fox").with_color(theme.corrected),
+                Text::from(" j").with_color(theme.cursor).with_underline(),
+                Text::from("umps over")
+                    .with_color(theme.untyped)
+                    .with_faint(),
+            ]);
+        }
+        lines.push(Vec::new());
+        lines.push(vec![
+            key("up/down"),
+            faint(" to choose, "),
+            key("enter"),
+            faint(" to continue, "),
+            key("esc"),
+            faint(" to go back, "),
+            key("ctrl-c"),
+            faint(" to quit"),
+        ]);
+    }
+
+    tui.reset_screen()?;
+    tui.display_lines(&lines)?;
+    tui.hide_cursor()?;
+    Ok(())
+}
+
+/// Runs the wizard with the keys of `tty`, then writes the choices to
+/// the config file of `config`.
+///
+/// Returns the config file, or `None` if the wizard was quit.
+pub fn run(config: &ToipeConfig, tty: &mut Tty) -> Result<Option<PathBuf>> {
+    let path = config.config_file()?;
+    let mut wizard = Wizard::new(
+        config,
+        &user_wordlists::list_installed()?,
+        &installed_layouts()?,
+    );
+
+    let mut tui = ToipeTui::new();
+    let events = EventStream::new(Terminal::keys(tty.try_clone()?));
+    display(&mut tui, &wizard, &path.display().to_string())?;
+    let mut outcome = Outcome::Quit;
+    for event in events {
+        if let ToipeEvent::Key(key) = event? {
+            if let Some(end) = wizard.handle(key) {
+                outcome = end;
+                break;
+            }
+            display(&mut tui, &wizard, &path.display().to_string())?;
+        }
+    }
+    tui.show_cursor()?;
+    if outcome == Outcome::Quit {
+        return Ok(None);
+    }
+
+    let mut table = match path.exists() {
+        true => read_toml_file(&path)?,
+        false => toml::value::Table::new(),
+    };
+    wizard.apply(&mut table);
+    storage::write(&path, to_toml_document(&table).as_bytes())?;
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn walks_through_the_steps() {
+        let config = ToipeConfig::parse_from(["toipe", "-f", "mine", "-n", "25"]);
+        let mut wizard = Wizard::new(&config, &["mine".to_string()], &[]);
+        assert_eq!(wizard.steps[1].choice().label, "mine (installed)");
+        assert_eq!(wizard.steps[2].choice().label, "25");
+        assert_eq!(wizard.steps[3].choice().label, "qwerty");
+
+        let keys = [
+            // the gradient theme
+            Key::Down,
+            Key::Down,
+            Key::Char('\n'),
+            // wraps around to the first word list
+            Key::Down,
+            Key::Char('\n'),
+            Key::Esc,
+            Key::Char('\n'),
+            Key::Down,
+            Key::Char('\n'),
+            Key::Char('\n'),
+        ];
+        for key in keys {
+            assert_eq!(wizard.handle(key), None);
+        }
+        assert!(wizard.is_summary());
+        assert!(wizard.theme().gradient);
+        assert_eq!(wizard.handle(Key::Char('\n')), Some(Outcome::Save));
+
+        let mut table =
+            toml::from_str::<toml::value::Table>("file = 'mine'\npunctuation = true\n").unwrap();
+        wizard.apply(&mut table);
+        assert_eq!(
+            to_toml_document(&table),
+            "layout = \"qwerty\"\nnum-words = 30\npunctuation = true\n\
+             wordlist = \"top250\"\n\n[theme]\ngradient = true\n"
+        );
+    }
+}
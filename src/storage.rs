@@ -0,0 +1,118 @@
+//! Writing the files toipe stores in its directories.
+//!
+//! Every write to the configuration and data directories goes through
+//! this module, so that [incognito mode](enable_incognito) can
+//! guarantee that nothing is stored.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Error, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static INCOGNITO: AtomicBool = AtomicBool::new(false);
+
+/// Discards every write for the rest of the process.
+///
+/// Functions in this module then succeed without doing anything.
+pub fn enable_incognito() {
+    INCOGNITO.store(true, Ordering::SeqCst);
+}
+
+/// Whether writes are discarded, see [`enable_incognito`].
+pub fn is_incognito() -> bool {
+    INCOGNITO.load(Ordering::SeqCst)
+}
+
+fn create_parent_dir(path: &Path) -> Result<(), Error> {
+    match path.parent() {
+        Some(dir) => fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+}
+
+/// Like [`write`], but a new file gets the permissions `mode` on Unix.
+pub fn write_with_mode(path: &Path, contents: &[u8], mode: u32) -> Result<(), Error> {
+    if is_incognito() {
+        return Ok(());
+    }
+    create_parent_dir(path)?;
+
+    // write to a temporary file first so that the file is never left
+    // half written
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // Windows files have no mode, only the user can read them anyway
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+    #[cfg(not(unix))]
+    let _ = mode;
+    options.open(&tmp_path)?.write_all(contents)?;
+    fs::rename(tmp_path, path)
+}
+
+/// Replaces the contents of `path`, creating it and its parent
+/// directories if needed.
+pub fn write(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    write_with_mode(path, contents, 0o666)
+}
+
+/// Like [`write`], but the file is only readable by the user.
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    write_with_mode(path, contents, 0o600)
+}
+
+fn append_with_mode(path: &Path, contents: &[u8], mode: u32) -> Result<(), Error> {
+    if is_incognito() {
+        return Ok(());
+    }
+    create_parent_dir(path)?;
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+    #[cfg(not(unix))]
+    let _ = mode;
+    options.open(path)?.write_all(contents)
+}
+
+/// Appends to `path`, creating it and its parent directories if needed.
+pub fn append(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    append_with_mode(path, contents, 0o666)
+}
+
+/// Like [`append`], but a new file is only readable by the user.
+pub fn append_private(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    append_with_mode(path, contents, 0o600)
+}
+
+/// Replaces `path` with a new file to write to, creating its parent
+/// directories if needed.
+///
+/// In incognito mode, what is written is discarded.
+pub fn create(path: &Path) -> Result<Box<dyn Write + Send>, Error> {
+    if is_incognito() {
+        return Ok(Box::new(io::sink()));
+    }
+    create_parent_dir(path)?;
+    Ok(Box::new(fs::File::create(path)?))
+}
+
+/// Creates the directory at `path` and its parents if needed.
+pub fn create_dir(path: &Path) -> Result<(), Error> {
+    if is_incognito() {
+        return Ok(());
+    }
+    fs::create_dir_all(path)
+}
+
+/// Removes the file at `path`.
+pub fn remove(path: &Path) -> Result<(), Error> {
+    if is_incognito() {
+        return Ok(());
+    }
+    fs::remove_file(path)
+}
@@ -0,0 +1,370 @@
+//! Syncing the data directory between machines.
+//!
+//! The files of [`dirs::data_dir`] listed in [`SYNCED`] (the history,
+//! stats and installed word lists) are synced after each session with
+//! either
+//!
+//! - a git remote: the data directory is made into a git repository
+//!   whose changes to these files are committed, merged with the remote
+//!   and pushed. The history is merged by [`history::merge`], so entries
+//!   added on different machines never conflict.
+//! - an [rclone](https://rclone.org) remote: files are copied both ways
+//!   and the history files are merged by [`history::merge`].
+//!
+//! Keystroke logs and telemetry are never synced.
+//!
+//! An encrypted history can only be merged with the same key on every
+//! machine, which takes a passphrase (see [`encryption::PASSPHRASE_VAR`])
+//! as the key file is different on each.
+
+use std::{
+    io::{Error, ErrorKind},
+    path::Path,
+    process::Command,
+};
+
+use crate::{dirs, encryption, results::history, storage};
+
+/// Files and directories of the data directory that are synced.
+const SYNCED: &[&str] = &[
+    "history.jsonl",
+    "history.jsonl.enc",
+    "char_stats.json",
+    "baseline.json",
+    "plan.json",
+    "wordlists",
+];
+
+/// History files in [`SYNCED`], merged instead of replaced.
+pub(crate) const HISTORY_FILES: &[&str] = &["history.jsonl", "history.jsonl.enc"];
+
+/// Name of the git remote used for syncing.
+const GIT_REMOTE: &str = "toipe-sync";
+
+/// Branch the data is synced on.
+const GIT_BRANCH: &str = "main";
+
+/// Where to sync the data directory to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyncRemote {
+    /// URL of a git repository
+    Git(String),
+    /// rclone remote and path, such as `drive:toipe`
+    Rclone(String),
+}
+
+/// Syncs the data directory with `remote`.
+///
+/// Does nothing in incognito mode.
+pub fn sync(remote: &SyncRemote) -> Result<(), Error> {
+    if storage::is_incognito() {
+        return Ok(());
+    }
+
+    let dir = dirs::data_dir()?;
+    storage::create_dir(&dir)?;
+
+    match remote {
+        SyncRemote::Git(url) => sync_git(&dir, url),
+        SyncRemote::Rclone(remote) => sync_rclone(&dir, remote),
+    }
+}
+
+/// Runs a command, returning its standard output if it succeeds.
+fn output(command: &mut Command) -> Result<Vec<u8>, Error> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command.output().map_err(|err| {
+        if err.kind() == ErrorKind::NotFound {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("`{}` is required for syncing", program),
+            )
+        } else {
+            err
+        }
+    })?;
+
+    if output.status.success() {
+        return Ok(output.stdout);
+    }
+    // git merge explains conflicts on stdout
+    let message = match output.stderr.is_empty() {
+        true => output.stdout,
+        false => output.stderr,
+    };
+    Err(Error::other(format!(
+        "{} exited with {}: {}",
+        program,
+        output.status,
+        String::from_utf8_lossy(&message).trim()
+    )))
+}
+
+/// Like [`output`], as text.
+fn run(command: &mut Command) -> Result<String, Error> {
+    Ok(String::from_utf8_lossy(&output(command)?).into_owned())
+}
+
+fn git(dir: &Path) -> Command {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=toipe", "-c", "user.email=toipe@localhost"]);
+    command
+}
+
+fn sync_git(dir: &Path, url: &str) -> Result<(), Error> {
+    if !dir.join(".git").exists() {
+        run(git(dir).arg("init"))?;
+        run(git(dir)
+            .args(["symbolic-ref", "HEAD"])
+            .arg(format!("refs/heads/{}", GIT_BRANCH)))?;
+    }
+
+    match run(git(dir).args(["remote", "get-url", GIT_REMOTE])) {
+        Ok(current) if current.trim() == url => {}
+        Ok(_) => {
+            run(git(dir).args(["remote", "set-url", GIT_REMOTE, url]))?;
+        }
+        Err(_) => {
+            run(git(dir).args(["remote", "add", GIT_REMOTE, url]))?;
+        }
+    }
+
+    // only the synced files that exist or were deleted since the last
+    // sync, naming others would fail
+    let tracked = run(git(dir).arg("ls-files"))?;
+    let paths: Vec<_> = SYNCED
+        .iter()
+        .filter(|&&name| {
+            dir.join(name).exists()
+                || tracked
+                    .lines()
+                    .any(|file| file == name || file.starts_with(&format!("{}/", name)))
+        })
+        .collect();
+    if !paths.is_empty() {
+        run(git(dir).args(["add", "-A", "--"]).args(&paths))?;
+    }
+    commit(dir, "Sync toipe data")?;
+
+    run(git(dir).args(["fetch", "-q", GIT_REMOTE]))?;
+    let remote_branch = format!("{}/{}", GIT_REMOTE, GIT_BRANCH);
+    if run(git(dir).args(["rev-parse", "-q", "--verify", &remote_branch])).is_ok() {
+        merge_git(dir, &remote_branch)?;
+    }
+
+    run(git(dir)
+        .args(["push", "-q", GIT_REMOTE])
+        .arg(format!("HEAD:{}", GIT_BRANCH)))?;
+
+    Ok(())
+}
+
+/// Commits the staged changes, if any.
+fn commit(dir: &Path, message: &str) -> Result<(), Error> {
+    let staged = run(git(dir).args(["diff", "--cached", "--quiet"])).is_err();
+    let merging = run(git(dir).args(["rev-parse", "-q", "--verify", "MERGE_HEAD"])).is_ok();
+    if staged || merging {
+        run(git(dir).args(["commit", "-q", "-m", message]))?;
+    }
+    Ok(())
+}
+
+/// Merges `remote_branch`, with the entries of the histories on both
+/// sides. The merge is aborted if it fails.
+fn merge_git(dir: &Path, remote_branch: &str) -> Result<(), Error> {
+    // their histories, keeping the names to know the encrypted ones
+    let theirs = tempfile::tempdir()?;
+    let mut merged_histories = Vec::new();
+    for &name in HISTORY_FILES {
+        let spec = format!("{}:{}", remote_branch, name);
+        if let Ok(contents) = output(git(dir).args(["show", &spec])) {
+            storage::write_private(&theirs.path().join(name), &contents)?;
+            merged_histories.push(name);
+        }
+    }
+
+    let merged = (|| {
+        // conflicting histories are merged below, other conflicts are
+        // resolved with the files of this machine
+        run(git(dir).args([
+            "merge",
+            "-q",
+            "--no-commit",
+            "--allow-unrelated-histories",
+            "-X",
+            "ours",
+            remote_branch,
+        ]))?;
+        for name in merged_histories {
+            merge_history(&dir.join(name), &theirs.path().join(name))?;
+            run(git(dir).args(["add", "--", name]))?;
+        }
+        commit(dir, "Merge toipe data")
+    })();
+    if merged.is_err() {
+        let _ = run(git(dir).args(["merge", "--abort"]));
+    }
+    merged
+}
+
+/// Merges the history `other` into the history file at `path`, see
+/// [`history::merge_into`].
+fn merge_history(path: &Path, other: &Path) -> Result<(), Error> {
+    history::merge_into(path, other).map_err(|err| {
+        match err.kind() == ErrorKind::InvalidData && history::is_encrypted_file(other) {
+            true => Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Could not decrypt the synced history: {}. The key file is different on \
+                     every machine, so set ${} to the same passphrase on every synced machine",
+                    err,
+                    encryption::PASSPHRASE_VAR
+                ),
+            ),
+            false => err,
+        }
+    })
+}
+
+fn sync_rclone(dir: &Path, remote: &str) -> Result<(), Error> {
+    let history_file = history::history_file()?;
+    let history_name = history_file
+        .file_name()
+        .expect("history file has a name")
+        .to_string_lossy()
+        .into_owned();
+    let remote_history = dir.join(format!("remote-{}", history_name));
+
+    let fetched = run(Command::new("rclone")
+        .arg("copyto")
+        .arg(format!("{}/{}", remote, history_name))
+        .arg(&remote_history));
+    if fetched.is_ok() && remote_history.exists() {
+        merge_history(&history_file, &remote_history)?;
+        storage::remove(&remote_history)?;
+    }
+
+    run(Command::new("rclone")
+        .args(["copy", "--update"])
+        .args(rclone_filters(Some(&history_name)))
+        .arg(remote)
+        .arg(dir))?;
+    run(Command::new("rclone")
+        .arg("copy")
+        .args(rclone_filters(None))
+        .arg(dir)
+        .arg(remote))?;
+
+    Ok(())
+}
+
+/// Arguments for rclone to copy the files in [`SYNCED`], but `excluded`.
+fn rclone_filters(excluded: Option<&str>) -> Vec<String> {
+    let mut filters: Vec<_> = excluded
+        .map(|name| format!("- /{}", name))
+        .into_iter()
+        .collect();
+    for name in SYNCED {
+        filters.push(format!("+ /{}", name));
+        filters.push(format!("+ /{}/**", name));
+    }
+    filters.push("- **".to_string());
+    filters
+        .into_iter()
+        .flat_map(|filter| ["--filter".to_string(), filter])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::results::history::test_entry;
+
+    fn append(dir: &Path, timestamp: u64) {
+        history::append_to(&dir.join("history.jsonl"), &test_entry(timestamp, 60.0)).unwrap();
+    }
+
+    fn timestamps(dir: &Path) -> Vec<u64> {
+        history::load_file(&dir.join("history.jsonl"))
+            .unwrap()
+            .iter()
+            .map(|entry| entry.timestamp)
+            .collect()
+    }
+
+    /// A bare repository to sync with, and its URL.
+    fn bare_remote(root: &Path) -> String {
+        let remote = root.join("remote.git");
+        run(Command::new("git")
+            .args(["init", "-q", "--bare"])
+            .arg(&remote))
+        .unwrap();
+        remote.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn syncs_with_a_git_remote() {
+        let root = tempfile::tempdir().unwrap();
+        let url = bare_remote(root.path());
+        let (a, b) = (root.path().join("a"), root.path().join("b"));
+
+        append(&a, 1);
+        append(&a, 2);
+        storage::write(&a.join("wordlists/mine.txt"), b"some words\n").unwrap();
+        storage::write(&a.join("telemetry.json"), b"{}\n").unwrap();
+        storage::write(&a.join("keystrokes/1.json"), b"{}\n").unwrap();
+        sync_git(&a, &url).unwrap();
+
+        append(&b, 2);
+        append(&b, 3);
+        sync_git(&b, &url).unwrap();
+        assert_eq!(timestamps(&b), [1, 2, 3]);
+        assert!(b.join("wordlists/mine.txt").exists());
+
+        // entries added on both machines since they last synced
+        append(&a, 4);
+        sync_git(&a, &url).unwrap();
+        assert_eq!(timestamps(&a), [1, 2, 3, 4]);
+        append(&b, 5);
+        sync_git(&b, &url).unwrap();
+        sync_git(&a, &url).unwrap();
+        assert_eq!(timestamps(&a), [1, 2, 3, 4, 5]);
+        assert_eq!(timestamps(&b), [1, 2, 3, 4, 5]);
+
+        let files = run(Command::new("git").arg("--git-dir").arg(&url).args([
+            "ls-tree",
+            "-r",
+            "--name-only",
+            GIT_BRANCH,
+        ]))
+        .unwrap();
+        assert_eq!(files, "history.jsonl\nwordlists/mine.txt\n");
+    }
+
+    #[test]
+    fn aborts_a_failed_merge() {
+        let root = tempfile::tempdir().unwrap();
+        let url = bare_remote(root.path());
+        let (a, b) = (root.path().join("a"), root.path().join("b"));
+        let wordlist = |dir: &Path| dir.join("wordlists/mine.txt");
+
+        storage::write(&wordlist(&a), b"some words\n").unwrap();
+        sync_git(&a, &url).unwrap();
+        storage::create_dir(&b).unwrap();
+        sync_git(&b, &url).unwrap();
+
+        // removed on one machine and changed on the other
+        storage::remove(&wordlist(&a)).unwrap();
+        sync_git(&a, &url).unwrap();
+        storage::write(&wordlist(&b), b"other words\n").unwrap();
+        assert!(sync_git(&b, &url).is_err());
+
+        assert!(!b.join(".git/MERGE_HEAD").exists());
+        assert_eq!(fs::read_to_string(wordlist(&b)).unwrap(), "other words\n");
+    }
+}
@@ -0,0 +1,222 @@
+//! Anonymous usage statistics, only collected and sent when opted in
+//! with `--telemetry-endpoint`.
+//!
+//! Without an endpoint, nothing is collected. With one, the modes of
+//! every finished test are counted in [`counts_file`]: the names of its
+//! [`ToipeConfig::mode_flags`](crate::config::ToipeConfig::mode_flags)
+//! without their values, such as `punctuation`. At most once a day, when
+//! toipe exits, the counts are sent to the endpoint and reset.
+//!
+//! Everything that is sent is built by [`payload`], for example:
+//!
+//! ```json
+//! {"modes":{"num_words":3,"punctuation":1},"os":"linux","schema_version":1,"tests":3,"version":"0.5.0"}
+//! ```
+//!
+//! The text, keystrokes and results of tests are never sent, and neither
+//! are the values of options, which can be paths or names.
+//! `toipe telemetry` prints the payload that would be sent next.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value};
+
+use crate::{dirs, http::post_json, storage};
+
+const SCHEMA_VERSION: u64 = 1;
+
+/// Seconds between two payloads.
+const INTERVAL: u64 = 24 * 60 * 60;
+
+/// Tests counted since the last payload was sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// when counting started, in seconds since the Unix epoch
+    pub since: u64,
+    pub tests: u64,
+    /// number of tests taken in each mode
+    pub modes: BTreeMap<String, u64>,
+}
+
+impl Counts {
+    /// Counts a test taken with `flags`.
+    pub fn add(&mut self, flags: &[String]) {
+        self.tests += 1;
+        for name in mode_names(flags) {
+            *self.modes.entry(name.to_string()).or_default() += 1;
+        }
+    }
+
+    fn modes_json(&self) -> Value {
+        let modes: Map<String, Value> = self
+            .modes
+            .iter()
+            .map(|(name, count)| (name.clone(), (*count).into()))
+            .collect();
+        modes.into()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("schema_version".into(), SCHEMA_VERSION.into());
+        object.insert("since".into(), self.since.into());
+        object.insert("tests".into(), self.tests.into());
+        object.insert("modes".into(), self.modes_json());
+        object.into()
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, Error> {
+        let invalid = |field: &str| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Missing or invalid `{}` in the telemetry counts", field),
+            )
+        };
+        let number = |field| value.get(field).and_then(Value::as_u64);
+        let modes = value
+            .get("modes")
+            .and_then(Value::as_object)
+            .ok_or_else(|| invalid("modes"))?
+            .iter()
+            .map(|(name, count)| Some((name.clone(), count.as_u64()?)))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("modes"))?;
+        Ok(Self {
+            since: number("since").ok_or_else(|| invalid("since"))?,
+            tests: number("tests").ok_or_else(|| invalid("tests"))?,
+            modes,
+        })
+    }
+}
+
+/// The names of `flags` without their values, e.g. `punctuation` for
+/// `punctuation=0.3`.
+pub fn mode_names(flags: &[String]) -> BTreeSet<&str> {
+    flags
+        .iter()
+        .map(|flag| flag.split_once('=').map_or(flag.as_str(), |(name, _)| name))
+        .collect()
+}
+
+/// Everything sent to the endpoint: the version of toipe, the operating
+/// system and the [`Counts`] of tests, without when counting started.
+pub fn payload(counts: &Counts) -> Value {
+    let mut object = Map::new();
+    object.insert("schema_version".into(), SCHEMA_VERSION.into());
+    object.insert("version".into(), env!("CARGO_PKG_VERSION").into());
+    object.insert("os".into(), std::env::consts::OS.into());
+    object.insert("tests".into(), counts.tests.into());
+    object.insert("modes".into(), counts.modes_json());
+    object.into()
+}
+
+/// File the tests are counted in until they are sent.
+///
+/// Usually `~/.local/share/toipe/telemetry.json`.
+pub fn counts_file() -> Result<PathBuf, Error> {
+    Ok(dirs::data_dir()?.join("telemetry.json"))
+}
+
+/// The tests counted so far.
+pub fn load() -> Result<Counts, Error> {
+    match fs::read_to_string(counts_file()?) {
+        Ok(contents) => Counts::from_json(&serde_json::from_str::<Value>(&contents)?),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Counts::default()),
+        Err(err) => Err(err),
+    }
+}
+
+fn save(counts: &Counts) -> Result<(), Error> {
+    storage::write(
+        &counts_file()?,
+        format!("{}\n", counts.to_json()).as_bytes(),
+    )
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Counts a finished test taken with `flags`.
+///
+/// Only called with `--telemetry-endpoint`.
+pub fn record(flags: &[String]) -> Result<(), Error> {
+    let mut counts = load()?;
+    if counts.tests == 0 {
+        counts.since = now();
+    }
+    counts.add(flags);
+    save(&counts)
+}
+
+/// Sends the payload to `endpoint` and resets the counts, if a day
+/// passed since counting started.
+pub fn send_due(endpoint: &str) -> Result<(), Error> {
+    if storage::is_incognito() {
+        return Ok(());
+    }
+    let counts = load()?;
+    if counts.tests == 0 || now().saturating_sub(counts.since) < INTERVAL {
+        return Ok(());
+    }
+    post_json(endpoint, &payload(&counts))?;
+    save(&Counts::default())
+}
+
+/// Runs `toipe telemetry`: tells whether telemetry is on and prints the
+/// payload that would be sent next.
+pub fn run(endpoint: Option<&str>) -> Result<(), Error> {
+    match endpoint {
+        Some(endpoint) => println!(
+            "Telemetry is on: sent to {} at most once a day. The next payload is:",
+            endpoint
+        ),
+        None => println!(
+            "Telemetry is off: nothing is collected or sent. Once turned on with \
+             `--telemetry-endpoint`, the payload looks like:"
+        ),
+    }
+    println!("{}", payload(&load()?));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_has_only_mode_names() {
+        let mut counts = Counts {
+            since: 1_700_000_000,
+            ..Counts::default()
+        };
+        counts.add(&[
+            "num_words=30".to_string(),
+            "layout=/home/me/l.toml".to_string(),
+        ]);
+        counts.add(&[
+            "duration=60".to_string(),
+            "layout=qwerty".to_string(),
+            "keyboard=my split".to_string(),
+            "reveal".to_string(),
+        ]);
+        assert_eq!(Counts::from_json(&counts.to_json()).unwrap(), counts);
+
+        let payload = payload(&counts).to_string();
+        assert!(payload.contains(
+            "\"modes\":{\"duration\":1,\"keyboard\":1,\"layout\":2,\"num_words\":1,\"reveal\":1}"
+        ));
+        assert!(payload.contains("\"tests\":2"));
+        assert!(!payload.contains("home") && !payload.contains("split"));
+        assert!(!payload.contains("1700000000"));
+    }
+}
@@ -0,0 +1,270 @@
+//! A simulated typist for driving typing tests without a keyboard.
+//!
+//! [`SimulatedTypist`] turns a text into a [`Script`] of timed key
+//! presses, with mistakes and corrections made at the configured rates.
+//! A [`ScriptPlayer`] plays a script back in real time, so it can be
+//! given to [`EventStream::new`](crate::events::EventStream::new) in
+//! place of the keys of a terminal to drive [`Toipe::test`](crate::Toipe::test).
+//!
+//! Used by `toipe --demo`, `toipe demo` and for benchmarking the UI.
+
+use std::{
+    collections::VecDeque,
+    io,
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::backend::Key;
+
+/// Key presses, each with the time to wait before pressing it.
+pub type Script = Vec<(Duration, Key)>;
+
+/// A typist with a given speed and error model.
+pub struct SimulatedTypist {
+    /// typing speed in words per minute, counting mistakes and
+    /// corrections as typed chars
+    pub wpm: f64,
+    /// probability of typing any char wrong
+    pub error_rate: f64,
+    /// probability of noticing a mistake and correcting it right away
+    pub correction_probability: f64,
+    /// how much the time between key presses varies, as a fraction of
+    /// the average time
+    pub jitter: f64,
+    rng: StdRng,
+}
+
+impl SimulatedTypist {
+    /// Creates a typist typing at `wpm` words per minute who makes a
+    /// mistake in 3% of chars and corrects most of them.
+    pub fn new(wpm: f64) -> Self {
+        Self {
+            wpm,
+            error_rate: 0.03,
+            correction_probability: 0.8,
+            jitter: 0.3,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Makes the typist behave the same way every time for the same
+    /// `seed`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Sets the probability of typing any char wrong.
+    pub fn with_error_rate(mut self, error_rate: f64) -> Self {
+        self.error_rate = error_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the probability of correcting a mistake right away.
+    pub fn with_correction_probability(mut self, correction_probability: f64) -> Self {
+        self.correction_probability = correction_probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Average time between key presses.
+    fn key_interval(&self) -> Duration {
+        // a "word" is 5 chars, see `ToipeResults::wpm`
+        Duration::from_secs_f64(60.0 / (self.wpm.max(1.0) * 5.0))
+    }
+
+    /// Time to wait before the next key press.
+    fn next_delay(&mut self) -> Duration {
+        let variation = if self.jitter > 0.0 {
+            self.rng.gen_range(-self.jitter..=self.jitter)
+        } else {
+            0.0
+        };
+        self.key_interval().mul_f64(1.0 + variation)
+    }
+
+    /// A char that is not `c`, as if a neighbouring key was hit.
+    fn wrong_char(&mut self, c: char) -> char {
+        loop {
+            let wrong = self.rng.gen_range(b'a'..=b'z') as char;
+            if wrong != c {
+                return wrong;
+            }
+        }
+    }
+
+    /// Key presses for typing `text` from start to end.
+    pub fn script(&mut self, text: &str) -> Script {
+        let mut script = Script::new();
+
+        for c in text.chars() {
+            if self.rng.gen_bool(self.error_rate) {
+                let wrong = self.wrong_char(c);
+                script.push((self.next_delay(), Key::Char(wrong)));
+
+                if !self.rng.gen_bool(self.correction_probability) {
+                    // the mistake is left in the text
+                    continue;
+                }
+                script.push((self.next_delay(), Key::Backspace));
+            }
+            script.push((self.next_delay(), Key::Char(c)));
+        }
+
+        script
+    }
+}
+
+/// Keys of a [`Script`] pressed in real time.
+pub struct ScriptPlayer {
+    script: VecDeque<(Duration, Key)>,
+}
+
+impl ScriptPlayer {
+    pub fn new(script: Script) -> Self {
+        Self {
+            script: script.into(),
+        }
+    }
+}
+
+impl Iterator for ScriptPlayer {
+    type Item = io::Result<Key>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (delay, key) = self.script.pop_front()?;
+        thread::sleep(delay);
+        Some(Ok(key))
+    }
+}
+
+/// Keys of the [`Script`]s sent to it pressed in real time, one script
+/// after the other, until a key is pressed on the keyboard.
+///
+/// Waits for the next script once one is over. A key pressed on the
+/// keyboard is turned into ctrl + c, which quits.
+pub struct ScriptQueue {
+    scripts: Receiver<Script>,
+    keyboard: Receiver<io::Result<Key>>,
+    script: VecDeque<(Duration, Key)>,
+}
+
+impl ScriptQueue {
+    pub fn new(scripts: Receiver<Script>, keyboard: Receiver<io::Result<Key>>) -> Self {
+        Self {
+            scripts,
+            keyboard,
+            script: VecDeque::new(),
+        }
+    }
+}
+
+impl Iterator for ScriptQueue {
+    type Item = io::Result<Key>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.script.is_empty() {
+            self.script = self.scripts.recv().ok()?.into();
+        }
+        let (delay, key) = self.script.pop_front()?;
+        match self.keyboard.recv_timeout(delay) {
+            Ok(Err(err)) => Some(Err(err)),
+            Ok(Ok(_)) => Some(Ok(Key::Ctrl('c'))),
+            Err(RecvTimeoutError::Timeout) => Some(Ok(key)),
+            Err(RecvTimeoutError::Disconnected) => {
+                thread::sleep(delay);
+                Some(Ok(key))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    /// Text left after applying the key presses of `script`.
+    fn typed_text(script: &Script) -> String {
+        let mut typed = String::new();
+        for (_, key) in script {
+            match key {
+                Key::Char(c) => typed.push(*c),
+                Key::Backspace => {
+                    typed.pop();
+                }
+                _ => {}
+            }
+        }
+        typed
+    }
+
+    #[test]
+    fn scripts() {
+        let text = "the quick brown fox jumps over the lazy dog";
+
+        let mut perfect = SimulatedTypist::new(60.0).with_error_rate(0.0).with_seed(1);
+        perfect.jitter = 0.0;
+        let script = perfect.script(text);
+        assert_eq!(typed_text(&script), text);
+        // 60 wpm is 5 chars per second
+        assert!(script
+            .iter()
+            .all(|(delay, _)| *delay == Duration::from_millis(200)));
+
+        let mut corrects_all = SimulatedTypist::new(60.0)
+            .with_error_rate(0.5)
+            .with_correction_probability(1.0)
+            .with_seed(2);
+        let script = corrects_all.script(text);
+        assert_eq!(typed_text(&script), text);
+        assert!(script.len() > text.len());
+
+        let mut corrects_none = SimulatedTypist::new(60.0)
+            .with_error_rate(0.5)
+            .with_correction_probability(0.0)
+            .with_seed(3);
+        let script = corrects_none.script(text);
+        assert_eq!(script.len(), text.len());
+        assert_ne!(typed_text(&script), text);
+    }
+
+    #[test]
+    fn player_plays_back_keys() {
+        let player = ScriptPlayer::new(vec![
+            (Duration::ZERO, Key::Char('é')),
+            (Duration::ZERO, Key::Backspace),
+        ]);
+        let keys: Vec<Key> = player.map(Result::unwrap).collect();
+        assert_eq!(keys, [Key::Char('é'), Key::Backspace]);
+    }
+
+    #[test]
+    fn queue_plays_scripts_until_a_key_is_pressed() {
+        let (scripts, received) = mpsc::channel();
+        let (keyboard, pressed) = mpsc::channel();
+        let mut queue = ScriptQueue::new(received, pressed);
+
+        scripts
+            .send(vec![(Duration::ZERO, Key::Char('a'))])
+            .unwrap();
+        scripts
+            .send(vec![(Duration::ZERO, Key::Char('b'))])
+            .unwrap();
+        assert_eq!(queue.next().unwrap().unwrap(), Key::Char('a'));
+        assert_eq!(queue.next().unwrap().unwrap(), Key::Char('b'));
+
+        scripts
+            .send(vec![(Duration::from_secs(10), Key::Char('c'))])
+            .unwrap();
+        keyboard.send(Ok(Key::Char('x'))).unwrap();
+        assert_eq!(queue.next().unwrap().unwrap(), Key::Ctrl('c'));
+
+        drop(scripts);
+        assert!(queue.next().is_none());
+    }
+}
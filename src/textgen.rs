@@ -3,21 +3,49 @@
 
 use std::collections::VecDeque;
 use std::io;
+use std::str::FromStr;
 
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::Rng;
-
-use rand::prelude::ThreadRng;
+use rand::{Rng, SeedableRng};
 
+use crate::language::Language;
+use crate::locale::Locale;
 use crate::trie::Trie;
 
+/// Random number generator of the word selectors.
+///
+/// Every selector is given its own instead of using [`rand::thread_rng`],
+/// so that the whole text can be reproduced from a single seed (see
+/// `--seed`) and selectors can be tested deterministically.
+pub type SelectorRng = StdRng;
+
+/// Creates a generator for word selectors, seeded with `seed` if given
+/// or else from the OS.
+pub fn selector_rng(seed: Option<u64>) -> SelectorRng {
+    match seed {
+        Some(seed) => SelectorRng::seed_from_u64(seed),
+        None => SelectorRng::from_entropy(),
+    }
+}
+
+/// Creates a generator for another word selector from `rng`, so that it
+/// is seeded too if `rng` is.
+pub fn fork_rng(rng: &mut SelectorRng) -> SelectorRng {
+    SelectorRng::seed_from_u64(rng.gen())
+}
+
 pub struct RawWordSelector {
     trie: Trie,
+    rng: SelectorRng,
 }
 
 impl RawWordSelector {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_iter<T: Iterator<Item = Result<String, io::Error>>>(
         iter: T,
+        rng: SelectorRng,
     ) -> Result<Self, io::Error> {
         let mut trie = Trie::new();
         for elem in iter {
@@ -34,17 +62,43 @@ impl RawWordSelector {
         }
 
         trie.compress()
-            .map(|t| Self { trie: t })
+            .map(|t| Self { trie: t, rng })
             .map_err(|e| e.into())
     }
 
-    fn new_word_raw(&mut self, rng: &mut ThreadRng) -> Result<String, io::Error> {
-        self.trie
-            .sample(rng.gen_range(0..self.trie.num_words()))
-            .map_err(|e| e.into())
+    /// Number of distinct words in the word list.
+    pub fn num_distinct_words(&self) -> u64 {
+        self.trie.num_distinct_words()
+    }
+
+    /// Returns every word in the word list, in no particular order.
+    ///
+    /// Words that appear multiple times in the list are returned as
+    /// many times.
+    pub fn all_words(&self) -> Result<Vec<String>, io::Error> {
+        (0..self.trie.num_words())
+            .map(|id| self.trie.sample(id).map_err(|e| e.into()))
+            .collect()
     }
 }
 
+/// Word that ends a paragraph, typed by pressing enter in place of the
+/// space between words, see [`join_words`].
+pub const PARAGRAPH_BREAK: &str = "\n";
+
+/// The text of `words`, separated by spaces except around paragraph
+/// breaks.
+pub fn join_words(words: &[String]) -> String {
+    let mut text = String::new();
+    for (index, word) in words.iter().enumerate() {
+        if index > 0 && word != PARAGRAPH_BREAK && words[index - 1] != PARAGRAPH_BREAK {
+            text.push(' ');
+        }
+        text.push_str(word);
+    }
+    text
+}
+
 /// Describes a thing that provides new words.
 pub trait WordSelector {
     /// Returns a new word.
@@ -55,18 +109,32 @@ pub trait WordSelector {
         let mut words = Vec::<String>::new();
         for _ in 0..num_words {
             let word = self.new_word()?;
-            for part in word.split_whitespace() {
-                words.push(part.to_string());
+            // a line break in a word starts a new paragraph
+            for (index, paragraph) in word.split('\n').enumerate() {
+                if index > 0 {
+                    words.push(PARAGRAPH_BREAK.to_string());
+                }
+                for part in paragraph.split_whitespace() {
+                    words.push(part.to_string());
+                }
             }
         }
+        self.finish_words(&mut words);
         Ok(words)
     }
+
+    /// Finishes the words of a text, such as by closing the brackets left
+    /// open at its end. Called by [`WordSelector::new_words`].
+    ///
+    /// Selectors wrapping other selectors must pass the words on to them.
+    fn finish_words(&mut self, _words: &mut [String]) {}
 }
 
 impl WordSelector for RawWordSelector {
     fn new_word(&mut self) -> Result<String, io::Error> {
-        let mut rng = rand::thread_rng();
-        Ok(self.new_word_raw(&mut rng)?)
+        self.trie
+            .sample(self.rng.gen_range(0..self.trie.num_words()))
+            .map_err(|e| e.into())
     }
 }
 
@@ -74,6 +142,7 @@ pub struct NumberGeneratingWordSelector {
     selector: Box<dyn WordSelector>,
     number_chance: f64,
     number_max: u64,
+    rng: SelectorRng,
 }
 
 impl NumberGeneratingWordSelector {
@@ -81,33 +150,347 @@ impl NumberGeneratingWordSelector {
         word_selector: Box<dyn WordSelector>,
         number_chance: f64,
         number_max: u64,
+        rng: SelectorRng,
     ) -> Self {
         Self {
             selector: word_selector,
             number_chance,
             number_max,
+            rng,
         }
     }
 }
 
 impl WordSelector for NumberGeneratingWordSelector {
     fn new_word(&mut self) -> Result<String, io::Error> {
-        let mut rng = rand::thread_rng();
-        if !rng.gen_bool(self.number_chance) {
+        if !self.rng.gen_bool(self.number_chance) {
             return self.selector.new_word();
         }
-        let num = rng.gen_range(0..self.number_max);
+        let num = self.rng.gen_range(0..self.number_max);
         Ok(num.to_string())
     }
+
+    fn finish_words(&mut self, words: &mut [String]) {
+        self.selector.finish_words(words)
+    }
+}
+
+/// Generates random numbers below a maximum as words, or numbers
+/// formatted like real data from [`NumberTemplate`]s.
+pub struct NumberWordSelector {
+    number_max: u64,
+    templates: Vec<NumberTemplate>,
+    locale: Option<Locale>,
+    rng: SelectorRng,
+}
+
+impl NumberWordSelector {
+    /// Chance of a number having decimals, with a locale.
+    pub const DECIMAL_CHANCE: f64 = 0.25;
+
+    pub fn new(number_max: u64, rng: SelectorRng) -> Self {
+        Self {
+            number_max,
+            templates: Vec::new(),
+            locale: None,
+            rng,
+        }
+    }
+
+    /// Writes the numbers below the maximum as is usual in `locale`, with
+    /// their digits grouped, and some with two decimals.
+    ///
+    /// Numbers from templates are left as they are.
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// Generates numbers from one of the templates, picked at random,
+    /// instead of numbers below the maximum.
+    pub fn with_templates(mut self, templates: Vec<NumberTemplate>) -> Self {
+        self.templates = templates;
+        self
+    }
+}
+
+impl WordSelector for NumberWordSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        if let Some(template) = self.templates.choose(&mut self.rng) {
+            let template = template.clone();
+            return Ok(template.generate(&mut self.rng));
+        }
+        let num = self.rng.gen_range(0..self.number_max.max(1));
+        match &self.locale {
+            Some(locale) if self.rng.gen_bool(Self::DECIMAL_CHANCE) => {
+                let decimals = format!("{:02}", self.rng.gen_range(0..100));
+                Ok(locale.format_decimal(num, &decimals))
+            }
+            Some(locale) => Ok(locale.format_integer(num)),
+            None => Ok(num.to_string()),
+        }
+    }
+}
+
+/// Format of generated numbers, such as `N,###` for `1,204` or
+/// `(###) ###-####` for phone numbers.
+///
+/// Each `#` is replaced with a random digit and each `N` with a random
+/// digit other than 0. Everything else is kept as is, and spaces split
+/// the number into several words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumberTemplate(String);
+
+impl NumberTemplate {
+    /// Generates a number in this format.
+    pub fn generate(&self, rng: &mut impl Rng) -> String {
+        self.0
+            .chars()
+            .map(|c| match c {
+                '#' => char::from(b'0' + rng.gen_range(0..10)),
+                'N' => char::from(b'0' + rng.gen_range(1..10)),
+                c => c,
+            })
+            .collect()
+    }
+}
+
+impl FromStr for NumberTemplate {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        if !template.contains(['#', 'N']) {
+            return Err(format!(
+                "template `{}` has no digits, expected `#` or `N`",
+                template
+            ));
+        }
+        Ok(Self(template.to_string()))
+    }
+}
+
+impl std::fmt::Display for NumberTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Symbols used by [`SymbolWordSelector`].
+const SYMBOLS: [char; 30] = [
+    '!', '?', '.', ',', ':', ';', '@', '#', '$', '%', '^', '&', '*', '~', '/', '\\', '_', '-', '=',
+    '+', '\'', '"', '(', ')', '{', '}', '<', '>', '[', ']',
+];
+
+/// Generates "words" of one to three random symbols, such as `#(` or
+/// `%`.
+pub struct SymbolWordSelector {
+    rng: SelectorRng,
+}
+
+impl SymbolWordSelector {
+    pub fn new(rng: SelectorRng) -> Self {
+        Self { rng }
+    }
+}
+
+impl WordSelector for SymbolWordSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let len = self.rng.gen_range(1..=3);
+        Ok((0..len)
+            .map(|_| *SYMBOLS.choose(&mut self.rng).expect("not empty"))
+            .collect())
+    }
+}
+
+/// Kind of words mixed into the text with `--mix`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixSource {
+    /// words from the word list
+    Words,
+    /// see [`NumberWordSelector`]
+    Numbers,
+    /// see [`SymbolWordSelector`]
+    Symbols,
+}
+
+/// Kinds of words to mix into the text and their weights, such as
+/// `words:7,numbers:2,symbols:1`.
+///
+/// See [`CompositeWordSelector::from_mix`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mix(pub Vec<(MixSource, f64)>);
+
+impl FromStr for Mix {
+    type Err = String;
+
+    fn from_str(mix: &str) -> Result<Self, Self::Err> {
+        let mut sources = Vec::new();
+        for part in mix.split(',').map(str::trim) {
+            let (name, weight) = part
+                .split_once(':')
+                .ok_or_else(|| format!("expected `kind:weight`, got `{}`", part))?;
+            let source = match name.trim() {
+                "words" => MixSource::Words,
+                "numbers" => MixSource::Numbers,
+                "symbols" => MixSource::Symbols,
+                name => {
+                    return Err(format!(
+                        "unknown kind `{}`, expected words, numbers or symbols",
+                        name
+                    ))
+                }
+            };
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid weight `{}` for {}", weight, name))?;
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(format!("invalid weight `{}` for {}", weight, name));
+            }
+            if sources.iter().any(|(other, _)| *other == source) {
+                return Err(format!("{} is given more than once", name));
+            }
+            sources.push((source, weight));
+        }
+
+        if sources.iter().map(|(_, weight)| weight).sum::<f64>() <= 0.0 {
+            return Err("at least one weight must be more than 0".to_string());
+        }
+        Ok(Self(sources))
+    }
+}
+
+impl std::fmt::Display for Mix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = self
+            .0
+            .iter()
+            .map(|(source, weight)| {
+                let name = match source {
+                    MixSource::Words => "words",
+                    MixSource::Numbers => "numbers",
+                    MixSource::Symbols => "symbols",
+                };
+                format!("{}:{}", name, weight)
+            })
+            .collect();
+        f.write_str(&parts.join(","))
+    }
+}
+
+/// Interleaves the words of several word selectors, picking the selector
+/// for each word at random by their weights.
+pub struct CompositeWordSelector {
+    selectors: Vec<Box<dyn WordSelector>>,
+    weights: WeightedIndex<f64>,
+    rng: SelectorRng,
+}
+
+impl CompositeWordSelector {
+    /// Creates a CompositeWordSelector from word selectors and their
+    /// weights.
+    ///
+    /// Fails if there are no selectors or no weight is more than 0.
+    pub fn new(
+        selectors: Vec<(Box<dyn WordSelector>, f64)>,
+        rng: SelectorRng,
+    ) -> Result<Self, io::Error> {
+        let (selectors, weights): (Vec<_>, Vec<_>) = selectors.into_iter().unzip();
+        let weights = WeightedIndex::new(weights)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        Ok(Self {
+            selectors,
+            weights,
+            rng,
+        })
+    }
+
+    /// Creates a CompositeWordSelector for a [`Mix`], taking the words
+    /// of the word list from `word_selector`. Numbers are written as in
+    /// `locale`, if given.
+    ///
+    /// The other selectors get generators forked from `rng`.
+    pub fn from_mix(
+        mix: &Mix,
+        word_selector: Box<dyn WordSelector>,
+        number_max: u64,
+        locale: Option<&Locale>,
+        mut rng: SelectorRng,
+    ) -> Result<Self, io::Error> {
+        let mut word_selector = Some(word_selector);
+        let selectors = mix
+            .0
+            .iter()
+            .map(|(source, weight)| {
+                let selector: Box<dyn WordSelector> = match source {
+                    MixSource::Words => word_selector.take().expect("words are only mixed in once"),
+                    MixSource::Numbers => {
+                        let numbers = NumberWordSelector::new(number_max, fork_rng(&mut rng));
+                        Box::new(match locale {
+                            Some(locale) => numbers.with_locale(locale.clone()),
+                            None => numbers,
+                        })
+                    }
+                    MixSource::Symbols => Box::new(SymbolWordSelector::new(fork_rng(&mut rng))),
+                };
+                (selector, *weight)
+            })
+            .collect();
+        Self::new(selectors, rng)
+    }
+}
+
+impl WordSelector for CompositeWordSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let index = self.weights.sample(&mut self.rng);
+        self.selectors[index].new_word()
+    }
+
+    fn finish_words(&mut self, words: &mut [String]) {
+        for selector in &mut self.selectors {
+            selector.finish_words(words);
+        }
+    }
 }
 
 /// Wraps another word selector, taking words from it and adding punctuation to the end of or
 /// around words with a configurable chance. Will capitalize the next word when an end-of-sentence
 /// punctuation mark is used.
+///
+/// Marks are only used where they read plausibly: sentences have at least
+/// [`PunctuatedWordSelector::MIN_SENTENCE_WORDS`] words, their first word
+/// neither ends with a mark nor starts with a symbol such as `@`, and no
+/// two words in a row end with a mark.
+///
+/// Surrounding marks, such as brackets, may span up to
+/// [`PunctuatedWordSelector::MAX_SURROUNDED_WORDS`] words, and are always
+/// closed by the end of the text.
+///
+/// Heavy punctuation can be spread out with
+/// [`PunctuatedWordSelector::with_cooldown`] and
+/// [`PunctuatedWordSelector::with_max_marks`].
 pub struct PunctuatedWordSelector {
     selector: Box<dyn WordSelector>,
     next_is_capital: bool,
+    /// capitalizes the first word of each sentence
+    language: Language,
     punctuation_chance: f64,
+    /// number of words of the current sentence so far
+    sentence_words: usize,
+    /// whether the last word ended with a mark, such as `,`
+    after_ending: bool,
+    /// smallest number of words between punctuated words
+    cooldown: usize,
+    /// number of words since the last punctuated word
+    since_punctuated: usize,
+    /// largest number of marks on a word
+    max_marks: Option<usize>,
+    /// closing marks of the surrounding marks that are open, innermost
+    /// last, with the number of words left until they close
+    open: Vec<(char, usize)>,
+    /// opening and closing quotation marks, used instead of `"`
+    quotes: (char, char),
+    rng: SelectorRng,
 }
 
 enum PunctuationType {
@@ -154,60 +537,1010 @@ const PUNCTUATION: [PunctuationType; 33] = [
 ];
 
 impl PunctuatedWordSelector {
+    /// Largest number of words inside surrounding marks.
+    pub const MAX_SURROUNDED_WORDS: usize = 4;
+
+    /// Smallest number of words in a sentence.
+    pub const MIN_SENTENCE_WORDS: usize = 3;
+
     /// Creates a PunctuatedWordSelector from another WordSelector, allowing the selection of the
     /// chance of punctuation.
     pub fn from_word_selector(
         word_selector: Box<dyn WordSelector>,
         punctuation_chance: f64,
+        rng: SelectorRng,
     ) -> Self {
         Self {
             selector: word_selector,
             next_is_capital: true,
+            language: Language::default(),
             punctuation_chance,
+            sentence_words: 0,
+            after_ending: false,
+            cooldown: 0,
+            since_punctuated: usize::MAX,
+            max_marks: None,
+            open: Vec::new(),
+            quotes: ('"', '"'),
+            rng,
         }
     }
+
+    /// Quotes words with the quotation marks of `locale`, such as
+    /// `„word“` in German.
+    pub fn with_locale(mut self, locale: &Locale) -> Self {
+        self.quotes = locale.quotes;
+        self
+    }
+
+    /// Capitalizes sentences as is usual in `language`, such as `İstanbul`
+    /// in Turkish.
+    pub fn with_language(mut self, language: &Language) -> Self {
+        self.language = language.clone();
+        self
+    }
+
+    /// Leaves at least `words` words without punctuation between
+    /// punctuated words.
+    pub fn with_cooldown(mut self, words: usize) -> Self {
+        self.cooldown = words;
+        self
+    }
+
+    /// Puts at most `marks` marks on a word, counting the closing marks
+    /// of earlier words. Marks left open at the end of the text are
+    /// still closed on its last word.
+    pub fn with_max_marks(mut self, marks: usize) -> Self {
+        self.max_marks = Some(marks);
+        self
+    }
+
+    /// Number of surrounding marks that close on the next word.
+    fn closing_marks(&self) -> usize {
+        self.open.iter().filter(|(_, words)| *words == 1).count()
+    }
+
+    /// Whether the next word can have `marks` marks.
+    fn allows_marks(&self, marks: usize) -> bool {
+        marks <= self.max_marks.unwrap_or(usize::MAX)
+    }
+
+    /// Whether the mark can be used on the next word of the sentence.
+    fn allows(&self, punctuation: &PunctuationType) -> bool {
+        let closing = self.closing_marks();
+        match punctuation {
+            PunctuationType::Capitaizing(_) => {
+                !self.after_ending
+                    && self.sentence_words + 1 >= Self::MIN_SENTENCE_WORDS
+                    && self.allows_marks(self.open.len() + 1)
+            }
+            PunctuationType::Ending(_) => {
+                !self.after_ending && self.sentence_words > 0 && self.allows_marks(closing + 1)
+            }
+            PunctuationType::Starting(_) => {
+                self.sentence_words > 0 && self.allows_marks(closing + 1)
+            }
+            PunctuationType::Surrounding(_, _) => match self.open.last() {
+                // nested in a mark closing on this word, so closes on it too
+                Some((_, 1)) => self.allows_marks(closing + 2),
+                _ => self.allows_marks(closing + 1),
+            },
+        }
+    }
+}
+
+/// Adds closing marks to a word, before any punctuation ending it, as in
+/// `(word),`.
+fn close_marks(word: &mut String, closing: impl IntoIterator<Item = char>) {
+    let end = word.trim_end_matches(['!', '?', '.', ',', ':', ';']).len();
+    let closing: String = closing.into_iter().collect();
+    word.insert_str(end, &closing);
 }
 
 impl WordSelector for PunctuatedWordSelector {
     fn new_word(&mut self) -> Result<String, io::Error> {
-        let mut rng = rand::thread_rng();
+        let mut word = self.selector.new_word()?;
+
+        if self.next_is_capital {
+            assert!(!word.is_empty(), "got empty word");
+            word = self.language.capitalize(&word);
+            self.next_is_capital = false;
+        }
+
+        let mut ending = None;
+        let cooled_down = self.since_punctuated >= self.cooldown;
+        self.since_punctuated = self.since_punctuated.saturating_add(1);
+        if cooled_down && self.rng.gen_bool(self.punctuation_chance) {
+            let allowed: Vec<&PunctuationType> =
+                PUNCTUATION.iter().filter(|p| self.allows(p)).collect();
+            if !allowed.is_empty() {
+                self.since_punctuated = 0;
+            }
+            match allowed.choose(&mut self.rng) {
+                None => {}
+                Some(PunctuationType::Capitaizing(c)) => {
+                    self.next_is_capital = true;
+                    ending = Some(*c);
+                }
+                Some(PunctuationType::Ending(c)) => ending = Some(*c),
+                Some(PunctuationType::Starting(c)) => word.insert(0, *c),
+                Some(PunctuationType::Surrounding(opening, closing)) => {
+                    let (opening, closing) = match opening {
+                        '"' => (&self.quotes.0, &self.quotes.1),
+                        _ => (opening, closing),
+                    };
+                    // nested marks close before the ones around them
+                    let max_words = self
+                        .open
+                        .last()
+                        .map_or(Self::MAX_SURROUNDED_WORDS, |(_, words)| *words);
+                    // closing on the same word would be one mark too many
+                    let min_words = if self.allows_marks(self.closing_marks() + 2) {
+                        1
+                    } else {
+                        2
+                    };
+                    let words = self.rng.gen_range(min_words.min(max_words)..=max_words);
+                    word.insert(0, *opening);
+                    self.open.push((*closing, words));
+                }
+            }
+        }
+
+        for (_, words) in &mut self.open {
+            *words -= 1;
+        }
+        while let Some((closing, 0)) = self.open.last() {
+            word.push(*closing);
+            self.open.pop();
+        }
+        // the marks close before the end of the sentence
+        if self.next_is_capital {
+            close_marks(&mut word, self.open.drain(..).rev().map(|(c, _)| c));
+        }
+        word.extend(ending);
+
+        self.after_ending = ending.is_some();
+        self.sentence_words = if self.next_is_capital {
+            0
+        } else {
+            self.sentence_words + 1
+        };
+
+        Ok(word)
+    }
+
+    fn finish_words(&mut self, words: &mut [String]) {
+        self.selector.finish_words(words);
+        if let Some(last) = words.last_mut() {
+            close_marks(last, self.open.drain(..).rev().map(|(c, _)| c));
+        }
+    }
+}
+
+/// Wraps another word selector, remembering the last few words it
+/// generated and rerolling any word that repeats one of them.
+///
+/// Small word lists otherwise tend to produce the same word twice in a
+/// row fairly often.
+///
+/// If the wrapped selector cannot produce a fresh word within
+/// [`NoRepeatWordSelector::MAX_REROLLS`] attempts (e.g. when the window
+/// is larger than the word list), the last rerolled word is used as-is.
+pub struct NoRepeatWordSelector {
+    selector: Box<dyn WordSelector>,
+    window: usize,
+    recent: VecDeque<String>,
+}
+
+impl NoRepeatWordSelector {
+    /// Number of times a repeated word is rerolled before giving up.
+    pub const MAX_REROLLS: usize = 32;
+
+    /// Creates a NoRepeatWordSelector from another WordSelector, which
+    /// will not repeat any of the last `window` words.
+    pub fn from_word_selector(word_selector: Box<dyn WordSelector>, window: usize) -> Self {
+        Self {
+            selector: word_selector,
+            window,
+            recent: VecDeque::with_capacity(window),
+        }
+    }
+}
+
+impl WordSelector for NoRepeatWordSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let mut word = self.selector.new_word()?;
+        for _ in 0..Self::MAX_REROLLS {
+            if !self.recent.contains(&word) {
+                break;
+            }
+            word = self.selector.new_word()?;
+        }
+
+        if self.window > 0 {
+            if self.recent.len() == self.window {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(word.clone());
+        }
+
+        Ok(word)
+    }
+
+    fn finish_words(&mut self, words: &mut [String]) {
+        self.selector.finish_words(words)
+    }
+}
+
+/// Wraps another word selector, rerolling any word it generates that is
+/// not accepted by a filter, such as words that cannot be typed with one
+/// hand.
+///
+/// Like [`NoRepeatWordSelector`], the last rerolled word is used as-is if
+/// no accepted word is found within [`FilteredWordSelector::MAX_REROLLS`]
+/// attempts, so the words given to the wrapped selector should already
+/// be accepted for the most part.
+pub struct FilteredWordSelector {
+    selector: Box<dyn WordSelector>,
+    filter: Box<dyn Fn(&str) -> bool>,
+}
 
+impl FilteredWordSelector {
+    /// Number of times a word is rerolled before giving up.
+    pub const MAX_REROLLS: usize = 32;
+
+    /// Creates a FilteredWordSelector from another WordSelector, which
+    /// only generates words for which `filter` returns true.
+    pub fn from_word_selector(
+        word_selector: Box<dyn WordSelector>,
+        filter: Box<dyn Fn(&str) -> bool>,
+    ) -> Self {
+        Self {
+            selector: word_selector,
+            filter,
+        }
+    }
+}
+
+impl WordSelector for FilteredWordSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
         let mut word = self.selector.new_word()?;
+        for _ in 0..Self::MAX_REROLLS {
+            if (self.filter)(&word) {
+                break;
+            }
+            word = self.selector.new_word()?;
+        }
+        Ok(word)
+    }
+
+    fn finish_words(&mut self, words: &mut [String]) {
+        self.selector.finish_words(words)
+    }
+}
+
+/// Selects words like drawing cards from a shuffled deck: every word in
+/// the list is returned once before any word is repeated.
+///
+/// Once the deck runs out, it is reshuffled. Useful for small custom
+/// lists that are meant to be memorized.
+pub struct ShuffledDeckWordSelector {
+    deck: Vec<String>,
+    remaining: Vec<String>,
+    rng: SelectorRng,
+}
+
+impl ShuffledDeckWordSelector {
+    /// Creates a ShuffledDeckWordSelector containing all the words of a
+    /// [`RawWordSelector`].
+    pub fn from_raw_word_selector(
+        word_selector: &RawWordSelector,
+        rng: SelectorRng,
+    ) -> Result<Self, io::Error> {
+        Ok(Self::from_words(word_selector.all_words()?, rng))
+    }
+
+    /// Creates a ShuffledDeckWordSelector from the given words.
+    pub fn from_words(words: Vec<String>, rng: SelectorRng) -> Self {
+        Self {
+            deck: words,
+            remaining: Vec::new(),
+            rng,
+        }
+    }
+}
+
+impl WordSelector for ShuffledDeckWordSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        if self.remaining.is_empty() {
+            if self.deck.is_empty() {
+                return Err(io::Error::other("Cannot draw a word from an empty deck"));
+            }
+            self.remaining = self.deck.clone();
+            self.remaining.shuffle(&mut self.rng);
+        }
+        Ok(self
+            .remaining
+            .pop()
+            .expect("deck was just refilled if it was empty"))
+    }
+}
+
+/// Word selector picking words at random by their weights, such as to
+/// pick more words with the chars often typed wrong (see `--practice`).
+pub struct WeightedWordSelector {
+    words: Vec<String>,
+    weights: WeightedIndex<f64>,
+    rng: SelectorRng,
+}
+
+impl WeightedWordSelector {
+    /// Creates a WeightedWordSelector picking the words of a
+    /// [`RawWordSelector`] as weighted by `weight`.
+    pub fn from_raw_word_selector<F>(
+        word_selector: &RawWordSelector,
+        weight: F,
+        rng: SelectorRng,
+    ) -> Result<Self, io::Error>
+    where
+        F: Fn(&str) -> f64,
+    {
+        Self::from_words(word_selector.all_words()?, weight, rng)
+    }
+
+    /// Creates a WeightedWordSelector picking the given words as weighted
+    /// by `weight`.
+    ///
+    /// Fails if there are no words or no weight is more than 0.
+    pub fn from_words<F>(words: Vec<String>, weight: F, rng: SelectorRng) -> Result<Self, io::Error>
+    where
+        F: Fn(&str) -> f64,
+    {
+        let weights = WeightedIndex::new(words.iter().map(|word| weight(word)))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        Ok(Self {
+            words,
+            weights,
+            rng,
+        })
+    }
+}
+
+impl WordSelector for WeightedWordSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        Ok(self.words[self.weights.sample(&mut self.rng)].clone())
+    }
+}
+
+/// Largest number generated by [`Selector::with_numbers`].
+pub const DEFAULT_NUMBER_MAX: u64 = 9999;
+
+/// Builder for stacking word selectors, so that they don't have to be
+/// boxed and wrapped by hand:
+///
+/// ```
+/// use toipe::textgen::{Selector, WordSelector};
+///
+/// let mut selector = Selector::raw(["one", "two", "three"])?
+///     .with_numbers(0.1)
+///     .with_punctuation(0.2)
+///     .no_repeat(2);
+/// let words = selector.new_words(10)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// Every selector gets a generator forked from the one of the builder,
+/// see [`Selector::raw_with_rng`] for reproducible words.
+pub struct Selector {
+    selector: Box<dyn WordSelector>,
+    /// conventions of the numbers and punctuation added after it is set
+    locale: Option<Locale>,
+    /// capitalizes the punctuation added after it is set
+    language: Option<Language>,
+    rng: SelectorRng,
+}
+
+impl Selector {
+    /// Starts with random words from `words`, see [`RawWordSelector`].
+    pub fn raw<I, S>(words: I) -> Result<Self, io::Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::raw_with_rng(words, selector_rng(None))
+    }
+
+    /// Same as [`Selector::raw`], with all generators forked from `rng`.
+    pub fn raw_with_rng<I, S>(words: I, mut rng: SelectorRng) -> Result<Self, io::Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let raw = RawWordSelector::from_iter(
+            words.into_iter().map(|word| Ok(word.into())),
+            fork_rng(&mut rng),
+        )?;
+        Ok(Self::from_word_selector(Box::new(raw), rng))
+    }
+
+    /// Starts with the words of another selector.
+    pub fn from_word_selector(selector: Box<dyn WordSelector>, rng: SelectorRng) -> Self {
+        Self {
+            selector,
+            locale: None,
+            language: None,
+            rng,
+        }
+    }
+
+    /// Writes the numbers and quotes added from now on as is usual in
+    /// `locale`, see [`NumberWordSelector::with_locale`] and
+    /// [`PunctuatedWordSelector::with_locale`].
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// Capitalizes the sentences of the punctuation added from now on as
+    /// is usual in `language`, see [`PunctuatedWordSelector::with_language`].
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    /// Mixes in words from other generators by weight, see
+    /// [`CompositeWordSelector::from_mix`].
+    pub fn with_mix(mut self, mix: &Mix, number_max: u64) -> Result<Self, io::Error> {
+        let rng = fork_rng(&mut self.rng);
+        self.selector = Box::new(CompositeWordSelector::from_mix(
+            mix,
+            self.selector,
+            number_max,
+            self.locale.as_ref(),
+            rng,
+        )?);
+        Ok(self)
+    }
+
+    /// Replaces words with numbers up to [`DEFAULT_NUMBER_MAX`] with the
+    /// given chance.
+    pub fn with_numbers(self, number_chance: f64) -> Self {
+        self.with_numbers_up_to(number_chance, DEFAULT_NUMBER_MAX)
+    }
+
+    /// Replaces words with numbers up to `number_max` with the given
+    /// chance.
+    ///
+    /// Panics if the chance is not between 0 and 1, like
+    /// [`Rng::gen_bool`].
+    pub fn with_numbers_up_to(self, number_chance: f64, number_max: u64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&number_chance),
+            "chance of numbers must be between 0 and 1"
+        );
+        let mix = Mix(vec![
+            (MixSource::Words, 1.0 - number_chance),
+            (MixSource::Numbers, number_chance),
+        ]);
+        self.with_mix(&mix, number_max)
+            .expect("weights of words and numbers add up to 1")
+    }
+
+    /// Replaces words with numbers from the templates with the given
+    /// chance, see [`NumberTemplate`].
+    ///
+    /// Panics if the chance is not between 0 and 1, like
+    /// [`Rng::gen_bool`].
+    pub fn with_formatted_numbers(
+        mut self,
+        number_chance: f64,
+        templates: Vec<NumberTemplate>,
+    ) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&number_chance),
+            "chance of numbers must be between 0 and 1"
+        );
+        let numbers = NumberWordSelector::new(DEFAULT_NUMBER_MAX, fork_rng(&mut self.rng))
+            .with_templates(templates);
+        let rng = fork_rng(&mut self.rng);
+        self.selector = Box::new(
+            CompositeWordSelector::new(
+                vec![
+                    (self.selector, 1.0 - number_chance),
+                    (Box::new(numbers), number_chance),
+                ],
+                rng,
+            )
+            .expect("weights of words and numbers add up to 1"),
+        );
+        self
+    }
+
+    /// Adds punctuation with the given chance, see
+    /// [`PunctuatedWordSelector`].
+    pub fn with_punctuation(self, punctuation_chance: f64) -> Self {
+        self.with_limited_punctuation(punctuation_chance, 0, None)
+    }
+
+    /// Adds punctuation with the given chance, leaving at least
+    /// `cooldown` words between punctuated words and putting at most
+    /// `max_marks` marks on a word, see [`PunctuatedWordSelector`].
+    pub fn with_limited_punctuation(
+        mut self,
+        punctuation_chance: f64,
+        cooldown: usize,
+        max_marks: Option<usize>,
+    ) -> Self {
+        let rng = fork_rng(&mut self.rng);
+        let mut selector =
+            PunctuatedWordSelector::from_word_selector(self.selector, punctuation_chance, rng)
+                .with_cooldown(cooldown);
+        if let Some(max_marks) = max_marks {
+            selector = selector.with_max_marks(max_marks);
+        }
+        if let Some(locale) = &self.locale {
+            selector = selector.with_locale(locale);
+        }
+        if let Some(language) = &self.language {
+            selector = selector.with_language(language);
+        }
+        self.selector = Box::new(selector);
+        self
+    }
+
+    /// Does not repeat any of the last `window` words, see
+    /// [`NoRepeatWordSelector`].
+    pub fn no_repeat(mut self, window: usize) -> Self {
+        self.selector = Box::new(NoRepeatWordSelector::from_word_selector(
+            self.selector,
+            window,
+        ));
+        self
+    }
+
+    /// Rerolls words that `filter` does not accept, see
+    /// [`FilteredWordSelector`].
+    pub fn filtered<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&str) -> bool + 'static,
+    {
+        self.selector = Box::new(FilteredWordSelector::from_word_selector(
+            self.selector,
+            Box::new(filter),
+        ));
+        self
+    }
+
+    /// The stacked selectors.
+    pub fn build(self) -> Box<dyn WordSelector> {
+        self.selector
+    }
+}
+
+impl WordSelector for Selector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        self.selector.new_word()
+    }
+
+    fn finish_words(&mut self, words: &mut [String]) {
+        self.selector.finish_words(words)
+    }
+}
+
+/// Letters that are used least often in English, and are usually the
+/// hardest to reach.
+const RARE_LETTERS: [char; 6] = ['j', 'k', 'q', 'v', 'x', 'z'];
+
+/// Estimated difficulty of a text.
+///
+/// Used to compare typing speeds across texts of different difficulty.
+/// See [`TextDifficulty::score`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextDifficulty {
+    /// average number of letters per word, leaving out punctuation and
+    /// digits, which are counted in `punctuation_density` instead
+    pub avg_word_length: f64,
+    /// fraction of letters that are one of `j`, `k`, `q`, `v`, `x` or `z`
+    pub rare_letter_ratio: f64,
+    /// fraction of (non-whitespace) chars that are neither letters nor
+    /// digits
+    pub punctuation_density: f64,
+}
 
-        let will_punctuate = rng.gen_bool(self.punctuation_chance);
-        if will_punctuate || self.next_is_capital {
-            let mut chars: VecDeque<char> = word.chars().collect();
-            if self.next_is_capital {
-                // some unicode chars map to multiple chars when uppercased.
-                for c in chars
-                    .pop_front()
-                    .expect("got empty word")
-                    .to_uppercase()
-                    .rev()
-                {
-                    chars.push_front(c)
+impl TextDifficulty {
+    /// Average word length of ordinary English text.
+    ///
+    /// Same as the length of a "word" used for calculating WPM.
+    pub const REFERENCE_WORD_LENGTH: f64 = 5.0;
+    /// Fraction of rare letters in ordinary English text.
+    pub const REFERENCE_RARE_LETTER_RATIO: f64 = 0.022;
+
+    /// Estimates the difficulty of the given words.
+    pub fn from_words(words: &[String]) -> Self {
+        let mut num_chars = 0usize;
+        let mut num_letters = 0usize;
+        let mut num_rare_letters = 0usize;
+        let mut num_punctuation = 0usize;
+
+        for c in words.iter().flat_map(|word| word.chars()) {
+            if c.is_whitespace() {
+                continue;
+            }
+            num_chars += 1;
+            if c.is_alphabetic() {
+                num_letters += 1;
+                if c.to_lowercase().any(|c| RARE_LETTERS.contains(&c)) {
+                    num_rare_letters += 1;
                 }
-                self.next_is_capital = false;
-            }
-            if will_punctuate {
-                match PUNCTUATION
-                    .choose(&mut rng)
-                    .expect("only returns none if the slice is empty")
-                {
-                    PunctuationType::Capitaizing(c) => {
-                        self.next_is_capital = true;
-                        chars.push_back(*c)
-                    }
-                    PunctuationType::Ending(c) => chars.push_back(*c),
-                    PunctuationType::Starting(c) => chars.push_front(*c),
-                    PunctuationType::Surrounding(opening, closing) => {
-                        chars.push_front(*opening);
-                        chars.push_back(*closing);
+            } else if !c.is_numeric() {
+                num_punctuation += 1;
+            }
+        }
+
+        let ratio = |part: usize, total: usize| {
+            if total == 0 {
+                0.0
+            } else {
+                part as f64 / total as f64
+            }
+        };
+
+        Self {
+            avg_word_length: ratio(num_letters, words.len()),
+            rare_letter_ratio: ratio(num_rare_letters, num_letters),
+            punctuation_density: ratio(num_punctuation, num_chars),
+        }
+    }
+
+    /// A single number summarizing the difficulty.
+    ///
+    /// Ordinary English text without punctuation scores around 1.0, and
+    /// harder texts score higher. Longer words, rare letters and
+    /// punctuation all increase the score. Never goes below 0.1.
+    ///
+    /// This is a rough heuristic, meant for comparing texts with each
+    /// other rather than as an absolute measure.
+    pub fn score(&self) -> f64 {
+        let word_length =
+            (self.avg_word_length - Self::REFERENCE_WORD_LENGTH) / Self::REFERENCE_WORD_LENGTH;
+        let rare_letters = self.rare_letter_ratio - Self::REFERENCE_RARE_LETTER_RATIO;
+
+        (1.0 + 0.5 * word_length + 5.0 * rare_letters + 3.0 * self.punctuation_density).max(0.1)
+    }
+}
+
+/// Returns the word with a deliberate typo, for proofreading practice.
+///
+/// The typo swaps two adjacent ASCII letters or replaces one with another
+/// letter, so the word keeps its length. Returns `None` if the word has
+/// fewer than two ASCII letters.
+pub fn with_typo(word: &str, rng: &mut impl Rng) -> Option<String> {
+    let mut chars: Vec<char> = word.chars().collect();
+    let letters: Vec<usize> = (0..chars.len())
+        .filter(|i| chars[*i].is_ascii_alphabetic())
+        .collect();
+    if letters.len() < 2 {
+        return None;
+    }
+
+    let swappable: Vec<usize> = letters
+        .iter()
+        .copied()
+        .filter(|i| {
+            chars
+                .get(i + 1)
+                .is_some_and(|next| next.is_ascii_alphabetic() && *next != chars[*i])
+        })
+        .collect();
+
+    if !swappable.is_empty() && rng.gen_bool(0.5) {
+        let i = *swappable.choose(rng).expect("not empty");
+        chars.swap(i, i + 1);
+    } else {
+        let i = *letters.choose(rng).expect("not empty");
+        let original = chars[i];
+        let mut replacement = original;
+        while replacement.eq_ignore_ascii_case(&original) {
+            replacement = rng.gen_range(b'a'..=b'z') as char;
+        }
+        chars[i] = if original.is_ascii_uppercase() {
+            replacement.to_ascii_uppercase()
+        } else {
+            replacement
+        };
+    }
+
+    Some(chars.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typos() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for word in ["the", "Hello,", "aaa", "it's"] {
+            for _ in 0..100 {
+                let typo = with_typo(word, &mut rng).unwrap();
+                assert_ne!(typo, word);
+                assert_eq!(typo.len(), word.len());
+                assert!(typo
+                    .chars()
+                    .zip(word.chars())
+                    .all(|(a, b)| a == b || (a.is_ascii_alphabetic() && b.is_ascii_alphabetic())));
+            }
+        }
+        assert_eq!(with_typo("a", &mut rng), None);
+        assert_eq!(with_typo("42", &mut rng), None);
+    }
+
+    #[test]
+    fn mixes() {
+        assert_eq!(
+            "words:7, numbers:2,symbols:0.5".parse::<Mix>(),
+            Ok(Mix(vec![
+                (MixSource::Words, 7.0),
+                (MixSource::Numbers, 2.0),
+                (MixSource::Symbols, 0.5),
+            ]))
+        );
+        assert!("words".parse::<Mix>().is_err());
+        assert!("letters:1".parse::<Mix>().is_err());
+        assert!("words:-1".parse::<Mix>().is_err());
+        assert!("words:1,words:2".parse::<Mix>().is_err());
+        assert!("words:0,numbers:0".parse::<Mix>().is_err());
+    }
+
+    #[test]
+    fn composite_selector_uses_weights() {
+        let words =
+            ShuffledDeckWordSelector::from_words(vec!["word".to_string()], selector_rng(None));
+        let mix: Mix = "words:1,symbols:0,numbers:1".parse().unwrap();
+        let mut selector =
+            CompositeWordSelector::from_mix(&mix, Box::new(words), 10, None, selector_rng(None))
+                .unwrap();
+
+        let words = selector.new_words(1000).unwrap();
+        let num_words = words.iter().filter(|w| *w == "word").count();
+        assert!(words
+            .iter()
+            .all(|w| w == "word" || w.parse::<u64>().is_ok_and(|n| n < 10)));
+        assert!((300..700).contains(&num_words));
+    }
+
+    #[test]
+    fn seeded_selectors_are_deterministic() {
+        let words = || {
+            let mut rng = selector_rng(Some(42));
+            let deck = ShuffledDeckWordSelector::from_words(
+                ["a", "b", "c", "d", "e"]
+                    .iter()
+                    .map(|w| w.to_string())
+                    .collect(),
+                fork_rng(&mut rng),
+            );
+            let mix: Mix = "words:2,numbers:1,symbols:1".parse().unwrap();
+            let mixed = CompositeWordSelector::from_mix(
+                &mix,
+                Box::new(deck),
+                100,
+                None,
+                fork_rng(&mut rng),
+            )
+            .unwrap();
+            PunctuatedWordSelector::from_word_selector(Box::new(mixed), 0.5, fork_rng(&mut rng))
+                .new_words(50)
+                .unwrap()
+        };
+        assert_eq!(words(), words());
+    }
+
+    #[test]
+    fn estimates_difficulty() {
+        let words = |text: &str| text.split(' ').map(String::from).collect::<Vec<_>>();
+
+        let plain = TextDifficulty::from_words(&words("hello world"));
+        assert_eq!(plain.avg_word_length, 5.0);
+        assert_eq!(plain.rare_letter_ratio, 0.0);
+        assert_eq!(plain.punctuation_density, 0.0);
+
+        // punctuation does not make words longer
+        let punctuated = TextDifficulty::from_words(&words("hello, world."));
+        assert_eq!(punctuated.avg_word_length, 5.0);
+        assert_eq!(punctuated.punctuation_density, 2.0 / 12.0);
+        assert!(punctuated.score() > plain.score());
+
+        let rare = TextDifficulty::from_words(&words("Jazz quiz"));
+        assert_eq!(rare.avg_word_length, 4.0);
+        assert_eq!(rare.rare_letter_ratio, 5.0 / 8.0);
+        assert!(rare.score() > plain.score());
+
+        let empty = TextDifficulty::from_words(&[]);
+        assert_eq!(empty.avg_word_length, 0.0);
+        assert!(empty.score() < plain.score());
+    }
+
+    #[test]
+    fn no_repeat_within_window() {
+        let words: Vec<String> = (0..10).map(|i| format!("w{}", i)).collect();
+        for seed in 0..10 {
+            let window = 5;
+            let generated = Selector::raw_with_rng(words.iter(), selector_rng(Some(seed)))
+                .unwrap()
+                .no_repeat(window)
+                .new_words(500)
+                .unwrap();
+            for (i, word) in generated.iter().enumerate() {
+                let recent = &generated[i.saturating_sub(window)..i];
+                assert!(!recent.contains(word), "{} repeats in {:?}", word, recent);
+            }
+        }
+    }
+
+    #[test]
+    fn punctuation_is_balanced() {
+        for seed in 0..20 {
+            let mut selector = Selector::raw_with_rng(["word"], selector_rng(Some(seed)))
+                .unwrap()
+                .with_punctuation(1.0);
+            for num_words in [1, 2, 5, 30] {
+                let text = selector.new_words(num_words).unwrap().join(" ");
+                for (opening, closing) in [('(', ')'), ('{', '}'), ('[', ']'), ('<', '>')] {
+                    let mut depth = 0;
+                    for c in text.chars() {
+                        if c == opening {
+                            depth += 1;
+                        } else if c == closing {
+                            depth -= 1;
+                            assert!(depth >= 0, "{} closes too early in {:?}", closing, text);
+                        }
                     }
+                    assert_eq!(depth, 0, "{} is not closed in {:?}", opening, text);
                 }
             }
-            word = chars.into_iter().collect();
         }
-        Ok(word)
+    }
+
+    #[test]
+    fn locales_change_numbers_and_quotes() {
+        let locale: Locale = "de".parse().unwrap();
+        let words = Selector::raw_with_rng(["word"], selector_rng(Some(1)))
+            .unwrap()
+            .with_locale(locale)
+            .with_mix(&"numbers:1".parse().unwrap(), 100_000)
+            .unwrap()
+            .new_words(50)
+            .unwrap();
+        assert!(words.iter().any(|word| word.contains(',')), "{:?}", words);
+        for word in words.iter().filter(|word| word.len() > 4) {
+            assert!(word.contains(['.', ',']), "{:?} is not grouped", word);
+        }
+
+        let text = Selector::raw_with_rng(["word"], selector_rng(Some(1)))
+            .unwrap()
+            .with_locale("fr".parse().unwrap())
+            .with_punctuation(1.0)
+            .new_words(200)
+            .unwrap()
+            .join(" ");
+        assert!(text.contains('«') && !text.contains('"'), "{:?}", text);
+    }
+
+    #[test]
+    fn punctuation_follows_sentences() {
+        for seed in 0..20 {
+            let words = Selector::raw_with_rng(["word"], selector_rng(Some(seed)))
+                .unwrap()
+                .with_punctuation(1.0)
+                .new_words(100)
+                .unwrap();
+            let mut sentence_words = 0;
+            let mut after_ending = false;
+            for word in &words {
+                let ends_sentence = word.contains(['.', '!', '?']);
+                let ends = ends_sentence || word.contains([',', ';']);
+                if sentence_words == 0 {
+                    assert!(!ends, "{:?} starts a sentence in {:?}", word, words);
+                    assert!(!word.starts_with(['@', '#', '~']), "{:?}", words);
+                }
+                assert!(!(ends && after_ending), "{:?} in {:?}", word, words);
+                if ends_sentence {
+                    assert!(sentence_words + 1 >= PunctuatedWordSelector::MIN_SENTENCE_WORDS);
+                    sentence_words = 0;
+                } else {
+                    sentence_words += 1;
+                }
+                after_ending = ends;
+            }
+        }
+    }
+
+    #[test]
+    fn punctuation_is_spread_out() {
+        for seed in 0..20 {
+            let words = Selector::raw_with_rng(["word"], selector_rng(Some(seed)))
+                .unwrap()
+                .with_limited_punctuation(1.0, 2, Some(1))
+                .new_words(100)
+                .unwrap();
+            // only the last word can have more, closing the marks left open
+            for word in &words[..words.len() - 1] {
+                let marks = word.chars().filter(|c| !c.is_alphabetic()).count();
+                assert!(marks <= 1, "{:?} in {:?}", word, words);
+            }
+            let punctuated: Vec<usize> = words
+                .iter()
+                .enumerate()
+                .filter(|(_, word)| word.starts_with(|c: char| !c.is_alphabetic()))
+                .map(|(i, _)| i)
+                .collect();
+            assert!(
+                punctuated.windows(2).all(|w| w[1] - w[0] > 2),
+                "{:?}",
+                words
+            );
+        }
+    }
+
+    #[test]
+    fn number_templates() {
+        let mut rng = selector_rng(Some(7));
+        let phone: NumberTemplate = "(###) ###-####".parse().unwrap();
+        let thousands: NumberTemplate = "N,###".parse().unwrap();
+        for _ in 0..100 {
+            let number = phone.generate(&mut rng);
+            assert_eq!(number.len(), 14);
+            assert!(number.starts_with('(') && number[4..].starts_with(") "));
+            assert!(!thousands.generate(&mut rng).starts_with('0'));
+        }
+        assert!("abc".parse::<NumberTemplate>().is_err());
+
+        let mut selector = NumberWordSelector::new(10, rng).with_templates(vec![thousands]);
+        assert_eq!(selector.new_word().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn weighted_selector_prefers_heavy_words() {
+        let words = vec!["light".to_string(), "heavy".to_string(), "none".to_string()];
+        let weight = |word: &str| match word {
+            "heavy" => 9.0,
+            "light" => 1.0,
+            _ => 0.0,
+        };
+        let mut selector =
+            WeightedWordSelector::from_words(words, weight, selector_rng(Some(1))).unwrap();
+        let words = selector.new_words(1000).unwrap();
+        let heavy = words.iter().filter(|word| *word == "heavy").count();
+        assert!(heavy > 800, "{} heavy words", heavy);
+        assert!(!words.iter().any(|word| word == "none"));
+
+        assert!(WeightedWordSelector::from_words(Vec::new(), weight, selector_rng(None)).is_err());
+    }
+
+    #[test]
+    fn filtered_selector_rerolls_words() {
+        let mut selector = Selector::raw_with_rng(["a", "bb", "cc", "d"], selector_rng(Some(3)))
+            .unwrap()
+            .filtered(|word| word.len() == 1);
+        let words = selector.new_words(50).unwrap();
+        assert!(words.iter().all(|word| word == "a" || word == "d"));
+    }
+
+    #[test]
+    fn shuffled_deck_covers_all_words() {
+        let words: Vec<String> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let mut selector = ShuffledDeckWordSelector::from_words(words.clone(), selector_rng(None));
+
+        // every deck contains each word exactly once
+        for _ in 0..3 {
+            let mut drawn = selector.new_words(words.len()).unwrap();
+            drawn.sort();
+            assert_eq!(drawn, words);
+        }
     }
 }
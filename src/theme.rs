@@ -0,0 +1,465 @@
+//! Colors of the terminal UI.
+//!
+//! Each part of the UI has a role (such as [`Theme::correct`] for chars
+//! typed correctly) whose color can be changed in the `[theme]` section
+//! of the config file (see [`crate::config::ToipeConfig::load`]):
+//!
+//! ```toml
+//! [theme]
+//! correct = "cyan"
+//! wrong = "#ff8800"
+//! untyped = "none"
+//! gradient = true
+//! ```
+//!
+//! RGB colors are shown as they are in terminals with 24-bit colors
+//! (`COLORTERM=truecolor`), and as the closest color of the palette in
+//! others, see [`Theme::for_depth`].
+
+use std::{
+    env, fmt,
+    io::{Error, ErrorKind},
+    str::FromStr,
+};
+
+use crate::ansi::color::{self, Color};
+
+/// Names of the colors of the terminal, by their ANSI value.
+const COLOR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "light-black",
+    "light-red",
+    "light-green",
+    "light-yellow",
+    "light-blue",
+    "light-magenta",
+    "light-cyan",
+    "light-white",
+];
+
+/// Colors of the 16 named ANSI colors, as in xterm.
+pub const ANSI_COLORS: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+/// Levels of each component in the 6×6×6 color cube of the 256-color
+/// palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Color that the default color of the terminal is taken to be when
+/// blended, see [`ThemeColor::blend`].
+const DEFAULT_RGB: [u8; 3] = [128, 128, 128];
+
+/// Number of colors a terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ColorDepth {
+    /// the 16 named colors
+    Ansi16,
+    /// the 256-color palette
+    Ansi256,
+    /// any 24-bit RGB color
+    #[default]
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Color depth of the terminal toipe runs in, from `$COLORTERM` and
+    /// `$TERM`.
+    pub fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default();
+        Self::from_env(&var("COLORTERM"), &var("TERM"))
+    }
+
+    fn from_env(colorterm: &str, term: &str) -> Self {
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+/// Color of a role of a [`Theme`].
+///
+/// Parsed from a color name such as `light-green`, an RGB color such as
+/// `#00ff00` or `none`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeColor {
+    /// the default color of the terminal, for monochrome terminals
+    None,
+    /// one of [`COLOR_NAMES`], or beyond them a color of the 256-color
+    /// palette
+    Ansi(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color for ThemeColor {
+    fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ThemeColor::None => Ok(()),
+            ThemeColor::Ansi(value) => color::AnsiValue(value).write_fg(f),
+            ThemeColor::Rgb(r, g, b) => color::Rgb(r, g, b).write_fg(f),
+        }
+    }
+
+    fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ThemeColor::None => Ok(()),
+            ThemeColor::Ansi(value) => color::AnsiValue(value).write_bg(f),
+            ThemeColor::Rgb(r, g, b) => color::Rgb(r, g, b).write_bg(f),
+        }
+    }
+}
+
+impl ThemeColor {
+    /// RGB value of the color, approximate for the named colors whose
+    /// value depends on the terminal.
+    pub fn to_rgb(self) -> Option<[u8; 3]> {
+        match self {
+            ThemeColor::None => None,
+            ThemeColor::Rgb(r, g, b) => Some([r, g, b]),
+            ThemeColor::Ansi(value @ 0..=15) => Some(ANSI_COLORS[value as usize]),
+            ThemeColor::Ansi(value @ 16..=231) => {
+                let cube = value - 16;
+                Some([cube / 36, cube / 6 % 6, cube % 6].map(|level| CUBE_LEVELS[level as usize]))
+            }
+            ThemeColor::Ansi(value) => Some([8 + (value - 232) * 10; 3]),
+        }
+    }
+
+    /// The closest color that a terminal with `depth` can show.
+    pub fn for_depth(self, depth: ColorDepth) -> Self {
+        let Some(rgb) = self.to_rgb() else {
+            return self;
+        };
+        match (self, depth) {
+            (_, ColorDepth::TrueColor) | (ThemeColor::Ansi(0..=15), _) => self,
+            (ThemeColor::Ansi(16..), ColorDepth::Ansi256) => self,
+            (_, ColorDepth::Ansi256) => {
+                // the closest of the color cube and the grays
+                let cube = rgb.map(|c| closest(&CUBE_LEVELS, c));
+                let cube = ThemeColor::Ansi(16 + 36 * cube[0] + 6 * cube[1] + cube[2]);
+                let gray = (rgb.iter().map(|c| *c as u16).sum::<u16>() / 3).clamp(8, 238);
+                let gray = ThemeColor::Ansi(232 + ((gray - 8) / 10).min(23) as u8);
+                [cube, gray]
+                    .into_iter()
+                    .min_by_key(|color| distance(color.to_rgb().unwrap_or_default(), rgb))
+                    .unwrap_or(cube)
+            }
+            (_, ColorDepth::Ansi16) => ThemeColor::Ansi(
+                (0..16)
+                    .min_by_key(|value| distance(ANSI_COLORS[*value as usize], rgb))
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// The color `t` of the way from this color to `other`, from 0 to 1,
+    /// as shown in a terminal with `depth`.
+    pub fn blend(self, other: ThemeColor, t: f64, depth: ColorDepth) -> Self {
+        let from = self.to_rgb().unwrap_or(DEFAULT_RGB);
+        let to = other.to_rgb().unwrap_or(DEFAULT_RGB);
+        let t = t.clamp(0.0, 1.0);
+        let mix = |i: usize| (from[i] as f64 + (to[i] as f64 - from[i] as f64) * t).round() as u8;
+        ThemeColor::Rgb(mix(0), mix(1), mix(2)).for_depth(depth)
+    }
+}
+
+/// Index of the value in `levels` closest to `c`.
+fn closest(levels: &[u8], c: u8) -> u8 {
+    (0..levels.len())
+        .min_by_key(|i| levels[*i].abs_diff(c))
+        .unwrap_or_default() as u8
+}
+
+/// Squared distance between two RGB colors.
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a.abs_diff(b) as u32).pow(2))
+        .sum()
+}
+
+impl FromStr for ThemeColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "none" {
+            return Ok(ThemeColor::None);
+        }
+        if let Some(value) = COLOR_NAMES.iter().position(|&name| name == s) {
+            return Ok(ThemeColor::Ansi(value as u8));
+        }
+
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.is_ascii());
+        let component =
+            |i: usize| hex.and_then(|hex| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok());
+        match (component(0), component(1), component(2)) {
+            (Some(r), Some(g), Some(b)) => Ok(ThemeColor::Rgb(r, g, b)),
+            _ => Err(format!(
+                "invalid color `{}`, expected `none`, `#rrggbb` or one of: {}",
+                s,
+                COLOR_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Colors of the roles of the terminal UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// chars typed correctly
+    pub correct: ThemeColor,
+    /// chars typed correctly after a mistake
+    pub corrected: ThemeColor,
+    /// chars typed wrong
+    pub wrong: ThemeColor,
+    /// chars not typed yet
+    pub untyped: ThemeColor,
+    /// the char to be typed next
+    pub cursor: ThemeColor,
+    /// keys in hints and the position in the minimap
+    pub hint: ThemeColor,
+    /// speeds on the results and stats pages
+    pub results: ThemeColor,
+    /// accuracy on the results page
+    pub accuracy: ThemeColor,
+    pub warning: ThemeColor,
+    /// whether the chars ahead of the cursor fade from the color of
+    /// correct chars to that of untyped chars, in terminals with at least
+    /// 256 colors
+    pub gradient: bool,
+    /// colors the terminal can show, see [`Theme::for_depth`]
+    pub depth: ColorDepth,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let ansi = |name| ThemeColor::from_str(name).expect("valid color name");
+        Self {
+            correct: ansi("light-green"),
+            corrected: ansi("red"),
+            wrong: ansi("light-red"),
+            untyped: ThemeColor::None,
+            cursor: ansi("magenta"),
+            hint: ansi("blue"),
+            results: ansi("green"),
+            accuracy: ansi("blue"),
+            warning: ansi("yellow"),
+            gradient: false,
+            depth: ColorDepth::default(),
+        }
+    }
+}
+
+impl Theme {
+    /// Bright colors only, for `--low-vision`.
+    pub fn high_contrast() -> Self {
+        let ansi = |name| ThemeColor::from_str(name).expect("valid color name");
+        Self {
+            correct: ansi("light-green"),
+            corrected: ansi("light-yellow"),
+            wrong: ansi("light-red"),
+            untyped: ansi("light-white"),
+            cursor: ansi("light-magenta"),
+            hint: ansi("light-cyan"),
+            results: ansi("light-green"),
+            accuracy: ansi("light-cyan"),
+            warning: ansi("light-yellow"),
+            gradient: false,
+            depth: ColorDepth::default(),
+        }
+    }
+
+    /// This theme with its colors replaced by the closest ones a terminal
+    /// with `depth` can show.
+    pub fn for_depth(self, depth: ColorDepth) -> Self {
+        Self {
+            correct: self.correct.for_depth(depth),
+            corrected: self.corrected.for_depth(depth),
+            wrong: self.wrong.for_depth(depth),
+            untyped: self.untyped.for_depth(depth),
+            cursor: self.cursor.for_depth(depth),
+            hint: self.hint.for_depth(depth),
+            results: self.results.for_depth(depth),
+            accuracy: self.accuracy.for_depth(depth),
+            warning: self.warning.for_depth(depth),
+            gradient: self.gradient,
+            depth,
+        }
+    }
+
+    /// Whether the chars ahead of the cursor are shown fading, see
+    /// [`Theme::gradient`]. With 16 colors, the fade would not be
+    /// smooth.
+    pub fn shows_gradient(&self) -> bool {
+        self.gradient && self.depth >= ColorDepth::Ansi256
+    }
+
+    /// This theme with the colors of the roles in `table` (the `[theme]`
+    /// section of the config file) changed.
+    pub fn with_table(self, table: &toml::value::Table) -> Result<Self, Error> {
+        let mut theme = self;
+        for (role, value) in table {
+            if role == "gradient" {
+                theme.gradient = value.as_bool().ok_or_else(|| {
+                    invalid(format!(
+                        "theme option `gradient` must be a boolean, not {}",
+                        value.type_str()
+                    ))
+                })?;
+                continue;
+            }
+            let color = match role.as_str() {
+                "correct" => &mut theme.correct,
+                "corrected" => &mut theme.corrected,
+                "wrong" => &mut theme.wrong,
+                "untyped" => &mut theme.untyped,
+                "cursor" => &mut theme.cursor,
+                "hint" => &mut theme.hint,
+                "results" => &mut theme.results,
+                "accuracy" => &mut theme.accuracy,
+                "warning" => &mut theme.warning,
+                _ => return Err(invalid(format!("unknown theme role `{}`", role))),
+            };
+            *color = value
+                .as_str()
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "theme role `{}` must be a string, not {}",
+                        role,
+                        value.type_str()
+                    ))
+                })?
+                .parse()
+                .map_err(invalid)?;
+        }
+        Ok(theme)
+    }
+}
+
+fn invalid(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!("none".parse(), Ok(ThemeColor::None));
+        assert_eq!("light-green".parse(), Ok(ThemeColor::Ansi(10)));
+        assert_eq!("#ff8000".parse(), Ok(ThemeColor::Rgb(255, 128, 0)));
+        for invalid in ["", "greenish", "#ff80", "#gg0000", "#ff80é"] {
+            assert!(invalid.parse::<ThemeColor>().is_err(), "{}", invalid);
+        }
+
+        // same escape codes as the named colors
+        assert_eq!(
+            color::Fg(ThemeColor::Ansi(10)).to_string(),
+            color::Fg(color::LightGreen).to_string()
+        );
+        assert_eq!(color::Fg(ThemeColor::None).to_string(), "");
+    }
+
+    #[test]
+    fn degrades_colors() {
+        let orange = ThemeColor::Rgb(255, 135, 0);
+        assert_eq!(orange.for_depth(ColorDepth::TrueColor), orange);
+        assert_eq!(orange.for_depth(ColorDepth::Ansi256), ThemeColor::Ansi(208));
+        assert_eq!(
+            ThemeColor::Rgb(100, 100, 100).for_depth(ColorDepth::Ansi256),
+            ThemeColor::Ansi(241)
+        );
+        assert_eq!(orange.for_depth(ColorDepth::Ansi16), ThemeColor::Ansi(3));
+        assert_eq!(
+            ThemeColor::Ansi(208).for_depth(ColorDepth::Ansi16),
+            ThemeColor::Ansi(3)
+        );
+        assert_eq!(
+            ThemeColor::None.for_depth(ColorDepth::Ansi16),
+            ThemeColor::None
+        );
+
+        assert_eq!(
+            ColorDepth::from_env("truecolor", "xterm"),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_env("", "xterm-256color"),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(ColorDepth::from_env("", "linux"), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn blends_colors() {
+        let black = ThemeColor::Rgb(0, 0, 0);
+        let white = ThemeColor::Ansi(15);
+        assert_eq!(
+            black.blend(white, 0.5, ColorDepth::TrueColor),
+            ThemeColor::Rgb(128, 128, 128)
+        );
+        assert_eq!(
+            black.blend(white, 2.0, ColorDepth::TrueColor),
+            ThemeColor::Rgb(255, 255, 255)
+        );
+        assert_eq!(
+            black.blend(ThemeColor::None, 1.0, ColorDepth::Ansi256),
+            ThemeColor::Ansi(244)
+        );
+    }
+
+    #[test]
+    fn themes_from_tables() {
+        let table =
+            toml::from_str::<toml::value::Table>("correct = 'cyan'\nuntyped = '#000000'").unwrap();
+        let theme = Theme::default().with_table(&table).unwrap();
+        assert_eq!(theme.correct, ThemeColor::Ansi(6));
+        assert_eq!(theme.untyped, ThemeColor::Rgb(0, 0, 0));
+        assert_eq!(theme.wrong, Theme::default().wrong);
+
+        let table = toml::from_str::<toml::value::Table>("gradient = true").unwrap();
+        let theme = Theme::default().with_table(&table).unwrap();
+        assert!(theme.gradient);
+        assert!(!theme.for_depth(ColorDepth::Ansi16).shows_gradient());
+
+        for invalid in [
+            "nope = 'red'",
+            "correct = 1",
+            "correct = 'reddish'",
+            "gradient = 'yes'",
+        ] {
+            assert!(Theme::default()
+                .with_table(&toml::from_str::<toml::value::Table>(invalid).unwrap())
+                .is_err());
+        }
+    }
+}
@@ -1,16 +1,16 @@
 use core::fmt;
-use std::{collections::HashMap, io};
+use std::{collections::BTreeMap, io};
 
 #[derive(Clone)]
 struct Node {
-    children: HashMap<String, usize>,
+    children: BTreeMap<String, usize>,
     count: u64,
 }
 
 impl Node {
     fn new() -> Self {
         Node {
-            children: HashMap::<String, usize>::new(),
+            children: BTreeMap::<String, usize>::new(),
             count: 0,
         }
     }
@@ -20,6 +20,12 @@ pub struct Trie {
     nodes: Vec<Node>,
 }
 
+impl Default for Trie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Trie {
     pub fn new() -> Self {
         Self {
@@ -37,7 +43,7 @@ impl Trie {
             .ok_or(TrieErr::missing_node(index))
     }
 
-    fn add_node<'a>(&mut self, parent_index: usize, prefix: &'a str) -> Result<usize, TrieErr> {
+    fn add_node(&mut self, parent_index: usize, prefix: &str) -> Result<usize, TrieErr> {
         let index = self.nodes.len();
         let parent = self.get_mut_node(parent_index)?;
 
@@ -50,7 +56,7 @@ impl Trie {
         Ok(index)
     }
 
-    pub fn insert<'a>(&mut self, word: &'a str) -> Result<&mut Self, TrieErr> {
+    pub fn insert(&mut self, word: &str) -> Result<&mut Self, TrieErr> {
         let mut node_index = 0usize;
 
         for char in word.chars() {
@@ -92,7 +98,7 @@ impl Trie {
         while let Some(index) = stack.pop() {
             if index != 0 && new_nodes[index].children.len() == 1 {
                 let cprefix = new_nodes[index].children.keys().nth(0).unwrap().clone();
-                let cindex = new_nodes[index].children.get(&cprefix).unwrap().clone();
+                let cindex = *new_nodes[index].children.get(&cprefix).unwrap();
                 let child = &self.nodes[cindex];
 
                 if new_nodes[index].count == child.count {
@@ -118,10 +124,9 @@ impl Trie {
 
             for (cprefix, cindex) in node.children.iter() {
                 let new_index = new_nodes.len();
-                new_nodes[index]
-                    .children
-                    .get_mut(cprefix)
-                    .map(|valref| *valref = new_index);
+                if let Some(valref) = new_nodes[index].children.get_mut(cprefix) {
+                    *valref = new_index;
+                }
 
                 new_nodes.push(self.nodes[*cindex].clone());
                 stack.push(new_index);
@@ -137,6 +142,63 @@ impl Trie {
         self.get_node(0).map_or(0, |node| node.count)
     }
 
+    /// Number of distinct words in the trie.
+    ///
+    /// Unlike [`Trie::num_words`], words inserted multiple times are only
+    /// counted once.
+    pub fn num_distinct_words(&self) -> u64 {
+        self.nodes
+            .iter()
+            .filter(|node| {
+                let children_count: u64 = node
+                    .children
+                    .values()
+                    .filter_map(|index| self.get_node(*index).ok())
+                    .map(|child| child.count)
+                    .sum();
+                node.count > children_count
+            })
+            .count() as u64
+    }
+
+    /// The only distinct word in the trie starting with `prefix`, if
+    /// there is exactly one.
+    pub fn completion(&self, prefix: &str) -> Option<String> {
+        let mut node = self.get_node(0).ok()?;
+        let mut word = String::new();
+        let mut rest = prefix;
+
+        // follow the prefix, which may end within a compressed edge
+        while !rest.is_empty() {
+            let (edge, index) = node
+                .children
+                .iter()
+                .find(|(edge, _)| edge.starts_with(rest) || rest.starts_with(edge.as_str()))?;
+            word += edge;
+            rest = rest.strip_prefix(edge.as_str()).unwrap_or("");
+            node = self.get_node(*index).ok()?;
+        }
+
+        // then the only path down from there, without words ending on it
+        loop {
+            let children_count: u64 = node
+                .children
+                .values()
+                .filter_map(|index| self.get_node(*index).ok())
+                .map(|child| child.count)
+                .sum();
+            if node.children.is_empty() {
+                return Some(word);
+            }
+            if node.children.len() > 1 || node.count > children_count {
+                return None;
+            }
+            let (edge, index) = node.children.iter().next()?;
+            word += edge;
+            node = self.get_node(*index).ok()?;
+        }
+    }
+
     pub fn sample(&self, mut id: u64) -> Result<String, TrieErr> {
         let mut node = self.get_node(0)?;
         if node.count == 0 {
@@ -146,7 +208,7 @@ impl Trie {
         let mut word = "".to_string();
 
         // expect `id < node.count` but wrap the id in case it's too big
-        id = id % node.count;
+        id %= node.count;
 
         loop {
             let mut should_stop = true;
@@ -194,9 +256,9 @@ impl std::fmt::Display for Trie {
                 prefix = "root";
             }
             let count = self.get_node(index).ok().map_or(0, |node| node.count);
-            let _ = write!(
+            writeln!(
                 f,
-                "{}{} (count={}, index={})\n",
+                "{}{} (count={}, index={})",
                 "    ".repeat(depth),
                 prefix,
                 count,
@@ -227,7 +289,7 @@ impl TrieErr {
 
 impl From<TrieErr> for io::Error {
     fn from(value: TrieErr) -> Self {
-        Self::new(io::ErrorKind::Other, format!("TrieErr: {}", value.msg))
+        Self::other(format!("TrieErr: {}", value.msg))
     }
 }
 
@@ -236,3 +298,59 @@ impl fmt::Display for TrieErr {
         write!(f, "TrieErr: {}", self.msg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completions() {
+        let mut trie = Trie::new();
+        for word in ["the", "then", "this", "word", "world", "a"] {
+            trie.insert(word).ok();
+        }
+
+        let compressed = match trie.compress() {
+            Ok(compressed) => compressed,
+            Err(err) => panic!("{}", err),
+        };
+        for trie in [compressed, trie] {
+            assert_eq!(trie.completion("thi"), Some("this".to_string()));
+            assert_eq!(trie.completion("the"), None);
+            assert_eq!(trie.completion("then"), Some("then".to_string()));
+            assert_eq!(trie.completion("wor"), None);
+            assert_eq!(trie.completion("worl"), Some("world".to_string()));
+            assert_eq!(trie.completion("a"), Some("a".to_string()));
+            assert_eq!(trie.completion("x"), None);
+            assert_eq!(trie.completion("thisx"), None);
+        }
+    }
+
+    #[test]
+    fn samples_in_order() {
+        // the same ids give the same words on every run, so that seeded
+        // texts can be generated again
+        let mut trie = Trie::new();
+        for word in ["world", "a", "then", "the", "word"] {
+            trie.insert(word).ok();
+        }
+        let words: Vec<String> = (0..5).filter_map(|id| trie.sample(id).ok()).collect();
+        assert_eq!(words, ["a", "then", "the", "word", "world"]);
+    }
+
+    #[test]
+    fn counts_distinct_words() {
+        let mut trie = Trie::new();
+        for word in ["the", "the", "then", "the", "a", "then", "them"] {
+            trie.insert(word).ok();
+        }
+        assert_eq!(trie.num_distinct_words(), 4);
+
+        let compressed = match trie.compress() {
+            Ok(compressed) => compressed,
+            Err(err) => panic!("{}", err),
+        };
+        assert_eq!(compressed.num_distinct_words(), 4);
+        assert_eq!(Trie::new().num_distinct_words(), 0);
+    }
+}
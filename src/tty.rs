@@ -1,7 +1,10 @@
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::{Read, Result, Stdin};
 
-use crate::config::ToipeConfig;
+use crate::{
+    backend::{Backend, Terminal},
+    config::ToipeConfig,
+};
 
 pub enum Tty {
     Stdin(Stdin),
@@ -22,10 +25,14 @@ impl From<Stdin> for Tty {
 
 impl Tty {
     pub fn new(config: &ToipeConfig) -> Result<Self> {
-        if config.is_stdin_tty {
+        if config.is_stdin_tty || Terminal::READS_CONSOLE {
             Ok(std::io::stdin().into())
         } else {
-            Ok(termion::get_tty()?.into())
+            Ok(OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open("/dev/tty")?
+                .into())
         }
     }
 
@@ -36,6 +43,15 @@ impl Tty {
         }
     }
 
+    /// Returns a new handle to the same source, which can be moved to
+    /// another thread.
+    pub fn try_clone(&self) -> Result<Box<dyn Read + Send>> {
+        match self {
+            Self::Stdin(_) => Ok(Box::new(std::io::stdin())),
+            Self::File(file) => Ok(Box::new(file.try_clone()?)),
+        }
+    }
+
     pub fn is_stdin(&self) -> bool {
         matches!(self, Self::Stdin(_))
     }
@@ -1,22 +1,172 @@
 //! Utilities for the terminal UI of toipe.
 
+pub mod graphics;
+
 use std::{
+    collections::{BTreeMap, HashMap},
     fmt::Display,
-    io::{stdout, Stdout, Write},
+    io::{BufWriter, Write},
+    thread,
+    time::Duration,
 };
 
-use termion::{
-    clear,
-    color::{self, Color},
-    cursor::{self, DetectCursorPos},
-    raw::{IntoRawMode, RawTerminal},
-    style, terminal_size,
+use crate::{
+    ansi::{clear, color, cursor, style, title},
+    backend::{terminal_size, Backend, Terminal},
+    debug_log, hyphenation,
+    layout::{Hand, KeyboardLayout, Row as KeyRow},
+    textgen::PARAGRAPH_BREAK,
+    theme::{Theme, ThemeColor},
+    ToipeError,
 };
-
-use crate::ToipeError;
 use anyhow::Result;
+use clap::ArgEnum;
+use graphics::Protocol;
+
+/// Narrowest terminal the text is shown in, in columns.
+pub const MIN_LINE_WIDTH: usize = 50;
+
+/// Shown in place of the paragraph breaks of the text, which are typed
+/// with enter.
+pub const PARAGRAPH_MARK: char = '↵';
+
+/// How a line of [`ToipeTui::display_words`] ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineEnd {
+    /// after the last word or its space
+    Word,
+    /// with the start of a broken word and a hyphen, see `set_hyphenate`
+    Hyphen,
+    /// at a paragraph break, with a blank row after it
+    Paragraph,
+    /// with the start of a word too long for a line, see [`WRAP_MARK`]
+    Wrap,
+}
+
+impl LineEnd {
+    /// What is shown after the line.
+    fn mark(self) -> Option<char> {
+        match self {
+            LineEnd::Hyphen => Some('-'),
+            LineEnd::Wrap => Some(WRAP_MARK),
+            LineEnd::Word | LineEnd::Paragraph => None,
+        }
+    }
+}
+
+/// Shown at the right margin after the start of a word too long to fit
+/// on a line, which goes on at the start of the next line.
+pub const WRAP_MARK: char = '↪';
+
+/// Number of columns taken by `c` when printed: none for combining
+/// marks, two for wide glyphs such as CJK ideographs and most emoji, and
+/// one for any other char.
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x300..=0x36F | 0x1AB0..=0x1AFF | 0x20D0..=0x20FF => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// Number of columns taken by `text` when printed, see [`char_width`].
+pub fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// The longest start of `word` taking at most `width` columns, and the
+/// rest of it. The start has at least one char, so that a word is always
+/// broken somewhere.
+fn split_at_width(word: &str, width: usize) -> (&str, &str) {
+    let mut taken = 0;
+    let mut index = word.len();
+    for (i, c) in word.char_indices() {
+        taken += char_width(c);
+        if taken > width && i > 0 {
+            index = i;
+            break;
+        }
+    }
+    word.split_at(index)
+}
 
-const MIN_LINE_WIDTH: usize = 50;
+/// Wraps `words` into lines of at most `max_width` columns, counting the
+/// space after each word and the mark at the end of the line.
+///
+/// Words not fitting at the end of a line are broken between syllables
+/// with `hyphenate`, or else moved to the next line. Words longer than a
+/// whole line are broken at the margin, see [`WRAP_MARK`].
+fn wrap_words(words: &[String], max_width: usize, hyphenate: bool) -> Vec<(String, LineEnd)> {
+    let mut lines = Vec::new();
+    let mut line: Vec<&str> = Vec::new();
+    let mut current_len = 0;
+
+    for word in words {
+        if word == PARAGRAPH_BREAK {
+            // the mark takes the place of the space after the word
+            lines.push((
+                line.join(" ") + &PARAGRAPH_MARK.to_string(),
+                LineEnd::Paragraph,
+            ));
+            line.clear();
+            current_len = 0;
+            continue;
+        }
+        let mut word = word.as_str();
+        loop {
+            let width = text_width(word);
+            if current_len + width < max_width {
+                // add to line, with a space after it
+                line.push(word);
+                current_len += width + 1;
+                break;
+            }
+            let room = max_width.saturating_sub(current_len);
+            let broken = hyphenate
+                .then(|| hyphenation::split(word, room))
+                .flatten()
+                .filter(|(start, _, _)| !start.is_empty());
+            if let Some((start, rest, adds_hyphen)) = broken {
+                // the start of the word fills the line, without a
+                // space after it
+                line.push(start);
+                let end = match adds_hyphen {
+                    true => LineEnd::Hyphen,
+                    false => LineEnd::Word,
+                };
+                lines.push((line.join(" "), end));
+                word = rest;
+            } else if !line.is_empty() {
+                // add an extra space at the end of each line because
+                //  user will instinctively type a space after every word
+                //  (at least I did)
+                lines.push((line.join(" ") + " ", LineEnd::Word));
+            } else {
+                let (start, rest) = split_at_width(word, max_width.saturating_sub(1));
+                lines.push((start.to_string(), LineEnd::Wrap));
+                word = rest;
+            }
+            line.clear();
+            current_len = 0;
+        }
+    }
+
+    // last line wasn't added in loop
+    // last line doesn't have an extra space at the end
+    //   - the typing test stops as soon as the user types last char
+    //   - won't hang there waiting for user to type space
+    lines.push((line.join(" "), LineEnd::Word));
+    lines
+}
 
 /// Describes something that has a printable length.
 ///
@@ -41,63 +191,141 @@ pub trait HasLength {
 /// slice of [`Text`]s with each formatted in a different way.
 #[derive(Debug, Clone)]
 pub struct Text {
-    /// the raw text
-    // TODO: make this private
-    raw_text: String,
     /// text without formatting
     text: String,
     /// actual number of char width taken when printed on the terminal
     length: usize,
+    style: Style,
+}
+
+/// How a [`Text`] is formatted. The attributes can be combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<ThemeColor>,
+    pub bg: Option<ThemeColor>,
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    /// Writes the sequences turning on the attributes of the style.
+    fn write_start(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.bold {
+            write!(f, "{}", style::Bold)?;
+        }
+        if self.faint {
+            write!(f, "{}", style::Faint)?;
+        }
+        if self.italic {
+            write!(f, "{}", style::Italic)?;
+        }
+        if self.underline {
+            write!(f, "{}", style::Underline)?;
+        }
+        if let Some(fg) = self.fg {
+            write!(f, "{}", color::Fg(fg))?;
+        }
+        if let Some(bg) = self.bg {
+            write!(f, "{}", color::Bg(bg))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the sequences turning off the attributes of the style, and
+    /// only those, so that styles of surrounding text are kept.
+    fn write_end(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // `NoFaint` is the reset of both bold and faint, unlike `NoBold`
+        // which is double underline on some terminals
+        if self.bold || self.faint {
+            write!(f, "{}", style::NoFaint)?;
+        }
+        if self.italic {
+            write!(f, "{}", style::NoItalic)?;
+        }
+        if self.underline {
+            write!(f, "{}", style::NoUnderline)?;
+        }
+        if self.fg.is_some() {
+            write!(f, "{}", color::Fg(color::Reset))?;
+        }
+        if self.bg.is_some() {
+            write!(f, "{}", color::Bg(color::Reset))?;
+        }
+        Ok(())
+    }
 }
 
 impl Text {
     /// Constructs a new Text from a raw string
     ///
     /// NOTE: ensure that this string does not itself have formatting
-    /// characters, zero-width characters or multi-width characters.
+    /// characters.
     pub fn new(text: String) -> Self {
-        let length = text.len();
+        let length = text_width(&text);
         Self {
-            raw_text: text.clone(),
             text,
             length,
+            style: Style::default(),
         }
     }
 
-    /// the raw text with all formatting
-    // TODO: remove this
-    pub fn raw_text(&self) -> &String {
-        &self.raw_text
-    }
-
     /// the actual printed text without formatting
     pub fn text(&self) -> &String {
         &self.text
     }
 
+    /// how the text is formatted
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
+    /// formats the text with `style`, replacing its current formatting
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
     /// adds faint style to the text
     pub fn with_faint(mut self) -> Self {
-        self.raw_text = format!("{}{}{}", style::Faint, self.raw_text, style::NoFaint);
+        self.style.faint = true;
+        self
+    }
+
+    /// adds bold style to the text
+    pub fn with_bold(mut self) -> Self {
+        self.style.bold = true;
+        self
+    }
+
+    /// adds italic style to the text
+    pub fn with_italic(mut self) -> Self {
+        self.style.italic = true;
         self
     }
 
     /// adds underline to the text
     pub fn with_underline(mut self) -> Self {
-        self.raw_text = format!("{}{}{}", style::Underline, self.raw_text, style::Reset);
+        self.style.underline = true;
         self
     }
 
     /// adds given color to the text
     pub fn with_color<C>(mut self, color: C) -> Self
     where
-        C: Color,
+        C: Into<ThemeColor>,
     {
-        self.raw_text = format!(
-            "{}{}{}",
-            color::Fg(color),
-            self.raw_text,
-            color::Fg(color::Reset)
-        );
+        self.style.fg = Some(color.into());
+        self
+    }
+
+    /// adds given background color to the text
+    pub fn with_background<C>(mut self, color: C) -> Self
+    where
+        C: Into<ThemeColor>,
+    {
+        self.style.bg = Some(color.into());
         self
     }
 }
@@ -120,7 +348,7 @@ impl From<String> for Text {
     /// Constructs a new Text from a raw string
     ///
     /// NOTE: ensure that this string does not itself have formatting
-    /// characters, zero-width characters or multi-width characters.
+    /// characters.
     fn from(text: String) -> Self {
         Self::new(text)
     }
@@ -130,7 +358,7 @@ impl From<&str> for Text {
     /// Constructs a new Text from a raw string
     ///
     /// NOTE: ensure that this string does not itself have formatting
-    /// characters, zero-width characters or multi-width characters.
+    /// characters.
     fn from(text: &str) -> Self {
         Self::new(text.to_string())
     }
@@ -140,31 +368,73 @@ impl From<char> for Text {
     /// Constructs a new Text from a character
     ///
     /// NOTE: ensure that this character is itself not a formatting
-    /// character, zero-width character or a multi-width character.
+    /// character.
     fn from(c: char) -> Self {
         Self::new(c.to_string())
     }
 }
 
-/// Displays the raw string as-is. No surprises here.
+/// Displays the text with the escape sequences of its style around it.
 impl Display for Text {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.raw_text)
+        self.style.write_start(f)?;
+        f.write_str(&self.text)?;
+        self.style.write_end(f)
     }
 }
 
+/// A displayed line of which only a part is to be typed, see
+/// [`ToipeTui::display_decorated_lines`].
+#[derive(Debug, Clone)]
+pub struct DecoratedLine {
+    /// shown before the text to be typed, such as a line number
+    pub decoration: Vec<Text>,
+    /// text to be typed, if any
+    pub text: Option<Text>,
+    /// shown after the text, such as the hyphen of a broken word
+    pub ending: Vec<Text>,
+}
+
 /// the position of a line of words
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 struct LinePos {
-    /// y-position of line in the terminal window
-    pub y: u16,
+    /// row of the text area the line is in, see [`TextArea`]
+    pub row: usize,
     /// x-position of the first char in the line
     pub x: u16,
     /// length (number of chars) in this line
     pub length: u16,
+    /// columns taken by the chars before each char, and by the whole line
+    /// last, see [`text_width`]
+    offsets: Vec<u16>,
+}
+
+impl LinePos {
+    fn new(row: usize, x: u16, text: &str) -> Self {
+        let mut offsets = vec![0];
+        for c in text.chars() {
+            offsets.push(offsets[offsets.len() - 1] + char_width(c) as u16);
+        }
+        Self {
+            row,
+            x,
+            length: text.chars().count() as u16,
+            offsets,
+        }
+    }
+
+    /// Number of columns from the start of the line to the char at
+    /// `index`, with `spacing` columns after each char instead of one.
+    fn column(&self, index: u16, spacing: u16) -> u16 {
+        let index = index.min(self.length);
+        self.offsets[index as usize] + index * (spacing - 1)
+    }
 }
 
-/// TODO: document this
+/// Position of the cursor in the lines of the text to be typed.
+///
+/// Positions of chars are given as (line, char in line), see
+/// [`ToipeTui::screen_pos`] for where they are on the screen.
 struct CursorPos {
     pub lines: Vec<LinePos>,
     pub cur_line: usize,
@@ -180,8 +450,8 @@ impl CursorPos {
         }
     }
 
-    pub fn next(&mut self) -> (u16, u16) {
-        let line = self.lines[self.cur_line];
+    pub fn next(&mut self) -> (usize, u16) {
+        let line = &self.lines[self.cur_line];
         let max_chars_index = line.length - 1;
 
         if self.cur_char_in_line < max_chars_index {
@@ -199,7 +469,7 @@ impl CursorPos {
         self.cur_pos()
     }
 
-    pub fn prev(&mut self) -> (u16, u16) {
+    pub fn prev(&mut self) -> (usize, u16) {
         if self.cur_char_in_line > 0 {
             // more chars behind in line
             self.cur_char_in_line -= 1;
@@ -215,18 +485,562 @@ impl CursorPos {
         self.cur_pos()
     }
 
-    pub fn cur_pos(&self) -> (u16, u16) {
-        let line = self.lines[self.cur_line];
-        (line.x + self.cur_char_in_line, line.y)
+    pub fn cur_pos(&self) -> (usize, u16) {
+        (self.cur_line, self.cur_char_in_line)
+    }
+
+    /// Position of the char at `index`, counting from the first char of
+    /// the first line.
+    pub fn pos_of_char(&self, mut index: usize) -> Option<(usize, u16)> {
+        for (line_no, line) in self.lines.iter().enumerate() {
+            if index < line.length as usize {
+                return Some((line_no, index as u16));
+            }
+            index -= line.length as usize;
+        }
+        None
+    }
+}
+
+/// Novelty challenges changing how the text is shown, while it is typed
+/// as usual.
+#[derive(Copy, Clone, PartialEq, Eq, ArgEnum, Debug)]
+pub enum Challenge {
+    /// each line is shown mirrored, from right to left
+    Mirror,
+    /// the letters are shown upside down
+    Flip,
+}
+
+/// Letters and marks with an upside down lookalike, see
+/// [`Challenge::Flip`].
+const FLIPPED: [(char, char); 37] = [
+    ('a', 'ɐ'),
+    ('b', 'q'),
+    ('c', 'ɔ'),
+    ('d', 'p'),
+    ('e', 'ǝ'),
+    ('f', 'ɟ'),
+    ('g', 'ƃ'),
+    ('h', 'ɥ'),
+    ('i', 'ᴉ'),
+    ('j', 'ɾ'),
+    ('k', 'ʞ'),
+    ('m', 'ɯ'),
+    ('n', 'u'),
+    ('p', 'd'),
+    ('q', 'b'),
+    ('r', 'ɹ'),
+    ('t', 'ʇ'),
+    ('u', 'n'),
+    ('v', 'ʌ'),
+    ('w', 'ʍ'),
+    ('y', 'ʎ'),
+    ('A', '∀'),
+    ('E', 'Ǝ'),
+    ('F', 'Ⅎ'),
+    ('J', 'ſ'),
+    ('L', '˥'),
+    ('M', 'W'),
+    ('T', '┴'),
+    ('U', '∩'),
+    ('V', 'Λ'),
+    ('W', 'M'),
+    ('Y', '⅄'),
+    ('.', '˙'),
+    ('!', '¡'),
+    ('?', '¿'),
+    ('6', '9'),
+    ('9', '6'),
+];
+
+/// Marks with a mirrored lookalike, see [`Challenge::Mirror`].
+const MIRRORED: [(char, char); 10] = [
+    ('(', ')'),
+    (')', '('),
+    ('[', ']'),
+    (']', '['),
+    ('{', '}'),
+    ('}', '{'),
+    ('<', '>'),
+    ('>', '<'),
+    ('/', '\\'),
+    ('\\', '/'),
+];
+
+impl Challenge {
+    /// The char as shown in this challenge.
+    fn transform_char(self, c: char) -> char {
+        let lookalikes: &[(char, char)] = match self {
+            Self::Mirror => &MIRRORED,
+            Self::Flip => &FLIPPED,
+        };
+        lookalikes
+            .iter()
+            .find_map(|&(from, to)| (from == c).then_some(to))
+            .unwrap_or(c)
+    }
+
+    /// Formatted text as shown in this challenge, keeping the formatting.
+    ///
+    /// The text of a [`Challenge::Mirror`] is also reversed, which keeps
+    /// the formatting only if all of the text is formatted the same way,
+    /// such as a line not typed yet.
+    fn transform(self, raw_text: &str) -> String {
+        let mut chars: Vec<char> = raw_text.chars().collect();
+        let shown = shown_indices(&chars);
+
+        let mut shown_chars: Vec<char> = shown
+            .iter()
+            .map(|index| self.transform_char(chars[*index]))
+            .collect();
+        if self == Self::Mirror {
+            shown_chars.reverse();
+        }
+        for (index, c) in shown.into_iter().zip(shown_chars) {
+            chars[index] = c;
+        }
+        chars.into_iter().collect()
+    }
+}
+
+/// Indices of the chars of formatted text that are not part of escape
+/// sequences, which all end with a letter, such as `\x1b[2m`.
+fn shown_indices(chars: &[char]) -> Vec<usize> {
+    let mut shown = Vec::new();
+    let mut in_escape = false;
+    for (index, c) in chars.iter().enumerate() {
+        if *c == '\x1b' {
+            in_escape = true;
+        } else if in_escape {
+            in_escape = !c.is_ascii_alphabetic();
+        } else {
+            shown.push(index);
+        }
+    }
+    shown
+}
+
+/// Window title with the progress through a test, from 0 to 1, and the
+/// speed so far, see [`ToipeTui::set_window_title`].
+pub fn progress_title(progress: f64, wpm: f64) -> String {
+    format!(
+        "toipe — {:.0}% — {:.0}wpm",
+        progress.clamp(0.0, 1.0) * 100.0,
+        wpm.max(0.0)
+    )
+}
+
+/// Formatted text in bold and without faint parts, see
+/// [`ToipeTui::set_low_vision`].
+fn bold_text(raw_text: &str) -> String {
+    let text = raw_text
+        .replace(&style::Faint.to_string(), "")
+        .replace(&style::NoFaint.to_string(), "");
+    format!("{}{}{}", style::Bold, text, style::Reset)
+}
+
+/// Formatted text with a space after every shown char, see
+/// [`ToipeTui::set_low_vision`].
+fn spaced_text(raw_text: &str) -> String {
+    let chars: Vec<char> = raw_text.chars().collect();
+    let mut shown = shown_indices(&chars).into_iter().peekable();
+    let mut spaced = String::new();
+    for (index, c) in chars.into_iter().enumerate() {
+        spaced.push(c);
+        if shown.next_if_eq(&index).is_some() {
+            spaced.push(' ');
+        }
+    }
+    spaced
+}
+
+/// Width of the space bar of the on-screen keyboard, see
+/// [`ToipeTui::set_keyboard`].
+const SPACE_BAR_WIDTH: usize = 13;
+
+/// Colors of the keys of the on-screen keyboard pressed the least to the
+/// most often in a test, see [`KeyboardState::heat`].
+const HEAT_COLORS: [ThemeColor; 3] = [
+    ThemeColor::Ansi(2),
+    ThemeColor::Ansi(3),
+    ThemeColor::Ansi(9),
+];
+
+/// A key of the on-screen keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum KeyId {
+    /// the key in a column of a row of the layout
+    Key(KeyRow, usize),
+    /// the shift key of a hand
+    Shift(Hand),
+}
+
+/// What the on-screen keyboard shows, see [`ToipeTui::set_keyboard`].
+#[derive(Debug, Default)]
+struct KeyboardState {
+    /// char whose key is highlighted to be pressed next
+    next: Option<char>,
+    /// keys of the char typed last
+    pressed: Vec<KeyId>,
+    /// number of times each key was pressed in the current test
+    heat: HashMap<KeyId, usize>,
+}
+
+impl KeyboardState {
+    /// Keys pressed to type `c` in `layout`, with the shift key held with
+    /// the hand not typing it.
+    fn keys(layout: &KeyboardLayout, c: char) -> Vec<KeyId> {
+        let Some(position) = layout.position(c) else {
+            return Vec::new();
+        };
+        let mut keys = vec![KeyId::Key(position.row, position.column)];
+        if position.shifted {
+            keys.push(KeyId::Shift(match position.hand() {
+                Hand::Left => Hand::Right,
+                Hand::Right => Hand::Left,
+            }));
+        }
+        keys
+    }
+
+    /// Color of `key` by how often it was pressed compared to the key
+    /// pressed the most, or `None` if it was not pressed.
+    fn heat_color(&self, key: KeyId) -> Option<ThemeColor> {
+        let presses = *self.heat.get(&key)?;
+        let max_presses = self.heat.values().copied().max()?;
+        let level = (presses * HEAT_COLORS.len()).div_ceil(max_presses);
+        Some(HEAT_COLORS[level.clamp(1, HEAT_COLORS.len()) - 1])
+    }
+}
+
+/// Lines of the on-screen keyboard of `layout`, each with its indentation.
+///
+/// The keys of the next char to type are highlighted, and the others
+/// colored by how often they were pressed, the ones just pressed in bold.
+fn keyboard_lines(
+    layout: &KeyboardLayout,
+    state: &KeyboardState,
+    theme: &Theme,
+) -> Vec<(u16, Vec<Text>)> {
+    let next = state
+        .next
+        .map(|c| KeyboardState::keys(layout, c))
+        .unwrap_or_default();
+    let key_text = |text: Text, key: KeyId| {
+        let text = if next.contains(&key) {
+            text.with_underline().with_color(theme.cursor)
+        } else if let Some(color) = state.heat_color(key) {
+            text.with_color(color)
+        } else {
+            text.with_faint()
+        };
+        if state.pressed.contains(&key) {
+            text.with_bold()
+        } else {
+            text
+        }
+    };
+
+    layout
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            if row.row == KeyRow::Space {
+                let bar = Text::from("─".repeat(SPACE_BAR_WIDTH));
+                return (7, vec![key_text(bar, KeyId::Key(row.row, 0))]);
+            }
+
+            let mut texts = Vec::new();
+            let mut indent = index as u16;
+            if row.row == KeyRow::Bottom {
+                texts.extend([
+                    key_text(Text::from('⇧'), KeyId::Shift(Hand::Left)),
+                    Text::from(' '),
+                ]);
+                indent = indent.saturating_sub(2);
+            }
+            for (column, key) in row.keys.iter().enumerate() {
+                texts.extend([
+                    key_text(Text::from(key.key), KeyId::Key(row.row, column)),
+                    Text::from(' '),
+                ]);
+            }
+            if row.row == KeyRow::Bottom {
+                texts.push(key_text(Text::from('⇧'), KeyId::Shift(Hand::Right)));
+            }
+            (indent, texts)
+        })
+        .collect()
+}
+
+/// Number of frames of [`ToipeTui::collapse_text`].
+const COLLAPSE_FRAMES: u16 = 8;
+
+/// Columns covered from each side of a screen `width` columns wide at
+/// `frame` of [`ToipeTui::collapse_text`], easing out so that the text
+/// collapses quickly at first.
+fn collapsed_columns(width: u16, frame: u16) -> u16 {
+    let t = frame.min(COLLAPSE_FRAMES) as f64 / COLLAPSE_FRAMES as f64;
+    let eased = 1.0 - (1.0 - t).powi(2);
+    (width.div_ceil(2) as f64 * eased).round() as u16
+}
+
+/// Minimum number of rows of the text shown at once. Longer texts are
+/// scrolled through while typing.
+const MIN_TEXT_AREA_HEIGHT: usize = 3;
+
+/// Minimum number of columns of the single row of text of
+/// [`ToipeTui::set_mini`].
+const MIN_MINI_WIDTH: usize = 10;
+
+/// A row of the [`TextArea`], already formatted.
+struct Row {
+    /// shown before the text to be typed
+    decoration: String,
+    /// the text to be typed as first displayed, if any
+    text: Option<String>,
+    /// shown after the text
+    ending: String,
+}
+
+/// The rows of the text being typed.
+///
+/// When there are more rows than fit on the screen, only `height` rows
+/// are shown like in an editor: typed rows scroll up so that the current
+/// row stays near the middle, and a minimap column next to the text shows
+/// where in the text it is.
+#[derive(Default)]
+struct TextArea {
+    rows: Vec<Row>,
+    /// x-position of the first column of the rows
+    x: u16,
+    /// y-position of the first row shown
+    y: u16,
+    /// number of rows shown at once
+    height: usize,
+    /// index of the first row shown
+    first_row: usize,
+    /// row the cursor is in
+    current_row: usize,
+    /// x-position of the minimap column, if there is room for it
+    minimap_x: Option<u16>,
+    /// the single row of the text in the minimal layout, if in use
+    scroll: Option<Scroll>,
+}
+
+/// The text in a single row scrolled horizontally, in the minimal layout
+/// of [`ToipeTui::set_mini`].
+///
+/// The lines of the text follow each other in the row, and the columns
+/// of the row are counted from the start of the text.
+#[derive(Debug, Default)]
+struct Scroll {
+    /// chars of the text as first displayed
+    chars: Vec<char>,
+    /// number of columns shown
+    width: u16,
+    /// first column shown
+    first_column: usize,
+}
+
+impl Scroll {
+    /// Columns that are currently shown.
+    fn shown_columns(&self) -> std::ops::Range<usize> {
+        self.first_column..(self.first_column + self.width as usize).min(self.chars.len())
+    }
+
+    /// Scrolls once the cursor at `column` moves out of the first two
+    /// thirds of the row, so that it is a third of the way into it again.
+    ///
+    /// Returns whether the row scrolled.
+    fn move_to_column(&mut self, column: usize) -> bool {
+        let width = self.width as usize;
+        if (self.first_column..self.first_column + width * 2 / 3).contains(&column) {
+            return false;
+        }
+        let first_column = column
+            .saturating_sub(width / 3)
+            .min(self.chars.len().saturating_sub(width));
+        let scrolled = first_column != self.first_column;
+        self.first_column = first_column;
+        scrolled
     }
 }
 
+impl TextArea {
+    /// Whether the text is longer than the area.
+    fn is_scrolled(&self) -> bool {
+        self.rows.len() > self.height
+    }
+
+    /// Rows that are currently shown.
+    fn shown_rows(&self) -> std::ops::Range<usize> {
+        self.first_row..(self.first_row + self.height).min(self.rows.len())
+    }
+
+    /// Position on the screen of the char at `index` of `line`, if it is
+    /// shown, with `spacing` columns per char (see
+    /// [`ToipeTui::spacing`]) and the line `mirrored` (see
+    /// [`Challenge::Mirror`]).
+    fn screen_pos(
+        &self,
+        line: &LinePos,
+        index: u16,
+        spacing: u16,
+        mirrored: bool,
+    ) -> Option<(u16, u16)> {
+        if let Some(scroll) = &self.scroll {
+            let column = line.x as usize + index as usize;
+            return scroll
+                .shown_columns()
+                .contains(&column)
+                .then(|| (self.x + (column - scroll.first_column) as u16, self.y));
+        }
+        let y = self.row_y(line.row)?;
+        let column = if mirrored {
+            // the chars after it come first
+            line.column(line.length, spacing) - line.column(index + 1, spacing)
+        } else {
+            line.column(index, spacing)
+        };
+        Some((line.x + column, y))
+    }
+
+    /// y-position of `row` on the screen, if it is shown.
+    fn row_y(&self, row: usize) -> Option<u16> {
+        self.shown_rows()
+            .contains(&row)
+            .then(|| self.y + (row - self.first_row) as u16)
+    }
+
+    /// Moves the cursor to `row`, scrolling so that it stays near the
+    /// middle of the area.
+    ///
+    /// Returns whether the area scrolled.
+    fn move_to_row(&mut self, row: usize) -> bool {
+        self.current_row = row;
+        let first_row = row
+            .saturating_sub(self.height / 2)
+            .min(self.rows.len().saturating_sub(self.height));
+        let scrolled = first_row != self.first_row;
+        self.first_row = first_row;
+        scrolled
+    }
+
+    /// Char of the minimap at `index` from the top.
+    ///
+    /// Each char stands for a part of the rows. The part with the cursor
+    /// is a block, the shown rows a thick line, the typed rows a line and
+    /// the rows to be typed a faint line.
+    fn minimap_char(&self, index: usize, theme: &Theme) -> Text {
+        let start = index * self.rows.len() / self.height;
+        let end = ((index + 1) * self.rows.len() / self.height).max(start + 1);
+        let shown = self.shown_rows();
+
+        if (start..end).contains(&self.current_row) {
+            Text::from('█').with_color(theme.hint)
+        } else if start < shown.end && end > shown.start {
+            Text::from('┃')
+        } else if end <= self.current_row {
+            Text::from('│')
+        } else {
+            Text::from('│').with_faint()
+        }
+    }
+}
+
+/// A rectangle of the terminal to draw the UI in, see
+/// [`ToipeTui::set_region`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// column of the left edge, starting at 1
+    pub x: u16,
+    /// row of the top edge, starting at 1
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Region {
+    /// Moves the cursor to column `x` and row `y` of the region, counted
+    /// from 1.
+    fn goto(&self, x: u16, y: u16) -> cursor::Goto {
+        cursor::Goto(self.x + x.max(1) - 1, self.y + y.max(1) - 1)
+    }
+
+    /// Clears row `y` of the region with spaces.
+    fn clear_row(&self, y: u16) -> String {
+        format!("{}{}", self.goto(1, y), " ".repeat(self.width as usize))
+    }
+}
+
+/// Size of the buffer for output to the terminal.
+///
+/// Large enough that a full screen of colored text is written at once.
+const OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
 /// terminal UI of toipe
+///
+/// Changes to single chars of the displayed words (see
+/// [`ToipeTui::replace_text`], [`ToipeTui::type_text`] and
+/// [`ToipeTui::display_at_char`]) are collected and only written on
+/// [`ToipeTui::flush`], skipping chars that would not change. The cursor
+/// is also only moved on flush.
 pub struct ToipeTui {
-    stdout: RawTerminal<Stdout>,
+    /// output is buffered until [`ToipeTui::flush`]
+    stdout: BufWriter<<Terminal as Backend>::RawStdout>,
     cursor_pos: CursorPos,
-    track_lines: bool,
+    text_area: TextArea,
     bottom_lines_len: usize,
+    /// chars to be written on the next flush, by (line, char) position so
+    /// that they are written in order
+    dirty_chars: BTreeMap<(usize, u16), String>,
+    /// chars written by previous flushes, by (line, char) position
+    flushed_chars: HashMap<(usize, u16), String>,
+    /// whether the cursor needs to be moved to its position on the next
+    /// flush
+    cursor_moved: bool,
+    /// changes how the text to be typed is shown, see `--challenge`
+    challenge: Option<Challenge>,
+    /// row of the status shown by [`ToipeTui::display_status`], if any
+    status_y: Option<u16>,
+    /// rows covered by [`ToipeTui::display_overlay`]
+    overlay_ys: Vec<u16>,
+    theme: Theme,
+    /// whether the text to be typed is spaced out and in bold
+    low_vision: bool,
+    /// whether words are broken across lines, see
+    /// [`ToipeTui::set_hyphenate`]
+    hyphenate: bool,
+    /// layout of the on-screen keyboard, if it is shown
+    keyboard: Option<KeyboardLayout>,
+    keyboard_state: KeyboardState,
+    /// whether the on-screen keyboard is drawn as in `keyboard_state`
+    keyboard_drawn: bool,
+    /// whether the window title shows the progress of the test, see
+    /// [`ToipeTui::display_title`]
+    window_title: bool,
+    /// window title last set, if any
+    shown_title: Option<String>,
+    /// whether the text is shown in a single row, see
+    /// [`ToipeTui::set_mini`]
+    mini: bool,
+    /// image protocol of the terminal, if any
+    graphics: Option<Protocol>,
+    /// whether images are shown, which may need to be removed when the
+    /// screen is cleared
+    images_shown: bool,
+    /// position and length of the lines shown by
+    /// [`ToipeTui::display_lines`] and [`ToipeTui::display_lines_bottom`],
+    /// which confetti does not cover
+    shown_lines: Vec<(u16, u16, u16)>,
+    /// cells covered by [`ToipeTui::display_confetti`]
+    confetti_cells: Vec<(u16, u16)>,
+    /// part of the terminal the UI is drawn in, if not all of it, see
+    /// [`ToipeTui::set_region`]
+    region: Option<Region>,
 }
 
 type MaybeError<T = ()> = Result<T>;
@@ -237,36 +1051,411 @@ impl ToipeTui {
     /// NOTE: does not clear the screen when initialized.
     pub fn new() -> Self {
         Self {
-            stdout: stdout().into_raw_mode().unwrap(),
+            stdout: BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, Terminal::raw_stdout().unwrap()),
             cursor_pos: CursorPos::new(),
-            track_lines: false,
+            text_area: TextArea::default(),
             bottom_lines_len: 0,
+            dirty_chars: BTreeMap::new(),
+            flushed_chars: HashMap::new(),
+            cursor_moved: false,
+            challenge: None,
+            status_y: None,
+            overlay_ys: Vec::new(),
+            theme: Theme::default(),
+            low_vision: false,
+            hyphenate: false,
+            keyboard: None,
+            keyboard_state: KeyboardState::default(),
+            keyboard_drawn: false,
+            window_title: false,
+            shown_title: None,
+            mini: false,
+            graphics: Protocol::detect(),
+            images_shown: false,
+            shown_lines: Vec::new(),
+            confetti_cells: Vec::new(),
+            region: None,
+        }
+    }
+
+    /// Shows the UI in the colors of `theme` from now on.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Shows the text to be typed differently from now on, see
+    /// [`Challenge`].
+    pub fn set_challenge(&mut self, challenge: Option<Challenge>) {
+        self.challenge = challenge;
+    }
+
+    /// Shows the text to be typed with a space between chars and in bold
+    /// from now on, for low-vision users. Faint text is shown as usual.
+    pub fn set_low_vision(&mut self, low_vision: bool) {
+        self.low_vision = low_vision;
+    }
+
+    /// Breaks words that do not fit at the end of a line from now on,
+    /// rather than moving them to the next line, see [`hyphenation`].
+    pub fn set_hyphenate(&mut self, hyphenate: bool) {
+        self.hyphenate = hyphenate;
+    }
+
+    /// Shows an on-screen keyboard of `layout` below the text from now
+    /// on, see [`ToipeTui::display_keyboard`].
+    pub fn set_keyboard(&mut self, layout: Option<KeyboardLayout>) {
+        self.keyboard = layout;
+    }
+
+    /// Shows the text to be typed in a single row from now on, scrolled
+    /// horizontally as it is typed, to fit in tiny terminals such as a
+    /// tmux pane.
+    pub fn set_mini(&mut self, mini: bool) {
+        self.mini = mini;
+    }
+
+    /// Draws the UI inside `region` from now on, leaving the rest of the
+    /// terminal to the application toipe is embedded in, or on the whole
+    /// terminal if `None`.
+    ///
+    /// Positions are relative to the region, which is only cleared on
+    /// [`ToipeTui::reset_screen`].
+    pub fn set_region(&mut self, region: Option<Region>) {
+        self.region = region;
+    }
+
+    /// Columns and rows the UI is drawn in: those of the region, if any,
+    /// or of the terminal.
+    pub fn size(&self) -> MaybeError<(u16, u16)> {
+        match self.region {
+            Some(region) => Ok((region.width, region.height)),
+            None => Ok(terminal_size()?),
+        }
+    }
+
+    /// Moves the cursor to column `x` and row `y` of the region, counted
+    /// from 1.
+    fn goto(&self, x: u16, y: u16) -> cursor::Goto {
+        match self.region {
+            Some(region) => region.goto(x, y),
+            None => cursor::Goto(x, y),
+        }
+    }
+
+    /// Clears row `y` of the region, leaving the cursor in it.
+    fn clear_row(&self, y: u16) -> String {
+        match self.region {
+            Some(region) => region.clear_row(y),
+            None => format!("{}{}", cursor::Goto(1, y), clear::CurrentLine),
+        }
+    }
+
+    /// Clears the whole region.
+    fn clear_all(&self) -> String {
+        match self.region {
+            Some(region) => (1..=region.height).map(|y| region.clear_row(y)).collect(),
+            None => clear::All.to_string(),
+        }
+    }
+
+    /// Shows the progress of tests in the window title from now on, see
+    /// [`ToipeTui::display_title`]. The title is restored on exit in
+    /// terminals that support it.
+    pub fn set_window_title(&mut self, window_title: bool) -> MaybeError {
+        if window_title && !self.window_title {
+            write!(self.stdout, "{}", title::Save)?;
+        }
+        self.window_title = window_title;
+        Ok(())
+    }
+
+    /// Sets the window title to `text` on the next flush, if enabled with
+    /// [`ToipeTui::set_window_title`].
+    pub fn display_title(&mut self, text: &str) -> MaybeError {
+        if self.window_title && self.shown_title.as_deref() != Some(text) {
+            write!(self.stdout, "{}", title::Set(text))?;
+            self.shown_title = Some(text.to_string());
+        }
+        Ok(())
+    }
+
+    /// Lights up the keys of `c` on the on-screen keyboard, and adds them
+    /// to the heat of the test.
+    pub fn press_key(&mut self, c: char) {
+        let Some(layout) = &self.keyboard else {
+            return;
+        };
+        let keys = KeyboardState::keys(layout, c);
+        for key in &keys {
+            *self.keyboard_state.heat.entry(*key).or_default() += 1;
+        }
+        self.keyboard_state.pressed = keys;
+        self.keyboard_drawn = false;
+    }
+
+    /// Forgets the keys pressed on the on-screen keyboard, for a new test.
+    pub fn clear_keyboard_heat(&mut self) {
+        self.keyboard_state.heat.clear();
+        self.keyboard_state.pressed.clear();
+        self.keyboard_drawn = false;
+    }
+
+    /// Number of rows taken by the on-screen keyboard, with the empty row
+    /// above it.
+    fn keyboard_height(&self) -> usize {
+        self.keyboard
+            .as_ref()
+            .map_or(0, |layout| layout.rows.len() + 1)
+    }
+
+    /// Number of columns taken by each char of the text to be typed.
+    fn spacing(&self) -> u16 {
+        if self.low_vision {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Formatted text as shown in the current challenge, if any, and in
+    /// bold for low-vision users.
+    fn challenge_text(&self, raw_text: String) -> String {
+        let text = match self.challenge {
+            Some(challenge) => challenge.transform(&raw_text),
+            None => raw_text,
+        };
+        if self.low_vision {
+            bold_text(&text)
+        } else {
+            text
+        }
+    }
+
+    /// Formatted row of the text to be typed as shown, see
+    /// [`ToipeTui::challenge_text`].
+    fn row_text(&self, text: &Text) -> String {
+        let text = self.challenge_text(text.to_string());
+        if self.low_vision {
+            spaced_text(&text)
+        } else {
+            text
         }
     }
 
     pub fn reset(&mut self) {
         self.cursor_pos = CursorPos::new();
+        self.text_area = TextArea::default();
+        self.keyboard_drawn = false;
+        self.forget_chars();
+    }
+
+    /// Forgets about chars written so far, for when they are overwritten
+    /// or cleared by anything other than a char change.
+    fn forget_chars(&mut self) {
+        self.dirty_chars.clear();
+        self.flushed_chars.clear();
+        self.cursor_moved = false;
+    }
+
+    /// Position on the screen of the char at (`line`, `index`), if it is
+    /// shown.
+    fn screen_pos(&self, line: usize, index: u16) -> Option<(u16, u16)> {
+        self.text_area.screen_pos(
+            self.cursor_pos.lines.get(line)?,
+            index,
+            self.spacing(),
+            self.challenge == Some(Challenge::Mirror),
+        )
     }
 
     // TODO: make this private
-    /// Flushes stdout
+    /// Writes changed chars, moves the cursor to its position and
+    /// flushes stdout
+    ///
+    /// Scrolls the text area first if the cursor moved to another row.
     pub fn flush(&mut self) -> MaybeError {
+        let mut scrolled = false;
+        if let Some(line) = self.cursor_pos.lines.get(self.cursor_pos.cur_line) {
+            let row = line.row;
+            let column = line.x as usize + self.cursor_pos.cur_char_in_line as usize;
+            if row != self.text_area.current_row {
+                if self.text_area.move_to_row(row) {
+                    self.redraw_text_area()?;
+                    scrolled = true;
+                }
+                self.display_minimap()?;
+            }
+            if let Some(scroll) = &mut self.text_area.scroll {
+                if scroll.move_to_column(column) {
+                    self.redraw_text_area()?;
+                    scrolled = true;
+                }
+            }
+        }
+
+        // where the terminal cursor is after the last write, if known
+        let mut terminal_pos = None;
+        let mut written = 0usize;
+        for ((line, index), text) in std::mem::take(&mut self.dirty_chars) {
+            if self.flushed_chars.get(&(line, index)) == Some(&text) {
+                continue;
+            }
+            // chars of rows scrolled out of view are written when they are
+            // shown again
+            if let Some((x, y)) = self.screen_pos(line, index) {
+                if terminal_pos != Some((x, y)) {
+                    write!(self.stdout, "{}", self.goto(x, y))?;
+                }
+                write!(self.stdout, "{}", text)?;
+                terminal_pos = Some((x + 1, y));
+                written += 1;
+            }
+            self.flushed_chars.insert((line, index), text);
+        }
+
+        if self.cursor_moved || terminal_pos.is_some() {
+            let (line, index) = self.cursor_pos.cur_pos();
+            let cursor = self.screen_pos(line, index);
+            if let Some((x, y)) = cursor {
+                if terminal_pos != Some((x, y)) {
+                    write!(self.stdout, "{}", self.goto(x, y))?;
+                }
+            }
+            self.cursor_moved = false;
+            debug_log::log(
+                "flush",
+                [
+                    ("chars", written.into()),
+                    ("scrolled", scrolled.into()),
+                    (
+                        "cursor",
+                        cursor.map(|(x, y)| vec![x as u64, y as u64]).into(),
+                    ),
+                ],
+            );
+        }
+
         self.stdout.flush()?;
         Ok(())
     }
 
+    /// Queues `text` to be written at (`line`, `index`) on the next flush.
+    fn display_char_at<T>(&mut self, (line, index): (usize, u16), text: &T)
+    where
+        T: Display,
+    {
+        let text = self.challenge_text(text.to_string());
+        self.dirty_chars.insert((line, index), text);
+    }
+
+    /// Writes the shown rows of the text area, with all chars changed
+    /// since they were first displayed.
+    ///
+    /// Chars waiting for a flush are written too.
+    fn redraw_text_area(&mut self) -> MaybeError {
+        let area = &self.text_area;
+        if let Some(scroll) = &area.scroll {
+            let shown: String = scroll.chars[scroll.shown_columns()].iter().collect();
+            let text = Text::from(shown)
+                .with_faint()
+                .with_color(self.theme.untyped);
+            let text = self.challenge_text(text.to_string());
+            write!(
+                self.stdout,
+                "{}{}{}",
+                self.clear_row(area.y),
+                self.goto(area.x, area.y),
+                text
+            )?;
+        }
+        let area = &self.text_area;
+        for row in area.shown_rows().filter(|_| area.scroll.is_none()) {
+            let y = area.y + (row - area.first_row) as u16;
+            write!(
+                self.stdout,
+                "{}{}{}{}{}",
+                self.clear_row(y),
+                self.goto(area.x, y),
+                area.rows[row].decoration,
+                area.rows[row].text.as_deref().unwrap_or_default(),
+                area.rows[row].ending,
+            )?;
+        }
+
+        let dirty_chars = std::mem::take(&mut self.dirty_chars);
+        self.flushed_chars.extend(dirty_chars);
+        let changed_chars: Vec<((u16, u16), String)> = self
+            .flushed_chars
+            .iter()
+            .filter_map(|(&(line, index), text)| {
+                Some((self.screen_pos(line, index)?, text.clone()))
+            })
+            .collect();
+        debug_log::log(
+            "redraw",
+            [
+                ("first_row", self.text_area.first_row.into()),
+                (
+                    "first_column",
+                    self.text_area
+                        .scroll
+                        .as_ref()
+                        .map(|scroll| scroll.first_column)
+                        .into(),
+                ),
+                ("chars", changed_chars.len().into()),
+            ],
+        );
+        for ((x, y), text) in changed_chars {
+            write!(self.stdout, "{}{}", self.goto(x, y), text)?;
+        }
+
+        self.cursor_moved = true;
+        Ok(())
+    }
+
+    /// Writes the minimap column next to the text area, if the text is
+    /// scrolled and there is room for it.
+    fn display_minimap(&mut self) -> MaybeError {
+        let area = &self.text_area;
+        let Some(x) = area.minimap_x.filter(|_| area.is_scrolled()) else {
+            return Ok(());
+        };
+        for index in 0..area.height {
+            write!(
+                self.stdout,
+                "{}{}",
+                self.goto(x, area.y + index as u16),
+                area.minimap_char(index, &self.theme)
+            )?;
+        }
+        self.cursor_moved = true;
+        Ok(())
+    }
+
     /// Resets the TUI.
     ///
     /// Clears screen, moves cursor to the center and changes cursor to
     /// a blinking bar.
     pub fn reset_screen(&mut self) -> MaybeError {
-        let (sizex, sizey) = terminal_size()?;
+        let (sizex, sizey) = self.size()?;
+        debug_log::log(
+            "reset_screen",
+            [
+                ("width", u64::from(sizex).into()),
+                ("height", u64::from(sizey).into()),
+            ],
+        );
+        self.reset();
+        self.clear_images()?;
+        self.shown_lines.clear();
+        self.confetti_cells.clear();
 
         write!(
             self.stdout,
             "{}{}{}",
-            clear::All,
-            cursor::Goto(sizex / 2, sizey / 2),
+            self.clear_all(),
+            self.goto(sizex / 2, sizey / 2),
             cursor::BlinkingBar
         )?;
         self.flush()?;
@@ -300,12 +1489,6 @@ impl ToipeTui {
         let len = text.as_ref().length() as u16;
         // write!(self.stdout, "{}", cursor::Left(len / 2),)?;
 
-        // TODO: find a better way to enable this only in certain contexts
-        if self.track_lines {
-            let (x, y) = self.stdout.cursor_pos()?;
-            self.cursor_pos.lines.push(LinePos { x, y, length: len });
-        }
-
         for t in text.as_ref() {
             self.display_raw_text(t)?;
         }
@@ -329,24 +1512,218 @@ impl ToipeTui {
         [U]: HasLength,
         U: Display,
     {
-        let (sizex, sizey) = terminal_size()?;
-        let start_column = (sizex / 2).checked_sub(32).unwrap_or(0);
-
-        let line_offset = lines.len() as u16 / 2;
+        // the lines may overwrite chars written before
+        self.forget_chars();
 
-        for (line_no, line) in lines.iter().enumerate() {
-            write!(
-                self.stdout,
-                "{}",
-                cursor::Goto(start_column, sizey / 2 + (line_no as u16) - line_offset)
-            )?;
+        for (line, (x, y)) in lines.iter().zip(self.line_positions(lines.len())?) {
+            write!(self.stdout, "{}", self.goto(x, y))?;
             self.display_a_line_raw(line.as_ref())?;
+            self.shown_lines.push((x, y, line.as_ref().length() as u16));
         }
         self.flush()?;
 
         Ok(())
     }
 
+    /// Positions of the first char of `num_lines` lines displayed by
+    /// [`ToipeTui::display_lines`].
+    fn line_positions(&self, num_lines: usize) -> MaybeError<Vec<(u16, u16)>> {
+        let (sizex, sizey) = self.size()?;
+        let start_column = (sizex / 2).saturating_sub(32);
+        let line_offset = num_lines as u16 / 2;
+
+        Ok((0..num_lines as u16)
+            .map(|line_no| (start_column, sizey / 2 + line_no - line_offset))
+            .collect())
+    }
+
+    /// Whether charts are drawn as images by [`ToipeTui::display_chart`].
+    pub fn supports_images(&self) -> bool {
+        self.graphics.is_some()
+    }
+
+    /// Draws a bar chart of `values` over `rows` empty lines, starting at
+    /// `line` of `num_lines` lines displayed by
+    /// [`ToipeTui::display_lines`], if the terminal supports images.
+    pub fn display_chart(
+        &mut self,
+        num_lines: usize,
+        line: usize,
+        rows: u16,
+        values: &[f64],
+        color: ThemeColor,
+    ) -> MaybeError {
+        let Some(protocol) = self.graphics else {
+            return Ok(());
+        };
+        let Some(&(x, y)) = self.line_positions(num_lines)?.get(line) else {
+            return Ok(());
+        };
+        let image = graphics::bar_chart(values, rows as usize, color);
+        debug_log::log(
+            "chart",
+            [
+                ("protocol", format!("{:?}", protocol).into()),
+                ("columns", values.len().into()),
+                ("rows", u64::from(rows).into()),
+            ],
+        );
+        write!(
+            self.stdout,
+            "{}{}",
+            self.goto(x, y),
+            protocol.escape(&image, values.len() as u16, rows)
+        )?;
+        self.images_shown = true;
+        self.stdout.flush()?;
+        Ok(())
+    }
+
+    /// Removes the images drawn by [`ToipeTui::display_chart`].
+    fn clear_images(&mut self) -> MaybeError {
+        if let Some(protocol) = self.graphics.filter(|_| self.images_shown) {
+            write!(self.stdout, "{}", protocol.clear())?;
+            self.images_shown = false;
+        }
+        Ok(())
+    }
+
+    /// Displays lines of which only some parts are to be typed, such as
+    /// code with line numbers.
+    ///
+    /// Returns the parts to be typed, which are tracked like the lines of
+    /// [`ToipeTui::display_words`].
+    ///
+    /// When the lines do not fit on the screen, they are scrolled through
+    /// as they are typed, see [`TextArea`].
+    pub fn display_decorated_lines(&mut self, lines: &[DecoratedLine]) -> MaybeError<Vec<Text>> {
+        self.reset();
+        if self.mini {
+            return self.display_mini(lines);
+        }
+
+        let (terminal_width, terminal_height) = self.size()?;
+        // rows left between the top of the screen and the lines at the
+        // bottom. The text is centered, so it loses two rows for every row
+        // of the keyboard below it
+        let reserved = self.bottom_lines_len + 2 + 2 * self.keyboard_height();
+        let height = (terminal_height as usize)
+            .saturating_sub(reserved)
+            .min(lines.len());
+        if height < MIN_TEXT_AREA_HEIGHT.min(lines.len()) {
+            return Err(ToipeError::from(format!(
+                "Terminal height is too short! Toipe requires at least {} lines, got {} lines",
+                MIN_TEXT_AREA_HEIGHT + reserved,
+                terminal_height,
+            ))
+            .into());
+        }
+
+        let (x, y) = self.line_positions(height)?[0];
+        // columns start at 1
+        let x = x.max(1);
+
+        let mut typed = Vec::new();
+        let mut rows = Vec::new();
+        let mut width = 0;
+        for (row, line) in lines.iter().enumerate() {
+            let decoration_length = line.decoration.length() as u16;
+            let mut text_length = 0;
+            if let Some(text) = &line.text {
+                let line_pos = LinePos::new(row, x + decoration_length, text.text());
+                text_length = line_pos.column(line_pos.length, self.spacing());
+                self.cursor_pos.lines.push(line_pos);
+                typed.push(text.clone());
+            }
+            width = width.max(decoration_length + text_length + line.ending.length() as u16);
+            rows.push(Row {
+                decoration: line.decoration.iter().map(ToString::to_string).collect(),
+                text: line.text.as_ref().map(|text| self.row_text(text)),
+                ending: line.ending.iter().map(ToString::to_string).collect(),
+            });
+        }
+
+        // one column between the text and the minimap
+        let minimap_x = x + width + 1;
+        self.text_area = TextArea {
+            rows,
+            x,
+            y,
+            height,
+            first_row: 0,
+            current_row: 0,
+            minimap_x: (minimap_x <= terminal_width).then_some(minimap_x),
+            scroll: None,
+        };
+        if let Some(line) = self.cursor_pos.lines.first() {
+            self.text_area.move_to_row(line.row);
+        }
+        debug_log::log(
+            "text_area",
+            [
+                ("rows", lines.len().into()),
+                ("height", height.into()),
+                ("x", u64::from(x).into()),
+                ("y", u64::from(y).into()),
+                ("minimap", self.text_area.minimap_x.is_some().into()),
+            ],
+        );
+        self.redraw_text_area()?;
+        self.display_minimap()?;
+
+        self.move_to_cur_pos()?;
+        self.flush()?;
+
+        Ok(typed)
+    }
+
+    /// Displays the parts of `lines` to be typed one after the other in a
+    /// single row, see [`ToipeTui::set_mini`]. Decorations are left out.
+    fn display_mini(&mut self, lines: &[DecoratedLine]) -> MaybeError<Vec<Text>> {
+        let (terminal_width, terminal_height) = self.size()?;
+        if (terminal_width as usize) < MIN_MINI_WIDTH {
+            return Err(ToipeError::from(format!(
+                "Terminal width is too low! Toipe requires at least {} columns, got {} columns",
+                MIN_MINI_WIDTH, terminal_width,
+            ))
+            .into());
+        }
+
+        let mut typed = Vec::new();
+        let mut chars = Vec::new();
+        for text in lines.iter().filter_map(|line| line.text.as_ref()) {
+            let x = chars.len();
+            self.cursor_pos
+                .lines
+                .push(LinePos::new(0, x as u16, text.text()));
+            chars.extend(text.text().chars());
+            typed.push(text.clone());
+        }
+
+        self.text_area = TextArea {
+            rows: vec![Row {
+                decoration: String::new(),
+                text: None,
+                ending: String::new(),
+            }],
+            x: 1,
+            y: terminal_height.div_ceil(2).max(1),
+            height: 1,
+            scroll: Some(Scroll {
+                chars,
+                width: terminal_width,
+                first_column: 0,
+            }),
+            ..Default::default()
+        };
+        self.redraw_text_area()?;
+
+        self.move_to_cur_pos()?;
+        self.flush()?;
+
+        Ok(typed)
+    }
+
     /// Displays multiple lines of text at the bottom of the screen.
     ///
     /// See [`display_lines`] for more information.
@@ -356,94 +1733,271 @@ impl ToipeTui {
         [U]: HasLength,
         U: Display,
     {
-        let (sizex, sizey) = terminal_size()?;
-        let start_column = (sizex / 2).checked_sub(32).unwrap_or(0);
+        let (sizex, sizey) = self.size()?;
+        let start_column = (sizex / 2).saturating_sub(32);
 
         let line_offset = lines.len() as u16;
         self.bottom_lines_len = lines.len();
 
         for (line_no, line) in lines.iter().enumerate() {
-            write!(
-                self.stdout,
-                "{}",
-                cursor::Goto(start_column, sizey - 1 + (line_no as u16) - line_offset)
-            )?;
+            let y = sizey - 1 + (line_no as u16) - line_offset;
+            write!(self.stdout, "{}", self.goto(start_column, y))?;
             self.display_a_line_raw(line.as_ref())?;
+            self.shown_lines
+                .push((start_column, y, line.as_ref().length() as u16));
         }
         self.flush()?;
 
         Ok(())
     }
 
-    // TODO: document this
+    /// Displays the words to be typed, wrapped into lines.
+    ///
+    /// Returns the lines, see [`ToipeTui::display_decorated_lines`] for
+    /// how they are displayed.
     pub fn display_words(&mut self, words: &[String]) -> MaybeError<Vec<Text>> {
-        self.reset();
-        let mut current_len = 0;
-        let mut max_word_len = 0;
-        let mut line = Vec::new();
-        let mut lines = Vec::new();
-        let (terminal_width, terminal_height) = terminal_size()?;
-
-        let max_width = 64;
-
-        for word in words {
-            max_word_len = std::cmp::max(max_word_len, word.len() + 1);
-            let new_len = current_len + word.len() as u16 + 1;
-            if new_len <= max_width {
-                // add to line
-                line.push(word.clone());
-                current_len += word.len() as u16 + 1
-            } else {
-                // add an extra space at the end of each line because
-                //  user will instinctively type a space after every word
-                //  (at least I did)
-                lines.push(Text::from(line.join(" ") + " ").with_faint());
-
-                // clear line
-                line = vec![word.clone()];
-                current_len = word.len() as u16 + 1;
-            }
-        }
-
-        // last line wasn't added in loop
-        // last line doesn't have an extra space at the end
-        //   - the typing test stops as soon as the user types last char
-        //   - won't hang there waiting for user to type space
-        lines.push(Text::from(line.join(" ")).with_faint());
-
-        max_word_len = std::cmp::max(max_word_len + 1, MIN_LINE_WIDTH);
-        if lines.len() + self.bottom_lines_len + 2 > terminal_height as usize {
-            return Err(ToipeError::from(format!(
-                "Terminal height is too short! Toipe requires at least {} lines, got {} lines",
-                lines.len() + self.bottom_lines_len + 2,
-                terminal_height,
-            ))
-            .into());
-        } else if max_word_len > terminal_width as usize {
+        let (terminal_width, _) = self.size()?;
+        if !self.mini && (terminal_width as usize) < MIN_LINE_WIDTH {
             return Err(ToipeError::from(format!(
                 "Terminal width is too low! Toipe requires at least {} columns, got {} columns",
-                max_word_len, terminal_width,
+                MIN_LINE_WIDTH, terminal_width,
             ))
             .into());
         }
 
-        self.track_lines = true;
-        self.display_lines(
-            lines
+        let spacing = self.spacing() as usize;
+        let mut max_width = 64 / spacing;
+        // columns from the start of the lines to the right edge
+        let room = match self.mini {
+            true => usize::MAX,
+            false => {
+                let (x, _) = self.line_positions(1)?[0];
+                (terminal_width - x.max(1) + 1) as usize
+            }
+        };
+        let wrapped = loop {
+            let wrapped = wrap_words(words, max_width.min(room / spacing), self.hyphenate);
+            // the terminal would wrap lines going past its edge on its own,
+            // and the chars after the edge would no longer be where the
+            // cursor is moved to, so the lines are wrapped narrower
+            let widest = wrapped
                 .iter()
-                .cloned()
-                .map(|line| [line])
-                .collect::<Vec<[Text; 1]>>()
-                .as_slice(),
+                .map(|(line, end)| text_width(line) * spacing + end.mark().map_or(0, char_width))
+                .max()
+                .unwrap_or_default();
+            if widest <= room || max_width <= 1 {
+                break wrapped;
+            }
+            debug_log::log(
+                "overflow",
+                [
+                    ("max_width", max_width.into()),
+                    ("widest", widest.into()),
+                    ("room", room.into()),
+                ],
+            );
+            max_width = max_width
+                .min(room / spacing)
+                .saturating_sub((widest - room).div_ceil(spacing))
+                .max(1);
+        };
+        let (lines, line_ends): (Vec<Text>, Vec<LineEnd>) = wrapped
+            .into_iter()
+            .map(|(line, end)| {
+                (
+                    Text::from(line).with_faint().with_color(self.theme.untyped),
+                    end,
+                )
+            })
+            .unzip();
+
+        self.display_decorated_lines(
+            &lines
+                .iter()
+                .zip(line_ends)
+                .flat_map(|(line, end)| {
+                    let line = DecoratedLine {
+                        decoration: Vec::new(),
+                        text: Some(line.clone()),
+                        ending: end
+                            .mark()
+                            .map(|mark| vec![Text::from(mark).with_faint()])
+                            .unwrap_or_default(),
+                    };
+                    let blank = DecoratedLine {
+                        decoration: Vec::new(),
+                        text: None,
+                        ending: Vec::new(),
+                    };
+                    match end {
+                        LineEnd::Paragraph => vec![line, blank],
+                        _ => vec![line],
+                    }
+                })
+                .collect::<Vec<_>>(),
         )?;
-        self.track_lines = false;
-
-        self.move_to_cur_pos()?;
-        self.flush()?;
 
         Ok(lines)
     }
 
+    /// Displays a short status, such as the time left, in the row above
+    /// the text being typed, if there is one.
+    pub fn display_status(&mut self, line: &[Text]) -> MaybeError {
+        let area = &self.text_area;
+        let y = area.y.saturating_sub(1);
+        if let Some(old_y) = self.status_y.filter(|old_y| *old_y != y && *old_y < area.y) {
+            write!(self.stdout, "{}", self.clear_row(old_y))?;
+        }
+        if y >= 1 {
+            write!(self.stdout, "{}{}", self.clear_row(y), self.goto(area.x, y))?;
+            for text in line {
+                write!(self.stdout, "{}", text)?;
+            }
+            self.status_y = Some(y);
+            self.cursor_moved = true;
+        }
+        Ok(())
+    }
+
+    /// Covers the text being typed with `lines`, displayed like
+    /// [`ToipeTui::display_lines`], until [`ToipeTui::hide_overlay`].
+    pub fn display_overlay(&mut self, lines: &[Vec<Text>]) -> MaybeError {
+        let area = &self.text_area;
+        let text_ys = area
+            .shown_rows()
+            .map(|row| area.y + (row - area.first_row) as u16);
+        let positions = self.line_positions(lines.len())?;
+        self.overlay_ys = text_ys.chain(positions.iter().map(|(_, y)| *y)).collect();
+        for y in self.overlay_ys.clone() {
+            write!(self.stdout, "{}", self.clear_row(y))?;
+        }
+        for (line, (x, y)) in lines.iter().zip(positions) {
+            write!(self.stdout, "{}", self.goto(x, y))?;
+            self.display_a_line_raw(line)?;
+        }
+        self.flush()
+    }
+
+    /// Removes the overlay of [`ToipeTui::display_overlay`], showing the
+    /// text being typed again.
+    pub fn hide_overlay(&mut self) -> MaybeError {
+        for y in std::mem::take(&mut self.overlay_ys) {
+            write!(self.stdout, "{}", self.clear_row(y))?;
+        }
+        self.redraw_text_area()?;
+        self.display_minimap()?;
+        self.flush()
+    }
+
+    /// Animates the text being typed and its status collapsing into the
+    /// middle of the screen over `duration`, before it is replaced by
+    /// another screen.
+    ///
+    /// Each frame is built in the output buffer and written at once, so
+    /// that frames are never shown half drawn.
+    pub fn collapse_text(&mut self, duration: Duration) -> MaybeError {
+        let (terminal_width, _) = self.size()?;
+        let area = &self.text_area;
+        let ys: Vec<u16> = self
+            .status_y
+            .into_iter()
+            .chain(
+                area.shown_rows()
+                    .map(|row| area.y + (row - area.first_row) as u16),
+            )
+            .collect();
+
+        write!(self.stdout, "{}", cursor::Hide)?;
+        for frame in 1..=COLLAPSE_FRAMES {
+            let covered = collapsed_columns(terminal_width, frame);
+            let blank = " ".repeat(covered as usize);
+            for y in &ys {
+                write!(
+                    self.stdout,
+                    "{}{}{}{}",
+                    self.goto(1, *y),
+                    blank,
+                    self.goto(terminal_width - covered + 1, *y),
+                    blank
+                )?;
+            }
+            self.stdout.flush()?;
+            thread::sleep(duration / COLLAPSE_FRAMES as u32);
+        }
+        write!(self.stdout, "{}", cursor::Show)?;
+        self.forget_chars();
+        Ok(())
+    }
+
+    /// Draws confetti `pieces` at their positions, in place of the ones
+    /// drawn before. Pieces over the lines of [`ToipeTui::display_lines`]
+    /// are hidden behind them.
+    pub fn display_confetti(&mut self, pieces: &[((u16, u16), Text)]) -> MaybeError {
+        let is_free = |(x, y): (u16, u16)| {
+            !self.shown_lines.iter().any(|(line_x, line_y, length)| {
+                y == *line_y && (*line_x..line_x + length).contains(&x)
+            })
+        };
+        let pieces: Vec<&((u16, u16), Text)> =
+            pieces.iter().filter(|(cell, _)| is_free(*cell)).collect();
+
+        for (x, y) in std::mem::take(&mut self.confetti_cells) {
+            if !pieces.iter().any(|(cell, _)| *cell == (x, y)) {
+                write!(self.stdout, "{} ", self.goto(x, y))?;
+            }
+        }
+        for ((x, y), text) in pieces {
+            write!(self.stdout, "{}{}", self.goto(*x, *y), text)?;
+            self.confetti_cells.push((*x, *y));
+        }
+        self.stdout.flush()?;
+        Ok(())
+    }
+
+    /// Draws the on-screen keyboard above the lines at the bottom, with
+    /// the key to type `next` highlighted, if the keyboard is shown (see
+    /// [`ToipeTui::set_keyboard`]).
+    pub fn display_keyboard(&mut self, next: Option<char>) -> MaybeError {
+        let Some(layout) = &self.keyboard else {
+            return Ok(());
+        };
+        if self.keyboard_drawn && self.keyboard_state.next == next {
+            return Ok(());
+        }
+        self.keyboard_state.next = next;
+
+        let (terminal_width, terminal_height) = self.size()?;
+        let lines = keyboard_lines(layout, &self.keyboard_state, &self.theme);
+        let width = lines
+            .iter()
+            .map(|(indent, texts)| {
+                // the keys are single chars, but not all of them one byte
+                let chars: usize = texts.iter().map(|text| text.text().chars().count()).sum();
+                *indent as usize + chars
+            })
+            .max()
+            .unwrap_or_default();
+        let x = (terminal_width / 2).saturating_sub(width as u16 / 2).max(1);
+        let y = (terminal_height as usize)
+            .saturating_sub(self.bottom_lines_len + lines.len() + 2)
+            .max(1) as u16;
+
+        for (row, (indent, texts)) in lines.iter().enumerate() {
+            write!(
+                self.stdout,
+                "{}{}",
+                self.clear_row(y + row as u16),
+                self.goto(x + indent, y + row as u16)
+            )?;
+            for text in texts {
+                write!(self.stdout, "{}", text)?;
+            }
+        }
+        self.keyboard_drawn = true;
+        self.cursor_moved = true;
+        Ok(())
+    }
+
     /// Displays a [`Text`].
     pub fn display_raw_text<T>(&mut self, text: &T) -> MaybeError
     where
@@ -480,32 +2034,56 @@ impl ToipeTui {
         T: Display,
     {
         self.move_to_prev_char()?;
-        self.display_raw_text(&text)?;
-        self.move_to_cur_pos()?;
+        self.display_char_at(self.cursor_pos.cur_pos(), &text);
 
         Ok(())
     }
 
+    /// Displays a [`Text`] in place of the char under the cursor and
+    /// moves the cursor to the next char.
+    ///
+    /// NOTE: only call this with [`Text`]s containing one character.
+    pub fn type_text<T>(&mut self, text: &T) -> MaybeError
+    where
+        T: Display,
+    {
+        self.display_char_at(self.cursor_pos.cur_pos(), text);
+        self.move_to_next_char()
+    }
+
+    /// Displays a [`Text`] in place of the char at `index` of the
+    /// displayed words, without moving the cursor.
+    ///
+    /// NOTE: only call this with [`Text`]s containing one character.
+    pub fn display_at_char<T>(&mut self, index: usize, text: &T) -> MaybeError
+    where
+        T: Display,
+    {
+        if let Some(pos) = self.cursor_pos.pos_of_char(index) {
+            self.display_char_at(pos, text);
+        }
+        Ok(())
+    }
+
     /// Moves the cursor to the next char
     pub fn move_to_next_char(&mut self) -> MaybeError {
-        let (x, y) = self.cursor_pos.next();
-        write!(self.stdout, "{}", cursor::Goto(x, y))?;
+        self.cursor_pos.next();
+        self.cursor_moved = true;
 
         Ok(())
     }
 
     /// Moves the cursor to the previous char
     pub fn move_to_prev_char(&mut self) -> MaybeError {
-        let (x, y) = self.cursor_pos.prev();
-        write!(self.stdout, "{}", cursor::Goto(x, y))?;
+        self.cursor_pos.prev();
+        self.cursor_moved = true;
 
         Ok(())
     }
 
     /// Moves the cursor to just before the character to be typed next
     pub fn move_to_cur_pos(&mut self) -> MaybeError {
-        let (x, y) = self.cursor_pos.cur_pos();
-        write!(self.stdout, "{}", cursor::Goto(x, y))?;
+        self.cursor_moved = true;
 
         Ok(())
     }
@@ -533,11 +2111,290 @@ impl Drop for ToipeTui {
         write!(
             self.stdout,
             "{}{}{}",
-            clear::All,
+            self.clear_all(),
             cursor::SteadyBlock,
-            cursor::Goto(1, 1)
+            self.goto(1, 1)
         )
         .expect("Could not reset terminal while exiting");
+        self.clear_images()
+            .expect("Could not reset terminal while exiting");
+        if self.window_title {
+            write!(self.stdout, "{}", title::Restore)
+                .expect("Could not reset terminal while exiting");
+        }
         self.flush().expect("Could not flush stdout while exiting");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_area(num_rows: usize, height: usize) -> TextArea {
+        TextArea {
+            rows: (0..num_rows)
+                .map(|_| Row {
+                    decoration: String::new(),
+                    text: Some("words ".to_string()),
+                    ending: String::new(),
+                })
+                .collect(),
+            y: 10,
+            height,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn wrapped_lines_fit_with_extreme_punctuation() {
+        use crate::textgen::{selector_rng, RawWordSelector, Selector};
+
+        let words = [
+            "a",
+            "internationalization",
+            "café",
+            "東京都庁舎",
+            "🎉🎉🎉",
+            "supercalifragilisticexpialidocious",
+        ];
+        let raw = RawWordSelector::from_iter(
+            words.iter().map(|word| Ok(word.to_string())),
+            selector_rng(Some(1)),
+        )
+        .unwrap();
+        let mut selector = Selector::from_word_selector(Box::new(raw), selector_rng(Some(2)))
+            .with_punctuation(1.0)
+            .build();
+        let words = selector.new_words(200).unwrap();
+
+        for max_width in [8, 20, 64] {
+            for hyphenate in [false, true] {
+                let lines = wrap_words(&words, max_width, hyphenate);
+                for (line, end) in &lines {
+                    let width = text_width(line) + end.mark().map_or(0, char_width);
+                    assert!(width <= max_width, "{:?} is {} columns", line, width);
+                }
+                let text: String = lines.iter().map(|(line, _)| line.as_str()).collect();
+                assert_eq!(text, words.join(" "));
+            }
+        }
+        assert_eq!(
+            wrap_words(&["東京都庁舎".to_string()], 6, false),
+            [
+                ("東京".to_string(), LineEnd::Wrap),
+                ("都庁".to_string(), LineEnd::Wrap),
+                ("舎".to_string(), LineEnd::Word)
+            ]
+        );
+    }
+
+    #[test]
+    fn scrolls_to_keep_the_current_row_in_the_middle() {
+        let mut area = text_area(20, 5);
+        assert!(!area.move_to_row(2));
+        assert_eq!(area.row_y(0), Some(10));
+
+        assert!(area.move_to_row(3));
+        assert_eq!(area.shown_rows(), 1..6);
+        assert_eq!(area.row_y(0), None);
+        assert_eq!(area.row_y(3), Some(12));
+
+        // the last rows are shown without scrolling further
+        area.move_to_row(19);
+        assert_eq!(area.shown_rows(), 15..20);
+    }
+
+    #[test]
+    fn positions_are_relative_to_the_region() {
+        let region = Region {
+            x: 41,
+            y: 5,
+            width: 3,
+            height: 2,
+        };
+        assert_eq!(region.goto(1, 1), cursor::Goto(41, 5));
+        assert_eq!(region.goto(3, 2), cursor::Goto(43, 6));
+        // columns and rows start at 1, as on the whole terminal
+        assert_eq!(region.goto(0, 0), cursor::Goto(41, 5));
+        assert_eq!(region.clear_row(2), format!("{}   ", cursor::Goto(41, 6)));
+    }
+
+    #[test]
+    fn challenges_keep_formatting() {
+        let text = Text::from("(bad) 69").with_faint().to_string();
+        assert_eq!(
+            Challenge::Mirror.transform(&text),
+            Text::from("96 (dab)").with_faint().to_string()
+        );
+        assert_eq!(
+            Challenge::Flip.transform(&text),
+            Text::from("(qɐp) 96").with_faint().to_string()
+        );
+    }
+
+    #[test]
+    fn wide_chars_take_two_columns() {
+        let area = text_area(1, 1);
+        let line = LinePos::new(0, 5, "東京 to");
+        let pos = |index, spacing, mirrored| area.screen_pos(&line, index, spacing, mirrored);
+        assert_eq!(pos(0, 1, false), Some((5, 10)));
+        assert_eq!(pos(1, 1, false), Some((7, 10)));
+        assert_eq!(pos(2, 1, false), Some((9, 10)));
+        assert_eq!(pos(4, 1, false), Some((11, 10)));
+        // spaced out for low vision
+        assert_eq!(pos(2, 2, false), Some((11, 10)));
+        // shown as "ot 京東"
+        assert_eq!(pos(0, 1, true), Some((10, 10)));
+        assert_eq!(pos(1, 1, true), Some((8, 10)));
+        assert_eq!(pos(4, 1, true), Some((5, 10)));
+        assert_eq!(Text::from("東京 to").length(), 7);
+    }
+
+    #[test]
+    fn short_texts_are_not_scrolled() {
+        let mut area = text_area(3, 3);
+        assert!(!area.is_scrolled());
+        assert!(!area.move_to_row(2));
+        assert_eq!(area.shown_rows(), 0..3);
+    }
+
+    #[test]
+    fn styles_combine() {
+        assert_eq!(Text::from("ab").to_string(), "ab");
+        assert_eq!(
+            Text::from("ab").with_bold().with_underline().to_string(),
+            "\x1b[1m\x1b[4mab\x1b[22m\x1b[24m"
+        );
+        assert_eq!(
+            Text::from("ab")
+                .with_color(ThemeColor::Rgb(1, 2, 3))
+                .with_background(color::Blue)
+                .with_italic()
+                .to_string(),
+            "\x1b[3m\x1b[38;2;1;2;3m\x1b[48;5;4mab\x1b[23m\x1b[39m\x1b[49m"
+        );
+        // the color is not nested, the last one is used
+        assert_eq!(
+            Text::from("a")
+                .with_color(color::Red)
+                .with_color(color::Green)
+                .with_faint()
+                .to_string(),
+            "\x1b[2m\x1b[38;5;2ma\x1b[22m\x1b[39m"
+        );
+        let style = Text::from("a").with_faint().with_underline().style();
+        assert!(style.faint && style.underline && !style.bold);
+        assert_eq!(
+            Text::from("b").with_style(style).to_string(),
+            "\x1b[2m\x1b[4mb\x1b[22m\x1b[24m"
+        );
+    }
+
+    #[test]
+    fn low_vision_text() {
+        let text = Text::from("ab")
+            .with_faint()
+            .with_color(color::Red)
+            .to_string();
+        assert_eq!(
+            spaced_text(&text),
+            Text::from("a b ")
+                .with_faint()
+                .with_color(color::Red)
+                .to_string()
+        );
+
+        let bold = bold_text(&text);
+        assert!(bold.starts_with(&style::Bold.to_string()));
+        assert!(!bold.contains(&style::Faint.to_string()));
+        assert_eq!(
+            bold_text(&Text::from("ab").with_faint().to_string()),
+            format!("{}ab{}", style::Bold, style::Reset)
+        );
+    }
+
+    #[test]
+    fn keyboard_highlights_next_key() {
+        let layout = KeyboardLayout::qwerty();
+        let theme = Theme::default();
+        let highlighted = |next| -> Vec<String> {
+            let state = KeyboardState {
+                next,
+                ..KeyboardState::default()
+            };
+            keyboard_lines(&layout, &state, &theme)
+                .into_iter()
+                .flat_map(|(_, texts)| texts)
+                .filter(|text| text.style().underline)
+                .map(|text| text.text().clone())
+                .collect()
+        };
+
+        assert_eq!(highlighted(Some('a')), ["a"]);
+        // the right shift for a key of the left hand
+        assert_eq!(highlighted(Some('A')), ["a", "⇧"]);
+        assert_eq!(highlighted(Some(' ')), ["─".repeat(SPACE_BAR_WIDTH)]);
+        assert!(highlighted(Some('é')).is_empty());
+        assert!(highlighted(None).is_empty());
+
+        let lines = keyboard_lines(&layout, &KeyboardState::default(), &theme);
+        assert_eq!(lines.len(), layout.rows.len());
+        // the right shift comes last
+        let bottom = &lines[3].1;
+        assert_eq!(bottom.last().unwrap().text(), "⇧");
+    }
+
+    #[test]
+    fn keyboard_heat() {
+        let layout = KeyboardLayout::qwerty();
+        let mut state = KeyboardState::default();
+        for c in "aaaaaaS".chars() {
+            for key in KeyboardState::keys(&layout, c) {
+                *state.heat.entry(key).or_default() += 1;
+            }
+        }
+
+        let key = |c| KeyboardState::keys(&layout, c)[0];
+        assert_eq!(state.heat_color(key('a')), Some(HEAT_COLORS[2]));
+        assert_eq!(state.heat_color(key('s')), Some(HEAT_COLORS[0]));
+        assert_eq!(
+            state.heat_color(KeyId::Shift(Hand::Right)),
+            Some(HEAT_COLORS[0])
+        );
+        assert_eq!(state.heat_color(KeyId::Shift(Hand::Left)), None);
+        assert_eq!(state.heat_color(key('z')), None);
+    }
+
+    #[test]
+    fn mini_row_scrolls_with_the_cursor() {
+        let mut scroll = Scroll {
+            chars: vec!['a'; 100],
+            width: 30,
+            first_column: 0,
+        };
+        assert!(!scroll.move_to_column(19));
+        assert_eq!(scroll.shown_columns(), 0..30);
+        assert!(scroll.move_to_column(20));
+        assert_eq!(scroll.shown_columns(), 10..40);
+        // the end of the text stays at the end of the row
+        assert!(scroll.move_to_column(95));
+        assert_eq!(scroll.shown_columns(), 70..100);
+        assert!(scroll.move_to_column(5));
+        assert_eq!(scroll.shown_columns(), 0..30);
+    }
+
+    #[test]
+    fn text_collapses_to_the_middle() {
+        assert_eq!(collapsed_columns(80, 0), 0);
+        assert_eq!(collapsed_columns(80, COLLAPSE_FRAMES / 2), 30);
+        // the middle column of an odd width is covered too
+        assert_eq!(collapsed_columns(81, COLLAPSE_FRAMES), 41);
+    }
+
+    #[test]
+    fn window_titles() {
+        assert_eq!(progress_title(0.624, 84.4), "toipe — 62% — 84wpm");
+        assert_eq!(progress_title(1.5, f64::NAN), "toipe — 100% — 0wpm");
+        assert_eq!(title::Set("a\x07b").to_string(), "\x1b]0;ab\x07");
+    }
+}
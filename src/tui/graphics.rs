@@ -0,0 +1,303 @@
+//! Charts drawn as inline images, in terminals that support an image
+//! protocol, or else as a sparkline of block chars.
+//!
+//! Images are sent with the graphics protocol of kitty, or the inline
+//! images protocol of iTerm2 (also supported by WezTerm), which needs
+//! them as PNG files. Both are detected from environment variables set by
+//! the terminal. Inside tmux or screen, which do not pass images through,
+//! the sparkline is used.
+
+use std::env;
+
+use crate::theme::ThemeColor;
+
+/// Block chars of the sparkline, from lowest to highest.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Pixels of the image per column of a chart.
+const PIXELS_PER_COLUMN: usize = 8;
+
+/// Pixels of the image per row of a chart.
+const PIXELS_PER_ROW: usize = 16;
+
+/// Largest chunk of base64 data in one kitty graphics command.
+const KITTY_CHUNK_LEN: usize = 4096;
+
+/// Image protocol of a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Iterm2,
+}
+
+impl Protocol {
+    /// The protocol of the terminal toipe runs in, if it has one.
+    pub fn detect() -> Option<Self> {
+        let var = |name| env::var(name).unwrap_or_default();
+        Self::from_env(&var("TERM"), &var("TERM_PROGRAM"), &var("KITTY_WINDOW_ID"))
+    }
+
+    fn from_env(term: &str, term_program: &str, kitty_window_id: &str) -> Option<Self> {
+        if term.starts_with("tmux") || term.starts_with("screen") {
+            None
+        } else if term == "xterm-kitty" || !kitty_window_id.is_empty() {
+            Some(Self::Kitty)
+        } else if term_program == "iTerm.app" || term_program == "WezTerm" {
+            Some(Self::Iterm2)
+        } else {
+            None
+        }
+    }
+
+    /// Escape sequence drawing `image` at the cursor, stretched over
+    /// `columns` by `rows` cells.
+    pub fn escape(self, image: &Image, columns: u16, rows: u16) -> String {
+        match self {
+            Self::Kitty => {
+                let data = base64(&image.pixels);
+                let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK_LEN).collect();
+                let mut escape = String::new();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = (i + 1 < chunks.len()) as u8;
+                    let chunk = std::str::from_utf8(chunk).expect("base64 is ASCII");
+                    if i == 0 {
+                        // the cursor stays put, the text around the image
+                        // is written separately
+                        escape.push_str(&format!(
+                            "\x1b_Ga=T,f=32,s={},v={},c={},r={},C=1,q=2,m={};{}\x1b\\",
+                            image.width, image.height, columns, rows, more, chunk
+                        ));
+                    } else {
+                        escape.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                    }
+                }
+                escape
+            }
+            Self::Iterm2 => format!(
+                "\x1b]1337;File=inline=1;width={};height={};preserveAspectRatio=0:{}\x07",
+                columns,
+                rows,
+                base64(&image.png())
+            ),
+        }
+    }
+
+    /// Escape sequence removing the images drawn so far, if they are not
+    /// removed along with the text.
+    pub fn clear(self) -> &'static str {
+        match self {
+            Self::Kitty => "\x1b_Ga=d,q=2\x1b\\",
+            Self::Iterm2 => "",
+        }
+    }
+}
+
+/// An image with 8-bit RGBA pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// pixels row by row, from the top left
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// A transparent image.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, [r, g, b]: [u8; 3]) {
+        let start = (y * self.width + x) * 4;
+        self.pixels[start..start + 4].copy_from_slice(&[r, g, b, 255]);
+    }
+
+    /// The image as a PNG file, with the pixels stored uncompressed.
+    pub fn png(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8 bits per channel, RGBA, no interlacing
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        // each row starts with a filter type, none here
+        let mut scanlines = Vec::with_capacity(self.pixels.len() + self.height);
+        for row in self.pixels.chunks(self.width * 4) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+/// A bar chart of `values`, a bar per column over `rows` rows, in `color`.
+///
+/// Bars are scaled so that the highest value fills the chart.
+pub fn bar_chart(values: &[f64], rows: usize, color: ThemeColor) -> Image {
+    let height = rows * PIXELS_PER_ROW;
+    let mut image = Image::new(values.len() * PIXELS_PER_COLUMN, height);
+    let max = values.iter().copied().fold(0.0, f64::max);
+    // the default color of the terminal is unknown
+    let color = color.to_rgb().unwrap_or([255, 255, 255]);
+    for (column, value) in values.iter().enumerate() {
+        let bar_height = if max > 0.0 {
+            ((value / max) * height as f64).round() as usize
+        } else {
+            0
+        };
+        // a pixel of space between bars
+        let bar_x = column * PIXELS_PER_COLUMN;
+        for x in bar_x + 1..bar_x + PIXELS_PER_COLUMN {
+            for y in height - bar_height.min(height)..height {
+                image.set(x, y, color);
+            }
+        }
+    }
+    image
+}
+
+/// `values` as block chars of different heights, scaled so that the
+/// highest value is a full block.
+pub fn sparkline(values: &[f64]) -> String {
+    let max = values.iter().copied().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            let level = if max > 0.0 {
+                (value / max * (SPARKS.len() - 1) as f64).round() as usize
+            } else {
+                0
+            };
+            SPARKS[level.min(SPARKS.len() - 1)]
+        })
+        .collect()
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | ((*byte as u32) << (16 - 8 * i))
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Appends a PNG chunk of `kind` with `data`.
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// `data` as a zlib stream of stored (uncompressed) deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // deflate with a 32K window, no dictionary
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = data.chunks(u16::MAX as usize).collect();
+    if blocks.is_empty() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    for (i, block) in blocks.iter().enumerate() {
+        let len = block.len() as u16;
+        zlib.push((i + 1 == blocks.len()) as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(data).to_be_bytes());
+    zlib
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_protocols() {
+        assert_eq!(
+            Protocol::from_env("xterm-kitty", "", ""),
+            Some(Protocol::Kitty)
+        );
+        assert_eq!(
+            Protocol::from_env("xterm-256color", "iTerm.app", ""),
+            Some(Protocol::Iterm2)
+        );
+        assert_eq!(Protocol::from_env("tmux-256color", "", "1"), None);
+        assert_eq!(Protocol::from_env("xterm-256color", "", ""), None);
+    }
+
+    #[test]
+    fn sparklines() {
+        assert_eq!(sparkline(&[0.0, 35.0, 70.0]), "▁▅█");
+        assert_eq!(sparkline(&[0.0, 0.0]), "▁▁");
+    }
+
+    #[test]
+    fn checksums_and_encodings() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(base64(b"toipe"), "dG9pcGU=");
+        assert_eq!(base64(b"wpm"), "d3Bt");
+    }
+
+    #[test]
+    fn charts() {
+        let image = bar_chart(&[1.0, 2.0], 1, ThemeColor::Rgb(1, 2, 3));
+        assert_eq!((image.width, image.height), (16, 16));
+        // the first bar is half as high as the second
+        let alpha = |x: usize, y: usize| image.pixels[(y * image.width + x) * 4 + 3];
+        assert_eq!((alpha(4, 7), alpha(4, 8)), (0, 255));
+        assert_eq!(alpha(12, 0), 255);
+        // the gap between bars
+        assert_eq!(alpha(8, 15), 0);
+
+        let png = image.png();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+    }
+}
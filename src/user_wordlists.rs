@@ -0,0 +1,292 @@
+//! Word lists installed by the user with `toipe wordlist install`.
+//!
+//! Installed word lists are stored as plain files in
+//! [`dirs::wordlists_dir`], named after the word list. They can be used
+//! with `toipe -f <name>`.
+//!
+//! Word lists can be installed by name from a curated index (see
+//! [`DEFAULT_INDEX_URL`]) or directly from a URL, see [`download`].
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    checksum::sha256_hex, config::WordlistCommand, dirs, http::download, storage, wordlist_lint,
+};
+
+/// URL of the curated word list index.
+///
+/// See `wordlists/index.tsv` in the repository for the format.
+pub const DEFAULT_INDEX_URL: &str =
+    "https://raw.githubusercontent.com/Samyak2/toipe/main/wordlists/index.tsv";
+
+/// An entry in the word list index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// name the word list is installed as
+    pub name: String,
+    /// expected SHA-256 checksum of the word list file, in hex
+    pub sha256: String,
+    /// where to download the word list from
+    pub url: String,
+    /// short human-readable description
+    pub description: String,
+}
+
+/// Parses the contents of a word list index.
+///
+/// Each non-empty line not starting with `#` has the tab-separated
+/// fields `name`, `sha256`, `url` and an optional `description`.
+pub fn parse_index(contents: &str) -> Result<Vec<IndexEntry>, Error> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(line_no, line)| {
+            let mut fields = line.split('\t');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(name), Some(sha256), Some(url)) => Ok(IndexEntry {
+                    name: name.to_string(),
+                    sha256: sha256.to_ascii_lowercase(),
+                    url: url.to_string(),
+                    description: fields.next().unwrap_or_default().to_string(),
+                }),
+                _ => Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Malformed word list index at line {}", line_no + 1),
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Fetches and parses the word list index at `index_url`.
+pub fn fetch_index(index_url: &str) -> Result<Vec<IndexEntry>, Error> {
+    let contents = download(index_url)?;
+    parse_index(&String::from_utf8_lossy(&contents))
+}
+
+/// Checks that `name` can be used as a file name for an installed word
+/// list.
+fn validate_name(name: &str) -> Result<(), Error> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Invalid word list name `{}`. Names may only contain letters, digits, `-`, `_` and `.`",
+                name
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Path of the installed word list with the given name, if it exists.
+pub fn find_installed(name: &str) -> Option<PathBuf> {
+    validate_name(name).ok()?;
+    let path = dirs::wordlists_dir().ok()?.join(name);
+    path.is_file().then_some(path)
+}
+
+/// Names of all installed word lists, sorted.
+pub fn list_installed() -> Result<Vec<String>, Error> {
+    let dir = dirs::wordlists_dir()?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Downloads a word list and installs it under `name`.
+///
+/// If `expected_sha256` is given, the download is verified against it
+/// before anything is written to disk.
+pub fn install(name: &str, url: &str, expected_sha256: Option<&str>) -> Result<PathBuf, Error> {
+    validate_name(name)?;
+
+    let contents = download(url)?;
+
+    if let Some(expected) = expected_sha256 {
+        let actual = sha256_hex(&contents);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Checksum mismatch for {}: expected {}, got {}",
+                    url, expected, actual
+                ),
+            ));
+        }
+    }
+
+    let path = dirs::wordlists_dir()?.join(name);
+    storage::write(&path, &contents)?;
+    Ok(path)
+}
+
+/// Removes the installed word list with the given name.
+pub fn remove(name: &str) -> Result<(), Error> {
+    let path = find_installed(name).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("No installed word list named `{}`", name),
+        )
+    })?;
+    storage::remove(&path)
+}
+
+/// Derives a word list name from the last segment of a URL.
+fn name_from_url(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    Path::new(path).file_name()?.to_str()
+}
+
+fn is_url(name_or_url: &str) -> bool {
+    name_or_url.contains("://")
+}
+
+/// Runs a `toipe wordlist` command, printing its output to stdout.
+pub fn run(command: &WordlistCommand) -> Result<(), Error> {
+    match command {
+        WordlistCommand::Install {
+            name_or_url,
+            name,
+            sha256,
+            index,
+        } => {
+            let (name, url, sha256) = if is_url(name_or_url) {
+                let name = name.as_deref().or_else(|| name_from_url(name_or_url));
+                let name = name.ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        "Could not determine word list name from URL, use --name",
+                    )
+                })?;
+                (name.to_string(), name_or_url.clone(), sha256.clone())
+            } else {
+                let entry = fetch_index(index)?
+                    .into_iter()
+                    .find(|entry| &entry.name == name_or_url)
+                    .ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::NotFound,
+                            format!(
+                                "No word list named `{}` in the index. \
+                                See `toipe wordlist list --available`",
+                                name_or_url
+                            ),
+                        )
+                    })?;
+                (
+                    name.clone().unwrap_or(entry.name),
+                    entry.url,
+                    Some(entry.sha256),
+                )
+            };
+
+            if sha256.is_none() {
+                eprintln!("warning: no checksum given, the download will not be verified");
+            }
+
+            let path = install(&name, &url, sha256.as_deref())?;
+            println!("Installed `{}` to {}", name, path.display());
+            println!("Use it with: toipe -f {}", name);
+        }
+        WordlistCommand::List { available, index } => {
+            if *available {
+                for entry in fetch_index(index)? {
+                    println!("{}\t{}", entry.name, entry.description);
+                }
+            } else {
+                let names = list_installed()?;
+                if names.is_empty() {
+                    println!("No word lists installed. See `toipe wordlist install --help`");
+                }
+                for name in names {
+                    println!("{}", name);
+                }
+            }
+        }
+        WordlistCommand::Lint { file } => {
+            let lints = wordlist_lint::lint(&fs::read_to_string(file)?);
+            for lint in &lints {
+                println!("{}", lint);
+            }
+            match lints.len() {
+                0 => println!("No problems found in {}", file),
+                1 => println!("1 problem found in {}", file),
+                n => println!("{} problems found in {}", n, file),
+            }
+        }
+        WordlistCommand::Remove { name } => {
+            remove(name)?;
+            println!("Removed `{}`", name);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_index() {
+        let entries = parse_index(
+            "# comment\n\
+            \n\
+            top250\tABCD\thttps://example.com/top250\tTop 250 words\n\
+            nodesc\t1234\thttps://example.com/nodesc\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                IndexEntry {
+                    name: "top250".to_string(),
+                    sha256: "abcd".to_string(),
+                    url: "https://example.com/top250".to_string(),
+                    description: "Top 250 words".to_string(),
+                },
+                IndexEntry {
+                    name: "nodesc".to_string(),
+                    sha256: "1234".to_string(),
+                    url: "https://example.com/nodesc".to_string(),
+                    description: "".to_string(),
+                },
+            ]
+        );
+
+        assert!(parse_index("missing\tfields\n").is_err());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            name_from_url("https://example.com/lists/german.txt?raw=1"),
+            Some("german.txt")
+        );
+        assert!(validate_name("german.txt").is_ok());
+        assert!(validate_name("../etc/passwd").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("").is_err());
+    }
+}
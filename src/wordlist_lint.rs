@@ -0,0 +1,180 @@
+//! Checks for problems in word lists, see `toipe wordlist lint`.
+
+use std::{collections::HashMap, fmt};
+
+use crate::tui::{char_width, text_width, MIN_LINE_WIDTH};
+
+/// Widest word, in columns, that is not broken across lines in the
+/// narrowest terminal, leaving a column for the space after it.
+pub const MAX_WORD_WIDTH: usize = MIN_LINE_WIDTH - 1;
+
+/// A problem with a word in a word list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// the word already appeared on an earlier line (ignoring case, as
+    /// words are lowercased when read)
+    Duplicate { first_line: usize },
+    /// the word contains a control or invisible char
+    NonPrintable(char),
+    /// the word is wider than [`MAX_WORD_WIDTH`], in columns
+    TooLong(usize),
+    /// the word mixes letters from several scripts, such as a Cyrillic
+    /// `а` in a Latin word
+    MixedScripts(Vec<&'static str>),
+    /// the word contains a combining char, which is not displayed
+    /// correctly
+    BreaksRendering(char),
+}
+
+/// A problem found by [`lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// line of the word, starting at 1
+    pub line: usize,
+    pub word: String,
+    pub kind: LintKind,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {:?} ", self.line, self.word)?;
+        match &self.kind {
+            LintKind::Duplicate { first_line } => {
+                write!(f, "is a duplicate of line {}", first_line)
+            }
+            LintKind::NonPrintable(c) => {
+                write!(f, "contains the non-printable char U+{:04X}", *c as u32)
+            }
+            LintKind::TooLong(width) => write!(
+                f,
+                "is {} columns wide, wider than a line can be ({} columns)",
+                width, MAX_WORD_WIDTH
+            ),
+            LintKind::MixedScripts(scripts) => {
+                write!(f, "mixes the scripts {}", scripts.join(", "))
+            }
+            LintKind::BreaksRendering(c) => write!(
+                f,
+                "contains {:?} (U+{:04X}), which is not displayed correctly",
+                c, *c as u32
+            ),
+        }
+    }
+}
+
+/// Writing system of a letter, for the scripts commonly found in word
+/// lists.
+fn script(c: char) -> Option<&'static str> {
+    let script = match c as u32 {
+        0x41..=0x5A | 0x61..=0x7A | 0xC0..=0xD6 | 0xD8..=0xF6 | 0xF8..=0x24F => "Latin",
+        0x1E00..=0x1EFF => "Latin",
+        0x370..=0x3FF | 0x1F00..=0x1FFF => "Greek",
+        0x400..=0x52F => "Cyrillic",
+        0x530..=0x58F => "Armenian",
+        0x590..=0x5FF => "Hebrew",
+        0x600..=0x6FF => "Arabic",
+        0x900..=0x97F => "Devanagari",
+        0xE00..=0xE7F => "Thai",
+        0x1100..=0x11FF | 0xAC00..=0xD7AF => "Hangul",
+        0x3040..=0x30FF | 0x4E00..=0x9FFF => "CJK",
+        _ => return None,
+    };
+    Some(script)
+}
+
+/// Whether a char is invisible when printed.
+fn is_non_printable(c: char) -> bool {
+    c.is_control() || matches!(c as u32, 0xAD | 0x200B..=0x200F | 0x2060..=0x2064 | 0xFEFF)
+}
+
+/// Whether a char is combined with the previous one when printed, so
+/// that it takes no column of its own. Wide chars are fine.
+fn breaks_rendering(c: char) -> bool {
+    char_width(c) == 0
+}
+
+/// Finds problems with the words in the contents of a word list file.
+///
+/// Words are separated by whitespace, as when the word list is used.
+pub fn lint(contents: &str) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let mut first_lines = HashMap::new();
+
+    for (line_no, line) in contents.lines().enumerate() {
+        let line_no = line_no + 1;
+        for word in line.split_whitespace() {
+            let mut push = |kind| {
+                lints.push(Lint {
+                    line: line_no,
+                    word: word.to_string(),
+                    kind,
+                })
+            };
+
+            let first_line = *first_lines
+                .entry(word.to_ascii_lowercase())
+                .or_insert(line_no);
+            if first_line != line_no {
+                push(LintKind::Duplicate { first_line });
+            }
+
+            if let Some(c) = word.chars().find(|c| is_non_printable(*c)) {
+                push(LintKind::NonPrintable(c));
+            }
+
+            let width = text_width(word);
+            if width > MAX_WORD_WIDTH {
+                push(LintKind::TooLong(width));
+            }
+
+            let mut scripts: Vec<_> = word.chars().filter_map(script).collect();
+            scripts.sort_unstable();
+            scripts.dedup();
+            if scripts.len() > 1 {
+                push(LintKind::MixedScripts(scripts));
+            }
+
+            if let Some(c) = word.chars().find(|c| breaks_rendering(*c)) {
+                push(LintKind::BreaksRendering(c));
+            }
+        }
+    }
+
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_problems() {
+        let long = "a".repeat(MAX_WORD_WIDTH + 1);
+        let contents = format!(
+            "hello world\nHello\nbe\u{200b}ll\n{}\nаpple\n日本\nnaïve café\ncafe\u{301}\n",
+            long
+        );
+
+        let kinds: Vec<_> = lint(&contents)
+            .into_iter()
+            .map(|lint| (lint.line, lint.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (2, LintKind::Duplicate { first_line: 1 }),
+                (3, LintKind::NonPrintable('\u{200b}')),
+                (4, LintKind::TooLong(MAX_WORD_WIDTH + 1)),
+                (5, LintKind::MixedScripts(vec!["Cyrillic", "Latin"])),
+                (8, LintKind::BreaksRendering('\u{301}')),
+            ]
+        );
+
+        // wide chars take two columns each
+        let kinds: Vec<_> = lint(&"日".repeat(25))
+            .into_iter()
+            .map(|lint| lint.kind)
+            .collect();
+        assert_eq!(kinds, [LintKind::TooLong(50)]);
+    }
+}
@@ -0,0 +1,160 @@
+//! The screen shown when the word list of a test has no words.
+//!
+//! Instead of only failing with an [`EmptyWordlistError`], an
+//! interactive `toipe` offers to take the test with one of the built-in
+//! word lists, see
+//! [`ToipeConfig::use_built_in_wordlist`](crate::config::ToipeConfig::use_built_in_wordlist).
+
+use anyhow::Result;
+use clap::ArgEnum;
+
+use crate::{
+    backend::{Backend, Key, Terminal},
+    events::{EventStream, ToipeEvent},
+    theme::{ColorDepth, Theme},
+    tty::Tty,
+    tui::{Text, ToipeTui},
+    wordlists::BuiltInWordlist,
+    EmptyWordlistError,
+};
+
+/// The built-in word lists to pick from and the one selected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Picker {
+    pub wordlists: Vec<BuiltInWordlist>,
+    pub selected: usize,
+}
+
+impl Default for Picker {
+    fn default() -> Self {
+        Self {
+            wordlists: BuiltInWordlist::value_variants().to_vec(),
+            selected: 0,
+        }
+    }
+}
+
+impl Picker {
+    /// Moves through the word lists with up and down.
+    ///
+    /// Returns the word list picked with enter, or `None` when quit with
+    /// esc or ctrl-c. Returns nothing while still picking.
+    pub fn handle(&mut self, key: Key) -> Option<Option<BuiltInWordlist>> {
+        match key {
+            Key::Ctrl('c') | Key::Esc => return Some(None),
+            Key::Char('\n') => return Some(Some(self.wordlists[self.selected])),
+            Key::Up | Key::Char('k') => {
+                self.selected = self
+                    .selected
+                    .checked_sub(1)
+                    .unwrap_or(self.wordlists.len() - 1);
+            }
+            Key::Down | Key::Char('j') | Key::Char('\t') => {
+                self.selected = (self.selected + 1) % self.wordlists.len();
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Name of `wordlist` as given to `--wordlist`.
+fn name(wordlist: BuiltInWordlist) -> String {
+    wordlist
+        .to_possible_value()
+        .map_or_else(String::new, |value| value.get_name().to_string())
+}
+
+fn display(
+    tui: &mut ToipeTui,
+    picker: &Picker,
+    error: &EmptyWordlistError,
+    theme: Theme,
+) -> Result<()> {
+    let key = |text: &str| Text::from(text.to_string()).with_color(theme.hint);
+    let faint = |text: &str| Text::from(text.to_string()).with_faint();
+    let mut lines: Vec<Vec<Text>> = vec![
+        vec![Text::from(error.to_string()).with_color(theme.warning)],
+        Vec::new(),
+        vec![Text::from("Take the test with a built-in word list instead?").with_bold()],
+        Vec::new(),
+    ];
+
+    let width = picker
+        .wordlists
+        .iter()
+        .map(|&wordlist| name(wordlist).len())
+        .max()
+        .unwrap_or_default();
+    for (i, &wordlist) in picker.wordlists.iter().enumerate() {
+        let label = format!("{:<width$}", name(wordlist), width = width);
+        lines.push(match i == picker.selected {
+            true => vec![
+                Text::from("> ").with_color(theme.cursor),
+                Text::from(label).with_color(theme.cursor).with_bold(),
+            ],
+            false => vec![Text::from("  "), Text::from(label)],
+        });
+    }
+    lines.push(Vec::new());
+    lines.push(vec![
+        key("up/down"),
+        faint(" to choose, "),
+        key("enter"),
+        faint(" to start, "),
+        key("esc"),
+        faint(" to quit"),
+    ]);
+
+    tui.reset_screen()?;
+    tui.display_lines(&lines)?;
+    tui.hide_cursor()?;
+    Ok(())
+}
+
+/// Shows `error` and lets the user pick a built-in word list with the
+/// keys of `tty`.
+///
+/// Returns the word list picked, or `None` if the user quit.
+pub fn run(
+    error: &EmptyWordlistError,
+    theme: Theme,
+    tty: &mut Tty,
+) -> Result<Option<BuiltInWordlist>> {
+    let theme = theme.for_depth(ColorDepth::detect());
+    let mut picker = Picker::default();
+    let mut tui = ToipeTui::new();
+    let events = EventStream::new(Terminal::keys(tty.try_clone()?));
+    display(&mut tui, &picker, error, theme)?;
+    let mut picked = None;
+    for event in events {
+        if let ToipeEvent::Key(key) = event? {
+            if let Some(wordlist) = picker.handle(key) {
+                picked = wordlist;
+                break;
+            }
+            display(&mut tui, &picker, error, theme)?;
+        }
+    }
+    tui.show_cursor()?;
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_a_built_in_wordlist() {
+        let mut picker = Picker::default();
+        assert_eq!(picker.handle(Key::Up), None);
+        assert_eq!(picker.selected, picker.wordlists.len() - 1);
+        assert_eq!(picker.handle(Key::Down), None);
+        assert_eq!(picker.handle(Key::Down), None);
+        assert_eq!(
+            picker.handle(Key::Char('\n')),
+            Some(Some(picker.wordlists[1]))
+        );
+        assert_eq!(picker.handle(Key::Esc), Some(None));
+    }
+}
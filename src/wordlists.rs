@@ -1,4 +1,9 @@
 //! Built-in wordlists, system wordlist and utils for retrieving them.
+use std::{
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
 use clap::ArgEnum;
 use include_flate::flate;
 
@@ -45,7 +50,7 @@ pub enum BuiltInWordlist {
 
     /// The operating system's builtin word list.
     ///
-    /// See [`OS_WORDLIST_PATH`].
+    /// See [`find_os_wordlist`].
     OS,
 }
 
@@ -69,9 +74,60 @@ impl BuiltInWordlist {
     }
 }
 
-/// Path to the default word list file in Linux/Unix-based systems.
+/// Environment variable that overrides the path to the OS word list.
+pub const OS_WORDLIST_ENV_VAR: &str = "TOIPE_OS_DICT";
+
+/// Candidate paths of the word list file in Linux/Unix-based systems,
+/// in the order they are tried.
 ///
 /// Note: the OS word list varies a lot from system to system and usually
 /// has more than 100,000 words. This can lead to difficult and esoteric
 /// words appearing in the test, reducing your typing speed.
-pub const OS_WORDLIST_PATH: &str = "/usr/share/dict/words";
+pub const OS_WORDLIST_PATHS: &[&str] = &[
+    // most Linux distributions, Mac OS and the BSDs
+    "/usr/share/dict/words",
+    // Debian/Ubuntu `wamerican`/`wbritish` packages
+    "/usr/share/dict/american-english",
+    "/usr/share/dict/british-english",
+    // Fedora/RHEL `words` package
+    "/usr/share/dict/linux.words",
+    // Mac OS and the BSDs (Webster's 2nd International)
+    "/usr/share/dict/web2",
+    // older Unix systems
+    "/usr/dict/words",
+    // packages installed under /usr/local, e.g. on FreeBSD
+    "/usr/local/share/dict/words",
+];
+
+/// Path to the default word list file in Linux/Unix-based systems.
+#[deprecated(note = "the OS word list is looked up in several paths, use `find_os_wordlist`")]
+pub const OS_WORDLIST_PATH: &str = OS_WORDLIST_PATHS[0];
+
+/// Finds the OS word list.
+///
+/// If the [`OS_WORDLIST_ENV_VAR`] environment variable is set, its value
+/// is used as-is. Otherwise, the first existing file among
+/// [`OS_WORDLIST_PATHS`] is returned.
+///
+/// The error lists all the paths that were tried.
+pub fn find_os_wordlist() -> Result<PathBuf, Error> {
+    if let Some(path) = std::env::var_os(OS_WORDLIST_ENV_VAR) {
+        return Ok(PathBuf::from(path));
+    }
+
+    OS_WORDLIST_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "Could not find the OS word list. Tried: {}. \
+                    Set {} to the path of a word list to use it instead",
+                    OS_WORDLIST_PATHS.join(", "),
+                    OS_WORDLIST_ENV_VAR,
+                ),
+            )
+        })
+}
@@ -1,14 +1,75 @@
 use std::{
+    collections::VecDeque,
     fs::File,
-    io::{BufRead, BufReader, Cursor, Error, ErrorKind, Read},
+    io::{BufRead, BufReader, Cursor, Error, ErrorKind, IsTerminal, Read},
     path::PathBuf,
+    process::{Command, Stdio},
 };
 
+use rand::Rng;
+
 use crate::{
     config::ToipeConfig,
-    wordlists::{BuiltInWordlist, OS_WORDLIST_PATH},
+    textgen::{SelectorRng, WordSelector, PARAGRAPH_BREAK},
+    user_wordlists::find_installed,
+    wordlists::{find_os_wordlist, BuiltInWordlist},
 };
 
+/// Text source that runs a shell command and reads its output, such as
+/// `fortune`.
+pub struct CommandSource {
+    command: String,
+}
+
+impl CommandSource {
+    pub fn new(command: String) -> Self {
+        Self { command }
+    }
+
+    /// Runs the command with `sh -c`, returning its standard output.
+    pub fn run(&self) -> Result<Vec<u8>, Error> {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::null())
+            .output()?;
+
+        if output.status.success() {
+            Ok(output.stdout)
+        } else {
+            Err(Error::other(format!(
+                "`{}` exited with {}: {}",
+                self.command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+}
+
+/// Number of commits read by [`commit_subjects`].
+const MAX_COMMITS: usize = 500;
+
+/// Subject lines of the most recent commits in a git repository, one per
+/// line.
+pub fn commit_subjects(repo: &str) -> Result<Vec<u8>, Error> {
+    let output = Command::new("git")
+        .args(["-C", repo, "log", "--no-merges", "--format=%s"])
+        .arg(format!("--max-count={}", MAX_COMMITS))
+        .stdin(Stdio::null())
+        .output()?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(Error::other(format!(
+            "Could not read the commits in {}: {}",
+            repo,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
 pub struct WordStream {
     stream: Box<dyn Read>,
     is_quote_mode: bool,
@@ -16,48 +77,233 @@ pub struct WordStream {
 
 impl WordStream {
     pub fn new(config: &ToipeConfig) -> Result<Self, Error> {
-        let stdin = std::io::stdin().lock();
+        // stdin is only locked when the text is read from it, since the
+        // keys of the terminal are read from it on another thread
+        let stdin = std::io::stdin();
 
-        let stream: Box<dyn Read> = if !termion::is_tty(&stdin) {
-            Box::new(stdin)
+        let stream: Box<dyn Read> = if !stdin.is_terminal() {
+            Box::new(stdin.lock())
+        } else if let Some(command) = &config.text_command {
+            Box::new(Cursor::new(CommandSource::new(command.clone()).run()?))
+        } else if let Some(repo) = config.git_log_repo() {
+            Box::new(Cursor::new(commit_subjects(repo)?))
+        } else if let Some(url) = config.feed_url() {
+            Self::feed(url)?
         } else if let Some(path) = &config.wordlist_file {
-            Box::new(File::open(PathBuf::from(path))?)
+            let path = PathBuf::from(path);
+            match find_installed(&path.to_string_lossy()) {
+                Some(installed) if !path.exists() => Box::new(File::open(installed)?),
+                _ => Box::new(File::open(path)?),
+            }
         } else if let Some(contents) = config.wordlist.contents().map(|c| c.to_string()) {
             Box::new(Cursor::<String>::new(contents))
         } else if let BuiltInWordlist::OS = config.wordlist {
-            Box::new(File::open(PathBuf::from(OS_WORDLIST_PATH))?)
+            Box::new(File::open(find_os_wordlist()?)?)
         } else {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "Could not determine word source",
-            ));
+            return Err(Error::other("Could not determine word source"));
         };
 
         Ok(Self {
             stream,
-            is_quote_mode: config.quote_mode,
+            is_quote_mode: config.is_quote_mode(),
         })
     }
 
+    #[cfg(feature = "feeds")]
+    fn feed(url: &str) -> Result<Box<dyn Read>, Error> {
+        Ok(Box::new(Cursor::new(crate::feeds::quotes(url)?)))
+    }
+
+    #[cfg(not(feature = "feeds"))]
+    fn feed(_url: &str) -> Result<Box<dyn Read>, Error> {
+        unreachable!("feeds are only configured with the `feeds` feature")
+    }
+
+    #[allow(clippy::should_implement_trait)]
     pub fn into_iter(self) -> impl Iterator<Item = Result<String, Error>> {
         let is_quote_mode = self.is_quote_mode;
         let reader = BufReader::new(self.stream);
-        reader
-            .lines()
-            .map(move |result| match result {
-                Ok(line) => {
-                    if is_quote_mode {
-                        vec![Ok(line)].into_iter()
-                    } else {
-                        line.to_ascii_lowercase()
-                            .split_whitespace()
-                            .map(|s| Ok(s.to_string()))
-                            .collect::<Vec<_>>()
-                            .into_iter()
+        reader.lines().flat_map(move |result| match result {
+            Ok(line) => {
+                if is_quote_mode {
+                    // a blank line is no quote to type
+                    match line.trim().is_empty() {
+                        true => Vec::new().into_iter(),
+                        false => vec![Ok(line)].into_iter(),
                     }
+                } else {
+                    line.to_ascii_lowercase()
+                        .split_whitespace()
+                        .map(|s| Ok(s.to_string()))
+                        .collect::<Vec<_>>()
+                        .into_iter()
                 }
-                Err(err) => vec![Err(err)].into_iter(),
-            })
-            .flatten()
+            }
+            Err(err) => vec![Err(err)].into_iter(),
+        })
+    }
+}
+
+/// Whether `reader` has a line with anything else than whitespace.
+pub fn has_words<R: BufRead>(reader: R) -> Result<bool, Error> {
+    for line in reader.lines() {
+        if !line?.trim().is_empty() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Picks a random section of `len` contiguous non-empty lines, or all of
+/// them if there are fewer.
+///
+/// Lines after blank lines start a new paragraph, and start with a
+/// [`PARAGRAPH_BREAK`] unless they are the first of the section.
+///
+/// Uses reservoir sampling over the sections, so that every section is
+/// equally likely while the lines are read only once and only two
+/// sections are kept in memory.
+pub fn random_section<R: BufRead>(
+    reader: R,
+    len: usize,
+    rng: &mut impl Rng,
+) -> Result<Vec<String>, Error> {
+    let mut window = VecDeque::with_capacity(len);
+    let mut chosen = None;
+    let mut num_sections: u64 = 0;
+
+    // whether the next line starts a paragraph
+    let mut after_blank = false;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            after_blank = true;
+            continue;
+        }
+        if window.len() == len {
+            window.pop_front();
+        }
+        window.push_back((std::mem::take(&mut after_blank), line));
+
+        if window.len() == len {
+            num_sections += 1;
+            if rng.gen_range(0..num_sections) == 0 {
+                chosen = Some(section(&window));
+            }
+        }
+    }
+
+    Ok(chosen.unwrap_or_else(|| section(&window)))
+}
+
+/// The lines of `window`, with a paragraph break before the ones that
+/// start a paragraph.
+fn section(window: &VecDeque<(bool, String)>) -> Vec<String> {
+    window
+        .iter()
+        .enumerate()
+        .map(|(index, (starts_paragraph, line))| match starts_paragraph {
+            true if index > 0 => format!("{}{}", PARAGRAPH_BREAK, line),
+            _ => line.clone(),
+        })
+        .collect()
+}
+
+/// Word selector returning the lines of random sections of a file, in
+/// order.
+///
+/// Each section has as many lines as a test has words, so that every
+/// test gets a new section (see [`random_section`]).
+pub struct RandomSectionSelector {
+    path: PathBuf,
+    section_len: usize,
+    section: VecDeque<String>,
+    rng: SelectorRng,
+}
+
+impl RandomSectionSelector {
+    pub fn new(path: PathBuf, section_len: usize, rng: SelectorRng) -> Self {
+        Self {
+            path,
+            section_len: section_len.max(1),
+            section: VecDeque::new(),
+            rng,
+        }
+    }
+}
+
+impl WordSelector for RandomSectionSelector {
+    fn new_word(&mut self) -> Result<String, Error> {
+        if self.section.is_empty() {
+            let reader = BufReader::new(File::open(&self.path)?);
+            self.section = random_section(reader, self.section_len, &mut self.rng)?.into();
+        }
+        self.section.pop_front().ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{} has no lines", self.path.display()),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn random_sections() {
+        let text = "1\n2\n\n3\n4\n5\n6\n";
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut starts = Vec::new();
+
+        for _ in 0..200 {
+            let section = random_section(text.as_bytes(), 3, &mut rng).unwrap();
+            let lines: Vec<usize> = section
+                .iter()
+                .map(|line| line.trim_start_matches(PARAGRAPH_BREAK).parse().unwrap())
+                .collect();
+            assert_eq!(lines.len(), 3);
+            assert!(lines.windows(2).all(|pair| pair[1] == pair[0] + 1));
+            starts.push(lines[0]);
+        }
+        starts.sort_unstable();
+        starts.dedup();
+        assert_eq!(starts, vec![1, 2, 3, 4]);
+
+        assert_eq!(
+            random_section(text.as_bytes(), 10, &mut rng).unwrap().len(),
+            6
+        );
+    }
+
+    #[test]
+    fn blank_lines_break_paragraphs() {
+        let text = "one\ntwo\n\n\nthree\n";
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            random_section(text.as_bytes(), 3, &mut rng).unwrap(),
+            vec!["one", "two", "\nthree"]
+        );
+        // no break before the first line of a section
+        assert!(
+            !random_section("\n\nthree".as_bytes(), 1, &mut rng).unwrap()[0]
+                .starts_with(PARAGRAPH_BREAK)
+        );
+    }
+
+    #[test]
+    fn blank_lines_are_no_quotes() {
+        let stream = WordStream {
+            stream: Box::new(Cursor::new("first quote\n  \n\t\nsecond quote\n")),
+            is_quote_mode: true,
+        };
+        let quotes: Vec<String> = stream.into_iter().map(Result::unwrap).collect();
+        assert_eq!(quotes, ["first quote", "second quote"]);
+
+        assert!(!has_words(" \n\n\t\n".as_bytes()).unwrap());
+        assert!(has_words("\n word\n".as_bytes()).unwrap());
     }
 }
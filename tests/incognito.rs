@@ -0,0 +1,62 @@
+//! Incognito mode lasts for the rest of the process, so it is tested in a
+//! test binary of its own.
+
+use std::{
+    env, fs,
+    io::{self, ErrorKind, Write},
+};
+
+use toipe::{challenge_board, profile, storage};
+
+#[test]
+fn discards_writes_appends_and_removes() {
+    let dir = env::temp_dir().join(format!("toipe-incognito-{}", std::process::id()));
+    let kept = dir.join("kept");
+    storage::write(&kept, b"kept").unwrap();
+
+    storage::enable_incognito();
+    storage::write(&dir.join("written"), b"written").unwrap();
+    storage::write_private(&dir.join("private"), b"private").unwrap();
+    storage::append(&kept, b" appended").unwrap();
+    storage::append_private(&dir.join("appended"), b"appended").unwrap();
+    storage::create(&dir.join("created"))
+        .unwrap()
+        .write_all(b"created")
+        .unwrap();
+    storage::create_dir(&dir.join("subdir")).unwrap();
+    storage::remove(&kept).unwrap();
+
+    let mut names: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["kept"]);
+    assert_eq!(fs::read(&kept).unwrap(), b"kept");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn does_not_submit_to_challenge_boards() {
+    storage::enable_incognito();
+    let submission = challenge_board::Submission {
+        name: "someone".to_string(),
+        wpm: 60.0,
+        accuracy: 0.95,
+        timestamp: 0,
+    };
+    // nothing listens there, so submitting would fail
+    challenge_board::submit("http://127.0.0.1:1", "code", &submission).unwrap();
+}
+
+#[test]
+fn refuses_backups_and_restores() {
+    storage::enable_incognito();
+    let archive = env::temp_dir().join(format!("toipe-incognito-{}.tar.gz", std::process::id()));
+    let refused =
+        |result: Result<(), io::Error>| result.unwrap_err().kind() == ErrorKind::Unsupported;
+    assert!(refused(profile::backup(&archive)));
+    assert!(refused(profile::restore(&archive)));
+    assert!(!archive.exists());
+}